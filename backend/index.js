@@ -4,7 +4,7 @@ const helmet = require('helmet');
 const morgan = require('morgan');
 const express = require('express');
 
-const { Issuer, ConfirmedParticipation, LedgerEntry, SignedResource } = require('prepams-shared');
+const { Issuer, ConfirmedParticipation, LedgerEntry, LedgerEntryType, SignedResource } = require('prepams-shared');
 
 const { openDatabase } = require('./src/utils/db');
 const { BadRequest } = require('./src/utils/errors');
@@ -50,9 +50,9 @@ if (process.argv[2] === '--init') {
     issuer = entries.reduce((issuer, entry) => {
       if (entry.participation) {
         const participation = ConfirmedParticipation.from(entry.participation, entry.tag, entry.study, entry.request, entry.signature, entry.value);
-        return issuer.appendEntry(LedgerEntry.fromTransaction(issuer.head, participation, entry.coin, entry.chain));
+        return issuer.appendEntry(LedgerEntry.fromTransaction(issuer.head, entry.timestamp, participation, entry.coin, entry.chain));
       } else {
-        return issuer.appendEntry(LedgerEntry.fromPayout(issuer.head, entry.coin, entry.chain));
+        return issuer.appendEntry(LedgerEntry.fromPayout(issuer.head, entry.timestamp, entry.coin, entry.chain));
       }
     }, Issuer.deserialize(Buffer.from(process.env['ISSUER_SECRET'], 'base64url'), []));
     console.log(`issuer credential loaded, ${entries.length} transaction${entries.length !== 1 ? 's' : ''} applied`);
@@ -67,7 +67,21 @@ if (process.argv[2] === '--init') {
   app.get('/api/issuer/pk', (req, res) => res.sendBinary(issuer.publicKey));
   app.get('/api/issuer/vk', (req, res) => res.sendBinary(issuer.verificationKey));
   app.get('/api/ledger/vk', (req, res) => res.sendBinary(issuer.ledgerVerificationKey));
-  app.get('/api/ledger', (req, res) => res.sendBinary(issuer.ledger));
+  app.get('/api/ledger', asyncWrapper(async (req, res) => {
+    const { from, to, after, limit, type, study } = req.query;
+    if (from === undefined && after === undefined) {
+      return res.sendBinary(issuer.ledger);
+    }
+
+    if (type !== undefined && LedgerEntryType[type] === undefined) {
+      throw new BadRequest('invalid entry type');
+    }
+
+    const slice = after !== undefined
+      ? issuer.ledgerAfter(Buffer.from(after, 'base64url'), parseInt(limit ?? 100, 10), LedgerEntryType[type], study)
+      : issuer.ledgerRange(parseInt(from, 10), parseInt(to ?? 0xffffffff, 10), LedgerEntryType[type], study);
+    res.sendBinary(slice.serialize());
+  }));
   app.post('/api/nulls', (req, res) => res.sendBinary(issuer.issueNulls(req.body)));
 
   app.post('/api/auth/signup', asyncWrapper(async (req, res) => {
@@ -249,8 +263,8 @@ if (process.argv[2] === '--init') {
     }
 
     await db.run(`
-      INSERT INTO ledger (participation, tag, iv, data, study, request, signature, value, coin, chain)
-        VALUES (:participation, :tag, :iv, :data, :study, :request, :signature, :value, :coin, :chain);
+      INSERT INTO ledger (participation, tag, iv, data, study, request, signature, value, coin, chain, timestamp)
+        VALUES (:participation, :tag, :iv, :data, :study, :request, :signature, :value, :coin, :chain, :timestamp);
       DELETE FROM participations WHERE id = :participation
     `, {
       ':participation': id,
@@ -263,6 +277,7 @@ if (process.argv[2] === '--init') {
       ':value': value,
       ':coin': entry.transaction.coin,
       ':chain': entry.signature,
+      ':timestamp': entry.timestamp,
     });
 
     res.sendBinary(entry.serialize());
@@ -274,13 +289,14 @@ if (process.argv[2] === '--init') {
     const receipt = entry.payout.serialize();
 
     await db.run(`
-      INSERT INTO ledger (participation, tag, iv, data, study, request, signature, value, coin, chain)
-        VALUES (NULL, :tag, NULL, NULL, NULL, NULL, NULL, :value, :coin, :chain)
+      INSERT INTO ledger (participation, tag, iv, data, study, request, signature, value, coin, chain, timestamp)
+        VALUES (NULL, :tag, NULL, NULL, NULL, NULL, NULL, :value, :coin, :chain, :timestamp)
     `, {
       ':tag': JSON.stringify({ target: payout.target, recipient: payout.recipient }),
       ':value': entry.payout.value,
       ':coin': receipt,
-      ':chain': entry.signature
+      ':chain': entry.signature,
+      ':timestamp': entry.timestamp
     });
 
     res.json({ receipt: Buffer.from(receipt).toString('base64') });
@@ -0,0 +1,12 @@
+--------------------------------------------------------------------------------
+-- Up
+--------------------------------------------------------------------------------
+
+-- existing entries were signed without a timestamp and keep the timestamp 0
+ALTER TABLE ledger ADD COLUMN timestamp INTEGER NOT NULL DEFAULT 0;
+
+--------------------------------------------------------------------------------
+-- Down
+--------------------------------------------------------------------------------
+
+ALTER TABLE ledger DROP COLUMN timestamp;
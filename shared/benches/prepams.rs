@@ -8,7 +8,11 @@ use group::Curve;
 use prepams_shared::bindings::{issuer::Issuer, organizer::Organizer};
 use prepams_shared::bindings::participant::Participant;
 use prepams_shared::types::{AttributeConstraint, ConfirmedParticipation, Participation, Resource};
-use bls12_381::{Scalar, G1Affine};
+use bls12_381::{Scalar, G1Affine, G1Projective};
+use prepams_shared::msm::{multiscalar_mul, vartime_multiscalar_mul};
+use prepams_shared::encode;
+use prepams_shared::proofs::participation::{ParticipationProof, ParticipationProofSecrets};
+use merlin::Transcript;
 use rand::{thread_rng, Rng, RngCore};
 use rand::seq::SliceRandom;
 
@@ -66,7 +70,7 @@ pub fn registration(c: &mut Criterion) {
                         (seed, attributes)
                     },
                     |(seed, attributes)| {
-                        let mut p = Participant::new(black_box("participant@example.org"), &attributes, &lvk);
+                        let mut p = Participant::new(black_box("participant@example.org"), &attributes, &lvk).ok().unwrap();
                         p.requestCredential(&ipk, &cvk, black_box(&seed)).ok().unwrap();
                     },
                     criterion::BatchSize::SmallInput
@@ -81,7 +85,7 @@ pub fn registration(c: &mut Criterion) {
                 b.iter_batched(
                     || {
                         let attributes: Vec<u32> = (0..*num_attributes).map(|_| rng.next_u32()).collect();
-                        let mut p = Participant::new(black_box("participant@example.org"), &attributes, &lvk);
+                        let mut p = Participant::new(black_box("participant@example.org"), &attributes, &lvk).ok().unwrap();
                         let mut seed: [u8; 32] = [0; 32];
                         rng.fill_bytes(&mut seed);
                         let request = p.requestCredential(&ipk, &cvk, black_box(&seed));
@@ -100,7 +104,7 @@ pub fn registration(c: &mut Criterion) {
                 b.iter_batched(
                     || {
                         let attributes: Vec<u32> = (0..*num_attributes).map(|_| rng.next_u32()).collect();
-                        let mut p = Participant::new(black_box("participant@example.org"), &attributes, &lvk);
+                        let mut p = Participant::new(black_box("participant@example.org"), &attributes, &lvk).ok().unwrap();
                         let mut seed: [u8; 32] = [0; 32];
                         rng.fill_bytes(&mut seed);
                         let request = p.requestCredential(&ipk, &cvk, black_box(&seed)).ok().unwrap();
@@ -138,7 +142,7 @@ fn gen_participation(issuer: &Issuer, p: &ParticipationParams) -> (Participant,
         AttributeConstraint::Range(_, lower, upper) => rng.gen_range(*lower..*upper),
         AttributeConstraint::Element(_, set) => set.choose(&mut rng).unwrap().clone()
     }).collect();
-    let mut participant = Participant::new(black_box("p@example.org"), &attributes, &lvk);
+    let mut participant = Participant::new(black_box("p@example.org"), &attributes, &lvk).ok().unwrap();
     let request = participant.requestCredential(&ipk, &cvk, &[0; 32]).ok().unwrap();
     let response = issuer.issueCredential(&request).ok().unwrap();
     participant.retrieveCredential(&response).ok().unwrap();
@@ -147,7 +151,7 @@ fn gen_participation(issuer: &Issuer, p: &ParticipationParams) -> (Participant,
 
     for _ in 0..p.num_qualifier {
         let qid = <bls12_381::Scalar as ff::Field>::random(&mut rng);
-        let tag = participant.credential().unwrap().derive_tag(&qid).unwrap();
+        let tag = participant.core().credential().unwrap().derive_tag(&qid).unwrap();
         let mut tags: Vec<G1Affine> = (0..(p.tags_per_qualifier.max(1) - 1))
             .map(|_| (G1Affine::generator() * <Scalar as ff::Field>::random(&mut rng)).to_affine())
             .collect();
@@ -338,6 +342,116 @@ pub fn participation(c: &mut Criterion) {
     }
 }
 
+pub fn large_qualifiers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ParticipationLarge");
+    group.sample_size(10);
+
+    for tags_per_qualifier in [128, 256, 512, 1024] {
+        bench_participation("QT", tags_per_qualifier, &mut group, ParticipationParams {
+            num_qualifier: 1,
+            tags_per_qualifier,
+            num_disqualifier: 0,
+            tags_per_disqualifier: 0,
+            num_range_constraints: 0,
+            range_bit_length: 0,
+            num_set_constraints: 0,
+            set_size: 0
+        });
+    }
+}
+
+pub fn batch_verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ParticipationBatch");
+    group.sample_size(10);
+
+    let params = ParticipationParams {
+        num_qualifier: 1,
+        tags_per_qualifier: 1,
+        num_disqualifier: 1,
+        tags_per_disqualifier: 1,
+        num_range_constraints: 1,
+        range_bit_length: 8,
+        num_set_constraints: 0,
+        set_size: 0
+    };
+    let issuer = Issuer::new(1, &[]);
+
+    for n in [1, 4, 16, 64] {
+        let participations: Vec<Participation> = (0..n)
+            .map(|_| {
+                let (participant, study) = gen_participation(&issuer, &params);
+                Participation::deserialize(&participant.participate(&study).ok().unwrap()).ok().unwrap()
+            })
+            .collect();
+
+        let experiment = format!("N-{}", n);
+        group.bench_function(
+            BenchmarkId::new("Single", &experiment),
+            |b| b.iter(|| participations.iter().for_each(|p| p.check().unwrap()))
+        );
+        group.bench_function(
+            BenchmarkId::new("Batch", &experiment),
+            |b| b.iter(|| Participation::check_batch(black_box(&participations)).unwrap())
+        );
+    }
+}
+
+pub fn proof_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ProofSize");
+    let issuer = Issuer::new(1, &[]);
+
+    for tags_per_qualifier in [1, 16, 128, 1024] {
+        let (participant, study) = gen_participation(&issuer, &ParticipationParams {
+            num_qualifier: 1,
+            tags_per_qualifier,
+            num_disqualifier: 0,
+            tags_per_disqualifier: 0,
+            num_range_constraints: 1,
+            range_bit_length: 8,
+            num_set_constraints: 0,
+            set_size: 0
+        });
+        let participation = Participation::deserialize(&participant.participate(&study).ok().unwrap()).ok().unwrap();
+
+        // version 1 additionally carried the blinded variables of the proof
+        let vars = participation.proof().variables::<ParticipationProofSecrets, ParticipationProof>(&mut Transcript::new(b"participation")).unwrap();
+        let size = encode(&participation).unwrap().len();
+        println!("ProofSize/QT-{}: {} bytes, {} bytes with variables", tags_per_qualifier, size, size + encode(&vars).unwrap().len());
+
+        group.bench_with_input(
+            BenchmarkId::new("Encode", format!("QT-{}", tags_per_qualifier)),
+            &participation,
+            |b, participation| b.iter(|| encode(black_box(participation)).unwrap())
+        );
+    }
+}
+
+pub fn multiscalar(c: &mut Criterion) {
+    let mut rng = thread_rng();
+    let mut group = c.benchmark_group("MultiscalarMul");
+
+    for n in [2, 16, 64, 256, 1024] {
+        let scalars: Vec<Scalar> = (0..n).map(|_| <Scalar as ff::Field>::random(&mut rng)).collect();
+        let points: Vec<G1Affine> = (0..n)
+            .map(|_| (G1Affine::generator() * <Scalar as ff::Field>::random(&mut rng)).to_affine())
+            .collect();
+
+        let experiment = format!("N-{}", n);
+        group.bench_function(
+            BenchmarkId::new("Naive", &experiment),
+            |b| b.iter(|| scalars.iter().zip(&points).fold(G1Projective::identity(), |acc, (s, p)| acc + p * s))
+        );
+        group.bench_function(
+            BenchmarkId::new("Straus", &experiment),
+            |b| b.iter(|| multiscalar_mul(black_box(&scalars), black_box(&points)))
+        );
+        group.bench_function(
+            BenchmarkId::new("Vartime", &experiment),
+            |b| b.iter(|| vartime_multiscalar_mul(black_box(&scalars), black_box(&points)))
+        );
+    }
+}
+
 pub fn payout(c: &mut Criterion) {
     let mut group = c.benchmark_group("Payout");
 
@@ -348,7 +462,7 @@ pub fn payout(c: &mut Criterion) {
     let cvk = issuer.verificationKey().ok().unwrap();
     let lvk = issuer.ledgerVerificationKey().ok().unwrap();
 
-    let mut participant = Participant::new(black_box("p@example.org"), &vec![], &lvk);
+    let mut participant = Participant::new(black_box("p@example.org"), &[], &lvk).ok().unwrap();
     let request = participant.requestCredential(&ipk, &cvk, &[0; 32]).ok().unwrap();
     let response = issuer.issueCredential(&request).ok().unwrap();
     participant.retrieveCredential(&response).ok().unwrap();
@@ -459,5 +573,5 @@ pub fn payout(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, registration, participation, payout);
+criterion_group!(benches, registration, participation, large_qualifiers, batch_verification, proof_size, multiscalar, payout);
 criterion_main!(benches);
@@ -0,0 +1,732 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use ed25519_zebra::VerificationKey;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Serialize, Deserialize};
+
+use prepams_shared::{audit, envelope, format, seal, open, vectors, PrepamsError};
+use prepams_shared::api::*;
+use prepams_shared::storage::FileStore;
+use prepams_shared::types::*;
+
+type CliResult<T> = Result<T, String>;
+
+#[derive(Parser)]
+#[command(name = "prepams", version, about = "Run the PrePaMS issuer, organizer and participant roles from the command line")]
+struct Cli {
+    /// Deployment domain that messages are sealed for
+    #[arg(long, global = true, default_value = envelope::DEFAULT_DOMAIN)]
+    domain: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Credential issuance, rewards and payouts
+    #[command(subcommand)]
+    Issuer(IssuerCommand),
+
+    /// Study creation and participation confirmation
+    #[command(subcommand)]
+    Organizer(OrganizerCommand),
+
+    /// Credentials, study participation and payout requests
+    #[command(subcommand)]
+    Participant(ParticipantCommand),
+
+    /// Independent checks of an exported ledger
+    #[command(subcommand)]
+    Ledger(LedgerCommand),
+
+    /// Conversion and schemas of messages
+    #[command(subcommand)]
+    Message(MessageCommand),
+}
+
+#[derive(Args)]
+struct IssuerState {
+    /// Issuer secret state
+    #[arg(long, default_value = "issuer.state")]
+    state: PathBuf,
+
+    /// Append-only ledger file of the issuer
+    #[arg(long, default_value = "issuer.ledger")]
+    ledger: PathBuf,
+}
+
+#[derive(Subcommand)]
+enum IssuerCommand {
+    /// Generate issuer keys and publish the public parameters
+    Init {
+        #[command(flatten)]
+        issuer: IssuerState,
+        /// Number of participant attributes
+        #[arg(long)]
+        attributes: usize,
+        /// 32 byte seed (base64url) for reproducible keys
+        #[arg(long)]
+        seed: Option<String>,
+        /// Output file for the public parameters
+        #[arg(long, default_value = "public.json")]
+        public: PathBuf,
+    },
+
+    /// Answer a participant credential request
+    Issue {
+        #[command(flatten)]
+        issuer: IssuerState,
+        #[arg(long)]
+        request: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Reward a confirmed participation and append it to the ledger
+    Reward {
+        #[command(flatten)]
+        issuer: IssuerState,
+        /// Confirmed participation created by the organizer
+        #[arg(long)]
+        confirmed: PathBuf,
+        /// Signed study the participation belongs to
+        #[arg(long)]
+        study: PathBuf,
+        /// Public key of the organizer
+        #[arg(long)]
+        organizer: PathBuf,
+    },
+
+    /// Sign a batch of null coins used to pad payout requests
+    Nulls {
+        #[command(flatten)]
+        issuer: IssuerState,
+        #[arg(long)]
+        request: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Verify a payout request and append it to the ledger
+    Payout {
+        #[command(flatten)]
+        issuer: IssuerState,
+        #[arg(long)]
+        request: PathBuf,
+        /// Output file for the payout receipt
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Export the ledger for participants and auditors
+    Export {
+        #[command(flatten)]
+        issuer: IssuerState,
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum OrganizerCommand {
+    /// Create an organizer identity
+    Init {
+        #[arg(long, default_value = "organizer.state")]
+        state: PathBuf,
+        #[arg(long)]
+        id: String,
+        #[arg(long, default_value = "public.json")]
+        public: PathBuf,
+        /// 32 byte seed (base64url) for a reproducible signing key
+        #[arg(long)]
+        seed: Option<String>,
+        /// Output file for the organizer public key
+        #[arg(long, default_value = "organizer.pk")]
+        key: PathBuf,
+    },
+
+    /// Write a study description that can be edited and signed
+    NewStudy {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        reward: u8,
+        #[arg(long, default_value = "")]
+        summary: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        #[arg(long, default_value = "")]
+        duration: String,
+        /// Numeric attribute constraint as ATTRIBUTE:LOWER:UPPER
+        #[arg(long)]
+        range: Vec<String>,
+        /// Select attribute constraint as ATTRIBUTE:VALUE,VALUE,...
+        #[arg(long)]
+        element: Vec<String>,
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Sign a study description
+    SignStudy {
+        #[arg(long, default_value = "organizer.state")]
+        state: PathBuf,
+        /// Study description in JSON
+        #[arg(long)]
+        study: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Verify a participation and confirm it for the issuer
+    Confirm {
+        #[arg(long, default_value = "organizer.state")]
+        state: PathBuf,
+        #[arg(long)]
+        participation: PathBuf,
+        /// Identifier of the participation, random if omitted
+        #[arg(long)]
+        id: Option<String>,
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ParticipantCommand {
+    /// Create a participant identity and a credential request
+    Register {
+        #[arg(long, default_value = "participant.state")]
+        state: PathBuf,
+        #[arg(long)]
+        id: String,
+        /// Attribute values in the order defined by the issuer
+        #[arg(long, value_delimiter = ',')]
+        attributes: Vec<u32>,
+        #[arg(long, default_value = "public.json")]
+        public: PathBuf,
+        /// 32 byte seed (base64url) for a reproducible credential
+        #[arg(long)]
+        seed: Option<String>,
+        /// Output file for the credential request
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Store the credential issued in response to the request
+    Activate {
+        #[arg(long, default_value = "participant.state")]
+        state: PathBuf,
+        #[arg(long)]
+        response: PathBuf,
+    },
+
+    /// Prove eligibility for a signed study
+    Participate {
+        #[arg(long, default_value = "participant.state")]
+        state: PathBuf,
+        #[arg(long)]
+        study: PathBuf,
+        /// Public key of the organizer that signed the study
+        #[arg(long)]
+        organizer: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Request null coins from the issuer
+    Nulls {
+        #[arg(long, default_value = "participant.state")]
+        state: PathBuf,
+        /// Output file for the request sent to the issuer
+        #[arg(long)]
+        out: PathBuf,
+        /// Output file for the blinding secrets needed by `payout`
+        #[arg(long)]
+        secret: PathBuf,
+    },
+
+    /// Prove ownership of unspent rewards and request a payout
+    Payout {
+        #[arg(long, default_value = "participant.state")]
+        state: PathBuf,
+        #[arg(long)]
+        amount: u8,
+        #[arg(long)]
+        target: String,
+        #[arg(long)]
+        recipient: String,
+        /// Blinding secrets written by `nulls`
+        #[arg(long)]
+        secret: PathBuf,
+        /// Null coins returned by the issuer
+        #[arg(long)]
+        nulls: PathBuf,
+        /// Ledger exported by the issuer
+        #[arg(long)]
+        ledger: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum LedgerCommand {
+    /// Verify all signatures and the conservation of value, print a JSON report
+    Verify {
+        #[arg(long)]
+        ledger: PathBuf,
+        #[arg(long, default_value = "public.json")]
+        public: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum MessageCommand {
+    /// Convert a message into postcard, canonical JSON or CBOR
+    Convert {
+        #[arg(long)]
+        input: PathBuf,
+        #[arg(long, value_parser = ["postcard", "json", "cbor"])]
+        format: String,
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Write the JSON schema of every message type
+    Schema {
+        /// Output directory
+        #[arg(long, default_value = "schema")]
+        out: PathBuf,
+    },
+
+    /// Write the known-answer test vectors of a seeded protocol round
+    Vectors {
+        /// Output file
+        #[arg(long, default_value = "vectors/protocol.json")]
+        out: PathBuf,
+    },
+}
+
+/// Public parameters of an issuer, shared with organizers and participants.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PublicParameters {
+    attributes: usize,
+    issuer_public_key: String,
+    credit_verification_key: String,
+    ledger_verification_key: String,
+}
+
+fn base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn from_base64url(data: &str) -> CliResult<Vec<u8>> {
+    base64::decode_config(data, base64::URL_SAFE_NO_PAD).map_err(|e| format!("invalid base64: {}", e))
+}
+
+fn read(path: &Path) -> CliResult<Vec<u8>> {
+    fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))
+}
+
+fn write(path: &Path, data: &[u8]) -> CliResult<()> {
+    fs::write(path, data).map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+// creates or replaces a file that only its owner may read
+fn write_private(path: &Path, data: &[u8]) -> CliResult<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path).and_then(|mut file| {
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(data)
+    }).map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+fn read_json<T: for<'a> Deserialize<'a>>(path: &Path) -> CliResult<T> {
+    serde_json::from_slice(&read(path)?).map_err(|e| format!("could not parse {}: {}", path.display(), e))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> CliResult<()> {
+    let json = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+    write(path, &json)
+}
+
+fn rng(seed: &Option<String>) -> CliResult<ChaCha20Rng> {
+    match seed {
+        Some(seed) => {
+            let seed: [u8; 32] = from_base64url(seed)?.try_into().map_err(|_| "seed must be 32 bytes".to_string())?;
+            Ok(ChaCha20Rng::from_seed(seed))
+        },
+        None => Ok(ChaCha20Rng::from_entropy())
+    }
+}
+
+fn call<T>(what: &str, result: Result<T, PrepamsError>) -> CliResult<T> {
+    result.map_err(|e| format!("{} failed: {}", what, e))
+}
+
+fn read_message<T: envelope::Message>(path: &Path) -> CliResult<T> {
+    format::open_any(&read(path)?).map_err(|e| format!("could not parse {}: {}", path.display(), e))
+}
+
+fn write_message<T: envelope::Message>(path: &Path, value: &T) -> CliResult<()> {
+    write(path, &call("encoding the message", seal(value))?)
+}
+
+// state files hold secret keys, credentials and blinding secrets
+fn write_state<T: envelope::Message>(path: &Path, value: &T) -> CliResult<()> {
+    write_private(path, &call("encoding the message", seal(value))?)
+}
+
+fn verification_key(data: &[u8]) -> CliResult<VerificationKey> {
+    VerificationKey::try_from(data).map_err(|e| format!("invalid verification key: {}", e))
+}
+
+fn load_issuer(issuer: &IssuerState) -> CliResult<IssuerCore> {
+    let core: IssuerCore = read_message(&issuer.state)?;
+    let store = FileStore::open(&issuer.ledger).map_err(|e| e.to_string())?;
+    call("loading the ledger", core.with_store(store))
+}
+
+fn run_issuer(command: IssuerCommand) -> CliResult<()> {
+    match command {
+        IssuerCommand::Init { issuer: paths, attributes, seed, public } => {
+            if paths.state.exists() {
+                return Err(format!("{} already exists", paths.state.display()));
+            }
+
+            let issuer = IssuerCore::new(attributes, rng(&seed)?);
+            let lvk: [u8; 32] = issuer.ledger_verification_key().into();
+            let params = PublicParameters {
+                attributes,
+                issuer_public_key: base64url(&call("encoding the public key", seal(issuer.public_key()))?),
+                credit_verification_key: base64url(&call("encoding the verification key", seal(issuer.credit_verification_key()))?),
+                ledger_verification_key: base64url(&lvk),
+            };
+
+            write_state(&paths.state, &issuer)?;
+            FileStore::open(&paths.ledger).map_err(|e| e.to_string())?;
+            write_json(&public, &params)
+        },
+        IssuerCommand::Issue { issuer, request, out } => {
+            let issuer = load_issuer(&issuer)?;
+            let request: IssueRequest = read_message(&request)?;
+            write_message(&out, &call("issuing the credential", issuer.issue_credential(&request))?)
+        },
+        IssuerCommand::Reward { issuer, confirmed, study, organizer } => {
+            let mut issuer = load_issuer(&issuer)?;
+            let confirmed: ConfirmedParticipation = read_message(&confirmed)?;
+            let study: SignedResource = read_message(&study)?;
+            let organizer = verification_key(&read(&organizer)?)?;
+
+            if !call("checking the study signature", issuer.check_resource_signature(&study, &organizer))? {
+                return Err("study is not signed by the organizer".to_string());
+            }
+            if confirmed.study() != study.resource().id() {
+                return Err("participation does not belong to the study".to_string());
+            }
+
+            let reward = study.resource().reward();
+            call("issuing the reward", issuer.issue_reward(&confirmed, &organizer, reward))?;
+            println!("{}", serde_json::json!({ "study": confirmed.study(), "reward": reward, "entries": issuer.store().len() }));
+            Ok(())
+        },
+        IssuerCommand::Nulls { issuer, request, out } => {
+            let issuer = load_issuer(&issuer)?;
+            let requests: Vec<BlindedSignRequest> = read_message(&request)?;
+            write_message(&out, &call("issuing null coins", issuer.issue_nulls(&requests))?)
+        },
+        IssuerCommand::Payout { issuer, request, out } => {
+            let mut issuer = load_issuer(&issuer)?;
+            let proof: PayoutProofData = read_message(&request)?;
+            let receipt = call("verifying the payout request", issuer.check_payout_request(&proof))?;
+            let payout = receipt.entry.payout().ok_or("payout entry is missing its payout")?;
+
+            write_message(&out, &payout)?;
+            println!("{}", serde_json::json!({ "target": receipt.target, "recipient": receipt.recipient, "value": payout.value() }));
+            Ok(())
+        },
+        IssuerCommand::Export { issuer, out } => {
+            let issuer = load_issuer(&issuer)?;
+            write_message(&out, &call("exporting the ledger", issuer.ledger())?)
+        },
+    }
+}
+
+fn run_organizer(command: OrganizerCommand) -> CliResult<()> {
+    match command {
+        OrganizerCommand::Init { state, id, public, seed, key } => {
+            if state.exists() {
+                return Err(format!("{} already exists", state.display()));
+            }
+
+            let params: PublicParameters = read_json(&public)?;
+            let ipk: IssuerPublicKey = call("parsing the issuer public key", open(&from_base64url(&params.issuer_public_key)?))?;
+            let organizer = OrganizerCore::new(&id, ipk, rng(&seed)?);
+            let pk: [u8; 32] = organizer.public_key().into();
+
+            write_state(&state, &organizer)?;
+            write(&key, &pk)
+        },
+        OrganizerCommand::NewStudy { name, reward, summary, description, duration, range, element, out } => {
+            let mut resource = Resource::random(rand::thread_rng());
+            for constraint in &range {
+                match constraint.split(':').map(str::parse).collect::<Result<Vec<u32>, _>>().as_deref() {
+                    Ok([attribute, lower, upper]) => resource.addConstraint(call("parsing the range constraint", AttributeConstraint::parse(*attribute, "number", &[*lower, *upper]))?),
+                    _ => return Err(format!("invalid range constraint {}", constraint)),
+                }
+            }
+            for constraint in &element {
+                let parsed = constraint.split_once(':').and_then(|(attribute, values)| {
+                    let values = values.split(',').map(str::parse).collect::<Result<Vec<u32>, _>>().ok()?;
+                    Some((attribute.parse().ok()?, values))
+                });
+                match parsed {
+                    Some((attribute, values)) => resource.addConstraint(AttributeConstraint::Element(attribute, values)),
+                    None => return Err(format!("invalid element constraint {}", constraint)),
+                }
+            }
+
+            let mut study = serde_json::to_value(&resource).map_err(|e| e.to_string())?;
+            study["name"] = name.into();
+            study["summary"] = summary.into();
+            study["description"] = description.into();
+            study["duration"] = duration.into();
+            study["reward"] = reward.into();
+            write_json(&out, &study)
+        },
+        OrganizerCommand::SignStudy { state, study, out } => {
+            let organizer: OrganizerCore = read_message(&state)?;
+            let resource: Resource = read_json(&study)?;
+            write_message(&out, &call("signing the study", organizer.sign_resource(&resource))?)
+        },
+        OrganizerCommand::Confirm { state, participation, id, out } => {
+            let organizer: OrganizerCore = read_message(&state)?;
+            let participation: Participation = read_message(&participation)?;
+
+            if participation.check().is_err() {
+                return Err("participation proof is invalid".to_string());
+            }
+
+            let id = match id {
+                Some(id) => id,
+                None => {
+                    let mut id = [0u8; 16];
+                    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut id);
+                    base64url(&id)
+                }
+            };
+            write_message(&out, &call("confirming the participation", organizer.confirm_participation(&participation, id))?)
+        },
+    }
+}
+
+fn run_participant(command: ParticipantCommand) -> CliResult<()> {
+    match command {
+        ParticipantCommand::Register { state, id, attributes, public, seed, out } => {
+            if state.exists() {
+                return Err(format!("{} already exists", state.display()));
+            }
+
+            let params: PublicParameters = read_json(&public)?;
+            if attributes.len() != params.attributes {
+                return Err(format!("issuer expects {} attributes", params.attributes));
+            }
+
+            let lvk = verification_key(&from_base64url(&params.ledger_verification_key)?)?;
+            let ipk = call("parsing the issuer public key", open(&from_base64url(&params.issuer_public_key)?))?;
+            let cvk = call("parsing the verification key", open(&from_base64url(&params.credit_verification_key)?))?;
+
+            let mut participant = ParticipantCore::new(&id, &attributes, lvk);
+            let request = call("requesting the credential", participant.request_credential(ipk, cvk, rng(&seed)?))?;
+
+            write_state(&state, &participant)?;
+            write_message(&out, &request)
+        },
+        ParticipantCommand::Activate { state, response } => {
+            let mut participant: ParticipantCore = read_message(&state)?;
+            let response: IssueResponse = read_message(&response)?;
+            call("retrieving the credential", participant.retrieve_credential(&response))?;
+            write_state(&state, &participant)
+        },
+        ParticipantCommand::Participate { state, study, organizer, out } => {
+            let participant: ParticipantCore = read_message(&state)?;
+            let study: SignedResource = read_message(&study)?;
+            let organizer = verification_key(&read(&organizer)?)?;
+
+            if !call("checking the study signature", participant.check_resource_signature(&study, &organizer))? {
+                return Err("study is not signed by the organizer".to_string());
+            }
+            write_message(&out, &call("proving the participation", participant.participate(&study.resource()))?)
+        },
+        ParticipantCommand::Nulls { state, out, secret } => {
+            let participant: ParticipantCore = read_message(&state)?;
+            let nulls = call("requesting null coins", participant.request_nulls())?;
+
+            write_state(&secret, &nulls)?;
+            write_message(&out, &nulls.requests().to_vec())
+        },
+        ParticipantCommand::Payout { state, amount, target, recipient, secret, nulls, ledger, out } => {
+            let participant: ParticipantCore = read_message(&state)?;
+            let secret: NullRequest = read_message(&secret)?;
+            let nulls: Vec<BlindedSignature> = read_message(&nulls)?;
+            let nulls = call("unblinding the null coins", secret.unblind_nulls(nulls))?;
+            let ledger: Ledger = read_message(&ledger)?;
+
+            let request = call("proving the payout", participant.request_payout(amount, &target, &recipient, nulls, &ledger))?;
+            write_message(&out, &request.proof)
+        },
+    }
+}
+
+fn run_ledger(command: LedgerCommand) -> CliResult<bool> {
+    match command {
+        LedgerCommand::Verify { ledger, public } => {
+            let params: PublicParameters = read_json(&public)?;
+            let lvk = verification_key(&from_base64url(&params.ledger_verification_key)?)?;
+            let ledger: Ledger = read_message(&ledger)?;
+
+            let report = audit::audit(&ledger, &lvk);
+            println!("{}", report.to_json());
+            Ok(report.valid)
+        },
+    }
+}
+
+fn run_message(command: MessageCommand) -> CliResult<()> {
+    match command {
+        MessageCommand::Convert { input, format, out } => {
+            let format = call("parsing the format", format.parse())?;
+            write(&out, &call("converting the message", format::transcode(&read(&input)?, format))?)
+        },
+        MessageCommand::Schema { out } => {
+            fs::create_dir_all(&out).map_err(|e| format!("could not create {}: {}", out.display(), e))?;
+            for kind in envelope::Kind::all() {
+                let schema = serde_json::to_vec_pretty(&format::schema(*kind)).map_err(|e| e.to_string())?;
+                write(&out.join(format!("{}.json", kind.id())), &schema)?;
+            }
+            Ok(())
+        },
+        MessageCommand::Vectors { out } => {
+            let corpus = call("generating the vectors", vectors::generate(&vectors::Inputs::default()))?;
+            if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir).map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+            }
+            write(&out, &serde_json::to_vec_pretty(&corpus).map_err(|e| e.to_string())?)
+        },
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    envelope::set_domain(&cli.domain);
+
+    let result = match cli.command {
+        Command::Issuer(command) => run_issuer(command).map(|_| true),
+        Command::Organizer(command) => run_organizer(command).map(|_| true),
+        Command::Participant(command) => run_participant(command).map(|_| true),
+        Command::Ledger(command) => run_ledger(command),
+        Command::Message(command) => run_message(command).map(|_| true),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> CliResult<bool> {
+        match Cli::try_parse_from(std::iter::once("prepams").chain(args.iter().cloned())).map_err(|e| e.to_string())?.command {
+            Command::Issuer(command) => run_issuer(command).map(|_| true),
+            Command::Organizer(command) => run_organizer(command).map(|_| true),
+            Command::Participant(command) => run_participant(command).map(|_| true),
+            Command::Ledger(command) => run_ledger(command),
+            Command::Message(command) => run_message(command).map(|_| true),
+        }
+    }
+
+    // runs `args` with all files in `dir` instead of the working directory
+    fn run_in(dir: &Path, args: &[&str]) -> CliResult<bool> {
+        const FILES: &[&str] = &[
+            "--state", "--ledger", "--public", "--key", "--request", "--response", "--out", "--study",
+            "--participation", "--confirmed", "--organizer", "--secret", "--nulls", "--input",
+        ];
+
+        let mut resolved: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        for i in 1..resolved.len() {
+            if FILES.contains(&args[i - 1]) {
+                resolved[i] = dir.join(args[i]).to_string_lossy().into_owned();
+            }
+        }
+        run(&resolved.iter().map(String::as_str).collect::<Vec<&str>>())
+    }
+
+    #[test]
+    fn pilot() {
+        let dir = tempfile::tempdir().unwrap();
+        let run = |args: &[&str]| run_in(dir.path(), args);
+
+        let steps: &[&[&str]] = &[
+            &["issuer", "init", "--state", "issuer.state", "--ledger", "issuer.ledger", "--public", "public.json", "--attributes", "2"],
+            &["organizer", "init", "--state", "organizer.state", "--public", "public.json", "--key", "organizer.pk", "--id", "lab"],
+            &["participant", "register", "--state", "participant.state", "--public", "public.json", "--id", "alice", "--attributes", "30,1", "--out", "request.bin"],
+            &["issuer", "issue", "--state", "issuer.state", "--ledger", "issuer.ledger", "--request", "request.bin", "--out", "response.bin"],
+            &["participant", "activate", "--state", "participant.state", "--response", "response.bin"],
+            &["organizer", "new-study", "--name", "survey", "--reward", "3", "--range", "0:18:99", "--out", "study.json"],
+            &["organizer", "sign-study", "--state", "organizer.state", "--study", "study.json", "--out", "study.bin"],
+            &["participant", "participate", "--state", "participant.state", "--study", "study.bin", "--organizer", "organizer.pk", "--out", "participation.bin"],
+            &["organizer", "confirm", "--state", "organizer.state", "--participation", "participation.bin", "--out", "confirmed.bin"],
+            &["issuer", "reward", "--state", "issuer.state", "--ledger", "issuer.ledger", "--confirmed", "confirmed.bin", "--study", "study.bin", "--organizer", "organizer.pk"],
+            &["issuer", "export", "--state", "issuer.state", "--ledger", "issuer.ledger", "--out", "ledger.bin"],
+            &["participant", "nulls", "--state", "participant.state", "--out", "nulls-request.bin", "--secret", "nulls.secret"],
+            &["issuer", "nulls", "--state", "issuer.state", "--ledger", "issuer.ledger", "--request", "nulls-request.bin", "--out", "nulls.bin"],
+            &["participant", "payout", "--state", "participant.state", "--amount", "2", "--target", "bank", "--recipient", "alice", "--secret", "nulls.secret", "--nulls", "nulls.bin", "--ledger", "ledger.bin", "--out", "payout.bin"],
+            &["issuer", "payout", "--state", "issuer.state", "--ledger", "issuer.ledger", "--request", "payout.bin", "--out", "receipt.bin"],
+            &["issuer", "export", "--state", "issuer.state", "--ledger", "issuer.ledger", "--out", "ledger.bin"],
+        ];
+
+        for step in steps {
+            assert_eq!(run(step), Ok(true), "{:?}", step);
+        }
+        assert_eq!(run(&["ledger", "verify", "--ledger", "ledger.bin", "--public", "public.json"]), Ok(true));
+        assert_eq!(run(&["message", "convert", "--input", "ledger.bin", "--format", "json", "--out", "ledger.json"]), Ok(true));
+        assert_eq!(run(&["ledger", "verify", "--ledger", "ledger.json", "--public", "public.json"]), Ok(true));
+        assert!(run(&["issuer", "init", "--state", "issuer.state", "--ledger", "issuer.ledger", "--public", "public.json", "--attributes", "2"]).is_err());
+
+        #[cfg(unix)]
+        for file in ["issuer.state", "organizer.state", "participant.state", "nulls.secret"] {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(dir.path().join(file)).unwrap().permissions().mode() & 0o777, 0o600, "{}", file);
+        }
+
+        assert_eq!(run(&["organizer", "init", "--state", "other.state", "--public", "public.json", "--key", "other.pk", "--id", "other"]), Ok(true));
+        let participate = run(&["participant", "participate", "--state", "participant.state", "--study", "study.bin", "--organizer", "other.pk", "--out", "participation.bin"]);
+        assert_eq!(participate, Err("study is not signed by the organizer".to_string()));
+
+        let reward = run(&["issuer", "reward", "--state", "issuer.state", "--ledger", "issuer.ledger", "--confirmed", "confirmed.bin", "--study", "study.bin", "--organizer", "organizer.pk"]);
+        assert_eq!(reward, Err("issuing the reward failed: reward for this participation already issued".to_string()));
+
+        let payout = run(&["issuer", "payout", "--state", "issuer.state", "--ledger", "issuer.ledger", "--request", "payout.bin", "--out", "receipt.bin"]);
+        assert_eq!(payout, Err("verifying the payout request failed: coin already spent".to_string()));
+    }
+}
@@ -0,0 +1,275 @@
+/* C ABI of the PrePaMS protocol roles, see ffi/src/lib.rs.
+ *
+ * Memory ownership:
+ *  - handles returned through an out pointer are owned by the caller and
+ *    released with the matching prepams_*_free function,
+ *  - buffers returned through a PrepamsBuffer out pointer are owned by the
+ *    caller and released with prepams_buffer_free,
+ *  - input pointers are borrowed for the duration of the call only.
+ *
+ * Every function returns a PrepamsStatus. On failure nothing is written to
+ * the out pointers and the stable error code and message are available
+ * through prepams_last_error_code and prepams_last_error_message on the
+ * calling thread.
+ *
+ * Regenerate with `cbindgen --config cbindgen.toml --output include/prepams.h`.
+ */
+
+#ifndef PREPAMS_H
+#define PREPAMS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of a call.
+typedef enum PrepamsStatus {
+  PREPAMS_STATUS_OK = 0,
+  // A required pointer argument was null.
+  PREPAMS_STATUS_NULL_POINTER = 1,
+  // An input could not be decoded or an output could not be encoded.
+  PREPAMS_STATUS_ENCODING = 2,
+  // A proof or signature failed to verify.
+  PREPAMS_STATUS_VERIFICATION = 3,
+  // The operation is not possible in the current state of the role.
+  PREPAMS_STATUS_STATE = 4,
+  // A well-formed request was rejected by the protocol rules.
+  PREPAMS_STATUS_REJECTED = 5,
+  // The ledger store failed.
+  PREPAMS_STATUS_STORAGE = 6,
+  // The call panicked, the handles passed to it must not be used anymore.
+  PREPAMS_STATUS_PANIC = 7,
+} PrepamsStatus;
+
+// Opaque issuer handle.
+typedef struct PrepamsIssuer PrepamsIssuer;
+
+// Opaque organizer handle.
+typedef struct PrepamsOrganizer PrepamsOrganizer;
+
+// Opaque participant handle.
+typedef struct PrepamsParticipant PrepamsParticipant;
+
+// Byte buffer owned by the caller, released with `prepams_buffer_free`.
+typedef struct PrepamsBuffer {
+  uint8_t *data;
+  size_t len;
+} PrepamsBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Releases a buffer returned by the library. Null buffers are ignored.
+void prepams_buffer_free(struct PrepamsBuffer buffer);
+
+// Returns the stable error code of the last call on this thread, e.g.
+// `verification.inner_product`, or null if it succeeded. The string is owned
+// by the library and valid until the next call on this thread.
+const char *prepams_last_error_code(void);
+
+// Copies the NUL-terminated error message of the last call on this thread
+// into `out`, truncated to `len` bytes, and returns the length of the full
+// message without the terminator.
+size_t prepams_last_error_message(char *out, size_t len);
+
+// Sets the deployment domain that messages are sealed for.
+enum PrepamsStatus prepams_set_domain(const char *domain);
+
+// Converts a message into `postcard`, `json` or `cbor`.
+enum PrepamsStatus prepams_convert(const uint8_t *data,
+                                   size_t len,
+                                   const char *format,
+                                   struct PrepamsBuffer *out);
+
+// Verifies a participation proof.
+enum PrepamsStatus prepams_verify_participation(const uint8_t *participation, size_t len);
+
+// Verifies the signatures and links of all ledger entries.
+enum PrepamsStatus prepams_verify_ledger(const uint8_t *ledger,
+                                         size_t len,
+                                         const uint8_t *lvk,
+                                         size_t lvk_len);
+
+// Creates an issuer for credentials with `attributes` attributes. `seed` is
+// null or points to 32 bytes.
+enum PrepamsStatus prepams_issuer_new(size_t attributes,
+                                      const uint8_t *seed,
+                                      struct PrepamsIssuer **out);
+
+// Releases an issuer handle. Null handles are ignored.
+void prepams_issuer_free(struct PrepamsIssuer *issuer);
+
+// Serializes the issuer state without the ledger, see `prepams_issuer_ledger`.
+enum PrepamsStatus prepams_issuer_serialize(const struct PrepamsIssuer *issuer,
+                                            struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_issuer_deserialize(const uint8_t *data,
+                                              size_t len,
+                                              struct PrepamsIssuer **out);
+
+enum PrepamsStatus prepams_issuer_public_key(const struct PrepamsIssuer *issuer,
+                                             struct PrepamsBuffer *out);
+
+// Returns the verification key of reward and null coin signatures.
+enum PrepamsStatus prepams_issuer_verification_key(const struct PrepamsIssuer *issuer,
+                                                   struct PrepamsBuffer *out);
+
+// Returns the raw 32 byte ed25519 key that signs the ledger.
+enum PrepamsStatus prepams_issuer_ledger_verification_key(const struct PrepamsIssuer *issuer,
+                                                          struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_issuer_ledger(const struct PrepamsIssuer *issuer,
+                                         struct PrepamsBuffer *out);
+
+// Replaces the ledger with a copy of `ledger`.
+enum PrepamsStatus prepams_issuer_load(struct PrepamsIssuer *issuer,
+                                       const uint8_t *ledger,
+                                       size_t len);
+
+enum PrepamsStatus prepams_issuer_issue_credential(const struct PrepamsIssuer *issuer,
+                                                   const uint8_t *request,
+                                                   size_t len,
+                                                   struct PrepamsBuffer *out);
+
+// Writes whether a signed study was signed by the raw ed25519 organizer key.
+enum PrepamsStatus prepams_issuer_check_resource_signature(const struct PrepamsIssuer *issuer,
+                                                           const uint8_t *resource,
+                                                           size_t len,
+                                                           const uint8_t *organizer_key,
+                                                           size_t key_len,
+                                                           bool *out);
+
+// Rewards a confirmed participation and returns the new ledger entry.
+enum PrepamsStatus prepams_issuer_issue_reward(struct PrepamsIssuer *issuer,
+                                               const uint8_t *participation,
+                                               size_t len,
+                                               const uint8_t *organizer_key,
+                                               size_t key_len,
+                                               uint8_t reward,
+                                               struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_issuer_issue_nulls(const struct PrepamsIssuer *issuer,
+                                              const uint8_t *request,
+                                              size_t len,
+                                              struct PrepamsBuffer *out);
+
+// Appends an entry that was signed by this issuer.
+enum PrepamsStatus prepams_issuer_append_entry(struct PrepamsIssuer *issuer,
+                                               const uint8_t *entry,
+                                               size_t len);
+
+// Verifies a payout request and appends the payout to the ledger. Returns the
+// new ledger entry and the UTF-8 encoded payout target and recipient, each
+// out pointer may be null if the value is not needed.
+enum PrepamsStatus prepams_issuer_check_payout_request(struct PrepamsIssuer *issuer,
+                                                       const uint8_t *request,
+                                                       size_t len,
+                                                       struct PrepamsBuffer *entry,
+                                                       struct PrepamsBuffer *target,
+                                                       struct PrepamsBuffer *recipient);
+
+// Creates an organizer for the issuer public key. `seed` is null or points to
+// 32 bytes.
+enum PrepamsStatus prepams_organizer_new(const char *identity,
+                                         const uint8_t *issuer_public_key,
+                                         size_t len,
+                                         const uint8_t *seed,
+                                         struct PrepamsOrganizer **out);
+
+// Releases an organizer handle. Null handles are ignored.
+void prepams_organizer_free(struct PrepamsOrganizer *organizer);
+
+enum PrepamsStatus prepams_organizer_serialize(const struct PrepamsOrganizer *organizer,
+                                               struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_organizer_deserialize(const uint8_t *data,
+                                                 size_t len,
+                                                 struct PrepamsOrganizer **out);
+
+// Returns the raw 32 byte ed25519 key that signs studies and confirmations.
+enum PrepamsStatus prepams_organizer_public_key(const struct PrepamsOrganizer *organizer,
+                                                struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_organizer_sign_resource(const struct PrepamsOrganizer *organizer,
+                                                   const uint8_t *resource,
+                                                   size_t len,
+                                                   struct PrepamsBuffer *out);
+
+// Confirms a participation under the organizer's participation id.
+enum PrepamsStatus prepams_organizer_confirm_participation(const struct PrepamsOrganizer *organizer,
+                                                           const uint8_t *participation,
+                                                           size_t len,
+                                                           const char *id,
+                                                           struct PrepamsBuffer *out);
+
+// Creates a participant with `count` attribute values, trusting the raw
+// ed25519 ledger verification key `lvk`.
+enum PrepamsStatus prepams_participant_new(const char *identity,
+                                           const uint32_t *attributes,
+                                           size_t count,
+                                           const uint8_t *lvk,
+                                           size_t lvk_len,
+                                           struct PrepamsParticipant **out);
+
+// Releases a participant handle. Null handles are ignored.
+void prepams_participant_free(struct PrepamsParticipant *participant);
+
+enum PrepamsStatus prepams_participant_serialize(const struct PrepamsParticipant *participant,
+                                                 struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_participant_deserialize(const uint8_t *data,
+                                                   size_t len,
+                                                   struct PrepamsParticipant **out);
+
+// Requests a credential from the issuer. `seed` is null or points to 32 bytes.
+enum PrepamsStatus prepams_participant_request_credential(struct PrepamsParticipant *participant,
+                                                          const uint8_t *issuer_public_key,
+                                                          size_t ipk_len,
+                                                          const uint8_t *verification_key,
+                                                          size_t cvk_len,
+                                                          const uint8_t *seed,
+                                                          struct PrepamsBuffer *out);
+
+enum PrepamsStatus prepams_participant_retrieve_credential(struct PrepamsParticipant *participant,
+                                                           const uint8_t *response,
+                                                           size_t len);
+
+enum PrepamsStatus prepams_participant_participate(const struct PrepamsParticipant *participant,
+                                                   const uint8_t *resource,
+                                                   size_t len,
+                                                   struct PrepamsBuffer *out);
+
+// Verifies the ledger and writes the unspent balance of the participant.
+enum PrepamsStatus prepams_participant_balance(const struct PrepamsParticipant *participant,
+                                               const uint8_t *ledger,
+                                               size_t len,
+                                               uint32_t *out);
+
+// Returns the blinding secret to keep and the request to send to the issuer.
+enum PrepamsStatus prepams_participant_request_nulls(const struct PrepamsParticipant *participant,
+                                                     struct PrepamsBuffer *secret,
+                                                     struct PrepamsBuffer *request);
+
+// Proves ownership of unspent rewards worth at least `amount` and returns the
+// value of the spent rewards and the payout request.
+enum PrepamsStatus prepams_participant_request_payout(const struct PrepamsParticipant *participant,
+                                                      uint8_t amount,
+                                                      const char *target,
+                                                      const char *recipient,
+                                                      const uint8_t *secret,
+                                                      size_t secret_len,
+                                                      const uint8_t *nulls,
+                                                      size_t nulls_len,
+                                                      const uint8_t *ledger,
+                                                      size_t ledger_len,
+                                                      uint32_t *costs,
+                                                      struct PrepamsBuffer *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PREPAMS_H */
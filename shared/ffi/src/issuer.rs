@@ -0,0 +1,146 @@
+use prepams_shared::api::{BlindedSignRequest, IssuerCore, PayoutProofData};
+use prepams_shared::types::{ConfirmedParticipation, SignedResource};
+
+use crate::*;
+
+/// Opaque issuer handle.
+pub struct PrepamsIssuer {
+    core: IssuerCore
+}
+
+/// Creates an issuer for credentials with `attributes` attributes. `seed` is
+/// null or points to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_new(attributes: usize, seed: *const u8, out: *mut *mut PrepamsIssuer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let issuer = PrepamsIssuer { core: IssuerCore::new(attributes, rng(seed)?) };
+        write(out, Box::into_raw(Box::new(issuer)))
+    })
+}
+
+/// Releases an issuer handle. Null handles are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_free(issuer: *mut PrepamsIssuer) {
+    if !issuer.is_null() {
+        drop(Box::from_raw(issuer));
+    }
+}
+
+/// Serializes the issuer state without the ledger, see `prepams_issuer_ledger`.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_serialize(issuer: *const PrepamsIssuer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| write(out, output(&handle(issuer)?.core)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_deserialize(data: *const u8, len: usize, out: *mut *mut PrepamsIssuer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let issuer = PrepamsIssuer { core: input(bytes(data, len)?)? };
+        write(out, Box::into_raw(Box::new(issuer)))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_public_key(issuer: *const PrepamsIssuer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| write(out, output(handle(issuer)?.core.public_key())?))
+}
+
+/// Returns the verification key of reward and null coin signatures.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_verification_key(issuer: *const PrepamsIssuer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| write(out, output(handle(issuer)?.core.credit_verification_key())?))
+}
+
+/// Returns the raw 32 byte ed25519 key that signs the ledger.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_ledger_verification_key(issuer: *const PrepamsIssuer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let lvk: [u8; 32] = handle(issuer)?.core.ledger_verification_key().into();
+        write(out, raw(&lvk))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_ledger(issuer: *const PrepamsIssuer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| write(out, output(&handle(issuer)?.core.ledger()?)?))
+}
+
+/// Replaces the ledger with a copy of `ledger`.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_load(issuer: *mut PrepamsIssuer, ledger: *const u8, len: usize) -> PrepamsStatus {
+    call(|| {
+        let ledger = input(bytes(ledger, len)?)?;
+        handle_mut(issuer)?.core.load(ledger)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_issue_credential(issuer: *const PrepamsIssuer, request: *const u8, len: usize, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let response = handle(issuer)?.core.issue_credential(&input(bytes(request, len)?)?)?;
+        write(out, output(&response)?)
+    })
+}
+
+/// Writes whether a signed study was signed by the raw ed25519 organizer key.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_check_resource_signature(issuer: *const PrepamsIssuer, resource: *const u8, len: usize, organizer_key: *const u8, key_len: usize, out: *mut bool) -> PrepamsStatus {
+    call(|| {
+        let resource: SignedResource = input(bytes(resource, len)?)?;
+        let vk = verification_key(bytes(organizer_key, key_len)?)?;
+        write(out, handle(issuer)?.core.check_resource_signature(&resource, &vk)?)
+    })
+}
+
+/// Rewards a confirmed participation and returns the new ledger entry.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_issue_reward(issuer: *mut PrepamsIssuer, participation: *const u8, len: usize, organizer_key: *const u8, key_len: usize, reward: u8, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let participation: ConfirmedParticipation = input(bytes(participation, len)?)?;
+        let vk = verification_key(bytes(organizer_key, key_len)?)?;
+        let entry = handle_mut(issuer)?.core.issue_reward(&participation, &vk, reward)?;
+        write(out, output(&entry)?)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_issue_nulls(issuer: *const PrepamsIssuer, request: *const u8, len: usize, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let requests: Vec<BlindedSignRequest> = input(bytes(request, len)?)?;
+        write(out, output(&handle(issuer)?.core.issue_nulls(&requests)?)?)
+    })
+}
+
+/// Appends an entry that was signed by this issuer.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_append_entry(issuer: *mut PrepamsIssuer, entry: *const u8, len: usize) -> PrepamsStatus {
+    call(|| {
+        let entry = input(bytes(entry, len)?)?;
+        handle_mut(issuer)?.core.append_entry(entry)?;
+        Ok(())
+    })
+}
+
+/// Verifies a payout request and appends the payout to the ledger. Returns the
+/// new ledger entry and the UTF-8 encoded payout target and recipient, each
+/// out pointer may be null if the value is not needed.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_issuer_check_payout_request(issuer: *mut PrepamsIssuer, request: *const u8, len: usize, entry: *mut PrepamsBuffer, target: *mut PrepamsBuffer, recipient: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let proof: PayoutProofData = input(bytes(request, len)?)?;
+        let receipt = handle_mut(issuer)?.core.check_payout_request(&proof)?;
+        let entry_buffer = output(&receipt.entry)?;
+
+        for (out, buffer) in [(entry, entry_buffer), (target, raw(receipt.target.as_bytes())), (recipient, raw(receipt.recipient.as_bytes()))] {
+            match out.is_null() {
+                true => prepams_buffer_free(buffer),
+                false => out.write(buffer),
+            }
+        }
+        Ok(())
+    })
+}
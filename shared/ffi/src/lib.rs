@@ -0,0 +1,259 @@
+//! C ABI of the protocol roles for embedding in non-JavaScript services.
+//!
+//! The roles are passed as opaque handles and protocol messages as sealed
+//! byte buffers, see `include/prepams.h`. Memory ownership is explicit:
+//!
+//! - handles returned through an out pointer are owned by the caller and
+//!   released with the matching `prepams_*_free` function,
+//! - buffers returned through a `PrepamsBuffer` out pointer are owned by the
+//!   caller and released with `prepams_buffer_free`,
+//! - input pointers are borrowed for the duration of the call only.
+//!
+//! Every function returns a `PrepamsStatus`. On failure nothing is written to
+//! the out pointers and the stable error code and message of the failure are
+//! available through `prepams_last_error_code` and
+//! `prepams_last_error_message` on the calling thread.
+
+// the safety contract of every exported function are the ownership rules above
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::str::FromStr;
+
+use ed25519_zebra::VerificationKey;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use prepams_shared::{envelope, format, seal, PrepamsError};
+use prepams_shared::format::Format;
+use prepams_shared::types::{Ledger, Participation};
+
+pub mod issuer;
+pub mod organizer;
+pub mod participant;
+
+/// Result of a call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrepamsStatus {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// An input could not be decoded or an output could not be encoded.
+    Encoding = 2,
+    /// A proof or signature failed to verify.
+    Verification = 3,
+    /// The operation is not possible in the current state of the role.
+    State = 4,
+    /// A well-formed request was rejected by the protocol rules.
+    Rejected = 5,
+    /// The ledger store failed.
+    Storage = 6,
+    /// The call panicked, the handles passed to it must not be used anymore.
+    Panic = 7,
+}
+
+/// Byte buffer owned by the caller, released with `prepams_buffer_free`.
+#[repr(C)]
+pub struct PrepamsBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl PrepamsBuffer {
+    fn new(data: Vec<u8>) -> PrepamsBuffer {
+        let data = Box::into_raw(data.into_boxed_slice());
+        PrepamsBuffer { data: data as *mut u8, len: data.len() }
+    }
+}
+
+pub(crate) enum Error {
+    NullPointer,
+    Prepams(PrepamsError),
+}
+
+impl From<PrepamsError> for Error {
+    fn from(e: PrepamsError) -> Self {
+        Error::Prepams(e)
+    }
+}
+
+struct LastError {
+    code: CString,
+    message: String,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+fn fail(status: PrepamsStatus, code: &str, message: String) -> PrepamsStatus {
+    let code = CString::new(code).expect("error codes contain no NUL");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(LastError { code, message }));
+    status
+}
+
+/// Runs `f`, records its error and converts panics into `PrepamsStatus::Panic`.
+pub(crate) fn call(f: impl FnOnce() -> Result<(), Error>) -> PrepamsStatus {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => PrepamsStatus::Ok,
+        Ok(Err(Error::NullPointer)) => fail(PrepamsStatus::NullPointer, "null_pointer", "required argument is null".to_string()),
+        Ok(Err(Error::Prepams(e))) => {
+            let status = match e {
+                PrepamsError::Encoding(_) => PrepamsStatus::Encoding,
+                PrepamsError::Verification(..) => PrepamsStatus::Verification,
+                PrepamsError::State(_) => PrepamsStatus::State,
+                PrepamsError::Rejected(_) => PrepamsStatus::Rejected,
+                PrepamsError::Storage(_) => PrepamsStatus::Storage,
+            };
+            fail(status, e.code(), e.to_string())
+        },
+        Err(_) => fail(PrepamsStatus::Panic, "panic", "internal error".to_string()),
+    }
+}
+
+/// Borrows `len` bytes at `data`, which may be null if `len` is zero.
+pub(crate) unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], Error> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(Error::NullPointer),
+        (false, _) => Ok(slice::from_raw_parts(data, len)),
+    }
+}
+
+/// Borrows a NUL-terminated UTF-8 string.
+pub(crate) unsafe fn string<'a>(s: *const c_char) -> Result<&'a str, Error> {
+    if s.is_null() {
+        Err(Error::NullPointer)?;
+    }
+    Ok(CStr::from_ptr(s).to_str().map_err(|e| PrepamsError::Encoding(e.to_string()))?)
+}
+
+/// Borrows the value behind a handle.
+pub(crate) unsafe fn handle<'a, T>(h: *const T) -> Result<&'a T, Error> {
+    h.as_ref().ok_or(Error::NullPointer)
+}
+
+pub(crate) unsafe fn handle_mut<'a, T>(h: *mut T) -> Result<&'a mut T, Error> {
+    h.as_mut().ok_or(Error::NullPointer)
+}
+
+/// Writes `value` to the out pointer `out`.
+pub(crate) unsafe fn write<T>(out: *mut T, value: T) -> Result<(), Error> {
+    if out.is_null() {
+        Err(Error::NullPointer)?;
+    }
+    out.write(value);
+    Ok(())
+}
+
+pub(crate) fn check_out<T>(out: *mut T) -> Result<(), Error> {
+    if out.is_null() {
+        Err(Error::NullPointer)?;
+    }
+    Ok(())
+}
+
+/// Decodes a message of the configured domain in any encoding.
+pub(crate) fn input<T: envelope::Message>(data: &[u8]) -> Result<T, Error> {
+    Ok(format::open_any(data)?)
+}
+
+/// Seals a message in the postcard encoding.
+pub(crate) fn output<T: envelope::Message>(o: &T) -> Result<PrepamsBuffer, Error> {
+    Ok(PrepamsBuffer::new(seal(o)?))
+}
+
+pub(crate) fn raw(data: &[u8]) -> PrepamsBuffer {
+    PrepamsBuffer::new(data.to_vec())
+}
+
+pub(crate) fn verification_key(data: &[u8]) -> Result<VerificationKey, Error> {
+    Ok(VerificationKey::try_from(data).map_err(PrepamsError::from)?)
+}
+
+// seeded generation is only used for evaluation, a null seed draws from the os
+pub(crate) unsafe fn rng(seed: *const u8) -> Result<ChaCha20Rng, Error> {
+    if seed.is_null() {
+        return ChaCha20Rng::from_rng(rand::thread_rng()).map_err(|e| Error::Prepams(PrepamsError::Encoding(e.to_string())));
+    }
+    let seed: [u8; 32] = bytes(seed, 32)?.try_into().expect("seed has 32 bytes");
+    Ok(ChaCha20Rng::from_seed(seed))
+}
+
+/// Releases a buffer returned by the library. Null buffers are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_buffer_free(buffer: PrepamsBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+    }
+}
+
+/// Returns the stable error code of the last call on this thread, e.g.
+/// `verification.inner_product`, or null if it succeeded. The string is owned
+/// by the library and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn prepams_last_error_code() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.code.as_ptr()))
+}
+
+/// Copies the NUL-terminated error message of the last call on this thread
+/// into `out`, truncated to `len` bytes, and returns the length of the full
+/// message without the terminator.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_last_error_message(out: *mut c_char, len: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        let message = last.as_ref().map_or("", |e| e.message.as_str()).as_bytes();
+        if !out.is_null() && len > 0 {
+            let n = message.len().min(len - 1);
+            ptr::copy_nonoverlapping(message.as_ptr(), out as *mut u8, n);
+            *out.add(n) = 0;
+        }
+        message.len()
+    })
+}
+
+/// Sets the deployment domain that messages are sealed for.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_set_domain(domain: *const c_char) -> PrepamsStatus {
+    call(|| {
+        envelope::set_domain(string(domain)?);
+        Ok(())
+    })
+}
+
+/// Converts a message into `postcard`, `json` or `cbor`.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_convert(data: *const u8, len: usize, format: *const c_char, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let format = Format::from_str(string(format)?)?;
+        write(out, PrepamsBuffer::new(format::transcode(bytes(data, len)?, format)?))
+    })
+}
+
+/// Verifies a participation proof.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_verify_participation(participation: *const u8, len: usize) -> PrepamsStatus {
+    call(|| {
+        input::<Participation>(bytes(participation, len)?)?.check()?;
+        Ok(())
+    })
+}
+
+/// Verifies the signatures and links of all ledger entries.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_verify_ledger(ledger: *const u8, len: usize, lvk: *const u8, lvk_len: usize) -> PrepamsStatus {
+    call(|| {
+        let vk = verification_key(bytes(lvk, lvk_len)?)?;
+        input::<Ledger>(bytes(ledger, len)?)?.check(&vk)?;
+        Ok(())
+    })
+}
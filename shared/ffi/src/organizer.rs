@@ -0,0 +1,71 @@
+use std::ffi::c_char;
+
+use prepams_shared::api::OrganizerCore;
+use prepams_shared::types::{Participation, Resource};
+
+use crate::*;
+
+/// Opaque organizer handle.
+pub struct PrepamsOrganizer {
+    core: OrganizerCore
+}
+
+/// Creates an organizer for the issuer public key. `seed` is null or points to
+/// 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_new(identity: *const c_char, issuer_public_key: *const u8, len: usize, seed: *const u8, out: *mut *mut PrepamsOrganizer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let core = OrganizerCore::new(string(identity)?, input(bytes(issuer_public_key, len)?)?, rng(seed)?);
+        write(out, Box::into_raw(Box::new(PrepamsOrganizer { core })))
+    })
+}
+
+/// Releases an organizer handle. Null handles are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_free(organizer: *mut PrepamsOrganizer) {
+    if !organizer.is_null() {
+        drop(Box::from_raw(organizer));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_serialize(organizer: *const PrepamsOrganizer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| write(out, output(&handle(organizer)?.core)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_deserialize(data: *const u8, len: usize, out: *mut *mut PrepamsOrganizer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let organizer = PrepamsOrganizer { core: input(bytes(data, len)?)? };
+        write(out, Box::into_raw(Box::new(organizer)))
+    })
+}
+
+/// Returns the raw 32 byte ed25519 key that signs studies and confirmations.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_public_key(organizer: *const PrepamsOrganizer, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let vk: [u8; 32] = handle(organizer)?.core.public_key().into();
+        write(out, raw(&vk))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_sign_resource(organizer: *const PrepamsOrganizer, resource: *const u8, len: usize, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let resource: Resource = input(bytes(resource, len)?)?;
+        write(out, output(&handle(organizer)?.core.sign_resource(&resource)?)?)
+    })
+}
+
+/// Confirms a participation under the organizer's participation id.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_organizer_confirm_participation(organizer: *const PrepamsOrganizer, participation: *const u8, len: usize, id: *const c_char, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let participation: Participation = input(bytes(participation, len)?)?;
+        let confirmed = handle(organizer)?.core.confirm_participation(&participation, string(id)?.to_string())?;
+        write(out, output(&confirmed)?)
+    })
+}
@@ -0,0 +1,134 @@
+use std::ffi::c_char;
+
+use prepams_shared::api::{BlindedSignature, ParticipantCore};
+use prepams_shared::types::{NullRequest, Resource};
+
+use crate::*;
+
+/// Opaque participant handle.
+pub struct PrepamsParticipant {
+    core: ParticipantCore
+}
+
+/// Creates a participant with `count` attribute values, trusting the raw
+/// ed25519 ledger verification key `lvk`.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_new(identity: *const c_char, attributes: *const u32, count: usize, lvk: *const u8, lvk_len: usize, out: *mut *mut PrepamsParticipant) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let attributes: &[u32] = match (attributes.is_null(), count) {
+            (true, 0) => &[],
+            (true, _) => Err(Error::NullPointer)?,
+            (false, _) => slice::from_raw_parts(attributes, count),
+        };
+        let core = ParticipantCore::new(string(identity)?, attributes, verification_key(bytes(lvk, lvk_len)?)?);
+        write(out, Box::into_raw(Box::new(PrepamsParticipant { core })))
+    })
+}
+
+/// Releases a participant handle. Null handles are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_free(participant: *mut PrepamsParticipant) {
+    if !participant.is_null() {
+        drop(Box::from_raw(participant));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_serialize(participant: *const PrepamsParticipant, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| write(out, output(&handle(participant)?.core)?))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_deserialize(data: *const u8, len: usize, out: *mut *mut PrepamsParticipant) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let participant = PrepamsParticipant { core: input(bytes(data, len)?)? };
+        write(out, Box::into_raw(Box::new(participant)))
+    })
+}
+
+/// Requests a credential from the issuer. `seed` is null or points to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_request_credential(participant: *mut PrepamsParticipant, issuer_public_key: *const u8, ipk_len: usize, verification_key: *const u8, cvk_len: usize, seed: *const u8, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        check_out(out)?;
+        let ipk = input(bytes(issuer_public_key, ipk_len)?)?;
+        let cvk = input(bytes(verification_key, cvk_len)?)?;
+        let request = handle_mut(participant)?.core.request_credential(ipk, cvk, rng(seed)?)?;
+        write(out, output(&request)?)
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_retrieve_credential(participant: *mut PrepamsParticipant, response: *const u8, len: usize) -> PrepamsStatus {
+    call(|| {
+        let response = input(bytes(response, len)?)?;
+        handle_mut(participant)?.core.retrieve_credential(&response)?;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_participate(participant: *const PrepamsParticipant, resource: *const u8, len: usize, out: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        let resource: Resource = input(bytes(resource, len)?)?;
+        write(out, output(&handle(participant)?.core.participate(&resource)?)?)
+    })
+}
+
+/// Verifies the ledger and writes the unspent balance of the participant.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_balance(participant: *const PrepamsParticipant, ledger: *const u8, len: usize, out: *mut u32) -> PrepamsStatus {
+    call(|| {
+        let (balance, _) = handle(participant)?.core.balance(&input(bytes(ledger, len)?)?)?;
+        write(out, balance)
+    })
+}
+
+/// Returns the blinding secret to keep and the request to send to the issuer.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_request_nulls(participant: *const PrepamsParticipant, secret: *mut PrepamsBuffer, request: *mut PrepamsBuffer) -> PrepamsStatus {
+    call(|| {
+        check_out(secret)?;
+        check_out(request)?;
+        let nulls = handle(participant)?.core.request_nulls()?;
+        let (nulls, requests) = (output(&nulls)?, output(&nulls.requests().to_vec())?);
+        secret.write(nulls);
+        request.write(requests);
+        Ok(())
+    })
+}
+
+/// Proves ownership of unspent rewards worth at least `amount` and returns the
+/// value of the spent rewards and the payout request.
+#[no_mangle]
+pub unsafe extern "C" fn prepams_participant_request_payout(
+    participant: *const PrepamsParticipant,
+    amount: u8,
+    target: *const c_char,
+    recipient: *const c_char,
+    secret: *const u8,
+    secret_len: usize,
+    nulls: *const u8,
+    nulls_len: usize,
+    ledger: *const u8,
+    ledger_len: usize,
+    costs: *mut u32,
+    out: *mut PrepamsBuffer
+) -> PrepamsStatus {
+    call(|| {
+        check_out(costs)?;
+        check_out(out)?;
+        let secret: NullRequest = input(bytes(secret, secret_len)?)?;
+        let nulls: Vec<BlindedSignature> = input(bytes(nulls, nulls_len)?)?;
+        let nulls = secret.unblind_nulls(nulls)?;
+
+        let ledger = input(bytes(ledger, ledger_len)?)?;
+        let request = handle(participant)?.core.request_payout(amount, string(target)?, string(recipient)?, nulls, &ledger)?;
+        let proof = output(&request.proof)?;
+        costs.write(request.costs);
+        out.write(proof);
+        Ok(())
+    })
+}
@@ -0,0 +1,123 @@
+// Drives a full issue -> participate -> reward -> payout cycle over the C ABI.
+//
+//   cc tests/harness.c -I include -L ../target/release -lprepams_ffi -o harness
+
+#include <stdio.h>
+#include <string.h>
+
+#include "prepams.h"
+
+#define CHECK(call) check(#call, call)
+#define EXPECT(status, call) expect(#call, status, call)
+
+static void check(const char *call, PrepamsStatus status) {
+    if (status != PREPAMS_STATUS_OK) {
+        char message[256];
+        prepams_last_error_message(message, sizeof(message));
+        fprintf(stderr, "%s failed with %d (%s): %s\n", call, status, prepams_last_error_code(), message);
+        exit(1);
+    }
+}
+
+static void expect(const char *call, PrepamsStatus expected, PrepamsStatus status) {
+    if (status != expected) {
+        fprintf(stderr, "%s returned %d instead of %d\n", call, status, expected);
+        exit(1);
+    }
+}
+
+static void assert_true(const char *what, bool value) {
+    if (!value) {
+        fprintf(stderr, "assertion failed: %s\n", what);
+        exit(1);
+    }
+}
+
+// study as JSON document, the id is a base64url encoded scalar
+static const char *STUDY =
+    "{\"type\":\"resource\",\"version\":1,\"domain\":\"harness\",\"payload\":{"
+    "\"id\":\"AQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHwA\","
+    "\"name\":\"survey\",\"summary\":\"\",\"description\":\"\",\"duration\":\"10 minutes\","
+    "\"reward\":2,\"webBased\":false,\"studyUrl\":null,\"qualifier\":[],\"disqualifier\":[],"
+    "\"constraints\":[{\"Range\":[0,18,99]}]}}";
+
+int main(void) {
+    PrepamsIssuer *issuer = NULL;
+    PrepamsOrganizer *organizer = NULL;
+    PrepamsParticipant *participant = NULL;
+    PrepamsBuffer ipk, cvk, lvk, okey, request, response, signed_study, participation, confirmed, entry, ledger;
+    PrepamsBuffer secret, nulls_request, nulls, payout, payout_entry, target;
+    const uint8_t *study = (const uint8_t *) STUDY;
+    uint32_t attributes[] = { 30, 1 };
+    uint32_t balance, costs;
+    bool valid;
+
+    CHECK(prepams_set_domain("harness"));
+
+    // setup
+    CHECK(prepams_issuer_new(2, NULL, &issuer));
+    CHECK(prepams_issuer_public_key(issuer, &ipk));
+    CHECK(prepams_issuer_verification_key(issuer, &cvk));
+    CHECK(prepams_issuer_ledger_verification_key(issuer, &lvk));
+    CHECK(prepams_organizer_new("lab", ipk.data, ipk.len, NULL, &organizer));
+    CHECK(prepams_organizer_public_key(organizer, &okey));
+    CHECK(prepams_participant_new("alice", attributes, 2, lvk.data, lvk.len, &participant));
+
+    // issue
+    CHECK(prepams_participant_request_credential(participant, ipk.data, ipk.len, cvk.data, cvk.len, NULL, &request));
+    CHECK(prepams_issuer_issue_credential(issuer, request.data, request.len, &response));
+    CHECK(prepams_participant_retrieve_credential(participant, response.data, response.len));
+
+    // participate
+    CHECK(prepams_organizer_sign_resource(organizer, study, strlen(STUDY), &signed_study));
+    CHECK(prepams_issuer_check_resource_signature(issuer, signed_study.data, signed_study.len, okey.data, okey.len, &valid));
+    assert_true("study signature is valid", valid);
+    CHECK(prepams_participant_participate(participant, study, strlen(STUDY), &participation));
+    CHECK(prepams_verify_participation(participation.data, participation.len));
+
+    // reward
+    CHECK(prepams_organizer_confirm_participation(organizer, participation.data, participation.len, "p1", &confirmed));
+    CHECK(prepams_issuer_issue_reward(issuer, confirmed.data, confirmed.len, okey.data, okey.len, 2, &entry));
+    EXPECT(PREPAMS_STATUS_REJECTED, prepams_issuer_issue_reward(issuer, confirmed.data, confirmed.len, okey.data, okey.len, 2, &entry));
+    CHECK(prepams_issuer_ledger(issuer, &ledger));
+    CHECK(prepams_verify_ledger(ledger.data, ledger.len, lvk.data, lvk.len));
+    CHECK(prepams_participant_balance(participant, ledger.data, ledger.len, &balance));
+    assert_true("balance is 2", balance == 2);
+
+    // payout
+    CHECK(prepams_participant_request_nulls(participant, &secret, &nulls_request));
+    CHECK(prepams_issuer_issue_nulls(issuer, nulls_request.data, nulls_request.len, &nulls));
+    CHECK(prepams_participant_request_payout(participant, 2, "bank", "alice", secret.data, secret.len, nulls.data, nulls.len, ledger.data, ledger.len, &costs, &payout));
+    assert_true("payout spends 2", costs == 2);
+    CHECK(prepams_issuer_check_payout_request(issuer, payout.data, payout.len, &payout_entry, &target, NULL));
+    assert_true("payout target is bank", target.len == 4 && memcmp(target.data, "bank", 4) == 0);
+
+    // coins are spent once
+    EXPECT(PREPAMS_STATUS_REJECTED, prepams_issuer_check_payout_request(issuer, payout.data, payout.len, NULL, NULL, NULL));
+    assert_true("error code is rejected", strcmp(prepams_last_error_code(), "rejected") == 0);
+
+    // errors
+    EXPECT(PREPAMS_STATUS_ENCODING, prepams_verify_participation((const uint8_t *) "PPMS", 4));
+    EXPECT(PREPAMS_STATUS_VERIFICATION, prepams_verify_ledger(ledger.data, ledger.len, okey.data, okey.len));
+    assert_true("error code is verification.ledger", strcmp(prepams_last_error_code(), "verification.ledger") == 0);
+    EXPECT(PREPAMS_STATUS_NULL_POINTER, prepams_issuer_ledger(issuer, NULL));
+    assert_true("error code is null_pointer", strcmp(prepams_last_error_code(), "null_pointer") == 0);
+
+    // the payout extends the ledger
+    prepams_buffer_free(ledger);
+    CHECK(prepams_issuer_ledger(issuer, &ledger));
+    CHECK(prepams_verify_ledger(ledger.data, ledger.len, lvk.data, lvk.len));
+    assert_true("error is cleared", prepams_last_error_code() == NULL);
+
+    PrepamsBuffer buffers[] = { ipk, cvk, lvk, okey, request, response, signed_study, participation, confirmed, entry, secret, nulls_request, nulls, payout, payout_entry, target };
+    for (size_t i = 0; i < sizeof(buffers) / sizeof(buffers[0]); i++) {
+        prepams_buffer_free(buffers[i]);
+    }
+    prepams_buffer_free(ledger);
+    prepams_participant_free(participant);
+    prepams_organizer_free(organizer);
+    prepams_issuer_free(issuer);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,59 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// exported functions declared in `src`
+fn exports(dir: &Path) -> Vec<String> {
+    let mut names = vec![];
+    for file in fs::read_dir(dir).unwrap() {
+        let source = fs::read_to_string(file.unwrap().path()).unwrap();
+        for line in source.lines() {
+            if let Some(rest) = line.strip_prefix("pub unsafe extern \"C\" fn ").or_else(|| line.strip_prefix("pub extern \"C\" fn ")) {
+                names.push(rest.split('(').next().unwrap().to_string());
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+#[test]
+fn header() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let header = fs::read_to_string(root.join("include/prepams.h")).unwrap();
+
+    // declarations are the only lines outside of comments naming a function
+    let mut declared: Vec<String> = header.lines()
+        .filter(|line| !line.trim_start().starts_with("//") && !line.trim_start().starts_with('*'))
+        .filter_map(|line| line.find("prepams_").map(|i| &line[i..]))
+        .filter_map(|name| name.split_once('(').map(|(name, _)| name.to_string()))
+        .collect();
+    declared.sort();
+
+    assert_eq!(declared, exports(&root.join("src")), "include/prepams.h is outdated");
+}
+
+#[test]
+fn harness() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // the test binary lives next to the library in `target/<profile>/deps`
+    let deps = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let exe = deps.join("prepams-harness");
+
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&cc)
+        .arg(root.join("tests/harness.c"))
+        .arg("-I").arg(root.join("include"))
+        .arg("-L").arg(&deps)
+        .arg(format!("-Wl,-rpath,{}", deps.display()))
+        .arg("-lprepams_ffi")
+        .arg("-o").arg(&exe)
+        .status()
+        .unwrap_or_else(|e| panic!("running {} failed: {}", cc, e));
+    assert!(status.success(), "compiling the harness failed");
+
+    let output = Command::new(&exe).output().unwrap();
+    assert!(output.status.success(), "harness failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,132 @@
+"""Python bindings of the PrePaMS protocol roles.
+
+Protocol messages are sealed ``bytes``. Inputs are accepted in any encoding
+(postcard, JSON or CBOR), outputs are sealed in the compact postcard encoding
+and can be converted with :func:`convert`. Keys and signatures of the ledger
+and organizers are raw ed25519 bytes.
+"""
+
+from typing import Any, Dict, List, Optional, Tuple
+
+class PrepamsError(Exception):
+    """Base class of all errors."""
+
+    code: str
+    """Stable error code, e.g. ``encoding`` or ``verification.inner_product``."""
+
+    check: Optional[str]
+    """Code of the failed check of a verification error."""
+
+class EncodingError(PrepamsError):
+    """An input could not be decoded or an output could not be encoded."""
+
+class VerificationError(PrepamsError):
+    """A proof or signature failed to verify."""
+
+class StateError(PrepamsError):
+    """The operation is not possible in the current state of the role."""
+
+class RejectedError(PrepamsError):
+    """A well-formed request was rejected by the protocol rules."""
+
+class StorageError(PrepamsError):
+    """The ledger store failed."""
+
+def set_domain(domain: str) -> None:
+    """Sets the deployment domain that messages are sealed for."""
+
+def domain() -> str:
+    """Returns the configured deployment domain."""
+
+def convert(data: bytes, format: str = "json") -> bytes:
+    """Converts a message into ``postcard``, ``json`` or ``cbor``."""
+
+def schema(kind: str) -> str:
+    """Returns the JSON schema of a message type, e.g. ``participation``."""
+
+def verify_participation(participation: bytes) -> None:
+    """Verifies a participation proof, raises :class:`VerificationError` otherwise."""
+
+def verify_ledger(ledger: bytes, ledger_verification_key: bytes) -> None:
+    """Verifies the signatures and links of all ledger entries."""
+
+def audit_ledger(ledger: bytes, ledger_verification_key: bytes) -> Dict[str, Any]:
+    """Audits a ledger and returns the report, see ``valid`` and ``anomalies``."""
+
+class PayoutReceipt:
+    """Ledger entry of an accepted payout together with the payout destination."""
+
+    @property
+    def target(self) -> str: ...
+    @property
+    def recipient(self) -> str: ...
+    @property
+    def entry(self) -> bytes: ...
+
+class Issuer:
+    def __init__(self, attributes: int, seed: Optional[bytes] = None) -> None: ...
+    @property
+    def attributes(self) -> int: ...
+    @property
+    def public_key(self) -> bytes: ...
+    @property
+    def verification_key(self) -> bytes: ...
+    @property
+    def ledger_verification_key(self) -> bytes: ...
+    @property
+    def ledger(self) -> bytes: ...
+    @property
+    def head(self) -> bytes: ...
+    def load(self, ledger: bytes) -> None:
+        """Replaces the ledger with a copy of ``ledger``."""
+    def serialize(self) -> bytes: ...
+    @staticmethod
+    def deserialize(data: bytes) -> "Issuer": ...
+    def issue_credential(self, request: bytes) -> bytes: ...
+    def check_resource_signature(self, resource: bytes, organizer_key: bytes) -> bool:
+        """Checks that a signed study was signed by the organizer key."""
+    def issue_reward(self, participation: bytes, organizer_key: bytes, reward: int) -> bytes:
+        """Rewards a confirmed participation and returns the new ledger entry."""
+    def issue_nulls(self, request: bytes) -> bytes: ...
+    def append_entry(self, entry: bytes) -> None:
+        """Appends an entry that was signed by this issuer."""
+    def check_payout_request(self, request: bytes) -> PayoutReceipt:
+        """Verifies a payout request and appends the payout to the ledger."""
+
+class Organizer:
+    def __init__(self, identity: str, issuer_public_key: bytes, seed: Optional[bytes] = None) -> None: ...
+    @property
+    def identity(self) -> str: ...
+    @property
+    def public_key(self) -> bytes: ...
+    @property
+    def issuer_public_key(self) -> bytes: ...
+    @issuer_public_key.setter
+    def issuer_public_key(self, issuer_public_key: bytes) -> None: ...
+    def serialize(self) -> bytes: ...
+    @staticmethod
+    def deserialize(data: bytes) -> "Organizer": ...
+    def sign_resource(self, resource: bytes) -> bytes:
+        """Signs a study, e.g. a JSON document following ``schema("resource")``."""
+    def confirm_participation(self, participation: bytes, id: str) -> bytes:
+        """Confirms a participation under the organizer's participation id."""
+
+class Participant:
+    def __init__(self, identity: str, attributes: List[int], ledger_verification_key: bytes) -> None: ...
+    @property
+    def identity(self) -> str: ...
+    @property
+    def attributes(self) -> List[int]: ...
+    def serialize(self) -> bytes: ...
+    @staticmethod
+    def deserialize(data: bytes) -> "Participant": ...
+    def request_credential(self, issuer_public_key: bytes, verification_key: bytes, seed: Optional[bytes] = None) -> bytes: ...
+    def retrieve_credential(self, response: bytes) -> None: ...
+    def participate(self, resource: bytes) -> bytes: ...
+    def balance(self, ledger: bytes) -> Tuple[int, List[str]]:
+        """Returns the unspent balance and the ids of the rewarded studies."""
+    def request_nulls(self) -> Tuple[bytes, bytes]:
+        """Returns the blinding secret to keep and the request to send to the issuer."""
+    def request_payout(self, amount: int, target: str, recipient: str, secret: bytes, nulls: bytes, ledger: bytes) -> Tuple[int, bytes]:
+        """Proves ownership of unspent rewards worth at least ``amount`` and
+        returns the value of the spent rewards and the payout request."""
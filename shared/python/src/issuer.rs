@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+
+use pyo3::prelude::*;
+
+use prepams_shared::api::{BlindedSignRequest, IssuerCore, PayoutProofData};
+use prepams_shared::types::{ConfirmedParticipation, SignedResource};
+
+use crate::{input, output, raise, rng, verification_key, Bytes};
+
+#[pyclass(module = "prepams", unsendable)]
+pub struct Issuer {
+    core: IssuerCore
+}
+
+/// Ledger entry of an accepted payout together with the payout destination.
+#[pyclass(module = "prepams", frozen)]
+pub struct PayoutReceipt {
+    #[pyo3(get)]
+    target: String,
+    #[pyo3(get)]
+    recipient: String,
+    entry: Vec<u8>
+}
+
+#[pymethods]
+impl PayoutReceipt {
+    #[getter]
+    fn entry(&self) -> Bytes {
+        Cow::Owned(self.entry.clone())
+    }
+}
+
+#[pymethods]
+impl Issuer {
+    #[new]
+    #[pyo3(signature = (attributes, seed = None))]
+    fn new(attributes: usize, seed: Option<&[u8]>) -> PyResult<Issuer> {
+        Ok(Issuer { core: IssuerCore::new(attributes, rng(seed)?) })
+    }
+
+    #[getter]
+    fn attributes(&self) -> usize {
+        self.core.attributes()
+    }
+
+    #[getter]
+    fn public_key(&self) -> PyResult<Bytes> {
+        output(self.core.public_key())
+    }
+
+    #[getter]
+    fn verification_key(&self) -> PyResult<Bytes> {
+        output(self.core.credit_verification_key())
+    }
+
+    #[getter]
+    fn ledger_verification_key(&self) -> Bytes {
+        let lvk: [u8; 32] = self.core.ledger_verification_key().into();
+        Cow::Owned(lvk.to_vec())
+    }
+
+    #[getter]
+    fn ledger(&self) -> PyResult<Bytes> {
+        output(&self.core.ledger().map_err(raise)?)
+    }
+
+    #[getter]
+    fn head(&self) -> Bytes {
+        Cow::Owned(self.core.head().to_bytes().to_vec())
+    }
+
+    /// Replaces the ledger with a copy of `ledger`.
+    fn load(&mut self, ledger: &[u8]) -> PyResult<()> {
+        self.core.load(input(ledger)?).map_err(raise)
+    }
+
+    fn serialize(&self) -> PyResult<Bytes> {
+        output(&self.core)
+    }
+
+    #[staticmethod]
+    fn deserialize(data: &[u8]) -> PyResult<Issuer> {
+        Ok(Issuer { core: input(data)? })
+    }
+
+    fn issue_credential(&self, request: &[u8]) -> PyResult<Bytes> {
+        output(&self.core.issue_credential(&input(request)?).map_err(raise)?)
+    }
+
+    /// Checks that a signed study was signed by the organizer key.
+    fn check_resource_signature(&self, resource: &[u8], organizer_key: &[u8]) -> PyResult<bool> {
+        let resource: SignedResource = input(resource)?;
+        self.core.check_resource_signature(&resource, &verification_key(organizer_key)?).map_err(raise)
+    }
+
+    /// Rewards a confirmed participation and returns the new ledger entry.
+    fn issue_reward(&mut self, participation: &[u8], organizer_key: &[u8], reward: u8) -> PyResult<Bytes> {
+        let participation: ConfirmedParticipation = input(participation)?;
+        let entry = self.core.issue_reward(&participation, &verification_key(organizer_key)?, reward).map_err(raise)?;
+        output(&entry)
+    }
+
+    fn issue_nulls(&self, request: &[u8]) -> PyResult<Bytes> {
+        let requests: Vec<BlindedSignRequest> = input(request)?;
+        output(&self.core.issue_nulls(&requests).map_err(raise)?)
+    }
+
+    /// Appends an entry that was signed by this issuer.
+    fn append_entry(&mut self, entry: &[u8]) -> PyResult<()> {
+        self.core.append_entry(input(entry)?).map_err(raise)
+    }
+
+    /// Verifies a payout request and appends the payout to the ledger.
+    fn check_payout_request(&mut self, request: &[u8]) -> PyResult<PayoutReceipt> {
+        let proof: PayoutProofData = input(request)?;
+        let receipt = self.core.check_payout_request(&proof).map_err(raise)?;
+
+        Ok(PayoutReceipt {
+            target: receipt.target,
+            recipient: receipt.recipient,
+            entry: output(&receipt.entry)?.into_owned()
+        })
+    }
+}
@@ -0,0 +1,154 @@
+//! Python bindings of the protocol roles.
+//!
+//! Like the JavaScript bindings, the classes exchange protocol messages as
+//! sealed `bytes`. Messages are accepted in any encoding, i.e. postcard, JSON
+//! or CBOR, and returned in the compact postcard encoding. Failures raise a
+//! subclass of `prepams.PrepamsError` carrying the stable error code.
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use ed25519_zebra::VerificationKey;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use prepams_shared::{audit, envelope, error, format, seal};
+use prepams_shared::format::Format;
+use prepams_shared::types::{Ledger, Participation};
+
+mod issuer;
+mod organizer;
+mod participant;
+
+create_exception!(prepams, PrepamsError, PyException, "Base class of all errors, `code` holds the stable error code.");
+create_exception!(prepams, EncodingError, PrepamsError, "An input could not be decoded or an output could not be encoded.");
+create_exception!(prepams, VerificationError, PrepamsError, "A proof or signature failed to verify, `check` names the failed check.");
+create_exception!(prepams, StateError, PrepamsError, "The operation is not possible in the current state of the role.");
+create_exception!(prepams, RejectedError, PrepamsError, "A well-formed request was rejected by the protocol rules.");
+create_exception!(prepams, StorageError, PrepamsError, "The ledger store failed.");
+
+type Bytes = Cow<'static, [u8]>;
+
+/// Converts an error of the native API into the matching Python exception.
+pub(crate) fn raise(e: error::PrepamsError) -> PyErr {
+    let message = e.to_string();
+    let err = match e {
+        error::PrepamsError::Encoding(_) => EncodingError::new_err(message),
+        error::PrepamsError::Verification(..) => VerificationError::new_err(message),
+        error::PrepamsError::State(_) => StateError::new_err(message),
+        error::PrepamsError::Rejected(_) => RejectedError::new_err(message),
+        error::PrepamsError::Storage(_) => StorageError::new_err(message),
+    };
+
+    Python::with_gil(|py| {
+        let value = err.value(py);
+        value.setattr("code", e.code())
+            .and_then(|_| value.setattr("check", e.check().map(|check| check.code())))
+            .map(|_| err.clone_ref(py))
+    }).unwrap_or(err)
+}
+
+pub(crate) fn invalid(msg: &str) -> PyErr {
+    raise(error::PrepamsError::Encoding(msg.to_string()))
+}
+
+/// Decodes a message of the configured domain in any encoding.
+pub(crate) fn input<T: envelope::Message>(data: &[u8]) -> PyResult<T> {
+    format::open_any(data).map_err(raise)
+}
+
+/// Seals a message in the postcard encoding.
+pub(crate) fn output<T: envelope::Message>(o: &T) -> PyResult<Bytes> {
+    seal(o).map(Cow::Owned).map_err(raise)
+}
+
+pub(crate) fn verification_key(data: &[u8]) -> PyResult<VerificationKey> {
+    VerificationKey::try_from(data).map_err(|e| raise(e.into()))
+}
+
+// seeded generation is only used for evaluation
+pub(crate) fn rng(seed: Option<&[u8]>) -> PyResult<ChaCha20Rng> {
+    match seed {
+        None => Ok(ChaCha20Rng::from_rng(rand::thread_rng()).map_err(|e| invalid(&e.to_string()))?),
+        Some(seed) => {
+            let seed: [u8; 32] = seed.try_into().map_err(|_| invalid("seed must be 32 bytes"))?;
+            Ok(ChaCha20Rng::from_seed(seed))
+        }
+    }
+}
+
+/// Sets the deployment domain that messages are sealed for.
+#[pyfunction]
+fn set_domain(domain: &str) {
+    envelope::set_domain(domain);
+}
+
+/// Returns the configured deployment domain.
+#[pyfunction]
+fn domain() -> String {
+    envelope::domain()
+}
+
+/// Converts a message into another encoding, e.g. JSON for inspection.
+#[pyfunction]
+#[pyo3(signature = (data, format = "json"))]
+fn convert(data: &[u8], format: &str) -> PyResult<Bytes> {
+    let format = Format::from_str(format).map_err(raise)?;
+    format::transcode(data, format).map(Cow::Owned).map_err(raise)
+}
+
+/// Returns the JSON schema of a message type, e.g. `participation`.
+#[pyfunction]
+fn schema(kind: &str) -> PyResult<String> {
+    let kind = envelope::Kind::from_id(kind).ok_or_else(|| invalid(&format!("unknown object type {:?}", kind)))?;
+    Ok(format::schema(kind).to_string())
+}
+
+/// Verifies a participation proof.
+#[pyfunction]
+fn verify_participation(participation: &[u8]) -> PyResult<()> {
+    input::<Participation>(participation)?.check().map_err(raise)
+}
+
+/// Verifies the signatures and links of all ledger entries.
+#[pyfunction]
+fn verify_ledger(ledger: &[u8], ledger_verification_key: &[u8]) -> PyResult<()> {
+    let vk = verification_key(ledger_verification_key)?;
+    input::<Ledger>(ledger)?.check(&vk).map_err(raise)
+}
+
+/// Audits a ledger and returns the report as dictionary.
+#[pyfunction]
+fn audit_ledger(py: Python<'_>, ledger: &[u8], ledger_verification_key: &[u8]) -> PyResult<PyObject> {
+    let vk = verification_key(ledger_verification_key)?;
+    let report = audit::audit(&input(ledger)?, &vk);
+    Ok(py.import("json")?.call_method1("loads", (report.to_json(),))?.unbind())
+}
+
+#[pymodule]
+fn prepams(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("PrepamsError", py.get_type::<PrepamsError>())?;
+    m.add("EncodingError", py.get_type::<EncodingError>())?;
+    m.add("VerificationError", py.get_type::<VerificationError>())?;
+    m.add("StateError", py.get_type::<StateError>())?;
+    m.add("RejectedError", py.get_type::<RejectedError>())?;
+    m.add("StorageError", py.get_type::<StorageError>())?;
+
+    m.add_class::<issuer::Issuer>()?;
+    m.add_class::<issuer::PayoutReceipt>()?;
+    m.add_class::<organizer::Organizer>()?;
+    m.add_class::<participant::Participant>()?;
+
+    m.add_function(wrap_pyfunction!(set_domain, m)?)?;
+    m.add_function(wrap_pyfunction!(domain, m)?)?;
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    m.add_function(wrap_pyfunction!(schema, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_participation, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_ledger, m)?)?;
+    m.add_function(wrap_pyfunction!(audit_ledger, m)?)?;
+    Ok(())
+}
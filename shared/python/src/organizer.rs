@@ -0,0 +1,64 @@
+use std::borrow::Cow;
+
+use pyo3::prelude::*;
+
+use prepams_shared::api::OrganizerCore;
+use prepams_shared::types::{Participation, Resource};
+
+use crate::{input, output, raise, rng, Bytes};
+
+#[pyclass(module = "prepams")]
+pub struct Organizer {
+    core: OrganizerCore
+}
+
+#[pymethods]
+impl Organizer {
+    #[new]
+    #[pyo3(signature = (identity, issuer_public_key, seed = None))]
+    fn new(identity: &str, issuer_public_key: &[u8], seed: Option<&[u8]>) -> PyResult<Organizer> {
+        Ok(Organizer { core: OrganizerCore::new(identity, input(issuer_public_key)?, rng(seed)?) })
+    }
+
+    #[getter]
+    fn identity(&self) -> String {
+        self.core.identity().to_string()
+    }
+
+    #[getter]
+    fn public_key(&self) -> Bytes {
+        let vk: [u8; 32] = self.core.public_key().into();
+        Cow::Owned(vk.to_vec())
+    }
+
+    #[getter]
+    fn issuer_public_key(&self) -> PyResult<Bytes> {
+        output(self.core.issuer_public_key())
+    }
+
+    #[setter]
+    fn set_issuer_public_key(&mut self, issuer_public_key: &[u8]) -> PyResult<()> {
+        self.core.set_issuer_public_key(input(issuer_public_key)?);
+        Ok(())
+    }
+
+    fn serialize(&self) -> PyResult<Bytes> {
+        output(&self.core)
+    }
+
+    #[staticmethod]
+    fn deserialize(data: &[u8]) -> PyResult<Organizer> {
+        Ok(Organizer { core: input(data)? })
+    }
+
+    fn sign_resource(&self, resource: &[u8]) -> PyResult<Bytes> {
+        let resource: Resource = input(resource)?;
+        output(&self.core.sign_resource(&resource).map_err(raise)?)
+    }
+
+    /// Confirms a participation under the organizer's participation id.
+    fn confirm_participation(&self, participation: &[u8], id: String) -> PyResult<Bytes> {
+        let participation: Participation = input(participation)?;
+        output(&self.core.confirm_participation(&participation, id).map_err(raise)?)
+    }
+}
@@ -0,0 +1,76 @@
+use pyo3::prelude::*;
+
+use prepams_shared::api::{BlindedSignature, ParticipantCore};
+use prepams_shared::types::{NullRequest, Resource};
+
+use crate::{input, output, raise, rng, verification_key, Bytes};
+
+#[pyclass(module = "prepams")]
+pub struct Participant {
+    core: ParticipantCore
+}
+
+#[pymethods]
+impl Participant {
+    #[new]
+    fn new(identity: &str, attributes: Vec<u32>, ledger_verification_key: &[u8]) -> PyResult<Participant> {
+        Ok(Participant { core: ParticipantCore::new(identity, &attributes, verification_key(ledger_verification_key)?) })
+    }
+
+    #[getter]
+    fn identity(&self) -> String {
+        self.core.identity().to_string()
+    }
+
+    #[getter]
+    fn attributes(&self) -> Vec<u32> {
+        self.core.attributes().to_vec()
+    }
+
+    fn serialize(&self) -> PyResult<Bytes> {
+        output(&self.core)
+    }
+
+    #[staticmethod]
+    fn deserialize(data: &[u8]) -> PyResult<Participant> {
+        Ok(Participant { core: input(data)? })
+    }
+
+    #[pyo3(signature = (issuer_public_key, verification_key, seed = None))]
+    fn request_credential(&mut self, issuer_public_key: &[u8], verification_key: &[u8], seed: Option<&[u8]>) -> PyResult<Bytes> {
+        let request = self.core.request_credential(input(issuer_public_key)?, input(verification_key)?, rng(seed)?).map_err(raise)?;
+        output(&request)
+    }
+
+    fn retrieve_credential(&mut self, response: &[u8]) -> PyResult<()> {
+        self.core.retrieve_credential(&input(response)?).map_err(raise)
+    }
+
+    fn participate(&self, resource: &[u8]) -> PyResult<Bytes> {
+        let resource: Resource = input(resource)?;
+        output(&self.core.participate(&resource).map_err(raise)?)
+    }
+
+    /// Returns the unspent balance and the ids of the rewarded studies.
+    fn balance(&self, ledger: &[u8]) -> PyResult<(u32, Vec<String>)> {
+        let (balance, studies) = self.core.balance(&input(ledger)?).map_err(raise)?;
+        Ok((balance, studies.iter().map(|s| base64::encode_config(s.to_bytes(), base64::URL_SAFE_NO_PAD)).collect()))
+    }
+
+    /// Returns the blinding secret to keep and the request to send to the issuer.
+    fn request_nulls(&self) -> PyResult<(Bytes, Bytes)> {
+        let nulls = self.core.request_nulls().map_err(raise)?;
+        Ok((output(&nulls)?, output(&nulls.requests().to_vec())?))
+    }
+
+    /// Proves ownership of unspent rewards worth at least `amount` and
+    /// returns the value of the spent rewards and the payout request.
+    fn request_payout(&self, amount: u8, target: &str, recipient: &str, secret: &[u8], nulls: &[u8], ledger: &[u8]) -> PyResult<(u32, Bytes)> {
+        let secret: NullRequest = input(secret)?;
+        let nulls: Vec<BlindedSignature> = input(nulls)?;
+        let nulls = secret.unblind_nulls(nulls).map_err(raise)?;
+
+        let request = self.core.request_payout(amount, target, recipient, nulls, &input(ledger)?).map_err(raise)?;
+        Ok((request.costs, output(&request.proof)?))
+    }
+}
@@ -0,0 +1,116 @@
+import json
+import os
+import unittest
+from base64 import urlsafe_b64encode
+
+import prepams
+
+
+def study(reward):
+    # scalars are 32 bytes little-endian, the zero top byte keeps them below the modulus
+    id = urlsafe_b64encode(os.urandom(31) + b"\x00").rstrip(b"=").decode()
+    return json.dumps({
+        "type": "resource",
+        "version": 1,
+        "domain": prepams.domain(),
+        "payload": {
+            "id": id,
+            "name": "survey",
+            "summary": "",
+            "description": "",
+            "duration": "10 minutes",
+            "reward": reward,
+            "webBased": False,
+            "studyUrl": None,
+            "qualifier": [],
+            "disqualifier": [],
+            "constraints": [{"Range": [0, 18, 99]}],
+        },
+    }).encode()
+
+
+class ProtocolTest(unittest.TestCase):
+    def setUp(self):
+        prepams.set_domain("prepams")
+        self.issuer = prepams.Issuer(2)
+        self.organizer = prepams.Organizer("lab", self.issuer.public_key)
+        self.participant = prepams.Participant("alice", [30, 1], self.issuer.ledger_verification_key)
+
+        request = self.participant.request_credential(self.issuer.public_key, self.issuer.verification_key)
+        self.participant.retrieve_credential(self.issuer.issue_credential(request))
+
+    def test_protocol(self):
+        resource = study(2)
+        signed = self.organizer.sign_resource(resource)
+        self.assertTrue(self.issuer.check_resource_signature(signed, self.organizer.public_key))
+
+        participation = self.participant.participate(resource)
+        prepams.verify_participation(participation)
+        confirmed = self.organizer.confirm_participation(participation, "p1")
+        self.issuer.issue_reward(confirmed, self.organizer.public_key, 2)
+
+        ledger = self.issuer.ledger
+        lvk = self.issuer.ledger_verification_key
+        prepams.verify_ledger(ledger, lvk)
+        self.assertEqual(self.participant.balance(ledger)[0], 2)
+
+        secret, request = self.participant.request_nulls()
+        nulls = self.issuer.issue_nulls(request)
+        costs, payout = self.participant.request_payout(2, "bank", "alice", secret, nulls, ledger)
+        self.assertEqual(costs, 2)
+
+        receipt = self.issuer.check_payout_request(payout)
+        self.assertEqual((receipt.target, receipt.recipient), ("bank", "alice"))
+
+        with self.assertRaises(prepams.RejectedError) as e:
+            self.issuer.check_payout_request(payout)
+        self.assertEqual(e.exception.code, "rejected")
+
+        report = prepams.audit_ledger(self.issuer.ledger, lvk)
+        self.assertTrue(report["valid"])
+        self.assertEqual((report["entries"], report["issued"], report["paid"]), (2, 2, 2))
+
+        # the ledger is stored separately from the issuer state
+        issuer = prepams.Issuer.deserialize(self.issuer.serialize())
+        issuer.load(self.issuer.ledger)
+        self.assertEqual(issuer.head, self.issuer.head)
+
+    def test_errors(self):
+        confirmed = self.organizer.confirm_participation(self.participant.participate(study(1)), "p1")
+        self.issuer.issue_reward(confirmed, self.organizer.public_key, 1)
+
+        with self.assertRaises(prepams.VerificationError) as e:
+            prepams.verify_ledger(self.issuer.ledger, prepams.Issuer(2).ledger_verification_key)
+        self.assertEqual(e.exception.code, "verification.ledger")
+        self.assertEqual(e.exception.check, "verification.ledger")
+
+        # the ledger head is covered by the check
+        ledger = json.loads(prepams.convert(self.issuer.ledger))
+        ledger["payload"]["head"] = [0] * 64
+        with self.assertRaises(prepams.VerificationError):
+            prepams.verify_ledger(json.dumps(ledger).encode(), self.issuer.ledger_verification_key)
+
+        with self.assertRaises(prepams.EncodingError) as e:
+            prepams.verify_participation(b"PPMS")
+        self.assertIsNone(e.exception.check)
+        self.assertIsInstance(e.exception, prepams.PrepamsError)
+
+        with self.assertRaises(prepams.StateError):
+            prepams.Participant("bob", [30, 1], self.issuer.ledger_verification_key).participate(study(1))
+
+        participation = self.participant.participate(study(1))
+        prepams.set_domain("other")
+        with self.assertRaises(prepams.RejectedError):
+            prepams.verify_participation(participation)
+
+    def test_encodings(self):
+        participation = self.participant.participate(study(1))
+        document = json.loads(prepams.convert(participation, "json"))
+        self.assertEqual(document["type"], "participation")
+        prepams.verify_participation(prepams.convert(participation, "cbor"))
+        self.assertEqual(prepams.convert(json.dumps(document).encode(), "postcard"), participation)
+        self.assertEqual(json.loads(prepams.schema("participation"))["properties"]["type"]["const"], "participation")
+
+
+if __name__ == "__main__":
+    unittest.main()
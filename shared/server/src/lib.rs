@@ -0,0 +1,497 @@
+//! Reference HTTP server of the PrePaMS issuer.
+//!
+//! Serves the `/api` endpoints of the Node backend directly over
+//! [`IssuerCore`], so that all protocol checks run in the shared library and
+//! the full stack can be run on localhost. The ledger and the [`records`] of
+//! users, studies and pending participations are kept either in a directory
+//! of files or in a single SQLite database.
+
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use ed25519_zebra::{Signature, VerificationKey};
+use rand::{CryptoRng, RngCore};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response};
+
+use prepams_shared::{format, seal, open, PrepamsError};
+use prepams_shared::api::*;
+use prepams_shared::storage::{FileStore, SqliteStore, StorageError};
+use prepams_shared::types::*;
+
+pub mod records;
+
+use records::{FileRecords, Records, SqliteRecords, Study, User};
+
+/// Largest accepted request body.
+pub const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+/// Number of ledger entries returned by `/api/ledger?after=` without a limit.
+pub const DEFAULT_LIMIT: u64 = 100;
+
+pub struct Config {
+    /// Sealed issuer state created by [`init`].
+    pub state: PathBuf,
+    /// Data directory, or a SQLite database if the path ends in `.sqlite` or `.db`.
+    pub data: PathBuf,
+    /// Attribute definitions served to the frontend, one per credential attribute.
+    pub attributes: Value,
+    /// Public URL of the frontend, used in participation links.
+    pub app_url: String,
+}
+
+/// Error of a request, carrying the HTTP status and a stable error code.
+#[derive(Debug)]
+pub struct Error {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Error {
+    fn new(status: u16, code: &'static str, message: &str) -> Error {
+        Error { status, code, message: message.to_string() }
+    }
+
+    fn bad_request(message: &str) -> Error {
+        Error::new(400, "bad_request", message)
+    }
+
+    fn not_found(message: &str) -> Error {
+        Error::new(404, "not_found", message)
+    }
+
+    fn conflict(message: &str) -> Error {
+        Error::new(409, "conflict", message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<PrepamsError> for Error {
+    fn from(e: PrepamsError) -> Self {
+        let status = match e {
+            PrepamsError::Encoding(_) | PrepamsError::Verification(..) | PrepamsError::State(_) => 400,
+            PrepamsError::Rejected(_) => 409,
+            PrepamsError::Storage(_) => 500,
+        };
+        Error { status, code: e.code(), message: e.to_string() }
+    }
+}
+
+impl From<StorageError> for Error {
+    fn from(e: StorageError) -> Self {
+        PrepamsError::from(e).into()
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        StorageError::from(e).into()
+    }
+}
+
+/// Response to a request.
+pub struct Reply {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    fn binary(body: Vec<u8>) -> Reply {
+        Reply { status: 200, content_type: "application/octet-stream", body }
+    }
+
+    fn message<T: prepams_shared::envelope::Message>(value: &T) -> Result<Reply, Error> {
+        Ok(Reply::binary(seal(value)?))
+    }
+
+    fn json<T: Serialize>(status: u16, value: &T) -> Reply {
+        let body = serde_json::to_vec(value).expect("JSON values are serializable");
+        Reply { status, content_type: "application/json", body }
+    }
+
+    fn error(e: &Error) -> Reply {
+        Reply::json(e.status, &json!({ "error": e.message, "code": e.code }))
+    }
+}
+
+/// Creates new issuer keys for `attributes` credential attributes and writes
+/// the sealed issuer state to `state`.
+pub fn init(state: &Path, attributes: usize, rng: impl RngCore + CryptoRng) -> Result<IssuerCore, Error> {
+    if state.exists() {
+        return Err(Error::conflict(&format!("{} already exists", state.display())));
+    }
+
+    let issuer = IssuerCore::new(attributes, rng);
+    fs::write(state, seal(&issuer)?)?;
+    Ok(issuer)
+}
+
+fn is_database(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("sqlite" | "db"))
+}
+
+fn base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn random_id() -> String {
+    let mut id = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut id);
+    base64url(&id)
+}
+
+// decodes a percent-encoded query component
+fn unescape(s: &str) -> String {
+    fn hex(b: u8) -> Option<u8> {
+        (b as char).to_digit(16).map(|d| d as u8)
+    }
+
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1).copied().and_then(hex), bytes.get(i + 2).copied().and_then(hex)) {
+            (b'+', _, _) => out.push(b' '),
+            (b'%', Some(high), Some(low)) => {
+                out.push(high << 4 | low);
+                i += 2;
+            },
+            (b, _, _) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+struct Query(Vec<(String, String)>);
+
+impl Query {
+    fn parse(query: &str) -> Query {
+        Query(query.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((k, v)) => (unescape(k), unescape(v)),
+                None => (unescape(pair), String::new()),
+            })
+            .collect())
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    fn require(&self, key: &str) -> Result<&str, Error> {
+        self.get(key).ok_or_else(|| Error::bad_request(&format!("missing query parameter {}", key)))
+    }
+
+    fn number(&self, key: &str) -> Result<Option<u64>, Error> {
+        self.get(key)
+            .map(|v| v.parse().map_err(|_| Error::bad_request(&format!("query parameter {} is not a number", key))))
+            .transpose()
+    }
+}
+
+fn organizer_key(user: &User) -> Result<VerificationKey, Error> {
+    let key = user.public_key.as_deref().ok_or_else(|| Error::bad_request("user is not an organizer"))?;
+    VerificationKey::try_from(key).map_err(|_| Error::bad_request("invalid organizer key"))
+}
+
+// study in the row format of the Node backend
+fn study_row(study: &SignedResource) -> Value {
+    let resource = study.resource();
+    let constraints: Vec<_> = resource.attribute_constraints().iter().map(AttributeConstraint::describe).collect();
+
+    json!({
+        "id": resource.id(),
+        "name": resource.name(),
+        "owner": study.owner(),
+        "abstract": resource.summary(),
+        "description": resource.description(),
+        "duration": resource.duration(),
+        "reward": resource.reward(),
+        "qualifier": resource.qualifier_ids(),
+        "disqualifier": resource.disqualifier_ids(),
+        "constraints": constraints,
+        "webBased": resource.webBased(),
+        "studyURL": resource.studyUrl(),
+        "signature": base64::encode(study.signature()),
+    })
+}
+
+/// Issuer behind the HTTP API.
+///
+/// The issuer is not `Send`, so a server is created and run on the same thread.
+pub struct Server {
+    issuer: IssuerCore,
+    records: Box<dyn Records>,
+    attributes: Value,
+    app_url: String,
+}
+
+impl Server {
+    /// Loads the issuer state and opens the ledger and records in `config.data`.
+    pub fn open(config: &Config) -> Result<Server, Error> {
+        let issuer: IssuerCore = open(&fs::read(&config.state)?)?;
+        match config.attributes.as_array() {
+            Some(attributes) if attributes.len() == issuer.attributes() => {},
+            _ => return Err(Error::bad_request(&format!("issuer expects {} attribute definitions", issuer.attributes()))),
+        }
+
+        let (issuer, records): (IssuerCore, Box<dyn Records>) = if is_database(&config.data) {
+            (issuer.with_store(SqliteStore::open(&config.data)?)?, Box::new(SqliteRecords::open(&config.data)?))
+        } else {
+            fs::create_dir_all(&config.data)?;
+            (issuer.with_store(FileStore::open(config.data.join("ledger"))?)?, Box::new(FileRecords::open(config.data.join("records.json"))?))
+        };
+
+        Ok(Server {
+            issuer,
+            records,
+            attributes: config.attributes.clone(),
+            app_url: config.app_url.clone(),
+        })
+    }
+
+    pub fn issuer(&self) -> &IssuerCore {
+        &self.issuer
+    }
+
+    /// Answers requests until the listener is closed.
+    pub fn serve(mut self, http: tiny_http::Server) {
+        for request in http.incoming_requests() {
+            self.respond(request);
+        }
+    }
+
+    fn respond(&mut self, mut request: Request) {
+        let mut body = vec![];
+        let reply = match request.as_reader().take(MAX_BODY + 1).read_to_end(&mut body) {
+            Ok(_) if body.len() as u64 > MAX_BODY => Reply::error(&Error::new(413, "bad_request", "request body too large")),
+            Ok(_) => self.handle(request.method(), request.url(), &body),
+            Err(e) => Reply::error(&Error::bad_request(&e.to_string())),
+        };
+        eprintln!("{} {} {}", request.method(), request.url(), reply.status);
+
+        let header = Header::from_bytes("Content-Type", reply.content_type).expect("content type is a valid header");
+        let response = Response::from_data(reply.body).with_status_code(reply.status).with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("could not send the response: {}", e);
+        }
+    }
+
+    /// Answers a single request to `url`, i.e. a path with an optional query.
+    pub fn handle(&mut self, method: &Method, url: &str, body: &[u8]) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let segments: Vec<String> = path.trim_matches('/').split('/').map(unescape).collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+        self.route(method, &segments, &Query::parse(query), body).unwrap_or_else(|e| Reply::error(&e))
+    }
+
+    fn route(&mut self, method: &Method, path: &[&str], query: &Query, body: &[u8]) -> Result<Reply, Error> {
+        match (method, path) {
+            (Method::Get, ["api", "issuer", "attributes"]) => Ok(Reply::json(200, &self.attributes)),
+            (Method::Get, ["api", "issuer", "pk"]) => Reply::message(self.issuer.public_key()),
+            (Method::Get, ["api", "issuer", "vk"]) => Reply::message(self.issuer.credit_verification_key()),
+            (Method::Get, ["api", "ledger", "vk"]) => Ok(Reply::binary(self.issuer.ledger_verification_key().as_ref().to_vec())),
+            (Method::Get, ["api", "ledger"]) => self.ledger(query),
+            (Method::Post, ["api", "nulls"]) => {
+                let requests: Vec<BlindedSignRequest> = format::open_any(body)?;
+                Reply::message(&self.issuer.issue_nulls(&requests)?)
+            },
+            (Method::Post, ["api", "auth", "signup"]) => self.signup(query, body),
+            (Method::Get, ["api", "auth", "signin"]) => self.signin(query),
+            (Method::Get, ["api", "studies"]) => self.studies(query.get("id")),
+            (Method::Post, ["api", "studies"]) => self.create_study(body),
+            (Method::Get, ["api", "participations"]) => {
+                let participations: Vec<Value> = self.records.participations()?.iter()
+                    .map(|(id, data)| json!({ "id": id, "data": base64::encode(data) }))
+                    .collect();
+                Ok(Reply::json(200, &participations))
+            },
+            (Method::Post, ["api", "participations"]) => {
+                let id = random_id();
+                self.records.add_participation(&id, body)?;
+                Ok(Reply::json(201, &json!({ "ok": true, "id": id, "url": format!("{}/participation/{}", self.app_url, id) })))
+            },
+            (Method::Get, ["api", "participations", id]) => match self.records.participation(id)? {
+                Some(data) => Ok(Reply::binary(data)),
+                None => Err(Error::not_found("participation does not exist")),
+            },
+            (Method::Get, ["api", "rewards"]) => self.rewards(None),
+            (Method::Get, ["api", "rewards", study]) => self.rewards(Some(study)),
+            (Method::Post, ["api", "rewards"]) => self.reward(body),
+            (Method::Post, ["api", "payout"]) => self.payout(body),
+            _ => Err(Error::not_found("not found")),
+        }
+    }
+
+    // the full ledger, or a slice of it if `from` or `after` is given
+    fn ledger(&self, query: &Query) -> Result<Reply, Error> {
+        if query.get("from").is_none() && query.get("after").is_none() {
+            return Reply::message(&self.issuer.ledger()?);
+        }
+
+        let filter = LedgerFilter::parse(query.get("type"), query.get("study"))?;
+        let slice = match query.get("after") {
+            Some(after) => {
+                let head = base64::decode_config(after, base64::URL_SAFE_NO_PAD).ok()
+                    .and_then(|head| Signature::try_from(head.as_slice()).ok())
+                    .ok_or_else(|| Error::bad_request("invalid ledger head"))?;
+                self.issuer.export_after(&head, query.number("limit")?.unwrap_or(DEFAULT_LIMIT), &filter)?
+            },
+            None => {
+                let from = query.number("from")?.unwrap_or(0);
+                self.issuer.export_range(from, query.number("to")?.unwrap_or(u64::MAX), query.number("limit")?, &filter)?
+            },
+        };
+        Reply::message(&slice)
+    }
+
+    fn signup(&mut self, query: &Query, body: &[u8]) -> Result<Reply, Error> {
+        let id = query.require("id")?;
+        if self.records.user(id)?.is_some() {
+            return Err(Error::conflict("id already registered"));
+        }
+
+        match query.require("role")? {
+            "participant" => {
+                let request: IssueRequest = format::open_any(body)?;
+                let response = seal(&self.issuer.issue_credential(&request)?)?;
+                self.records.add_user(&User { id: id.to_string(), role: "participant".to_string(), public_key: None })?;
+                self.records.add_issued(&response)?;
+                Ok(Reply::binary(response))
+            },
+            "organizer" => {
+                VerificationKey::try_from(body).map_err(|_| Error::bad_request("invalid organizer key"))?;
+                self.records.add_user(&User { id: id.to_string(), role: "organizer".to_string(), public_key: Some(body.to_vec()) })?;
+                Ok(Reply::json(200, &json!({ "ok": true })))
+            },
+            _ => Err(Error::bad_request("unknown role")),
+        }
+    }
+
+    fn signin(&self, query: &Query) -> Result<Reply, Error> {
+        let lvk: [u8; 32] = self.issuer.ledger_verification_key().into();
+        let mut response = json!({
+            "issuer": {
+                "pk": base64::encode(seal(self.issuer.public_key())?),
+                "vk": base64::encode(seal(self.issuer.credit_verification_key())?),
+            },
+            "ledger": {
+                "vk": base64::encode(lvk),
+            },
+        });
+
+        if query.get("role") == Some("participant") {
+            response["log"] = self.records.issued()?.iter().map(base64::encode).collect();
+        } else {
+            response["publicKeys"] = self.records.users()?.iter()
+                .filter_map(|user| user.public_key.as_ref().map(base64::encode))
+                .collect();
+        }
+
+        Ok(Reply::json(200, &response))
+    }
+
+    fn studies(&self, owner: Option<&str>) -> Result<Reply, Error> {
+        let rows = self.records.studies(owner)?.iter()
+            .map(|study| Ok(study_row(&open(&study.resource)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Reply::json(200, &rows))
+    }
+
+    fn create_study(&mut self, body: &[u8]) -> Result<Reply, Error> {
+        let study: SignedResource = format::open_any(body)?;
+        let owner = self.records.user(&study.owner())?.ok_or_else(|| Error::bad_request("unknown study owner"))?;
+
+        if !self.issuer.check_resource_signature(&study, &organizer_key(&owner)?)? {
+            return Err(Error::bad_request("signature not valid"));
+        }
+
+        let id = study.resource().id();
+        if self.records.study(&id)?.is_some() {
+            return Err(Error::conflict("study already exists"));
+        }
+
+        self.records.add_study(&Study { id: id.clone(), owner: owner.id, resource: seal(&study)? })?;
+        Ok(Reply::json(201, &json!({ "ok": true, "id": id })))
+    }
+
+    fn rewards(&self, study: Option<&str>) -> Result<Reply, Error> {
+        let mut transactions = vec![];
+        for entry in self.issuer.entries() {
+            if let Some(transaction) = entry?.transaction() {
+                let participation = transaction.participation();
+                if study.is_none_or(|study| study == participation.study()) {
+                    transactions.push(json!({
+                        "value": participation.value(),
+                        "study": participation.study(),
+                        "tag": participation.tag(),
+                        "coin": base64::encode(seal(transaction.reward_coin())?),
+                    }));
+                }
+            }
+        }
+
+        Ok(Reply::json(200, &json!({ "transactions": transactions })))
+    }
+
+    // rewards a confirmed participation with the reward of its signed study
+    fn reward(&mut self, body: &[u8]) -> Result<Reply, Error> {
+        let confirmed: ConfirmedParticipation = format::open_any(body)?;
+        let study = self.records.study(&confirmed.study())?.ok_or_else(|| Error::bad_request("unknown study"))?;
+        let owner = self.records.user(&study.owner)?.ok_or_else(|| Error::bad_request("unknown study owner"))?;
+        let reward = open::<SignedResource>(&study.resource)?.resource().reward();
+
+        if self.records.participation(&confirmed.id())?.is_none() {
+            return Err(Error::bad_request("participation does not exist"));
+        }
+
+        let entry = self.issuer.issue_reward(&confirmed, &organizer_key(&owner)?, reward)?;
+        self.records.remove_participation(&confirmed.id())?;
+        Reply::message(&entry)
+    }
+
+    fn payout(&mut self, body: &[u8]) -> Result<Reply, Error> {
+        let proof: PayoutProofData = format::open_any(body)?;
+        let receipt = self.issuer.check_payout_request(&proof)?;
+        let payout = receipt.entry.payout().ok_or_else(|| PrepamsError::State("payout entry is missing its payout".to_string()))?;
+
+        Ok(Reply::json(200, &json!({
+            "receipt": base64::encode(seal(&payout)?),
+            "target": receipt.target,
+            "recipient": receipt.recipient,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query() {
+        let query = Query::parse("from=3&study=a%2Bb&type=&limit=x&flag");
+        assert_eq!(query.number("from").unwrap(), Some(3));
+        assert_eq!(query.get("study"), Some("a+b"));
+        assert_eq!(query.get("type"), Some(""));
+        assert_eq!(query.get("flag"), Some(""));
+        assert!(query.number("limit").is_err());
+        assert!(query.require("to").is_err());
+        assert_eq!(unescape("100%"), "100%");
+        assert_eq!(unescape("%zz%41"), "%zzA");
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde_json::Value;
+
+use prepams_shared::envelope;
+use prepams_server::{Config, Server};
+
+#[derive(Parser)]
+#[command(name = "prepams-server", version, about = "Serve the PrePaMS issuer API over HTTP")]
+struct Cli {
+    /// Deployment domain that messages are sealed for
+    #[arg(long, default_value = envelope::DEFAULT_DOMAIN)]
+    domain: String,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// Issuer secret state
+    #[arg(long, default_value = "issuer.state")]
+    state: PathBuf,
+
+    /// Data directory, or a SQLite database if the path ends in `.sqlite` or `.db`
+    #[arg(long, default_value = "data")]
+    data: PathBuf,
+
+    /// Attribute definitions served to the frontend, a JSON array like the
+    /// `ATTRIBUTES` of the Node backend
+    #[arg(long, default_value = "attributes.json")]
+    attributes: PathBuf,
+
+    /// Public URL of the frontend, used in participation links
+    #[arg(long, default_value = "")]
+    app_url: String,
+
+    /// Create new issuer keys for the attribute definitions and exit
+    #[arg(long)]
+    init: bool,
+
+    /// 32 byte seed (base64url) for reproducible keys with --init
+    #[arg(long, requires = "init")]
+    seed: Option<String>,
+}
+
+fn rng(seed: &Option<String>) -> Result<ChaCha20Rng, String> {
+    match seed {
+        Some(seed) => {
+            let seed = base64::decode_config(seed, base64::URL_SAFE_NO_PAD).map_err(|e| format!("invalid base64: {}", e))?;
+            let seed: [u8; 32] = seed.try_into().map_err(|_| "seed must be 32 bytes".to_string())?;
+            Ok(ChaCha20Rng::from_seed(seed))
+        },
+        None => Ok(ChaCha20Rng::from_entropy())
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let attributes = fs::read(&cli.attributes).map_err(|e| format!("could not read {}: {}", cli.attributes.display(), e))?;
+    let attributes: Value = serde_json::from_slice(&attributes).map_err(|e| format!("could not parse {}: {}", cli.attributes.display(), e))?;
+
+    if cli.init {
+        let count = attributes.as_array().ok_or("attribute definitions must be a JSON array")?.len();
+        prepams_server::init(&cli.state, count, rng(&cli.seed)?).map_err(|e| e.to_string())?;
+        println!("issuer keys for {} attributes written to {}", count, cli.state.display());
+        return Ok(());
+    }
+
+    let config = Config {
+        state: cli.state,
+        data: cli.data,
+        attributes,
+        app_url: cli.app_url,
+    };
+    let server = Server::open(&config).map_err(|e| format!("could not open the issuer: {}", e))?;
+    let http = tiny_http::Server::http(&cli.listen).map_err(|e| format!("could not listen on {}: {}", cli.listen, e))?;
+
+    eprintln!("issuer loaded, {} ledger entries, listening on {}", server.issuer().store().len(), cli.listen);
+    server.serve(http);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    envelope::set_domain(&cli.domain);
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! Records of the server next to the ledger: registered users, issued
+//! credentials, signed studies and participations awaiting their reward.
+//!
+//! Rewards are not recorded here, they are read back from the ledger.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Serialize, Deserialize};
+
+use prepams_shared::storage::StorageError;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: String,
+    pub role: String,
+    /// Raw ed25519 key of an organizer.
+    #[serde(with = "base64url::option")]
+    pub public_key: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Study {
+    pub id: String,
+    pub owner: String,
+    /// Sealed signed study.
+    #[serde(with = "base64url::bytes")]
+    pub resource: Vec<u8>,
+}
+
+pub trait Records {
+    fn add_user(&mut self, user: &User) -> Result<(), StorageError>;
+
+    fn user(&self, id: &str) -> Result<Option<User>, StorageError>;
+
+    fn users(&self) -> Result<Vec<User>, StorageError>;
+
+    /// Records a credential issue response, in the order of issuance.
+    fn add_issued(&mut self, response: &[u8]) -> Result<(), StorageError>;
+
+    fn issued(&self) -> Result<Vec<Vec<u8>>, StorageError>;
+
+    fn add_study(&mut self, study: &Study) -> Result<(), StorageError>;
+
+    fn study(&self, id: &str) -> Result<Option<Study>, StorageError>;
+
+    /// Lists the studies in the order of creation, optionally of one owner.
+    fn studies(&self, owner: Option<&str>) -> Result<Vec<Study>, StorageError>;
+
+    fn add_participation(&mut self, id: &str, data: &[u8]) -> Result<(), StorageError>;
+
+    fn participation(&self, id: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    fn participations(&self) -> Result<Vec<(String, Vec<u8>)>, StorageError>;
+
+    fn remove_participation(&mut self, id: &str) -> Result<(), StorageError>;
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    users: Vec<User>,
+    #[serde(with = "base64url::list")]
+    issued: Vec<Vec<u8>>,
+    studies: Vec<Study>,
+    #[serde(with = "base64url::map")]
+    participations: BTreeMap<String, Vec<u8>>,
+}
+
+/// Records kept in memory and written to a JSON file after every change.
+///
+/// The file is replaced atomically, so a crash leaves either the previous or
+/// the new snapshot behind.
+pub struct FileRecords {
+    path: PathBuf,
+    snapshot: Snapshot,
+}
+
+impl FileRecords {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FileRecords, StorageError> {
+        let path = path.as_ref().to_path_buf();
+        let snapshot = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).map_err(|e| StorageError::Corrupted(e.to_string()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Snapshot::default(),
+            Err(e) => Err(e)?,
+        };
+
+        Ok(FileRecords { path, snapshot })
+    }
+
+    fn save(&self) -> Result<(), StorageError> {
+        let data = serde_json::to_vec_pretty(&self.snapshot).map_err(|e| StorageError::Encoding(e.to_string()))?;
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl Records for FileRecords {
+    fn add_user(&mut self, user: &User) -> Result<(), StorageError> {
+        self.snapshot.users.retain(|u| u.id != user.id);
+        self.snapshot.users.push(user.clone());
+        self.save()
+    }
+
+    fn user(&self, id: &str) -> Result<Option<User>, StorageError> {
+        Ok(self.snapshot.users.iter().find(|u| u.id == id).cloned())
+    }
+
+    fn users(&self) -> Result<Vec<User>, StorageError> {
+        Ok(self.snapshot.users.clone())
+    }
+
+    fn add_issued(&mut self, response: &[u8]) -> Result<(), StorageError> {
+        self.snapshot.issued.push(response.to_vec());
+        self.save()
+    }
+
+    fn issued(&self) -> Result<Vec<Vec<u8>>, StorageError> {
+        Ok(self.snapshot.issued.clone())
+    }
+
+    fn add_study(&mut self, study: &Study) -> Result<(), StorageError> {
+        self.snapshot.studies.retain(|s| s.id != study.id);
+        self.snapshot.studies.push(study.clone());
+        self.save()
+    }
+
+    fn study(&self, id: &str) -> Result<Option<Study>, StorageError> {
+        Ok(self.snapshot.studies.iter().find(|s| s.id == id).cloned())
+    }
+
+    fn studies(&self, owner: Option<&str>) -> Result<Vec<Study>, StorageError> {
+        Ok(self.snapshot.studies.iter().filter(|s| owner.is_none_or(|o| s.owner == o)).cloned().collect())
+    }
+
+    fn add_participation(&mut self, id: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.snapshot.participations.insert(id.to_string(), data.to_vec());
+        self.save()
+    }
+
+    fn participation(&self, id: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.snapshot.participations.get(id).cloned())
+    }
+
+    fn participations(&self) -> Result<Vec<(String, Vec<u8>)>, StorageError> {
+        Ok(self.snapshot.participations.iter().map(|(id, data)| (id.clone(), data.clone())).collect())
+    }
+
+    fn remove_participation(&mut self, id: &str) -> Result<(), StorageError> {
+        if self.snapshot.participations.remove(id).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+}
+
+/// Records in the tables of a SQLite database, which may be shared with the
+/// ledger store.
+pub struct SqliteRecords {
+    db: Connection,
+}
+
+impl SqliteRecords {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteRecords, StorageError> {
+        SqliteRecords::from_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<SqliteRecords, StorageError> {
+        SqliteRecords::from_connection(Connection::open_in_memory()?)
+    }
+
+    pub fn from_connection(db: Connection) -> Result<SqliteRecords, StorageError> {
+        db.execute_batch("
+            CREATE TABLE IF NOT EXISTS users (
+                id TEXT PRIMARY KEY,
+                role TEXT NOT NULL,
+                public_key BLOB
+            );
+            CREATE TABLE IF NOT EXISTS issued (
+                idx INTEGER PRIMARY KEY AUTOINCREMENT,
+                response BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS studies (
+                idx INTEGER PRIMARY KEY AUTOINCREMENT,
+                id TEXT NOT NULL UNIQUE,
+                owner TEXT NOT NULL,
+                resource BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS participations (
+                id TEXT PRIMARY KEY,
+                data BLOB NOT NULL
+            );
+        ")?;
+
+        Ok(SqliteRecords { db })
+    }
+}
+
+fn user(row: &rusqlite::Row) -> rusqlite::Result<User> {
+    Ok(User { id: row.get(0)?, role: row.get(1)?, public_key: row.get(2)? })
+}
+
+fn study(row: &rusqlite::Row) -> rusqlite::Result<Study> {
+    Ok(Study { id: row.get(0)?, owner: row.get(1)?, resource: row.get(2)? })
+}
+
+impl Records for SqliteRecords {
+    fn add_user(&mut self, user: &User) -> Result<(), StorageError> {
+        self.db.execute("INSERT OR REPLACE INTO users (id, role, public_key) VALUES (?1, ?2, ?3)", params![user.id, user.role, user.public_key])?;
+        Ok(())
+    }
+
+    fn user(&self, id: &str) -> Result<Option<User>, StorageError> {
+        Ok(self.db.query_row("SELECT id, role, public_key FROM users WHERE id = ?1", [id], user).optional()?)
+    }
+
+    fn users(&self) -> Result<Vec<User>, StorageError> {
+        let mut query = self.db.prepare("SELECT id, role, public_key FROM users ORDER BY rowid")?;
+        let users = query.query_map([], user)?.collect::<Result<_, _>>()?;
+        Ok(users)
+    }
+
+    fn add_issued(&mut self, response: &[u8]) -> Result<(), StorageError> {
+        self.db.execute("INSERT INTO issued (response) VALUES (?1)", [response])?;
+        Ok(())
+    }
+
+    fn issued(&self) -> Result<Vec<Vec<u8>>, StorageError> {
+        let mut query = self.db.prepare("SELECT response FROM issued ORDER BY idx")?;
+        let issued = query.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+        Ok(issued)
+    }
+
+    fn add_study(&mut self, study: &Study) -> Result<(), StorageError> {
+        self.db.execute("INSERT OR REPLACE INTO studies (id, owner, resource) VALUES (?1, ?2, ?3)", params![study.id, study.owner, study.resource])?;
+        Ok(())
+    }
+
+    fn study(&self, id: &str) -> Result<Option<Study>, StorageError> {
+        Ok(self.db.query_row("SELECT id, owner, resource FROM studies WHERE id = ?1", [id], study).optional()?)
+    }
+
+    fn studies(&self, owner: Option<&str>) -> Result<Vec<Study>, StorageError> {
+        let mut query = self.db.prepare("SELECT id, owner, resource FROM studies WHERE ?1 IS NULL OR owner = ?1 ORDER BY idx")?;
+        let studies = query.query_map([owner], study)?.collect::<Result<_, _>>()?;
+        Ok(studies)
+    }
+
+    fn add_participation(&mut self, id: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.db.execute("INSERT OR REPLACE INTO participations (id, data) VALUES (?1, ?2)", params![id, data])?;
+        Ok(())
+    }
+
+    fn participation(&self, id: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.db.query_row("SELECT data FROM participations WHERE id = ?1", [id], |row| row.get(0)).optional()?)
+    }
+
+    fn participations(&self) -> Result<Vec<(String, Vec<u8>)>, StorageError> {
+        let mut query = self.db.prepare("SELECT id, data FROM participations ORDER BY rowid")?;
+        let participations = query.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?.collect::<Result<_, _>>()?;
+        Ok(participations)
+    }
+
+    fn remove_participation(&mut self, id: &str) -> Result<(), StorageError> {
+        self.db.execute("DELETE FROM participations WHERE id = ?1", [id])?;
+        Ok(())
+    }
+}
+
+// binary fields of the JSON snapshot as base64url strings
+mod base64url {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    fn encode(data: &[u8]) -> String {
+        base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+    }
+
+    fn decode<E: serde::de::Error>(data: &str) -> Result<Vec<u8>, E> {
+        base64::decode_config(data, base64::URL_SAFE_NO_PAD).map_err(E::custom)
+    }
+
+    pub mod bytes {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(data: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&encode(data))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+            decode(&String::deserialize(d)?)
+        }
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(data: &Option<Vec<u8>>, s: S) -> Result<S::Ok, S::Error> {
+            match data {
+                Some(data) => s.serialize_some(&encode(data)),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<u8>>, D::Error> {
+            Option::<String>::deserialize(d)?.map(|data| decode(&data)).transpose()
+        }
+    }
+
+    pub mod list {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(data: &[Vec<u8>], s: S) -> Result<S::Ok, S::Error> {
+            s.collect_seq(data.iter().map(|data| encode(data)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<u8>>, D::Error> {
+            Vec::<String>::deserialize(d)?.iter().map(|data| decode(data)).collect()
+        }
+    }
+
+    pub mod map {
+        use std::collections::BTreeMap;
+        use super::*;
+
+        pub fn serialize<S: Serializer>(data: &BTreeMap<String, Vec<u8>>, s: S) -> Result<S::Ok, S::Error> {
+            s.collect_map(data.iter().map(|(k, v)| (k, encode(v))))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<BTreeMap<String, Vec<u8>>, D::Error> {
+            BTreeMap::<String, String>::deserialize(d)?.into_iter().map(|(k, v)| Ok((k, decode(&v)?))).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_records(records: &mut dyn Records) {
+        let lab = User { id: "lab".to_string(), role: "organizer".to_string(), public_key: Some(vec![7; 32]) };
+        let alice = User { id: "alice".to_string(), role: "participant".to_string(), public_key: None };
+        records.add_user(&lab).unwrap();
+        records.add_user(&alice).unwrap();
+        records.add_issued(b"first").unwrap();
+        records.add_issued(b"second").unwrap();
+        records.add_study(&Study { id: "s1".to_string(), owner: "lab".to_string(), resource: vec![1, 2] }).unwrap();
+        records.add_study(&Study { id: "s2".to_string(), owner: "other".to_string(), resource: vec![3] }).unwrap();
+        records.add_participation("p1", b"iv+data").unwrap();
+        records.add_participation("p2", b"more").unwrap();
+        records.remove_participation("p2").unwrap();
+        records.remove_participation("unknown").unwrap();
+    }
+
+    fn check_contents(records: &dyn Records) {
+        assert_eq!(records.user("lab").unwrap().unwrap().public_key, Some(vec![7; 32]));
+        assert_eq!(records.user("alice").unwrap().unwrap().public_key, None);
+        assert_eq!(records.user("bob").unwrap(), None);
+        assert_eq!(records.users().unwrap().len(), 2);
+        assert_eq!(records.issued().unwrap(), vec![b"first".to_vec(), b"second".to_vec()]);
+        assert_eq!(records.studies(None).unwrap().iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["s1", "s2"]);
+        assert_eq!(records.studies(Some("lab")).unwrap().len(), 1);
+        assert_eq!(records.study("s2").unwrap().unwrap().resource, vec![3]);
+        assert_eq!(records.participation("p1").unwrap(), Some(b"iv+data".to_vec()));
+        assert_eq!(records.participations().unwrap(), vec![("p1".to_string(), b"iv+data".to_vec())]);
+    }
+
+    #[test]
+    fn file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("records.json");
+
+        check_records(&mut FileRecords::open(&path).unwrap());
+        check_contents(&FileRecords::open(&path).unwrap());
+    }
+
+    #[test]
+    fn sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.sqlite");
+
+        check_records(&mut SqliteRecords::open(&path).unwrap());
+        check_contents(&SqliteRecords::open(&path).unwrap());
+    }
+}
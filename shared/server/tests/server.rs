@@ -0,0 +1,185 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::thread;
+
+use ed25519_zebra::VerificationKey;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde_json::{json, Value};
+
+use prepams_shared::{seal, open};
+use prepams_shared::api::*;
+use prepams_shared::types::*;
+use prepams_server::{Config, Server};
+
+struct Client(SocketAddr);
+
+impl Client {
+    fn request(&self, method: &str, path: &str, body: &[u8]) -> (u16, Vec<u8>) {
+        let mut stream = TcpStream::connect(self.0).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", method, path, body.len()).unwrap();
+        stream.write_all(body).unwrap();
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response).unwrap();
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").expect("response has a header");
+        let status = String::from_utf8_lossy(&response[..split]).split(' ').nth(1).unwrap().parse().unwrap();
+        (status, response[split + 4..].to_vec())
+    }
+
+    fn get(&self, path: &str) -> Vec<u8> {
+        let (status, body) = self.request("GET", path, &[]);
+        assert_eq!(status, 200, "GET {}: {}", path, String::from_utf8_lossy(&body));
+        body
+    }
+
+    fn post(&self, path: &str, body: &[u8]) -> Vec<u8> {
+        let (status, body) = self.request("POST", path, body);
+        assert!(status == 200 || status == 201, "POST {} returned {}: {}", path, status, String::from_utf8_lossy(&body));
+        body
+    }
+
+    fn json(&self, path: &str) -> Value {
+        serde_json::from_slice(&self.get(path)).unwrap()
+    }
+
+    // status and error code of a failing request
+    fn fail(&self, method: &str, path: &str, body: &[u8]) -> (u16, String) {
+        let (status, body) = self.request(method, path, body);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        (status, body["code"].as_str().unwrap().to_string())
+    }
+}
+
+fn start(dir: &Path, data: &str) -> Client {
+    let state = dir.join("issuer.state");
+    prepams_server::init(&state, 2, ChaCha20Rng::from_entropy()).unwrap();
+    assert_eq!(prepams_server::init(&state, 2, ChaCha20Rng::from_entropy()).err().unwrap().status, 409);
+
+    let config = Config {
+        state,
+        data: dir.join(data),
+        attributes: json!([["age", "number", 18, 99], ["student", "select", ["no", "yes"]]]),
+        app_url: "http://localhost:8080".to_string(),
+    };
+    let http = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let addr = http.server_addr().to_ip().unwrap();
+    thread::spawn(move || Server::open(&config).unwrap().serve(http));
+
+    Client(addr)
+}
+
+fn study(reward: u8) -> Resource {
+    let mut resource = Resource::random(rand::thread_rng());
+    resource.addConstraint(AttributeConstraint::Range(0, 18, 99));
+
+    let mut study = serde_json::to_value(&resource).unwrap();
+    study["name"] = "survey".into();
+    study["summary"] = "a short survey".into();
+    study["reward"] = reward.into();
+    serde_json::from_value(study).unwrap()
+}
+
+fn full_stack(data: &str) {
+    let dir = tempfile::tempdir().unwrap();
+    let client = start(dir.path(), data);
+
+    assert_eq!(client.json("/api/issuer/attributes").as_array().unwrap().len(), 2);
+    let ipk: IssuerPublicKey = open(&client.get("/api/issuer/pk")).unwrap();
+    let lvk = VerificationKey::try_from(client.get("/api/ledger/vk").as_slice()).unwrap();
+
+    // sign up
+    let organizer = OrganizerCore::new("lab", ipk.clone(), ChaCha20Rng::from_entropy());
+    let key: [u8; 32] = organizer.public_key().into();
+    assert_eq!(client.post("/api/auth/signup?id=lab&role=organizer", &key), br#"{"ok":true}"#);
+    assert_eq!(client.fail("POST", "/api/auth/signup?id=lab&role=organizer", &key), (409, "conflict".to_string()));
+
+    let mut participant = ParticipantCore::new("alice", &[30, 1], lvk);
+    let request = participant.request_credential(ipk, open(&client.get("/api/issuer/vk")).unwrap(), ChaCha20Rng::from_entropy()).unwrap();
+    let response = client.post("/api/auth/signup?id=alice&role=participant", &seal(&request).unwrap());
+    participant.retrieve_credential(&open(&response).unwrap()).unwrap();
+
+    let signin = client.json("/api/auth/signin?role=participant");
+    assert_eq!(signin["log"], json!([base64::encode(&response)]));
+    assert_eq!(signin["ledger"]["vk"], json!(base64::encode(lvk)));
+    assert_eq!(client.json("/api/auth/signin?role=organizer")["publicKeys"], json!([base64::encode(key)]));
+
+    // studies
+    let resource = study(3);
+    let signed = seal(&organizer.sign_resource(&resource).unwrap()).unwrap();
+    assert_eq!(client.post("/api/studies", &signed), serde_json::to_vec(&json!({ "ok": true, "id": resource.id() })).unwrap());
+    assert_eq!(client.fail("POST", "/api/studies", &signed), (409, "conflict".to_string()));
+
+    let impostor = OrganizerCore::new("lab", open(&client.get("/api/issuer/pk")).unwrap(), ChaCha20Rng::from_entropy());
+    let forged = seal(&impostor.sign_resource(&study(3)).unwrap()).unwrap();
+    assert_eq!(client.fail("POST", "/api/studies", &forged), (400, "bad_request".to_string()));
+
+    let studies = client.json("/api/studies?id=lab");
+    assert_eq!(studies.as_array().unwrap().len(), 1);
+    assert_eq!(studies[0]["abstract"], "a short survey");
+    assert_eq!(studies[0]["reward"], 3);
+    assert_eq!(studies[0]["constraints"], json!([[0, "number", [18, 99]]]));
+    assert_eq!(client.json("/api/studies?id=other"), json!([]));
+
+    // participation and reward
+    let participation = participant.participate(&resource).unwrap();
+    let created: Value = serde_json::from_slice(&client.post("/api/participations", &seal(&participation).unwrap())).unwrap();
+    let id = created["id"].as_str().unwrap();
+    assert_eq!(created["url"], format!("http://localhost:8080/participation/{}", id));
+    let stored: Participation = open(&client.get(&format!("/api/participations/{}", id))).unwrap();
+
+    let confirmed = seal(&organizer.confirm_participation(&stored, id.to_string()).unwrap()).unwrap();
+    let entry: LedgerEntry = open(&client.post("/api/rewards", &confirmed)).unwrap();
+    assert_eq!(entry.entryType(), LedgerEntryType::Transaction);
+    assert_eq!(client.fail("POST", "/api/rewards", &confirmed), (400, "bad_request".to_string()));
+    assert_eq!(client.request("GET", &format!("/api/participations/{}", id), &[]).0, 404);
+
+    let rewards = client.json(&format!("/api/rewards/{}", resource.id()));
+    assert_eq!(rewards["transactions"][0]["value"], 3);
+    assert_eq!(rewards["transactions"][0]["study"], resource.id());
+    assert_eq!(client.json("/api/rewards")["transactions"].as_array().unwrap().len(), 1);
+
+    // payout
+    let ledger: Ledger = open(&client.get("/api/ledger")).unwrap();
+    assert_eq!(participant.balance(&ledger).unwrap().0, 3);
+
+    let nulls = participant.request_nulls().unwrap();
+    let signatures = client.post("/api/nulls", &seal(&nulls.requests().to_vec()).unwrap());
+    let nulls = nulls.unblind_nulls(open(&signatures).unwrap()).unwrap();
+    let payout = participant.request_payout(3, "bank", "alice", nulls, &ledger).unwrap();
+    let payout = seal(&payout.proof).unwrap();
+
+    let receipt: Value = serde_json::from_slice(&client.post("/api/payout", &payout)).unwrap();
+    assert_eq!(receipt["target"], "bank");
+    let receipt: Payout = open(&base64::decode(receipt["receipt"].as_str().unwrap()).unwrap()).unwrap();
+    assert_eq!(receipt.value(), 3);
+    assert_eq!(client.fail("POST", "/api/payout", &payout), (409, "rejected".to_string()));
+
+    // ledger slices
+    let ledger: Ledger = open(&client.get("/api/ledger")).unwrap();
+    assert!(ledger.check(&lvk).is_ok());
+    assert_eq!(ledger.len(), 2);
+
+    let slice: LedgerSlice = open(&client.get("/api/ledger?from=0&type=Payout")).unwrap();
+    assert!(slice.check(&lvk).is_ok());
+    assert_eq!((slice.end(), slice.entries().len()), (2, 1));
+
+    let head = base64::encode_config(slice.cursor(), base64::URL_SAFE_NO_PAD);
+    let slice: LedgerSlice = open(&client.get(&format!("/api/ledger?after={}", head))).unwrap();
+    assert_eq!((slice.start(), slice.entries().len()), (2, 0));
+
+    assert_eq!(client.fail("GET", "/api/ledger?from=0&type=Reward", &[]), (400, "encoding".to_string()));
+    assert_eq!(client.fail("POST", "/api/nulls", b"PPMS"), (400, "encoding".to_string()));
+    assert_eq!(client.fail("GET", "/api/unknown", &[]), (404, "not_found".to_string()));
+}
+
+#[test]
+fn files() {
+    full_stack("data");
+}
+
+#[test]
+fn sqlite() {
+    full_stack("server.sqlite");
+}
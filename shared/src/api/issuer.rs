@@ -0,0 +1,538 @@
+use rand::{thread_rng, CryptoRng, RngCore};
+use bls12_381::Scalar;
+
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use ed25519_zebra::{Signature, SigningKey, VerificationKey};
+
+use crate::error::{Check, PrepamsError, Result};
+use crate::external::util::as_scalar;
+use crate::serialization::encode;
+use crate::storage::{self, EntryIter, LedgerStore, MemoryStore};
+use crate::pbss::{self, BlindedSignRequest, BlindedSignature};
+use crate::types::*;
+use crate::types::credential::*;
+use crate::credential;
+use crate::proofs::generic::Transcript;
+use crate::proofs::payout::{MAX_INPUTS, PayoutProof, PayoutProofData, PayoutProofSecrets};
+
+use super::participant::ParticipantCore;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IssuerCore {
+    attributes: usize,
+    #[schemars(with = "crate::serialization::schema::Ed25519Key")]
+    signingKey: SigningKey,
+    publicKey: IssuerPublicKey,
+    secretKey: IssuerSecretKey,
+    creditSigningKey: pbss::SecretKey,
+    creditVerificationKey: pbss::PublicKey,
+    #[serde(skip_serializing)]
+    #[serde(skip_deserializing, default = "memory_store")]
+    ledger: Box<dyn LedgerStore>,
+    #[serde(skip, default = "system_clock")]
+    clock: Box<dyn Fn() -> u64>
+}
+
+fn memory_store() -> Box<dyn LedgerStore> {
+    Box::new(MemoryStore::default())
+}
+
+fn system_clock() -> Box<dyn Fn() -> u64> {
+    Box::new(crate::types::now)
+}
+
+/// Ledger entry of an accepted payout together with the payout destination.
+#[derive(Debug, Clone)]
+pub struct PayoutReceipt {
+    pub entry: LedgerEntry,
+    pub target: String,
+    pub recipient: String
+}
+
+impl IssuerCore {
+    pub fn new(attributes: usize, mut rng: impl RngCore + CryptoRng) -> IssuerCore {
+        let (ipk, isk) = credential::init(&mut rng, attributes);
+        let (csk, cvk) = pbss::Gen(&mut rng, 2, 1, "payment");
+        let sk = SigningKey::new(&mut rng);
+
+        IssuerCore {
+            attributes,
+            signingKey: sk,
+            publicKey: ipk,
+            secretKey: isk,
+            creditSigningKey: csk,
+            creditVerificationKey: cvk,
+            ledger: memory_store(),
+            clock: system_clock()
+        }
+    }
+
+    pub fn attributes(&self) -> usize {
+        self.attributes
+    }
+
+    pub fn public_key(&self) -> &IssuerPublicKey {
+        &self.publicKey
+    }
+
+    pub fn credit_verification_key(&self) -> &pbss::PublicKey {
+        &self.creditVerificationKey
+    }
+
+    pub fn ledger_verification_key(&self) -> VerificationKey {
+        VerificationKey::from(&self.signingKey)
+    }
+
+    /// Exports the complete ledger.
+    pub fn ledger(&self) -> Result<Ledger> {
+        Ok(self.ledger.export()?)
+    }
+
+    pub fn head(&self) -> Signature {
+        self.ledger.head()
+    }
+
+    /// Replaces the ledger with an in-memory copy of `ledger`.
+    pub fn load(&mut self, ledger: Ledger) -> Result<()> {
+        self.ledger = Box::new(MemoryStore::try_from(ledger)?);
+        Ok(())
+    }
+
+    pub fn issue_credential(&self, request: &IssueRequest) -> Result<IssueResponse> {
+        self.issue_credential_with_rng(request, thread_rng())
+    }
+
+    /// Like [`issue_credential`](Self::issue_credential), with the signature
+    /// randomness drawn from `rng`.
+    pub fn issue_credential_with_rng(&self, request: &IssueRequest, rng: impl RngCore + CryptoRng) -> Result<IssueResponse> {
+        credential::issue(rng, &self.publicKey, &self.secretKey, request)
+    }
+
+    /// Checks that `resource` was signed by the organizer key `vk`.
+    pub fn check_resource_signature(&self, resource: &SignedResource, vk: &VerificationKey) -> Result<bool> {
+        let mut data = "resource:".as_bytes().to_vec();
+        data.extend_from_slice(&encode(&resource.resource)?);
+
+        Ok(vk.verify(&resource.signature, &data).is_ok())
+    }
+
+    /// Rewards a participation confirmed by the organizer key `vk` and appends
+    /// the transaction to the ledger.
+    pub fn issue_reward(&mut self, participation: &ConfirmedParticipation, vk: &VerificationKey, reward: u8) -> Result<LedgerEntry> {
+        self.issue_reward_with_rng(participation, vk, reward, thread_rng())
+    }
+
+    /// Like [`issue_reward`](Self::issue_reward), with the coin signature
+    /// randomness drawn from `rng`.
+    pub fn issue_reward_with_rng(&mut self, participation: &ConfirmedParticipation, vk: &VerificationKey, reward: u8, rng: impl RngCore + CryptoRng) -> Result<LedgerEntry> {
+        let mut data = participation.id.as_bytes().to_vec();
+        data.append(&mut encode(&participation.request)?);
+
+        if vk.verify(&participation.signature, &data).is_err() {
+            Err(PrepamsError::verification(Check::Signature, "reward signature invalid"))?;
+        }
+        if participation.value != reward {
+            Err(PrepamsError::rejected("reward amount does not match study"))?;
+        }
+        if participation.request.m != [as_scalar(reward as u32)] {
+            Err(PrepamsError::rejected("value of request does not match reward"))?;
+        }
+        if self.ledger.contains_tag(&participation.tag)? {
+            Err(PrepamsError::rejected("reward for this participation already issued"))?;
+        }
+
+        let coin = pbss::Sign(&self.creditVerificationKey, &self.creditSigningKey, &participation.request, rng)?;
+        let tx = Transaction {
+            participation: participation.clone(),
+            coin
+        };
+        let entry = LedgerEntry::sign_transaction(&self.signingKey, &self.ledger.head(), self.now()?, tx)?;
+        self.append(entry)
+    }
+
+    /// Signs null coins, which only carry the value zero.
+    pub fn issue_nulls(&self, requests: &[BlindedSignRequest]) -> Result<Vec<BlindedSignature>> {
+        self.issue_nulls_with_rng(requests, thread_rng())
+    }
+
+    /// Like [`issue_nulls`](Self::issue_nulls), with the signature randomness
+    /// drawn from `rng`.
+    pub fn issue_nulls_with_rng(&self, requests: &[BlindedSignRequest], mut rng: impl RngCore + CryptoRng) -> Result<Vec<BlindedSignature>> {
+        let mut coins: Vec<BlindedSignature> = vec![];
+
+        for req in requests {
+            if req.m.len() != 1 {
+                Err(PrepamsError::rejected("request contains invalid amount of attributes"))?;
+            }
+            if req.m[0] != Scalar::zero() {
+                Err(PrepamsError::rejected("value of request is not zero"))?;
+            }
+
+            coins.push(pbss::Sign(&self.creditVerificationKey, &self.creditSigningKey, req, &mut rng)?);
+        }
+
+        Ok(coins)
+    }
+
+    /// Appends an entry that was signed by this issuer, e.g. when replaying
+    /// a ledger from a database.
+    pub fn append_entry(&mut self, entry: LedgerEntry) -> Result<()> {
+        entry.check(&self.ledger_verification_key(), &self.ledger.head(), self.ledger.timestamp()?)?;
+        self.append(entry)?;
+        Ok(())
+    }
+
+    /// Verifies a payout request and appends the payout to the ledger.
+    pub fn check_payout_request(&mut self, proof: &PayoutProofData) -> Result<PayoutReceipt> {
+        if proof.inputs.inputs.len() != MAX_INPUTS {
+            Err(PrepamsError::rejected("invalid padding"))?;
+        }
+
+        if proof.inputs.cvk != self.creditVerificationKey {
+            Err(PrepamsError::rejected("invalid public key"))?;
+        }
+
+        for (i, nullifier) in proof.inputs.nullifier.iter().enumerate() {
+            if proof.inputs.nullifier[..i].contains(nullifier) || self.ledger.contains_nullifier(nullifier)? {
+                Err(PrepamsError::rejected("coin already spent"))?;
+            }
+        }
+
+        let mut verifier_transcript = Transcript::new(b"payout");
+        proof.verify::<PayoutProofSecrets, PayoutProof>(&mut verifier_transcript)?;
+
+        let entry = LedgerEntry::sign_payout(&self.signingKey, &self.ledger.head(), self.now()?, Payout::from(proof));
+        let entry = self.append(entry)?;
+
+        Ok(PayoutReceipt {
+            entry,
+            target: proof.inputs.target.clone(),
+            recipient: proof.inputs.recipient.clone()
+        })
+    }
+
+    // only used for evaluation purposes to quickly add a preceeding participation to the ledger
+    pub fn bootstrap_ledger(&mut self, participant: &ParticipantCore, organizer: &SigningKey, resource: &Resource, id: String) -> Result<LedgerEntry> {
+        let credential = participant.credential().ok_or(PrepamsError::state("credential not yet requested"))?;
+
+        let study = resource.id;
+        let tag = credential.derive_tag(&resource.id)?;
+        let mut prng = credential.derive_reward_rng(&study);
+        let s = <bls12_381::Scalar as ff::Field>::random(&mut prng);
+        let d = <bls12_381::Scalar as ff::Field>::random(&mut prng);
+        let reward = as_scalar(resource.reward.into());
+        let reward_request = pbss::Blind(&self.creditVerificationKey, &vec![reward], &vec![s, credential.identity], &d, &mut prng)?;
+
+        let mut data = id.as_bytes().to_vec();
+        data.append(&mut encode(&reward_request)?);
+        let sig = organizer.sign(&data);
+
+        let participation = ConfirmedParticipation {
+            id,
+            study,
+            tag,
+            value: resource.reward,
+            request: reward_request,
+            signature: sig
+        };
+
+        let coin = pbss::Sign(&self.creditVerificationKey, &self.creditSigningKey, &participation.request, thread_rng())?;
+        let tx = Transaction {
+            participation,
+            coin
+        };
+
+        let entry = LedgerEntry::sign_transaction(&self.signingKey, &self.ledger.head(), self.now()?, tx)?;
+        self.append(entry)
+    }
+
+    /// Replaces the system clock that timestamps new ledger entries, e.g. to
+    /// produce a reproducible ledger. Entries are never stamped older than
+    /// the latest entry.
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + 'static) -> IssuerCore {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Replaces the in-memory ledger with a persistent store.
+    ///
+    /// Every entry of an existing store is checked against the ledger
+    /// verification key while it is streamed, so a store that was written by
+    /// another issuer or modified on disk is rejected.
+    pub fn with_store<S: LedgerStore + 'static>(mut self, store: S) -> Result<IssuerCore> {
+        let vk = self.ledger_verification_key();
+        let mut head = storage::genesis();
+        let mut time = 0;
+
+        for entry in store.entries() {
+            let entry = entry?;
+            entry.check(&vk, &head, time)?;
+            head = entry.signature;
+            time = entry.timestamp;
+        }
+        if head != store.head() {
+            Err(PrepamsError::verification(Check::Ledger, "ledger store head does not match its entries"))?;
+        }
+
+        self.ledger = Box::new(store);
+        Ok(self)
+    }
+
+    /// Streams the ledger entries from the underlying store.
+    pub fn entries(&self) -> EntryIter<'_> {
+        self.ledger.entries()
+    }
+
+    /// Scans the positions `from..to` and collects the entries matching the
+    /// filter, stopping early once `limit` entries were collected.
+    pub fn export_range(&self, from: u64, to: u64, limit: Option<u64>, filter: &LedgerFilter) -> Result<LedgerSlice> {
+        let total = self.ledger.len();
+        let to = to.min(total);
+        let from = from.min(to);
+
+        let anchor = match from {
+            0 => storage::genesis(),
+            _ => match self.ledger.get(from - 1)? {
+                Some(entry) => entry.signature,
+                None => Err(PrepamsError::state("ledger entry is missing"))?
+            }
+        };
+
+        let mut slice = LedgerSlice {
+            start: from,
+            end: from,
+            total,
+            anchor,
+            cursor: anchor,
+            head: self.ledger.head(),
+            entries: vec![]
+        };
+
+        for (i, entry) in (from..to).zip(self.ledger.range(from, to)) {
+            if limit.is_some_and(|limit| slice.entries.len() as u64 >= limit) {
+                break;
+            }
+
+            let entry = entry?;
+            slice.end = i + 1;
+            slice.cursor = entry.signature;
+            if filter.matches(&entry) {
+                slice.entries.push((i, entry));
+            }
+        }
+
+        Ok(slice)
+    }
+
+    /// Exports up to `limit` matching entries following the entry signed with `head`.
+    pub fn export_after(&self, head: &Signature, limit: u64, filter: &LedgerFilter) -> Result<LedgerSlice> {
+        let from = if head == &storage::genesis() {
+            0
+        } else {
+            match self.ledger.find(head)? {
+                Some(i) => i + 1,
+                None => Err(PrepamsError::rejected("unknown ledger head"))?
+            }
+        };
+
+        self.export_range(from, u64::MAX, Some(limit), filter)
+    }
+
+    pub fn store(&self) -> &dyn LedgerStore {
+        self.ledger.as_ref()
+    }
+
+    // issuer time for the next entry, never older than the latest entry
+    fn now(&self) -> Result<u64> {
+        Ok((self.clock)().max(self.ledger.timestamp()?))
+    }
+
+    fn append(&mut self, entry: LedgerEntry) -> Result<LedgerEntry> {
+        self.ledger.append(&entry)?;
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_chacha::rand_core::SeedableRng;
+    use crate::external::util::rand_scalar;
+
+    fn issuer(n: usize) -> IssuerCore {
+        let mut issuer = IssuerCore::new(1, ChaCha20Rng::from_entropy());
+        for i in 0..n {
+            let payout = Payout { nullifier: vec![rand_scalar()], recipient: [i as u8; 32], value: i as u8 };
+            let entry = LedgerEntry::sign_payout(&issuer.signingKey, &issuer.ledger.head(), 1000 + i as u64, payout);
+            issuer.append(entry).unwrap();
+        }
+        issuer
+    }
+
+    #[test]
+    fn ledger_slices() {
+        let issuer = issuer(10);
+        let vk = issuer.ledger_verification_key();
+        let all = LedgerFilter::default();
+
+        let mut ledger = Ledger::default();
+        let mut cursor = storage::genesis();
+        while ledger.entries.len() < 10 {
+            let slice = issuer.export_after(&cursor, 4, &all).unwrap();
+            assert!(slice.check(&vk).is_ok());
+            slice.extend_ledger(&mut ledger, &vk).unwrap();
+            cursor = slice.cursor;
+        }
+        assert_eq!(ledger.head, issuer.head());
+        assert!(ledger.check(&vk).is_ok());
+
+        let mut truncated = ledger.clone();
+        truncated.entries.pop();
+        assert!(matches!(truncated.check(&vk), Err(PrepamsError::Verification(Check::Ledger, _))));
+
+        let slice = issuer.export_range(3, 7, None, &all).unwrap();
+        assert_eq!((slice.start, slice.end, slice.entries.len()), (3, 7, 4));
+        assert!(slice.check(&vk).is_ok());
+        assert!(slice.extend_ledger(&mut Ledger::default(), &vk).is_err());
+
+        let mut forged = slice.clone();
+        forged.entries.swap(1, 2);
+        assert!(forged.check(&vk).is_err());
+
+        let mut forged = slice.clone();
+        forged.entries.remove(1);
+        forged.entries[0].0 = 4;
+        assert!(forged.check(&vk).is_err());
+
+        let transactions = LedgerFilter::parse(Some("Transaction"), None).unwrap();
+        assert!(LedgerFilter::parse(Some("Reward"), None).is_err());
+        let slice = issuer.export_range(0, 10, None, &transactions).unwrap();
+        assert_eq!(slice.entries.len(), 0);
+        assert_eq!(slice.end, 10);
+        assert!(slice.check(&vk).is_ok());
+
+        let slice = issuer.export_after(&issuer.head(), 4, &all).unwrap();
+        assert_eq!((slice.start, slice.end), (10, 10));
+
+        let unknown = LedgerEntry::sign_payout(&issuer.signingKey, &issuer.head(), 2000, Payout { nullifier: vec![], recipient: [0; 32], value: 0 });
+        assert!(matches!(issuer.export_after(&unknown.signature, 4, &all), Err(PrepamsError::Rejected(_))));
+    }
+
+    #[test]
+    fn timestamps() {
+        let issuer = issuer(3);
+        let vk = issuer.ledger_verification_key();
+        let entries: Vec<LedgerEntry> = issuer.entries().map(|e| e.unwrap()).collect();
+
+        assert_eq!(issuer.ledger.timestamp().unwrap(), 1002);
+        assert!(issuer.now().unwrap() >= 1002);
+        assert!(entries[2].check(&vk, &entries[1].signature, 1001).is_ok());
+        assert!(entries[2].check(&vk, &entries[1].signature, 1003).is_err());
+
+        // the timestamp is covered by the signature
+        let mut forged = entries[2].clone();
+        forged.timestamp = 1005;
+        assert!(forged.check(&vk, &entries[1].signature, 1001).is_err());
+
+        let payout = Payout { nullifier: vec![rand_scalar()], recipient: [0; 32], value: 1 };
+        let late = LedgerEntry::sign_payout(&issuer.signingKey, &entries[2].signature, 900, payout);
+        assert!(late.check(&vk, &entries[2].signature, issuer.ledger.timestamp().unwrap()).is_err());
+
+        let issuer = issuer.with_clock(|| 5000);
+        assert_eq!(issuer.now().unwrap(), 5000);
+        assert_eq!(issuer.with_clock(|| 900).now().unwrap(), 1002);
+    }
+
+    #[test]
+    fn legacy_ledger() {
+        let mut source = IssuerCore::new(1, ChaCha20Rng::from_entropy());
+        let mut issuer = IssuerCore::new(1, ChaCha20Rng::from_entropy());
+        issuer.signingKey = source.signingKey;
+
+        // entries signed before timestamps were introduced, read with the default timestamp 0
+        let mut head = storage::genesis();
+        for i in 0..3 {
+            let payout = Payout { nullifier: vec![rand_scalar()], recipient: [i; 32], value: i };
+            let mut data = "payout:".as_bytes().to_vec();
+            data.extend_from_slice(&head.to_bytes());
+            data.extend_from_slice(&payout.recipient);
+            data.extend_from_slice(&payout.value.to_be_bytes());
+            data.extend_from_slice(&payout.nullifier[0].to_bytes());
+
+            let entry = LedgerEntry { previous: head, timestamp: 0, transaction: None, payout: Some(payout), signature: source.signingKey.sign(&data) };
+            head = entry.signature;
+            source.append(entry).unwrap();
+        }
+
+        for entry in source.entries() {
+            issuer.append_entry(entry.unwrap()).unwrap();
+        }
+        assert_eq!(issuer.head(), source.head());
+
+        // new entries are signed with their timestamp
+        let payout = Payout { nullifier: vec![rand_scalar()], recipient: [3; 32], value: 3 };
+        let entry = LedgerEntry::sign_payout(&source.signingKey, &head, 1000, payout);
+        issuer.append_entry(entry.clone()).unwrap();
+
+        let mut forged = entry.clone();
+        forged.timestamp = 0;
+        assert!(forged.check(&issuer.ledger_verification_key(), &head, 0).is_err());
+
+        // a legacy signature does not hold for any timestamp
+        let mut forged = source.entries().last().unwrap().unwrap();
+        forged.timestamp = 1000;
+        assert!(forged.check(&issuer.ledger_verification_key(), &forged.previous, 0).is_err());
+
+        // a new entry stamped 0 is signed under the new label with its timestamp
+        let payout = Payout { nullifier: vec![rand_scalar()], recipient: [4; 32], value: 4 };
+        let stamped = LedgerEntry::sign_payout(&source.signingKey, &entry.signature, 0, payout.clone());
+        stamped.check(&issuer.ledger_verification_key(), &entry.signature, 0).unwrap();
+        let legacy = payout.signed_data(&entry.signature, None);
+        assert!(issuer.ledger_verification_key().verify(&stamped.signature, &legacy).is_err());
+    }
+
+    #[test]
+    fn reward_value() {
+        let mut issuer = IssuerCore::new(1, ChaCha20Rng::from_entropy());
+        let organizer = SigningKey::new(ChaCha20Rng::from_entropy());
+
+        // a confirmed participation whose blinded request carries `value`
+        let cvk = issuer.credit_verification_key().clone();
+        let confirm = |value: u32| {
+            let s = vec![rand_scalar(), rand_scalar()];
+            let request = pbss::Blind(&cvk, &vec![as_scalar(value)], &s, &rand_scalar(), ChaCha20Rng::from_entropy()).unwrap();
+            let id = format!("p{}", value);
+            let mut data = id.as_bytes().to_vec();
+            data.append(&mut encode(&request).unwrap());
+            let signature = organizer.sign(&data);
+            ConfirmedParticipation { id, value: 2, tag: (bls12_381::G1Affine::generator() * rand_scalar()).into(), study: rand_scalar(), request, signature }
+        };
+
+        let forged = confirm(9);
+        let result = issuer.issue_reward(&forged, &VerificationKey::from(&organizer), 2);
+        assert!(matches!(result, Err(PrepamsError::Rejected(_))));
+        assert_eq!(issuer.ledger.len(), 0);
+
+        issuer.issue_reward(&confirm(2), &VerificationKey::from(&organizer), 2).unwrap();
+    }
+
+    #[test]
+    fn append_entry() {
+        let source = issuer(3);
+        let mut issuer = IssuerCore::new(1, ChaCha20Rng::from_entropy());
+        let entries: Vec<LedgerEntry> = source.entries().map(|e| e.unwrap()).collect();
+
+        // entries of another issuer are rejected
+        assert!(matches!(issuer.append_entry(entries[0].clone()), Err(PrepamsError::Verification(Check::Ledger, _))));
+
+        issuer.signingKey = source.signingKey;
+        for entry in &entries {
+            issuer.append_entry(entry.clone()).unwrap();
+        }
+        assert_eq!(issuer.head(), source.head());
+        assert!(issuer.append_entry(entries[1].clone()).is_err());
+    }
+}
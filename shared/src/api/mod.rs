@@ -0,0 +1,18 @@
+//! Native API of the protocol roles.
+//!
+//! The roles work with typed protocol messages and report failures as
+//! `PrepamsError`. Messages are exchanged between roles in the postcard wire
+//! format, see `encode` and `decode`. The JavaScript bindings are a thin layer
+//! over these types.
+
+pub mod issuer;
+pub mod organizer;
+pub mod participant;
+
+pub use issuer::{IssuerCore, PayoutReceipt};
+pub use organizer::OrganizerCore;
+pub use participant::{ParticipantCore, PayoutRequest};
+
+pub use crate::pbss::{PublicKey as CreditVerificationKey, BlindedSignRequest, BlindedSignature, UnblindedSignature};
+pub use crate::proofs::payout::PayoutProofData;
+pub use crate::types::credential::{IssuerPublicKey, IssueRequest, IssueResponse};
@@ -0,0 +1,74 @@
+use rand::{CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use ed25519_zebra::{SigningKey, VerificationKey};
+
+use crate::error::{PrepamsError, Result};
+use crate::serialization::encode;
+use crate::types::*;
+use crate::types::credential::*;
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct OrganizerCore {
+    identity: String,
+    #[schemars(with = "crate::serialization::schema::Ed25519Key")]
+    secretKey: SigningKey,
+    issuerPublicKey: IssuerPublicKey
+}
+
+impl OrganizerCore {
+    pub fn new(identity: &str, ipk: IssuerPublicKey, rng: impl RngCore + CryptoRng) -> OrganizerCore {
+        OrganizerCore {
+            identity: identity.to_string(),
+            secretKey: SigningKey::new(rng),
+            issuerPublicKey: ipk
+        }
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn public_key(&self) -> VerificationKey {
+        VerificationKey::from(&self.secretKey)
+    }
+
+    pub fn issuer_public_key(&self) -> &IssuerPublicKey {
+        &self.issuerPublicKey
+    }
+
+    pub fn set_issuer_public_key(&mut self, ipk: IssuerPublicKey) {
+        self.issuerPublicKey = ipk;
+    }
+
+    pub fn sign_resource(&self, resource: &Resource) -> Result<SignedResource> {
+        let mut data = "resource:".as_bytes().to_vec();
+        data.extend_from_slice(&encode(resource)?);
+
+        Ok(SignedResource {
+            owner: self.identity.clone(),
+            resource: resource.clone(),
+            signature: self.secretKey.sign(&data)
+        })
+    }
+
+    /// Confirms a verified participation so that the issuer rewards it.
+    pub fn confirm_participation(&self, participation: &Participation, id: String) -> Result<ConfirmedParticipation> {
+        if self.issuerPublicKey != participation.proof.inputs.ipk {
+            Err(PrepamsError::rejected("participation was proven for another issuer"))?;
+        }
+
+        let mut data = id.as_bytes().to_vec();
+        data.append(&mut encode(&participation.proof.inputs.reward_request)?);
+
+        Ok(ConfirmedParticipation {
+            id,
+            study: participation.id,
+            tag: participation.proof.inputs.tag,
+            value: participation.reward(),
+            request: participation.proof.inputs.reward_request.clone(),
+            signature: self.secretKey.sign(&data)
+        })
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use bls12_381::Scalar;
+use rand::{thread_rng, CryptoRng, RngCore};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use ed25519_zebra::VerificationKey;
+
+use crate::error::{PrepamsError, Result};
+use crate::external::knapsack::knapsack;
+use crate::external::util::{as_scalar, as_u32};
+use crate::pbss::{self, UnblindedSignature};
+use crate::serialization::encode;
+use crate::types::*;
+use crate::types::credential::*;
+use crate::credential;
+use crate::proofs::participation::{ParticipationProof, ParticipationProofInput, ParticipationProofSecrets};
+use crate::proofs::payout::{PayoutProof, PayoutProofData, PayoutProofInput, PayoutProofSecrets};
+use crate::proofs::generic::{Transcript, GenericProof, ProofInput};
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ParticipantCore {
+    identity: String,
+    attributes: Vec<u32>,
+    credential: Option<Credential>,
+    issuerPublicKey: Option<IssuerPublicKey>,
+    creditVerificationKey: Option<pbss::PublicKey>,
+    #[schemars(with = "crate::serialization::schema::Ed25519Key")]
+    ledgerVerificationKey: VerificationKey
+}
+
+/// Payout proof and the value of the rewards it spends.
+pub struct PayoutRequest {
+    pub costs: u32,
+    pub proof: PayoutProofData
+}
+
+impl ParticipantCore {
+    pub fn new(identity: &str, attributes: &[u32], lvk: VerificationKey) -> ParticipantCore {
+        ParticipantCore {
+            identity: identity.to_string(),
+            attributes: attributes.to_vec(),
+            credential: None,
+            issuerPublicKey: None,
+            creditVerificationKey: None,
+            ledgerVerificationKey: lvk
+        }
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn attributes(&self) -> &[u32] {
+        &self.attributes
+    }
+
+    pub fn credential(&self) -> Option<&Credential> {
+        self.credential.as_ref()
+    }
+
+    // returns the signed credential together with the issuer keys
+    fn signed(&self) -> Result<(&Credential, &IssuerPublicKey, &pbss::PublicKey)> {
+        match (&self.credential, &self.issuerPublicKey, &self.creditVerificationKey) {
+            (Some(credential), Some(ipk), Some(cvk)) => {
+                if !credential.is_signed() {
+                    Err(PrepamsError::state("credential not signed"))?;
+                }
+                Ok((credential, ipk, cvk))
+            },
+            _ => Err(PrepamsError::state("credential not yet requested"))
+        }
+    }
+
+    pub fn request_credential(&mut self, ipk: IssuerPublicKey, cvk: pbss::PublicKey, rng: impl RngCore) -> Result<IssueRequest> {
+        if self.credential.is_some() {
+            Err(PrepamsError::state("already requested credential"))?;
+        }
+
+        let attributes = self.attributes.iter().map(|e| as_scalar(*e)).collect();
+
+        let (request, credential) = credential::issue_request(rng, &ipk, &self.identity, attributes)?;
+        self.credential = Some(credential);
+        self.issuerPublicKey = Some(ipk);
+        self.creditVerificationKey = Some(cvk);
+
+        Ok(request)
+    }
+
+    pub fn retrieve_credential(&mut self, response: &IssueResponse) -> Result<()> {
+        match (&mut self.credential, &self.issuerPublicKey) {
+            (Some(credential), Some(ipk)) => {
+                if credential.is_signed() {
+                    Err(PrepamsError::state("credential already retrieved"))?;
+                }
+
+                credential::get_credential(ipk, response, credential)?;
+                Ok(())
+            },
+            _ => Err(PrepamsError::state("credential not yet requested"))
+        }
+    }
+
+    /// Checks that `resource` was signed by the organizer key `vk` before
+    /// proving eligibility for it.
+    pub fn check_resource_signature(&self, resource: &SignedResource, vk: &VerificationKey) -> Result<bool> {
+        let mut data = "resource:".as_bytes().to_vec();
+        data.extend_from_slice(&encode(&resource.resource)?);
+
+        Ok(vk.verify(&resource.signature, &data).is_ok())
+    }
+
+    pub fn participate(&self, resource: &Resource) -> Result<Participation> {
+        self.participate_with_rng(resource, thread_rng())
+    }
+
+    /// Like [`participate`](Self::participate), with the proof randomness
+    /// drawn from `rng`.
+    pub fn participate_with_rng(&self, resource: &Resource, mut rng: impl RngCore + CryptoRng) -> Result<Participation> {
+        let (credential, ipk, cvk) = self.signed()?;
+
+        for constraint in &resource.constraints {
+            if let AttributeConstraint::Range(i, from, to) = constraint {
+                let value = credential.values.get(*i as usize).map_or(*from, as_u32);
+                if value < *from || value > *to {
+                    Err(PrepamsError::rejected("attributes do not satisfy the constraints of the study"))?;
+                }
+            }
+        }
+
+        let (inputs, secrets) = ParticipationProofInput::new_with_rng(
+            ipk,
+            cvk,
+            credential,
+            resource,
+            &mut rng
+        )?;
+        inputs.validate()?;
+
+        let mut prover_transcript = Transcript::new(b"participation");
+        let proof = GenericProof::<ParticipationProofInput, ()>::proove_with_rng::<ParticipationProofSecrets, ParticipationProof>(&mut prover_transcript, inputs, secrets, rng)?;
+
+        Ok(Participation { id: resource.id, proof })
+    }
+
+    /// Verifies the ledger and returns the unspent balance together with the
+    /// studies the participant was rewarded for.
+    pub fn balance(&self, transactions: &Ledger) -> Result<(u32, Vec<Scalar>)> {
+        let credential = match &self.credential {
+            Some(credential) if !credential.is_signed() => Err(PrepamsError::state("credential not signed"))?,
+            Some(credential) => credential,
+            None => Err(PrepamsError::state("credential not yet requested"))?
+        };
+
+        let mut ledger: Ledger = Ledger::default();
+        let mut participated: Vec<Scalar> = vec![];
+        let mut owned: HashMap<[u8; 32], u8> = HashMap::new();
+
+        for entry in &transactions.entries {
+            // verify entry
+            ledger.verify(&self.ledgerVerificationKey, entry)?;
+
+            match (&entry.transaction, &entry.payout) {
+                (None, Some(payout)) => {
+                    for coin in &payout.nullifier {
+                        owned.remove(&coin.to_bytes());
+                    }
+                },
+                (Some(tx), _) => {
+                    let tag = credential.derive_tag(&tx.participation.study)?;
+                    if tx.participation.tag == tag {
+                        participated.push(tx.participation.study);
+                        let mut rng = credential.derive_reward_rng(&tx.participation.study);
+                        let s = <bls12_381::Scalar as ff::Field>::random(&mut rng);
+                        owned.insert(s.to_bytes(), tx.participation.value);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let balance = owned.values().fold(0, |acc, reward| acc + *reward as u32);
+        Ok((balance, participated))
+    }
+
+    pub fn request_nulls(&self) -> Result<NullRequest> {
+        self.request_nulls_with_rng(thread_rng())
+    }
+
+    /// Like [`request_nulls`](Self::request_nulls), with the blinding factors
+    /// drawn from `rng`.
+    pub fn request_nulls_with_rng(&self, rng: impl RngCore + CryptoRng) -> Result<NullRequest> {
+        let (credential, _, cvk) = self.signed()?;
+        NullRequest::new_with_rng(cvk, credential, rng)
+    }
+
+    /// Proves ownership of unspent rewards worth at least `amount`.
+    pub fn request_payout(&self, amount: u8, target: &str, recipient: &str, nulls: Vec<UnblindedSignature>, transactions: &Ledger) -> Result<PayoutRequest> {
+        self.request_payout_with_rng(amount, target, recipient, nulls, transactions, thread_rng())
+    }
+
+    /// Like [`request_payout`](Self::request_payout), with the shuffling and
+    /// proof randomness drawn from `rng`.
+    pub fn request_payout_with_rng(&self, amount: u8, target: &str, recipient: &str, nulls: Vec<UnblindedSignature>, transactions: &Ledger, mut rng: impl RngCore + CryptoRng) -> Result<PayoutRequest> {
+        let (credential, ipk, cvk) = self.signed()?;
+
+        let mut ledger: Ledger = Ledger::default();
+        let mut owned: Vec<(Scalar, Scalar, &Transaction)> = Vec::new();
+
+        for entry in &transactions.entries {
+            // verify entry
+            ledger.verify(&self.ledgerVerificationKey, entry)?;
+
+            match (&entry.transaction, &entry.payout) {
+                (None, Some(payout)) => {
+                    for coin in &payout.nullifier {
+                        owned.retain(|(c, _, _)| coin != c);
+                    }
+                },
+                (Some(tx), _) => {
+                    let tag = credential.derive_tag(&tx.participation.study)?;
+                    if tx.participation.tag == tag {
+                        let mut rng = credential.derive_reward_rng(&tx.participation.study);
+                        let s = <bls12_381::Scalar as ff::Field>::random(&mut rng);
+                        let d = <bls12_381::Scalar as ff::Field>::random(&mut rng);
+                        owned.push((s, d, tx));
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let unspend: Vec<usize> = owned.iter().map(|(_, _, tx)| tx.participation.value as usize).collect();
+        let balance: usize = unspend.iter().sum();
+        let remaining = match balance.checked_sub(amount as usize) {
+            Some(remaining) => remaining,
+            None => Err(PrepamsError::rejected("insufficient balance"))?
+        };
+
+        let mut costs = 0;
+        let (_, items) = knapsack(remaining, unspend);
+        let spend = owned.iter()
+            .enumerate()
+            .filter(|(i, _)| !items.contains(i))
+            .map(|(_, (s, d, tx))| {
+                let m = vec![as_scalar(tx.participation.value as u32)];
+                let s = vec![*s, credential.identity];
+                costs += tx.participation.value as u32;
+                pbss::Unblind(cvk, &tx.coin, &m, &s, d)
+            }).collect::<Result<Vec<UnblindedSignature>>>()?;
+
+        let (inputs, secrets) = PayoutProofInput::new_with_rng(
+            ipk,
+            cvk,
+            amount,
+            target,
+            recipient,
+            spend,
+            nulls,
+            &mut rng
+        );
+
+        let mut transcript = Transcript::new(b"payout");
+        let proof = PayoutProofData::proove_with_rng::<PayoutProofSecrets, PayoutProof>(&mut transcript, inputs, secrets, rng)?;
+
+        Ok(PayoutRequest { costs, proof })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_chacha::rand_core::SeedableRng;
+    use crate::api::{IssuerCore, OrganizerCore};
+    use crate::serialization::{encode, decode};
+
+    #[test]
+    fn roles() {
+        let mut issuer = IssuerCore::new(2, ChaCha20Rng::from_entropy());
+        let organizer = OrganizerCore::new("lab", issuer.public_key().clone(), ChaCha20Rng::from_entropy());
+        let mut participant = ParticipantCore::new("alice", &[30, 1], issuer.ledger_verification_key());
+
+        // messages travel in the wire format between the roles
+        let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), ChaCha20Rng::from_entropy()).unwrap();
+        let response = issuer.issue_credential(&decode(&encode(&request).unwrap()).unwrap()).unwrap();
+        participant.retrieve_credential(&decode(&encode(&response).unwrap()).unwrap()).unwrap();
+        assert!(matches!(participant.retrieve_credential(&response), Err(PrepamsError::State(_))));
+
+        let mut resource = Resource::random(ChaCha20Rng::from_entropy());
+        resource.reward = 3;
+        resource.addConstraint(AttributeConstraint::Range(0, 18, 99));
+        let study = organizer.sign_resource(&resource).unwrap();
+        assert!(issuer.check_resource_signature(&study, &organizer.public_key()).unwrap());
+        assert!(participant.check_resource_signature(&study, &organizer.public_key()).unwrap());
+        assert!(!participant.check_resource_signature(&study, &issuer.ledger_verification_key()).unwrap());
+
+        let participation: Participation = decode(&encode(&participant.participate(&study.resource).unwrap()).unwrap()).unwrap();
+        participation.check().unwrap();
+
+        let mut forged: Participation = decode(&encode(&participation).unwrap()).unwrap();
+        forged.proof.inputs.tag = bls12_381::G1Affine::generator();
+        assert_eq!(forged.check().unwrap_err().code(), "verification.inner_product");
+
+        let confirmed = organizer.confirm_participation(&participation, "p1".to_string()).unwrap();
+        issuer.issue_reward(&confirmed, &organizer.public_key(), 3).unwrap();
+        assert!(matches!(issuer.issue_reward(&confirmed, &organizer.public_key(), 3), Err(PrepamsError::Rejected(_))));
+
+        let ledger = issuer.ledger().unwrap();
+        assert_eq!(participant.balance(&ledger).unwrap(), (3, vec![resource.id]));
+
+        let nulls = participant.request_nulls().unwrap();
+        let coins = issuer.issue_nulls(nulls.requests()).unwrap();
+        let nulls = nulls.unblind_nulls(coins).unwrap();
+
+        assert!(matches!(participant.request_payout(4, "bank", "alice", nulls.clone(), &ledger), Err(PrepamsError::Rejected(_))));
+        let request = participant.request_payout(2, "bank", "alice", nulls, &ledger).unwrap();
+        assert_eq!(request.costs, 3);
+
+        let receipt = issuer.check_payout_request(&decode(&encode(&request.proof).unwrap()).unwrap()).unwrap();
+        assert_eq!((receipt.target.as_str(), receipt.recipient.as_str()), ("bank", "alice"));
+        assert!(matches!(issuer.check_payout_request(&request.proof), Err(PrepamsError::Rejected(_))));
+
+        assert_eq!(participant.balance(&issuer.ledger().unwrap()).unwrap().0, 0);
+    }
+
+    #[test]
+    fn seeded() {
+        let seed = |n: u8| ChaCha20Rng::from_seed([n; 32]);
+
+        let mut issuer = IssuerCore::new(1, seed(0));
+        let organizer = OrganizerCore::new("lab", issuer.public_key().clone(), seed(0));
+        let mut participant = ParticipantCore::new("alice", &[30], issuer.ledger_verification_key());
+
+        let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), seed(1)).unwrap();
+        let response = encode(&issuer.issue_credential_with_rng(&request, seed(2)).unwrap()).unwrap();
+        assert_eq!(encode(&issuer.issue_credential_with_rng(&request, seed(2)).unwrap()).unwrap(), response);
+        participant.retrieve_credential(&decode(&response).unwrap()).unwrap();
+
+        // the same seed reproduces the proof bytes, another seed does not
+        let mut resource = Resource::random(seed(3));
+        resource.reward = 2;
+        let participation = encode(&participant.participate_with_rng(&resource, seed(4)).unwrap()).unwrap();
+        assert_eq!(encode(&participant.participate_with_rng(&resource, seed(4)).unwrap()).unwrap(), participation);
+        assert_ne!(encode(&participant.participate_with_rng(&resource, seed(5)).unwrap()).unwrap(), participation);
+
+        let confirmed = organizer.confirm_participation(&decode(&participation).unwrap(), "p1".to_string()).unwrap();
+        issuer.issue_reward_with_rng(&confirmed, &organizer.public_key(), 2, seed(6)).unwrap();
+        let ledger = issuer.ledger().unwrap();
+
+        let nulls = participant.request_nulls_with_rng(seed(7)).unwrap();
+        assert_eq!(encode(&participant.request_nulls_with_rng(seed(7)).unwrap()).unwrap(), encode(&nulls).unwrap());
+        let coins = issuer.issue_nulls_with_rng(nulls.requests(), seed(8)).unwrap();
+        assert_eq!(encode(&issuer.issue_nulls_with_rng(nulls.requests(), seed(8)).unwrap()).unwrap(), encode(&coins).unwrap());
+        let nulls = nulls.unblind_nulls(coins).unwrap();
+
+        let payout = encode(&participant.request_payout_with_rng(2, "bank", "alice", nulls.clone(), &ledger, seed(9)).unwrap().proof).unwrap();
+        assert_eq!(encode(&participant.request_payout_with_rng(2, "bank", "alice", nulls.clone(), &ledger, seed(9)).unwrap().proof).unwrap(), payout);
+        assert_ne!(encode(&participant.request_payout_with_rng(2, "bank", "alice", nulls, &ledger, seed(10)).unwrap().proof).unwrap(), payout);
+
+        issuer.check_payout_request(&decode(&payout).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn batch() {
+        let issuer = IssuerCore::new(2, ChaCha20Rng::from_entropy());
+        let mut resource = Resource::random(ChaCha20Rng::from_entropy());
+        resource.addConstraint(AttributeConstraint::Range(0, 18, 99));
+
+        let participations: Vec<Participation> = ["alice", "bob", "carol", "dave"].iter().map(|id| {
+            let mut participant = ParticipantCore::new(id, &[30, 1], issuer.ledger_verification_key());
+            let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), ChaCha20Rng::from_entropy()).unwrap();
+            participant.retrieve_credential(&issuer.issue_credential(&request).unwrap()).unwrap();
+            participant.participate(&resource).unwrap()
+        }).collect();
+
+        Participation::check_batch(&participations).unwrap();
+        Participation::check_batch(&[]).unwrap();
+
+        // a forged opening breaks the merged multi-scalar multiplication
+        let mut forged: Vec<Participation> = decode(&encode(&participations).unwrap()).unwrap();
+        forged[2].proof.tau += Scalar::one();
+        let (i, e) = Participation::check_batch(&forged).unwrap_err();
+        assert_eq!((i, e.code()), (2, "verification.inner_product"));
+
+        // a forged credential response is only caught by the pairings
+        let mut forged: Vec<Participation> = decode(&encode(&participations).unwrap()).unwrap();
+        forged[1].proof.inputs.auth_request.z3 = bls12_381::G1Affine::generator();
+        let (i, e) = Participation::check_batch(&forged).unwrap_err();
+        assert_eq!((i, e.code()), (1, "verification.credential"));
+    }
+}
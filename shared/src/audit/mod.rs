@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use ed25519_zebra::{Signature, VerificationKey};
+use serde::Serialize;
+
+use bls12_381::Scalar;
+
+use crate::pbss::BlindedSignRequest;
+use crate::serialization::SerializableScalar;
+use crate::storage::genesis;
+use crate::types::{Ledger, LedgerEntry};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AnomalyKind {
+    /// The entry does not reference the signature of its predecessor.
+    BrokenChain,
+
+    /// The entry signature does not verify under the ledger verification key.
+    InvalidSignature,
+
+    /// The entry is older than its predecessor.
+    NonMonotonicTimestamp,
+
+    /// The entry is neither a transaction nor a payout.
+    MalformedEntry,
+
+    /// The value of a transaction differs from the value its coin was signed with.
+    ValueMismatch,
+
+    /// A participation was rewarded more than once.
+    DuplicateTag,
+
+    /// A coin was spent more than once.
+    DuplicateNullifier,
+
+    /// The value paid out up to this entry exceeds the value issued before it.
+    Overspent,
+
+    /// The ledger head does not match its last entry.
+    HeadMismatch,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Anomaly {
+    pub index: Option<u64>,
+    pub kind: AnomalyKind,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StudyTotals {
+    pub participations: u64,
+    pub issued: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PayoutRecord {
+    pub index: u64,
+    pub timestamp: u64,
+    pub recipient: String,
+    pub value: u64,
+    pub inputs: usize,
+}
+
+/// Result of an independent ledger audit.
+///
+/// The ledger conserves value if it is `valid`, i.e. all entries are
+/// correctly signed and chained, no coin was spent twice and the paid out
+/// value never exceeded the issued value at any point of the chain. The
+/// issued value is the one the reward coins were signed with.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AuditReport {
+    pub valid: bool,
+    pub entries: u64,
+    pub issued: u64,
+    pub paid: u64,
+    pub studies: BTreeMap<String, StudyTotals>,
+    pub payouts: Vec<PayoutRecord>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl AuditReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("audit report is serializable")
+    }
+}
+
+/// Walks ledger entries in chain order and collects an `AuditReport`.
+///
+/// Checking continues after an anomaly, so a single report lists every
+/// problem of the ledger.
+pub struct Auditor {
+    vk: VerificationKey,
+    head: Signature,
+    time: u64,
+    tags: HashMap<[u8; 48], u64>,
+    nullifiers: HashMap<[u8; 32], u64>,
+    report: AuditReport,
+}
+
+// value the coin of a reward request is signed with
+fn coin_value(request: &BlindedSignRequest) -> Option<u8> {
+    match request.m.as_slice() {
+        [m] => (0..=u8::MAX).find(|value| &Scalar::from(*value as u64) == m),
+        _ => None
+    }
+}
+
+impl Auditor {
+    pub fn new(vk: VerificationKey) -> Auditor {
+        Auditor {
+            vk,
+            head: genesis(),
+            time: 0,
+            tags: HashMap::new(),
+            nullifiers: HashMap::new(),
+            report: AuditReport::default(),
+        }
+    }
+
+    fn anomaly(&mut self, index: Option<u64>, kind: AnomalyKind, message: String) {
+        self.report.anomalies.push(Anomaly { index, kind, message });
+    }
+
+    pub fn check(&mut self, entry: &LedgerEntry) {
+        let index = self.report.entries;
+        self.report.entries += 1;
+
+        if entry.previous != self.head {
+            self.anomaly(Some(index), AnomalyKind::BrokenChain, "entry does not extend the previous entry".to_string());
+        }
+        if entry.timestamp < self.time {
+            self.anomaly(Some(index), AnomalyKind::NonMonotonicTimestamp, format!("timestamp {} is older than {}", entry.timestamp, self.time));
+        }
+        if let Err(e) = entry.check(&self.vk, &entry.previous, 0) {
+            self.anomaly(Some(index), AnomalyKind::InvalidSignature, e.to_string());
+        }
+        self.head = entry.signature;
+        self.time = self.time.max(entry.timestamp);
+
+        match (&entry.transaction, &entry.payout) {
+            (Some(tx), None) => {
+                let value = match coin_value(&tx.participation.request) {
+                    Some(value) if value == tx.participation.value => value,
+                    Some(value) => {
+                        self.anomaly(Some(index), AnomalyKind::ValueMismatch, format!("participation claims {} but the coin is worth {}", tx.participation.value, value));
+                        value
+                    },
+                    None => {
+                        self.anomaly(Some(index), AnomalyKind::ValueMismatch, format!("participation claims {} but the coin has no valid value", tx.participation.value));
+                        0
+                    }
+                } as u64;
+                let totals = self.report.studies.entry(SerializableScalar::to_string(&tx.participation.study)).or_default();
+                totals.participations += 1;
+                totals.issued += value;
+                self.report.issued += value;
+
+                if let Some(first) = self.tags.insert(tx.participation.tag.to_compressed(), index) {
+                    self.anomaly(Some(index), AnomalyKind::DuplicateTag, format!("participation was already rewarded in entry {}", first));
+                }
+            },
+            (None, Some(payout)) => {
+                let value = payout.value as u64;
+                self.report.paid += value;
+                self.report.payouts.push(PayoutRecord {
+                    index,
+                    timestamp: entry.timestamp,
+                    recipient: base64::encode_config(payout.recipient, base64::URL_SAFE_NO_PAD),
+                    value,
+                    inputs: payout.nullifier.len(),
+                });
+
+                let mut spent = HashSet::new();
+                for nullifier in &payout.nullifier {
+                    if !spent.insert(nullifier.to_bytes()) {
+                        continue;
+                    }
+                    if let Some(first) = self.nullifiers.insert(nullifier.to_bytes(), index) {
+                        self.anomaly(Some(index), AnomalyKind::DuplicateNullifier, format!("coin was already spent in entry {}", first));
+                    }
+                }
+                if spent.len() != payout.nullifier.len() {
+                    self.anomaly(Some(index), AnomalyKind::DuplicateNullifier, "payout spends the same coin twice".to_string());
+                }
+
+                if self.report.paid > self.report.issued {
+                    let message = format!("paid out {} but only {} were issued", self.report.paid, self.report.issued);
+                    self.anomaly(Some(index), AnomalyKind::Overspent, message);
+                }
+            },
+            _ => self.anomaly(Some(index), AnomalyKind::MalformedEntry, "entry is neither a transaction nor a payout".to_string())
+        }
+    }
+
+    /// Completes the audit of a ledger whose claimed head is `head`.
+    pub fn finish(mut self, head: &Signature) -> AuditReport {
+        if head != &self.head {
+            self.anomaly(None, AnomalyKind::HeadMismatch, "ledger head does not match the last entry".to_string());
+        }
+        self.report.valid = self.report.anomalies.is_empty();
+        self.report
+    }
+}
+
+/// Audits a complete ledger against the ledger verification key.
+pub fn audit(ledger: &Ledger, vk: &VerificationKey) -> AuditReport {
+    let mut auditor = Auditor::new(*vk);
+    for entry in &ledger.entries {
+        auditor.check(entry);
+    }
+    auditor.finish(&ledger.head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_zebra::SigningKey;
+    use rand_chacha::ChaCha20Rng;
+    use rand_chacha::rand_core::SeedableRng;
+    use crate::api::{IssuerCore, OrganizerCore, ParticipantCore};
+    use crate::storage::tests::chain;
+    use crate::types::Resource;
+
+    // signs the entries of a protocol round again with `key`, so that tests can change them
+    fn resign(key: &SigningKey, entries: Vec<LedgerEntry>) -> Ledger {
+        let mut ledger = Ledger::default();
+        for entry in entries {
+            match (entry.transaction, entry.payout) {
+                (Some(tx), None) => ledger.appendTransaction(key, tx).unwrap(),
+                (None, Some(payout)) => ledger.appendPayout(key, payout).unwrap(),
+                _ => unreachable!()
+            };
+        }
+        ledger
+    }
+
+    #[test]
+    fn audit_payouts() {
+        let key = SigningKey::new(rand::thread_rng());
+        let vk = VerificationKey::from(&key);
+        let mut entries = chain(&key, 4);
+
+        // reuse a coin of the first payout in the last one
+        let nullifier = entries[0].nullifiers()[0];
+        let mut payout = entries[3].payout.clone().unwrap();
+        payout.nullifier[1] = nullifier;
+        entries[3] = LedgerEntry::sign_payout(&key, &entries[3].previous, entries[3].timestamp, payout);
+
+        let ledger = Ledger { head: entries[3].signature, entries };
+        let report = audit(&ledger, &vk);
+
+        assert!(!report.valid);
+        assert_eq!(report.entries, 4);
+        assert_eq!(report.issued, 0);
+        assert_eq!(report.paid, 1 + 2 + 3);
+        assert_eq!(report.payouts.len(), 4);
+
+        let kinds: Vec<AnomalyKind> = report.anomalies.iter().map(|a| a.kind).collect();
+        assert_eq!(kinds, vec![AnomalyKind::Overspent, AnomalyKind::Overspent, AnomalyKind::DuplicateNullifier, AnomalyKind::Overspent]);
+        assert_eq!(report.anomalies[2].index, Some(3));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["anomalies"][2]["kind"], "duplicateNullifier");
+    }
+
+    #[test]
+    fn audit_studies() {
+        let mut issuer = IssuerCore::new(1, ChaCha20Rng::from_entropy());
+        let organizer = OrganizerCore::new("lab", issuer.public_key().clone(), ChaCha20Rng::from_entropy());
+        let studies: Vec<Resource> = [2, 3].iter().map(|reward| Resource { reward: *reward, ..Resource::random(ChaCha20Rng::from_entropy()) }).collect();
+
+        // alice takes part in both studies, bob only in the first one
+        let mut participants = vec![];
+        for (name, joined) in [("alice", &studies[..]), ("bob", &studies[..1])] {
+            let mut participant = ParticipantCore::new(name, &[30], issuer.ledger_verification_key());
+            let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), ChaCha20Rng::from_entropy()).unwrap();
+            let response = issuer.issue_credential(&request).unwrap();
+            participant.retrieve_credential(&response).unwrap();
+
+            for study in joined {
+                let participation = participant.participate(study).unwrap();
+                let confirmed = organizer.confirm_participation(&participation, format!("{}-{}", name, study.reward)).unwrap();
+                issuer.issue_reward(&confirmed, &organizer.public_key(), study.reward).unwrap();
+            }
+            participants.push(participant);
+        }
+
+        let nulls = participants[0].request_nulls().unwrap();
+        let coins = issuer.issue_nulls(nulls.requests()).unwrap();
+        let nulls = nulls.unblind_nulls(coins).unwrap();
+        let request = participants[0].request_payout(4, "bank", "alice", nulls, &issuer.ledger().unwrap()).unwrap();
+        issuer.check_payout_request(&request.proof).unwrap();
+
+        let ledger = issuer.ledger().unwrap();
+        let report = audit(&ledger, &issuer.ledger_verification_key());
+        let totals = |report: &AuditReport, study: &Resource| {
+            let totals = &report.studies[&SerializableScalar::to_string(&study.id)];
+            (totals.participations, totals.issued)
+        };
+
+        assert!(report.valid, "{}", report.to_json());
+        assert_eq!((report.entries, report.issued, report.paid), (4, 7, 4));
+        assert_eq!(totals(&report, &studies[0]), (2, 4));
+        assert_eq!(totals(&report, &studies[1]), (1, 3));
+
+        // the reward of bob claims more than his coin is worth
+        let key = SigningKey::new(rand::thread_rng());
+        let mut entries = ledger.entries.clone();
+        entries[2].transaction.as_mut().unwrap().participation.value = 9;
+        let report = audit(&resign(&key, entries), &VerificationKey::from(&key));
+
+        let kinds: Vec<AnomalyKind> = report.anomalies.iter().map(|a| a.kind).collect();
+        assert_eq!(kinds, vec![AnomalyKind::ValueMismatch]);
+        assert_eq!(report.anomalies[0].index, Some(2));
+        assert_eq!(report.issued, 7);
+        assert_eq!(totals(&report, &studies[0]), (2, 4));
+    }
+
+    #[test]
+    fn audit_chain() {
+        let key = SigningKey::new(rand::thread_rng());
+        let mut entries = chain(&key, 3);
+        entries.remove(1);
+
+        let ledger = Ledger { head: entries[1].signature, entries };
+        let report = audit(&ledger, &VerificationKey::from(&SigningKey::new(rand::thread_rng())));
+
+        let kinds: Vec<AnomalyKind> = report.anomalies.iter()
+            .filter(|a| a.kind != AnomalyKind::Overspent)
+            .map(|a| a.kind)
+            .collect();
+        assert_eq!(kinds, vec![AnomalyKind::InvalidSignature, AnomalyKind::BrokenChain, AnomalyKind::InvalidSignature]);
+    }
+}
@@ -1,45 +1,25 @@
-use rand::thread_rng;
-use bls12_381::Scalar;
 use wasm_bindgen::prelude::*;
 
-use postcard::to_stdvec;
-use serde::{Serialize, Deserialize};
 use ed25519_zebra::{SigningKey, VerificationKey};
 use rand_chacha::ChaCha20Rng;
 use rand_chacha::rand_core::SeedableRng;
 
-use crate::external::util::as_scalar;
-use crate::serialization::{input, output, convert};
-use crate::pbss::{self, BlindedSignRequest, BlindedSignature, RerandomizedProofResponse};
+use crate::api::{IssuerCore, PayoutReceipt};
+use crate::serialization::{input, output, convert, throw, SerializableScalar};
+use crate::pbss::BlindedSignRequest;
 use crate::types::*;
-use crate::types::credential::*;
-use crate::credential;
-use crate::proofs::generic::{Transcript, GenericProof};
-use crate::proofs::payout::{MAX_INPUTS, PayoutProof, PayoutProofInput, PayoutProofSecrets};
+use crate::proofs::payout::PayoutProofData;
 
 use super::participant::Participant;
 
 #[wasm_bindgen]
-#[allow(non_snake_case)]
-#[derive(Serialize, Deserialize)]
 pub struct Issuer {
-    attributes: usize,
-    signingKey: SigningKey,
-    publicKey: IssuerPublicKey,
-    secretKey: IssuerSecretKey,
-    creditSigningKey: pbss::SecretKey,
-    creditVerificationKey: pbss::PublicKey,
-    #[serde(skip_serializing)]
-    #[serde(skip_deserializing)]
-    pub(crate) ledger: Ledger
+    core: IssuerCore
 }
 
 #[wasm_bindgen]
-#[allow(non_snake_case)]
 pub struct PayoutResult {
-    target: String,
-    recipient: String,
-    entry: LedgerEntry
+    receipt: PayoutReceipt
 }
 
 #[wasm_bindgen]
@@ -47,223 +27,136 @@ pub struct PayoutResult {
 impl PayoutResult {
   #[wasm_bindgen(getter)]
   pub fn target(&self) -> String {
-    self.target.clone()
+    self.receipt.target.clone()
   }
 
   #[wasm_bindgen(getter)]
   pub fn recipient(&self) -> String {
-    self.recipient.clone()
+    self.receipt.recipient.clone()
   }
 
   #[wasm_bindgen(getter)]
   pub fn entry(&self) -> LedgerEntry {
-    self.entry.clone()
+    self.receipt.entry.clone()
   }
 }
 
+impl Issuer {
+    pub fn core(&self) -> &IssuerCore {
+        &self.core
+    }
+}
+
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 impl Issuer {
     #[wasm_bindgen(constructor)]
     pub fn new(attributes: usize, seed: &[u8]) -> Issuer {
-        let mut rng = if seed.len() != 32 {
+        let rng = if seed.len() != 32 {
             ChaCha20Rng::from_rng(rand::thread_rng()).unwrap()
         } else {
             // generation from seed for evaluation
             let seed: [u8;32] = seed.try_into().unwrap();
             ChaCha20Rng::from_seed(seed)
         };
-        let (ipk, isk) = credential::init(&mut rng, attributes);
-        let (csk, cvk) = pbss::Gen(&mut rng, 2, 1, "payment");
-        let sk = SigningKey::new(&mut rng);
-        
-        Issuer {
-            attributes,
-            signingKey: sk,
-            publicKey: ipk,
-            secretKey: isk,
-            creditSigningKey: csk,
-            creditVerificationKey: cvk,
-            ledger: Ledger::default()
-        }
+
+        Issuer { core: IssuerCore::new(attributes, rng) }
     }
 
     #[wasm_bindgen(getter)]
     pub fn attributes(&self) -> usize {
-        self.attributes
+        self.core.attributes()
     }
 
     #[wasm_bindgen(getter)]
     pub fn publicKey(&self) -> Result<Vec<u8>, JsError> {
-        output(&self.publicKey)
+        output(self.core.public_key())
     }
 
     #[wasm_bindgen(getter)]
     pub fn verificationKey(&self) -> Result<Vec<u8>, JsError> {
-        output(&self.creditVerificationKey)
+        output(self.core.credit_verification_key())
     }
 
     #[wasm_bindgen(getter)]
     pub fn ledgerVerificationKey(&self) -> Result<Vec<u8>, JsError> {
-        let lvk: [u8; 32] = VerificationKey::from(&self.signingKey).into();
-        output(lvk)
+        let lvk: [u8; 32] = self.core.ledger_verification_key().into();
+        Ok(lvk.to_vec())
     }
 
     #[wasm_bindgen(getter)]
     pub fn ledger(&self) -> Result<Vec<u8>, JsError> {
-        output(&self.ledger)
+        output(&self.core.ledger().map_err(throw)?)
     }
 
     #[wasm_bindgen(getter)]
     pub fn head(&self) -> Result<Vec<u8>, JsError> {
-        output(&self.ledger.head)
+        Ok(self.core.head().to_bytes().to_vec())
     }
 
     #[wasm_bindgen]
     pub fn load(&mut self, data: &[u8]) -> Result<(), JsError> {
-        let ledger: Ledger = input(&data)?;
-        self.ledger = ledger;
-        Ok(())
+        self.core.load(input(data)?).map_err(throw)
     }
 
     #[wasm_bindgen]
     pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-        output(&self)
+        output(&self.core)
     }
 
     #[wasm_bindgen]
     pub fn deserialize(data: &[u8]) -> Result<Issuer, JsError> {
-        input(data)
+        Ok(Issuer { core: input(data)? })
     }
 
     pub fn issueCredential(&self, request: &[u8]) -> Result<Vec<u8>, JsError> {
-        let request: IssueRequest = input(request)?;
-        let response = convert(credential::issue(&self.publicKey, &self.secretKey, &request))?;
-        output(response)
+        output(&self.core.issue_credential(&input(request)?).map_err(throw)?)
     }
 
     pub fn checkResourceSignature(&self, resource: &SignedResource, publicKey: &[u8]) -> Result<bool, JsError> {
-        let blob = output(resource.resource.clone())?;
-        let mut data = "resource:".as_bytes().to_vec();
-        data.extend_from_slice(&blob);
-
-        let vk: VerificationKey = input(publicKey)?;
-
-        Ok(vk.verify(&resource.signature, &data).is_ok())
+        let vk = convert(VerificationKey::try_from(publicKey))?;
+        self.core.check_resource_signature(resource, &vk).map_err(throw)
     }
 
     pub fn issueReward(&mut self, participation: &ConfirmedParticipation, pk: &[u8], reward: u8) -> Result<LedgerEntry, JsError> {
-        let mut data = participation.id.as_bytes().to_vec();
-        let mut req = to_stdvec(&participation.request)?;
-        data.append(&mut req);
-
         let vk = convert(VerificationKey::try_from(pk))?;
-        if vk.verify(&participation.signature, &data).is_err() {
-            Err(JsError::new("reward signature invalid"))?;
-        }
-        if participation.value != reward {
-            Err(JsError::new("reward amount does not match study"))?;
-        }
-
-        let coin = pbss::Sign(&self.creditVerificationKey, &self.creditSigningKey, &participation.request, thread_rng())?;
-        let tx = Transaction {
-            participation: participation.clone(),
-            coin
-        };
-        self.ledger.appendTransaction(&self.signingKey, tx)
+        self.core.issue_reward(participation, &vk, reward).map_err(throw)
     }
 
     pub fn issueNulls(&mut self, request: &[u8]) -> Result<Vec<u8>, JsError> {
         let requests: Vec<BlindedSignRequest> = input(request)?;
-        let mut coins: Vec<BlindedSignature> = vec![];
-
-        for req in requests {
-            if req.m.len() != 1 {
-                Err(JsError::new("request contains invalid amount of attributes"))?;
-            }
-            if req.m[0] != Scalar::zero() {
-                Err(JsError::new("value of request is not zero"))?;
-            }
-
-            coins.push(pbss::Sign(&self.creditVerificationKey, &self.creditSigningKey, &req, thread_rng())?);
-        }
-
-        output(coins)
+        output(&self.core.issue_nulls(&requests).map_err(throw)?)
     }
 
     pub fn appendEntry(mut self, entry: LedgerEntry) -> Result<Issuer, JsError> {
-        let vk = VerificationKey::from(&self.signingKey);
-        self.ledger.verify(&vk, &entry)?;
+        self.core.append_entry(entry).map_err(throw)?;
         Ok(self)
     }
 
     pub fn checkPayoutRequest(&mut self, request: &[u8]) -> Result<PayoutResult, JsError> {
-        let proof: GenericProof::<PayoutProofInput, Vec<RerandomizedProofResponse>> = input(request)?;
-
-        if proof.inputs.inputs.len() != MAX_INPUTS {
-            Err(JsError::new("invalid padding"))?;
-        }
-
-        if proof.inputs.cvk != self.creditVerificationKey {
-            Err(JsError::new("invalid public key"))?;
-        }
-
-        let mut verifier_transcript = Transcript::new(b"payout");
-        convert(proof.verify::<PayoutProofSecrets, PayoutProof>(&mut verifier_transcript))?;
-
-        let mut data = "payout:".as_bytes().to_vec();
-        let inputs = convert(to_stdvec(&proof.inputs))?;
-        data.extend_from_slice(&inputs);
+        let proof: PayoutProofData = input(request)?;
+        Ok(PayoutResult { receipt: self.core.check_payout_request(&proof).map_err(throw)? })
+    }
 
-        let entry = self.ledger.appendPayout(&self.signingKey, Payout::from(&proof))?;
+    /// Exports the entries at positions `from..to`, optionally filtered by
+    /// entry type and study.
+    pub fn ledgerRange(&self, from: usize, to: usize, entryType: Option<LedgerEntryType>, study: Option<String>) -> Result<LedgerSlice, JsError> {
+        let study = study.map(|s| SerializableScalar::from_string(&s)).transpose().map_err(throw)?;
+        self.core.export_range(from as u64, to as u64, None, &LedgerFilter { entryType, study }).map_err(throw)
+    }
 
-        Ok(PayoutResult {
-            entry: entry,
-            target: proof.inputs.target.clone(),
-            recipient: proof.inputs.recipient.clone()
-        })
+    /// Exports up to `limit` matching entries following the entry with the
+    /// signature `head`. The all-zero signature starts at the beginning.
+    pub fn ledgerAfter(&self, head: &[u8], limit: usize, entryType: Option<LedgerEntryType>, study: Option<String>) -> Result<LedgerSlice, JsError> {
+        let head: [u8; 64] = convert(head.try_into())?;
+        let study = study.map(|s| SerializableScalar::from_string(&s)).transpose().map_err(throw)?;
+        self.core.export_after(&head.into(), limit as u64, &LedgerFilter { entryType, study }).map_err(throw)
     }
 
     // only used for evaluation purposes to quickly add a preceeding participation to the ledger
     pub fn bootstrapLedger(&mut self, participant: &Participant, organizerSeed: &[u8], resource: &Resource, id: String) -> Result<LedgerEntry, JsError> {
-        let credential = participant.credential().unwrap();
-
-        let study = resource.id.clone();
-        let tag = credential.derive_tag(&resource.id).unwrap();
-        let mut prng = credential.derive_reward_rng(&study);
-        let s = <bls12_381::Scalar as ff::Field>::random(&mut prng);
-        let d = <bls12_381::Scalar as ff::Field>::random(&mut prng);
-        let reward = as_scalar(resource.reward.into());
-        let reward_request = pbss::Blind(&self.creditVerificationKey, &vec![reward], &vec![s, credential.identity], &d, &mut prng);
-
-        let mut data = id.as_bytes().to_vec();
-        let mut req = to_stdvec(&reward_request)?;
-        data.append(&mut req);
-
         let seed: [u8; 32] = convert(organizerSeed.try_into())?;
-        let mut rng = ChaCha20Rng::from_seed(seed);
-        let secretKey = SigningKey::new(&mut rng);
-        let sig = secretKey.sign(&data);
-
-        let participation = ConfirmedParticipation {
-            id: id,
-            study: study,
-            tag: tag,
-            value: resource.reward,
-            request: reward_request,
-            signature: sig
-        };
-
-        let mut data = participation.id.as_bytes().to_vec();
-        let mut req = to_stdvec(&participation.request)?;
-        data.append(&mut req);
-
-        let coin = pbss::Sign(&self.creditVerificationKey, &self.creditSigningKey, &participation.request, thread_rng())?;
-        let tx = Transaction {
-            participation: participation,
-            coin
-        };
-
-        self.ledger.appendTransaction(&self.signingKey, tx)
+        let organizer = SigningKey::new(ChaCha20Rng::from_seed(seed));
+        self.core.bootstrap_ledger(participant.core(), &organizer, resource, id).map_err(throw)
     }
 }
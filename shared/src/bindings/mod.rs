@@ -1,6 +1,12 @@
 use wasm_bindgen::prelude::*;
 
-use crate::serialization::convert;
+use ed25519_zebra::VerificationKey;
+
+use crate::audit;
+use crate::error::PrepamsError;
+use crate::types::Ledger;
+use crate::serialization::{convert, input, throw, envelope};
+use crate::serialization::format::{self, Format};
 extern crate console_error_panic_hook;
 
 pub mod issuer;
@@ -12,6 +18,29 @@ pub fn init() {
     console_error_panic_hook::set_once();
 }
 
+/// Sets the deployment domain that serialized objects are sealed for, e.g.
+/// the url of the issuer. Objects of other domains are rejected.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn setDomain(domain: &str) {
+    envelope::set_domain(domain);
+}
+
+/// Converts a serialized object into another format, e.g. JSON for inspection.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn convertMessage(data: &[u8], format: Format) -> Result<Vec<u8>, JsError> {
+    format::transcode(data, format).map_err(throw)
+}
+
+/// Returns the JSON schema of an object type, e.g. `participation`.
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn messageSchema(kind: &str) -> Result<String, JsError> {
+    let kind = envelope::Kind::from_id(kind).ok_or_else(|| throw(PrepamsError::Encoding(format!("unknown object type {:?}", kind))))?;
+    Ok(format::schema(kind).to_string())
+}
+
 #[wasm_bindgen]
 pub fn b64decode(input: String) -> Result<Vec<u8>, JsError> {
     convert(base64::decode_config(&input, base64::URL_SAFE_NO_PAD))
@@ -21,3 +50,11 @@ pub fn b64decode(input: String) -> Result<Vec<u8>, JsError> {
 pub fn b64encode(input: &[u8]) -> String {
     base64::encode_config(&input, base64::URL_SAFE_NO_PAD)
 }
+
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+pub fn auditLedger(ledger: &[u8], lvk: &[u8]) -> Result<String, JsError> {
+    let ledger: Ledger = input(ledger)?;
+    let vk = convert(VerificationKey::try_from(lvk))?;
+    Ok(audit::audit(&ledger, &vk).to_json())
+}
@@ -1,15 +1,18 @@
 use ff::Field;
-use simple_error::SimpleError;
 use std::ops::Neg;
 use group::Curve;
-use rand::RngCore;
+use rand::{CryptoRng, RngCore};
 use merlin::Transcript;
-use bls12_381::{G1Affine, G2Affine, Scalar, pairing};
+use std::sync::OnceLock;
+use bls12_381::{G1Affine, G2Affine, G2Prepared, Gt, Scalar, multi_miller_loop, pairing};
 use bls12_381::hash_to_curve::{HashToCurve, ExpandMsgXmd};
 use sha2::{Digest, Sha512};
 
+use crate::error::{Check, PrepamsError};
 use crate::types::credential::{AuthenticationRequest, Credential,IssueRequest, IssueResponse, IssuerPublicKey, IssuerSecretKey};
 use crate::external::transcript::TranscriptProtocol;
+use crate::msm::{fixed_base, multiscalar_mul, vartime_multiscalar_mul, GENERATOR};
+use crate::parallel;
 
 #[allow(non_snake_case)]
 pub fn CREDENTIAL_H() -> G1Affine {
@@ -31,6 +34,18 @@ pub fn BINDING_G() -> G1Affine {
     G1Affine::from_compressed(&[137, 179, 142, 119, 0, 117, 198, 112, 161, 144, 244, 121, 238, 137, 146, 174, 168, 52, 175, 13, 243, 130, 119, 106, 120, 73, 178, 201, 79, 108, 162, 118, 249, 82, 58, 61, 113, 201, 168, 211, 109, 109, 71, 166, 55, 5, 66, 182]).unwrap()
 }
 
+fixed_base!(#[allow(non_snake_case)] CREDENTIAL_H_TABLE, CREDENTIAL_H());
+fixed_base!(#[allow(non_snake_case)] CREDENTIAL_V_TABLE, CREDENTIAL_V());
+fixed_base!(#[allow(non_snake_case)] CREDENTIAL_I_TABLE, CREDENTIAL_I());
+fixed_base!(#[allow(non_snake_case)] BINDING_G_TABLE, BINDING_G());
+
+/// Generator of G2 prepared for the Miller loop.
+#[allow(non_snake_case)]
+pub(crate) fn G2_GENERATOR() -> &'static G2Prepared {
+    static PREPARED: OnceLock<G2Prepared> = OnceLock::new();
+    PREPARED.get_or_init(|| G2Prepared::from(G2Affine::generator()))
+}
+
 pub fn init(rng: impl RngCore, attributes: usize) -> (IssuerPublicKey, IssuerSecretKey) {
     let sk = Scalar::random(rng);
     let pk = pairing(&G1Affine::generator(), &G2Affine::generator()) * &sk;
@@ -39,7 +54,7 @@ pub fn init(rng: impl RngCore, attributes: usize) -> (IssuerPublicKey, IssuerSec
     (IssuerPublicKey {pk, a}, IssuerSecretKey{sk})
 }
 
-pub fn issue_request(rng: impl RngCore, pk: &IssuerPublicKey, id: &str, attributes: Vec<Scalar>) -> (IssueRequest, Credential) {
+pub fn issue_request(rng: impl RngCore, pk: &IssuerPublicKey, id: &str, attributes: Vec<Scalar>) -> Result<(IssueRequest, Credential), PrepamsError> {
     let mut rng = rng;
     let sk = Scalar::random(&mut rng);
     let d = Scalar::random(&mut rng);
@@ -50,18 +65,20 @@ pub fn issue_request(rng: impl RngCore, pk: &IssuerPublicKey, id: &str, attribut
     let hash: [u8;64] = hasher.finalize().into();
     let identity = Scalar::from_bytes_wide(&hash);
 
-    assert!(attributes.len() == pk.a.len());
+    if attributes.len() != pk.a.len() {
+        Err(PrepamsError::encoding("Invalid attributes supplied"))?;
+    }
 
     let v = CREDENTIAL_V();
     let h = CREDENTIAL_H();
     let i = CREDENTIAL_I();
 
-    let alpha = (&v * &sk + G1Affine::generator() * &d).to_affine();
+    let alpha = (CREDENTIAL_V_TABLE().mul(&sk) + GENERATOR().mul(&d)).to_affine();
 
     let b1 = Scalar::random(&mut rng);
     let b2 = Scalar::random(&mut rng);
 
-    let gamma = (&v * &b1 + G1Affine::generator() * &b2).to_affine();
+    let gamma = (CREDENTIAL_V_TABLE().mul(&b1) + GENERATOR().mul(&b2)).to_affine();
 
     let mut t = Transcript::new(b"issue-request");
     t.append_gt(b"pk", &pk.pk);
@@ -84,7 +101,7 @@ pub fn issue_request(rng: impl RngCore, pk: &IssuerPublicKey, id: &str, attribut
     let z1 = &b1 + &c * &sk;
     let z2 = &b2 + &c * &d;
 
-    (
+    Ok((
         IssueRequest {
             id: id.to_string(),
             alpha,
@@ -104,16 +121,16 @@ pub fn issue_request(rng: impl RngCore, pk: &IssuerPublicKey, id: &str, attribut
             attributes: pk.a.clone(),
             values: attributes
         }
-    )
+    ))
 }
 
-pub fn issue(pk: &IssuerPublicKey, sk: &IssuerSecretKey, request: &IssueRequest) -> Result<IssueResponse, SimpleError> {
+pub fn issue(rng: impl RngCore + CryptoRng, pk: &IssuerPublicKey, sk: &IssuerSecretKey, request: &IssueRequest) -> Result<IssueResponse, PrepamsError> {
     let v = CREDENTIAL_V();
     let h = CREDENTIAL_H();
     let i = CREDENTIAL_I();
 
     if pk.a.len() != request.attributes.len() {
-        Err(SimpleError::new("Invalid attributes supplied"))?;
+        Err(PrepamsError::encoding("Invalid attributes supplied"))?;
     }
 
     let mut hasher = Sha512::new();
@@ -140,42 +157,40 @@ pub fn issue(pk: &IssuerPublicKey, sk: &IssuerSecretKey, request: &IssueRequest)
 
     let c = t.challenge_scalar(b"c");
 
-    let l = &v * &request.z1 + G1Affine::generator() * &request.z2;
+    let l = CREDENTIAL_V_TABLE().mul(&request.z1) + GENERATOR().mul(&request.z2);
     let r = &request.alpha * c + &request.gamma;
 
     if l != r {
-        Err(SimpleError::new("Verification failed"))
+        Err(PrepamsError::verification(Check::Request, "Verification failed"))
     } else {
-        let r = Scalar::random(rand::thread_rng());
+        let r = Scalar::random(rng);
 
-        let tmp = pk.a.iter().zip(&request.attributes).fold(
-            &i * &identity + &request.alpha + &h,
-            |s, (g, e)| s + g * e
-        );
+        let tmp = CREDENTIAL_I_TABLE().mul(&identity) + request.alpha + h + multiscalar_mul(&request.attributes, &pk.a);
 
-        let sigma_1 = (G1Affine::generator() * sk.sk + tmp * &r).to_affine();
-        let sigma_2 = (G1Affine::generator() * &r).to_affine();
+        let sigma_1 = (GENERATOR().mul(&sk.sk) + tmp * r).to_affine();
+        let sigma_2 = GENERATOR().mul(&r).to_affine();
         let sigma_3 = (G2Affine::generator() * &r).to_affine();
 
         Ok(IssueResponse {sigma_1, sigma_2, sigma_3})
     }
 }
 
-pub fn get_credential(pk: &IssuerPublicKey, response: &IssueResponse, credential: &mut Credential) -> Result<(), SimpleError> {
-    let v = CREDENTIAL_V();
+pub fn get_credential(pk: &IssuerPublicKey, response: &IssueResponse, credential: &mut Credential) -> Result<(), PrepamsError> {
     let h = CREDENTIAL_H();
-    let i = CREDENTIAL_I();
 
-    let tmp = pk.a.iter().zip(&credential.values).fold(
-        &v * &credential.sk + G1Affine::generator() * &credential.d + &h + &i * &credential.identity,
-        |s, (g, e)| s + g * e
+    let tmp = (
+        CREDENTIAL_V_TABLE().mul(&credential.sk) + GENERATOR().mul(&credential.d) + h + CREDENTIAL_I_TABLE().mul(&credential.identity)
+        + multiscalar_mul(&credential.values, &pk.a)
     ).to_affine();
 
-    let l = pairing(&response.sigma_1, &G2Affine::generator());
-    let r = pairing(&tmp, &response.sigma_3) + pk.pk;
+    // e(sigma_1, g2) == e(tmp, sigma_3) + pk
+    let l = multi_miller_loop(&[
+        (&response.sigma_1, G2_GENERATOR()),
+        (&-tmp, &G2Prepared::from(response.sigma_3)),
+    ]).final_exponentiation();
 
-    if l != r {
-        Err(SimpleError::new("Verification failed"))
+    if l != pk.pk {
+        Err(PrepamsError::verification(Check::Signature, "Verification failed"))
     } else {
         credential.sigma_1 = Some((&response.sigma_1 + &response.sigma_2 * &credential.d.neg()).to_affine());
         credential.sigma_2 = Some(response.sigma_2.clone());
@@ -185,63 +200,40 @@ pub fn get_credential(pk: &IssuerPublicKey, response: &IssueResponse, credential
     }
 }
 
-pub fn authenticate(credential: &Credential, id: &Scalar) -> (AuthenticationRequest, (Scalar, G1Affine)) {
-    let mut rng = rand::thread_rng();
-
-    let v = CREDENTIAL_V();
+pub fn authenticate(mut rng: impl RngCore + CryptoRng, credential: &Credential, id: &Scalar) -> (AuthenticationRequest, (Scalar, G1Affine)) {
     let h = CREDENTIAL_H();
-    let i = CREDENTIAL_I();
 
-    let token = (G1Affine::generator() * (&credential.sk + id).invert().unwrap()).to_affine();
+    let token = GENERATOR().mul(&(credential.sk + id).invert().unwrap()).to_affine();
     let d = Scalar::random(&mut rng);
 
-    let u = &credential.attributes.iter().zip(&credential.values).fold(
-        i * &credential.identity,
-        |s, (g, e)| s + g * e
-    );
+    let u = CREDENTIAL_I_TABLE().mul(&credential.identity) + multiscalar_mul(&credential.values, &credential.attributes);
 
     // vector commitment
-    let g = BINDING_G();
     let r = Scalar::random(&mut rng);
-    let vc = credential.attributes.iter().zip(credential.values.iter()).fold(
-        g * r + i * &credential.identity,
-        |s, (u, v)| s + u * v
-    ).to_affine();
+    let vc = (BINDING_G_TABLE().mul(&r) + u).to_affine();
 
-    let s1 = (credential.sigma_1.as_ref().unwrap() + (u + &v * &credential.sk + &h) * &d).to_affine();
+    let s1 = (credential.sigma_1.as_ref().unwrap() + (u + CREDENTIAL_V_TABLE().mul(&credential.sk) + h) * d).to_affine();
     let s2 = (credential.sigma_3.as_ref().unwrap() + G2Affine::generator() * &d).to_affine();
 
     // proof of knowledge
     let b1 = Scalar::random(&mut rng);
     let b2 = Scalar::random(&mut rng);
     let bu: Vec<Scalar> = credential.attributes.iter().map(|_| Scalar::random(&mut rng)).collect();
-    let j1 = (G1Affine::generator() * Scalar::random(&mut rng)).to_affine();
+    let j1 = GENERATOR().mul(&Scalar::random(&mut rng)).to_affine();
 
-    let ub = &credential.attributes.iter().zip(bu.iter()).fold(
-        i * b2.neg(),
-        |s, (u, b)| s + u * b.neg()
-    );
+    let ib = CREDENTIAL_I_TABLE().mul(&b2) + multiscalar_mul(&bu, &credential.attributes);
 
     let s = Scalar::random(&mut rng);
-    let bp = &credential.attributes.iter().zip(bu.iter()).fold(
-        BINDING_G() * s + i * b2,
-        |s, (u, b)| s + u * b
-    ).to_affine();
+    let bp = (BINDING_G_TABLE().mul(&s) + ib).to_affine();
 
-    let e1 = pairing(&j1, &G2Affine::generator()) + pairing(&(ub + &v * &b1.neg()).to_affine(), &s2);
-    let e2 = pairing(&token, &(G2Affine::generator() * &b1).to_affine());
+    let e1 = multi_miller_loop(&[
+        (&j1, G2_GENERATOR()),
+        (&(ib + CREDENTIAL_V_TABLE().mul(&b1)).neg().to_affine(), &G2Prepared::from(s2)),
+    ]).final_exponentiation();
+    let e2 = multi_miller_loop(&[(&(token * b1).to_affine(), G2_GENERATOR())]).final_exponentiation();
 
     // challenge
-    let mut t = Transcript::new(b"authentication");
-    t.append_scalar(b"id", &id);
-    t.append_g1(b"token", &token);
-    t.append_g2(b"s2", &s2);
-    t.append_gt(b"e1", &e1);
-    t.append_gt(b"e2", &e2);
-    t.append_g1(b"vc", &vc);
-    t.append_g1(b"bp", &bp);
-
-    let c = t.challenge_scalar(b"c");
+    let c = challenge(id, &token, &s2, &e1, &e2, &vc, &bp);
 
     // response
     let z1 = &b1 + &c * &credential.sk;
@@ -250,66 +242,74 @@ pub fn authenticate(credential: &Credential, id: &Scalar) -> (AuthenticationRequ
     let z3 = (&s1 * &c + &j1).to_affine();
     let zv = &s + &c * &r;
 
-    (AuthenticationRequest {
-        id: id.clone(),
-        token: token,
-        s2: s2,
-        e1: e1,
-        e2: e2,
-        z1: z1,
-        z2: z2,
-        za: za,
-        z3: z3,
-        vc: vc.clone(),
-        bp: bp.clone(),
-        zv: zv
-    }, (r, vc))
+    (AuthenticationRequest { id: *id, token, s2, c, z1, z2, za, z3, vc, zv }, (r, vc))
 }
 
-pub fn verify(pk: &IssuerPublicKey, request: &AuthenticationRequest) -> bool {
-    let v = CREDENTIAL_V();
-    let h = CREDENTIAL_H();
-    let i = CREDENTIAL_I();
+/// Fiat-Shamir challenge of the commitments `e1`, `e2` and `bp` of an
+/// authentication request.
+pub(crate) fn challenge(id: &Scalar, token: &G1Affine, s2: &G2Affine, e1: &Gt, e2: &Gt, vc: &G1Affine, bp: &G1Affine) -> Scalar {
+    let mut t = Transcript::new(b"authentication");
+    t.append_scalar(b"id", id);
+    t.append_g1(b"token", token);
+    t.append_g2(b"s2", s2);
+    t.append_gt(b"e1", e1);
+    t.append_gt(b"e2", e2);
+    t.append_g1(b"vc", vc);
+    t.append_g1(b"bp", bp);
+
+    t.challenge_scalar(b"c")
+}
 
+/// Checks that the request proves possession of a credential of `pk`.
+///
+/// The commitments are recomputed from the responses and must hash to the
+/// challenge of the request.
+pub fn verify(pk: &IssuerPublicKey, request: &AuthenticationRequest) -> bool {
     if pk.a.len() != request.za.len() {
         return false;
     }
 
-    // challenge
-    let mut t = Transcript::new(b"authentication");
-    t.append_scalar(b"id", &request.id);
-    t.append_g1(b"token", &request.token);
-    t.append_g2(b"s2", &request.s2);
-    t.append_gt(b"e1", &request.e1);
-    t.append_gt(b"e2", &request.e2);
-    t.append_g1(b"vc", &request.vc);
-    t.append_g1(b"bp", &request.bp);
+    let (e1, e2, bp) = commitments(pk, request);
+    challenge(&request.id, &request.token, &request.s2, &e1, &e2, &request.vc, &bp) == request.c
+}
 
-    let c = t.challenge_scalar(b"c");
+/// Checks many authentication requests like [`verify`].
+///
+/// The challenge of a request hashes its commitments, so they have to be
+/// recomputed exactly: unlike the final equations of the proofs, the pairings
+/// of different requests cannot be merged into one multi-Miller loop. The
+/// requests are checked on the thread pool instead.
+pub(crate) fn verify_batch(requests: &[(&IssuerPublicKey, &AuthenticationRequest)]) -> bool {
+    parallel::map(requests, |(pk, request)| verify(pk, request)).into_iter().all(|valid| valid)
+}
 
-    let tmp = pk.a.iter().zip(&request.za).fold(
-        &v * &request.z1.neg() + &i * &request.z2.neg(),
-        |s, (u, z)| s + u * z.neg()
-    );
+/// Recomputes the commitments `e1`, `e2` and `bp` of a request from its
+/// challenge and responses.
+pub(crate) fn commitments(pk: &IssuerPublicKey, request: &AuthenticationRequest) -> (Gt, Gt, G1Affine) {
+    let c = request.c;
+    let iz = CREDENTIAL_I_TABLE().mul(&request.z2) + vartime_multiscalar_mul(&request.za, &pk.a);
+    let tmp = (iz + CREDENTIAL_V_TABLE().mul(&request.z1) + CREDENTIAL_H_TABLE().mul(&c)).neg();
 
-    let l1 = &request.e1 + (pk.pk * &c) + pairing(&(h * c).to_affine(), &request.s2);
-    let r1 = pairing(&request.z3, &G2Affine::generator()) + pairing(&tmp.to_affine(), &request.s2);
+    // e1 = e(z3, g2) + e(-(iz + z1 * V + c * H), s2) - c * pk
+    let e1 = multi_miller_loop(&[
+        (&request.z3, G2_GENERATOR()),
+        (&tmp.to_affine(), &G2Prepared::from(request.s2)),
+    ]).final_exponentiation() - pk.pk * c;
 
-    let l2 = &request.e2 + pairing(&(G1Affine::generator() * c).to_affine(), &G2Affine::generator()) + pairing(&(request.token * (c * request.id).neg()).to_affine(), &G2Affine::generator());
-    let r2 = pairing(&(request.token * request.z1).to_affine(), &G2Affine::generator());
+    // e2 = e(z1 * token + c * id * token - c * G, g2)
+    let e2 = multi_miller_loop(&[
+        (&vartime_multiscalar_mul(&[request.z1 + c * request.id, -c], &[request.token, G1Affine::generator()]).to_affine(), G2_GENERATOR()),
+    ]).final_exponentiation();
 
-    let l3 = request.vc * c + request.bp;
-    let r3 = pk.a.iter().zip(&request.za).fold(
-        BINDING_G() * request.zv + i * request.z2,
-        |s, (u, z)| s + u * z
-    );
+    // bp = zv * G' + iz - c * vc
+    let bp = (BINDING_G_TABLE().mul(&request.zv) + iz - request.vc * c).to_affine();
 
-    l1 == r1 && l2 == r2 && l3 == r3
+    (e1, e2, bp)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::external::util::assert_generators;
+    use crate::external::util::{assert_generators, rand_scalar};
 
     use super::*;
 
@@ -321,14 +321,22 @@ mod tests {
         let (ipk, isk) = init(&mut rng, 3);
 
         let attrs: Vec<Scalar> = ipk.a.iter().map(|_| Scalar::random(&mut rng)).collect();
-        let (request, mut credential) = issue_request(&mut rng, &ipk, &identity, attrs.clone());
-        let response = issue(&ipk, &isk, &request).unwrap();
+        let (request, mut credential) = issue_request(&mut rng, &ipk, identity, attrs.clone()).unwrap();
+        let response = issue(&mut rng, &ipk, &isk, &request).unwrap();
         get_credential(&ipk, &response, &mut credential).unwrap();
 
-        let resource = Scalar::random(rng);
-        let (request, _) = authenticate(&credential, &resource);
+        let resource = Scalar::random(&mut rng);
+        let (request, _) = authenticate(&mut rng, &credential, &resource);
 
         assert!(verify(&ipk, &request));
+
+        let (other, _) = authenticate(&mut rng, &credential, &rand_scalar());
+        assert!(verify_batch(&[(&ipk, &request), (&ipk, &other)]));
+
+        let mut forged = other.clone();
+        forged.z1 += Scalar::one();
+        assert!(!verify(&ipk, &forged));
+        assert!(!verify_batch(&[(&ipk, &request), (&ipk, &forged)]));
     }
 
     #[test]
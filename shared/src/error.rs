@@ -0,0 +1,195 @@
+use std::fmt;
+use std::error::Error;
+
+use crate::storage::StorageError;
+use crate::types::ProofError;
+
+/// Check of a proof or signature that failed to verify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// The inner product argument of a proof is invalid.
+    InnerProduct,
+
+    /// The polynomial commitment of a proof does not open to the constraints.
+    Commitment,
+
+    /// The participant holds a tag of a disqualifying study.
+    Disqualifier,
+
+    /// The credential presented in a participation proof is invalid.
+    Credential,
+
+    /// The authentication token does not match the participation tag.
+    Tag,
+
+    /// A rerandomized reward signature of a payout proof is invalid.
+    Rerandomization,
+
+    /// The proof of knowledge of a credential or blind signature request is invalid.
+    Request,
+
+    /// A credential, reward or organizer signature is invalid.
+    Signature,
+
+    /// A ledger entry or slice does not link to the ledger or is not signed by the issuer.
+    Ledger,
+}
+
+impl Check {
+    /// Returns the stable code of the check.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Check::InnerProduct => "verification.inner_product",
+            Check::Commitment => "verification.commitment",
+            Check::Disqualifier => "verification.disqualifier",
+            Check::Credential => "verification.credential",
+            Check::Tag => "verification.tag",
+            Check::Rerandomization => "verification.rerandomization",
+            Check::Request => "verification.request",
+            Check::Signature => "verification.signature",
+            Check::Ledger => "verification.ledger",
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Check::InnerProduct => "inner product argument invalid",
+            Check::Commitment => "constraint commitment invalid",
+            Check::Disqualifier => "participated in a disqualifying study",
+            Check::Credential => "credential invalid",
+            Check::Tag => "authentication token does not match tag",
+            Check::Rerandomization => "rerandomized signature invalid",
+            Check::Request => "proof of knowledge failed",
+            Check::Signature => "signature not valid",
+            Check::Ledger => "ledger invalid",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Error returned by the native API.
+///
+/// Every error has a stable code, see [`PrepamsError::code`].
+#[derive(Debug)]
+pub enum PrepamsError {
+    /// This error occurs when an input could not be decoded or an output could not be encoded.
+    Encoding(String),
+
+    /// This error occurs when a proof or signature failed to verify.
+    Verification(Check, String),
+
+    /// This error occurs when an operation is not possible in the current state of a role.
+    State(String),
+
+    /// This error occurs when a well-formed request is rejected by the protocol rules.
+    Rejected(String),
+
+    /// This error occurs when the ledger store failed.
+    Storage(StorageError),
+}
+
+impl PrepamsError {
+    pub(crate) fn encoding(msg: &str) -> PrepamsError {
+        PrepamsError::Encoding(msg.to_string())
+    }
+
+    pub(crate) fn state(msg: &str) -> PrepamsError {
+        PrepamsError::State(msg.to_string())
+    }
+
+    pub(crate) fn rejected(msg: &str) -> PrepamsError {
+        PrepamsError::Rejected(msg.to_string())
+    }
+
+    pub(crate) fn verification(check: Check, msg: &str) -> PrepamsError {
+        PrepamsError::Verification(check, msg.to_string())
+    }
+
+    /// Returns the stable machine-readable code of the error:
+    /// `encoding`, `state`, `rejected`, `storage` or the code of the failed [`Check`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            PrepamsError::Encoding(_) => "encoding",
+            PrepamsError::Verification(check, _) => check.code(),
+            PrepamsError::State(_) => "state",
+            PrepamsError::Rejected(_) => "rejected",
+            PrepamsError::Storage(_) => "storage",
+        }
+    }
+
+    /// Returns the failed check of a verification error.
+    pub fn check(&self) -> Option<Check> {
+        match self {
+            PrepamsError::Verification(check, _) => Some(*check),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrepamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrepamsError::Encoding(e)
+            | PrepamsError::Verification(_, e)
+            | PrepamsError::State(e)
+            | PrepamsError::Rejected(e) => write!(f, "{}", e),
+            PrepamsError::Storage(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for PrepamsError {}
+
+impl From<postcard::Error> for PrepamsError {
+    fn from(e: postcard::Error) -> Self {
+        PrepamsError::Encoding(e.to_string())
+    }
+}
+
+impl From<StorageError> for PrepamsError {
+    fn from(e: StorageError) -> Self {
+        PrepamsError::Storage(e)
+    }
+}
+
+impl From<ProofError> for PrepamsError {
+    fn from(e: ProofError) -> Self {
+        match e {
+            ProofError::VerificationError(check) => PrepamsError::Verification(check, check.to_string()),
+            ProofError::InvalidError => PrepamsError::Encoding(e.to_string()),
+        }
+    }
+}
+
+impl From<ed25519_zebra::Error> for PrepamsError {
+    fn from(e: ed25519_zebra::Error) -> Self {
+        match e {
+            ed25519_zebra::Error::InvalidSignature => PrepamsError::Verification(Check::Signature, e.to_string()),
+            _ => PrepamsError::Encoding(e.to_string()),
+        }
+    }
+}
+
+impl From<ed25519_zebra::ed25519::Error> for PrepamsError {
+    fn from(e: ed25519_zebra::ed25519::Error) -> Self {
+        PrepamsError::Encoding(e.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, PrepamsError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        assert_eq!(PrepamsError::from(ProofError::VerificationError(Check::InnerProduct)).code(), "verification.inner_product");
+        assert_eq!(PrepamsError::from(ProofError::InvalidError).code(), "encoding");
+        assert_eq!(PrepamsError::state("credential not yet requested").code(), "state");
+        assert_eq!(PrepamsError::rejected("coin already spent").to_string(), "coin already spent");
+        assert_eq!(PrepamsError::from(postcard::Error::DeserializeUnexpectedEnd).check(), None);
+    }
+}
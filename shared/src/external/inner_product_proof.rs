@@ -12,23 +12,30 @@ use alloc::borrow::Borrow;
 
 use group::Curve;
 use merlin::Transcript;
-use bls12_381::{G1Affine, G1Projective, Scalar};
+use bls12_381::{G1Affine, Scalar};
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use serde_with::serde_as;
 
+use crate::error::Check;
+use crate::msm::Equation;
 use crate::types::ProofError;
 use super::transcript::TranscriptProtocol;
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct InnerProductProof {
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub(crate) L_vec: Vec<G1Affine>,
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub(crate) R_vec: Vec<G1Affine>,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub(crate) a: Scalar,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub(crate) b: Scalar,
 }
 
@@ -39,11 +46,9 @@ pub fn vartime_multiscalar_mul<I, J>(scalars: I, points: J) -> G1Affine
         J: IntoIterator,
         J::Item: Borrow<G1Affine>,
     {
-        // I multiplications, I additions
-        scalars.into_iter()
-            .zip(points.into_iter())
-            .fold(G1Projective::identity(), |a, (l, r)| a + r.borrow() * l.borrow())
-            .to_affine()
+        let scalars: Vec<Scalar> = scalars.into_iter().map(|s| *s.borrow()).collect();
+        let points: Vec<G1Affine> = points.into_iter().map(|p| *p.borrow()).collect();
+        crate::msm::vartime_multiscalar_mul(&scalars, &points).to_affine()
     }
 
 impl InnerProductProof {
@@ -223,10 +228,10 @@ impl InnerProductProof {
         if lg_n >= 32 {
             // 4 billion multiplications should be enough for anyone
             // and this check prevents overflow in 1<<lg_n below.
-            return Err(ProofError::VerificationError);
+            return Err(ProofError::VerificationError(Check::InnerProduct));
         }
         if n != (1 << lg_n) {
-            return Err(ProofError::VerificationError);
+            return Err(ProofError::VerificationError(Check::InnerProduct));
         }
 
         transcript.append_u64(b"ipp_n", n as u64);
@@ -236,7 +241,7 @@ impl InnerProductProof {
         let mut challenges = Vec::with_capacity(lg_n);
         for (L, R) in self.L_vec.iter().zip(self.R_vec.iter()) {
             if L.is_identity().into() || R.is_identity().into() {
-                Err(ProofError::VerificationError)?;
+                Err(ProofError::VerificationError(Check::InnerProduct))?;
             }
 
             transcript.append_g1(b"L", L);
@@ -278,22 +283,19 @@ impl InnerProductProof {
         Ok((challenges_sq, challenges_inv_sq, s))
     }
 
-    /// This method is for testing that proof generation work,
-    /// but for efficiency the actual protocols would use `verification_scalars`
-    /// method to combine inner product verification with other checks
-    /// in a single multiscalar multiplication.
-    #[allow(dead_code)]
-    pub fn verify<IG, IH>(
+    /// Computes the terms of `P` from the proof, the argument is valid if
+    /// they sum to the commitment `P` the proof was created for.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn verification_equation<IG, IH>(
         &self,
         n: usize,
         transcript: &mut Transcript,
         G_factors: IG,
         H_factors: IH,
-        P: &G1Affine,
         Q: &G1Affine,
         G: &[G1Affine],
         H: &[G1Affine],
-    ) -> Result<(), ProofError>
+    ) -> Result<Equation, ProofError>
         where
             IG: IntoIterator,
             IG::Item: Borrow<Scalar>,
@@ -319,35 +321,45 @@ impl InnerProductProof {
         let neg_u_sq = u_sq.iter().map(|ui| -ui);
         let neg_u_inv_sq = u_inv_sq.iter().map(|ui| -ui);
 
-        let Ls = self
-            .L_vec
-            .iter()
-            .map(|p| Ok(p.clone()))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let Rs = self
-            .R_vec
-            .iter()
-            .map(|p| Ok(p.clone()))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let expect_P = vartime_multiscalar_mul(
-            iter::once(&self.a * &self.b)
-                .chain(g_times_a_times_s)
-                .chain(h_times_b_div_s)
-                .chain(neg_u_sq)
-                .chain(neg_u_inv_sq),
-            iter::once(Q)
-                .chain(G.iter())
-                .chain(H.iter())
-                .chain(Ls.iter())
-                .chain(Rs.iter()),
-        );
+        let mut equation = Equation::new();
+        equation.add(self.a * self.b, *Q);
+        equation.extend(g_times_a_times_s.zip(G.iter().copied()));
+        equation.extend(h_times_b_div_s.zip(H.iter().copied()));
+        equation.extend(neg_u_sq.zip(self.L_vec.iter().copied()));
+        equation.extend(neg_u_inv_sq.zip(self.R_vec.iter().copied()));
+
+        Ok(equation)
+    }
+
+    /// This method is for testing that proof generation work,
+    /// but for efficiency the actual protocols would use `verification_equation`
+    /// method to combine inner product verification with other checks
+    /// in a single multiscalar multiplication.
+    #[allow(dead_code, clippy::too_many_arguments)]
+    pub fn verify<IG, IH>(
+        &self,
+        n: usize,
+        transcript: &mut Transcript,
+        G_factors: IG,
+        H_factors: IH,
+        P: &G1Affine,
+        Q: &G1Affine,
+        G: &[G1Affine],
+        H: &[G1Affine],
+    ) -> Result<(), ProofError>
+        where
+            IG: IntoIterator,
+            IG::Item: Borrow<Scalar>,
+            IH: IntoIterator,
+            IH::Item: Borrow<Scalar>,
+    {
+        let mut equation = self.verification_equation(n, transcript, G_factors, H_factors, Q, G, H)?;
+        equation.add(-Scalar::one(), *P);
 
-        if expect_P == *P {
+        if equation.holds() {
             Ok(())
         } else {
-            Err(ProofError::VerificationError)
+            Err(ProofError::VerificationError(Check::InnerProduct))
         }
     }
 
@@ -396,6 +408,7 @@ pub fn inner_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
 mod tests {
     use super::*;
     use super::super::util;
+    use bls12_381::G1Projective;
 
     use ff::Field;
     use bls12_381::hash_to_curve::{HashToCurve, ExpandMsgXmd};
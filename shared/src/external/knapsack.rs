@@ -71,7 +71,6 @@ pub fn knapsack(w: usize, weights: Vec<usize>) -> (usize, Vec<usize>) {
     let n: usize = weights.len();
     // Find the knapsack table
     let m: Vec<Vec<usize>> = knapsack_table(&w, &weights);
-    println!("{:?}", m);
     // Find the indices of the items
     let items: Vec<usize> = knapsack_items(&weights, &m, n, w);
     // Return result
@@ -1,5 +1,4 @@
 pub mod transcript;
 pub mod knapsack;
-pub mod console;
 pub mod util;
 pub mod inner_product_proof;
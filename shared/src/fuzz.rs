@@ -0,0 +1,261 @@
+//! Mutation fuzzing of every wire format and string constructor.
+//!
+//! Valid messages of a complete protocol run are mutated and fed back into
+//! the entry points that consume them. Every input has to be rejected with an
+//! error or accepted, none may panic.
+
+use rand::{Rng, RngCore};
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::SeedableRng;
+
+use crate::api::{IssuerCore, OrganizerCore, ParticipantCore, PayoutRequest};
+use crate::error::PrepamsError;
+use crate::pbss::{self, BlindedSignRequest, BlindedSignature, UnblindedSignature};
+use crate::proofs::generic::Transcript;
+use crate::proofs::payout::{PayoutProof, PayoutProofData, PayoutProofSecrets};
+use crate::serialization::{encode, decode, seal, open, SerializableG1Affine, SerializableScalar};
+use crate::serialization::envelope::Message;
+use crate::types::*;
+use crate::types::credential::*;
+use crate::types::prerequisites::Qualifier;
+
+// mutations per message, can be raised with `PREPAMS_FUZZ_ROUNDS`
+fn rounds() -> usize {
+    std::env::var("PREPAMS_FUZZ_ROUNDS").ok().and_then(|n| n.parse().ok()).unwrap_or(128)
+}
+
+struct Run {
+    issuer: IssuerCore,
+    organizer: OrganizerCore,
+    participant: ParticipantCore,
+    request: IssueRequest,
+    response: IssueResponse,
+    resource: Resource,
+    signed: SignedResource,
+    participation: Participation,
+    confirmed: ConfirmedParticipation,
+    nulls: NullRequest,
+    coins: Vec<BlindedSignature>,
+    unblinded: Vec<UnblindedSignature>,
+    payout: PayoutRequest,
+}
+
+fn run() -> Run {
+    let mut issuer = IssuerCore::new(2, ChaCha20Rng::seed_from_u64(1));
+    let organizer = OrganizerCore::new("lab", issuer.public_key().clone(), ChaCha20Rng::seed_from_u64(2));
+    let mut participant = ParticipantCore::new("alice", &[30, 1], issuer.ledger_verification_key());
+
+    let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), ChaCha20Rng::seed_from_u64(3)).unwrap();
+    let response = issuer.issue_credential(&request).unwrap();
+    participant.retrieve_credential(&response).unwrap();
+
+    let mut resource = Resource::random(ChaCha20Rng::seed_from_u64(4));
+    resource.reward = 2;
+    resource.addConstraint(AttributeConstraint::Range(0, 18, 99));
+    resource.addConstraint(AttributeConstraint::Element(1, vec![0, 1]));
+    let signed = organizer.sign_resource(&resource).unwrap();
+
+    let participation = participant.participate(&resource).unwrap();
+    let confirmed = organizer.confirm_participation(&participation, "p1".to_string()).unwrap();
+    issuer.issue_reward(&confirmed, &organizer.public_key(), 2).unwrap();
+
+    let nulls = participant.request_nulls().unwrap();
+    let coins = issuer.issue_nulls(nulls.requests()).unwrap();
+    let unblinded = decode::<NullRequest>(&encode(&nulls).unwrap()).unwrap().unblind_nulls(coins.clone()).unwrap();
+    let payout = participant.request_payout(1, "bank", "alice", unblinded.clone(), &issuer.ledger().unwrap()).unwrap();
+
+    Run { issuer, organizer, participant, request, response, resource, signed, participation, confirmed, nulls, coins, unblinded, payout }
+}
+
+// flips, drops, inserts or overwrites bytes of a valid message
+fn mutate(data: &[u8], rng: &mut impl RngCore) -> Vec<u8> {
+    let mut data = data.to_vec();
+    for _ in 0..rng.gen_range(1..4) {
+        let i = rng.gen_range(0..=data.len());
+        match rng.gen_range(0..5) {
+            0 if i < data.len() => data[i] ^= 1 << rng.gen_range(0..8),
+            1 if i < data.len() => data[i] = rng.gen(),
+            2 => data.insert(i, rng.gen()),
+            3 if i < data.len() => { data.remove(i); },
+            _ => data.truncate(i),
+        }
+    }
+    data
+}
+
+// mutates the payload behind a valid envelope header, so that the mutations
+// reach the decoder of the message instead of the header checks
+fn fuzz<T: Message>(seed: u64, valid: &T, mut consume: impl FnMut(T)) {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    let data = seal(valid).unwrap();
+    let header = data.len() - encode(valid).unwrap().len();
+
+    for _ in 0..rounds() {
+        let mut mutated = data[..header].to_vec();
+        mutated.extend(mutate(&data[header..], &mut rng));
+        if let Ok(value) = open::<T>(&mutated) {
+            consume(value);
+        }
+    }
+}
+
+fn random_string(rng: &mut impl RngCore) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_=+/";
+    (0..rng.gen_range(0..100)).map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char).collect()
+}
+
+fn credentials(r: &mut Run) {
+    fuzz(10, &r.request, |request| { let _ = r.issuer.issue_credential(&request); });
+    fuzz(11, &r.response, |response| {
+        let mut participant = r.participant.clone();
+        let _ = participant.retrieve_credential(&response);
+    });
+    fuzz(12, r.issuer.public_key(), |ipk| {
+        let mut participant = ParticipantCore::new("bob", &[30, 1], r.issuer.ledger_verification_key());
+        let _ = participant.request_credential(ipk, r.issuer.credit_verification_key().clone(), ChaCha20Rng::seed_from_u64(0));
+    });
+    fuzz(13, &r.participant, |participant| { let _ = participant.request_nulls(); });
+    fuzz(14, &r.organizer, |organizer| { let _ = organizer.confirm_participation(&r.participation, "p2".to_string()); });
+    fuzz(15, &r.issuer, |issuer: IssuerCore| { let _ = issuer.issue_nulls(r.nulls.requests()); });
+    fuzz(16, r.issuer.credit_verification_key(), |cvk| {
+        let mut participant = ParticipantCore::new("bob", &[30, 1], r.issuer.ledger_verification_key());
+        let request = participant.request_credential(r.issuer.public_key().clone(), cvk, ChaCha20Rng::seed_from_u64(0)).unwrap();
+        participant.retrieve_credential(&r.issuer.issue_credential(&request).unwrap()).unwrap();
+        let _ = participant.request_nulls();
+        let _ = participant.participate(&r.resource);
+    });
+}
+
+fn participations(r: &mut Run) {
+    let vk = r.organizer.public_key();
+
+    fuzz(20, &r.resource, |resource: Resource| { let _ = r.organizer.sign_resource(&resource); });
+    fuzz(21, &r.signed, |signed| { let _ = r.issuer.check_resource_signature(&signed, &vk); });
+    fuzz(22, &r.participation, |participation| { let _ = participation.check(); });
+    fuzz(23, &r.confirmed, |confirmed| { let _ = r.issuer.issue_reward(&confirmed, &vk, 2); });
+}
+
+fn payouts(r: &mut Run) {
+    fuzz(30, &r.nulls.requests().to_vec(), |requests: Vec<BlindedSignRequest>| { let _ = r.issuer.issue_nulls(&requests); });
+    fuzz(31, &r.nulls, |nulls: NullRequest| { let _ = nulls.unblind_nulls(r.coins.clone()); });
+    fuzz(32, &r.coins, |coins| { let _ = decode::<NullRequest>(&encode(&r.nulls).unwrap()).unwrap().unblind_nulls(coins); });
+    fuzz(33, &r.unblinded, |unblinded: Vec<UnblindedSignature>| {
+        for coin in &unblinded {
+            let _ = pbss::Verify(r.issuer.credit_verification_key(), coin);
+        }
+    });
+    fuzz(34, &r.payout.proof, |proof: PayoutProofData| {
+        let _ = proof.verify::<PayoutProofSecrets, PayoutProof>(&mut Transcript::new(b"payout"));
+        // on a copy, as trailing bytes are ignored and the valid request
+        // would spend its coins on the issuer
+        let _ = decode::<IssuerCore>(&encode(&r.issuer).unwrap()).unwrap().check_payout_request(&proof);
+    });
+}
+
+fn ledgers(r: &mut Run) {
+    r.issuer.check_payout_request(&r.payout.proof).unwrap();
+
+    let lvk = r.issuer.ledger_verification_key();
+    let ledger = r.issuer.ledger().unwrap();
+    let slice = r.issuer.export_range(0, 2, None, &LedgerFilter::default()).unwrap();
+
+    fuzz(40, &ledger, |ledger: Ledger| { let _ = r.participant.balance(&ledger); });
+    fuzz(41, &ledger.entries[0], |entry: LedgerEntry| {
+        let mut issuer = IssuerCore::new(2, ChaCha20Rng::seed_from_u64(1));
+        let _ = issuer.append_entry(entry);
+    });
+    fuzz(42, &slice, |slice: LedgerSlice| {
+        let _ = slice.check(&lvk);
+        let _ = slice.extend_ledger(&mut Ledger::default(), &lvk);
+    });
+}
+
+fn constructors(r: &Run) {
+    let mut rng = ChaCha20Rng::seed_from_u64(50);
+
+    let tag = SerializableG1Affine::to_string(&r.confirmed.tag);
+    let study = SerializableScalar::to_string(&r.confirmed.study);
+    let request = seal(&r.confirmed.request).unwrap();
+    let signature = r.confirmed.signature.to_bytes();
+    let ledger = r.issuer.ledger().unwrap();
+    let entry = &ledger.entries[0];
+    let coin = seal(&entry.transaction.as_ref().unwrap().coin).unwrap();
+    let previous = entry.previous.to_bytes();
+
+    ConfirmedParticipation::from_parts("p1", &tag, &study, &request, &signature, 2).unwrap();
+    LedgerEntry::from_transaction(&previous, entry.timestamp, &r.confirmed, &coin, &entry.signature.to_bytes()).unwrap();
+
+    for _ in 0..rounds() {
+        let s = random_string(&mut rng);
+        let bytes = mutate(&previous, &mut rng);
+        let params: Vec<u32> = (0..rng.gen_range(0..4)).map(|_| rng.gen()).collect();
+
+        let _ = SerializableScalar::from_string(&s);
+        let _ = SerializableG1Affine::from_string(&s);
+        let _ = Qualifier::parse(&study, &[s.clone(), tag.clone()]);
+        let _ = AttributeConstraint::parse(rng.gen(), ["number", "select", s.as_str()][rng.gen_range(0..3)], &params);
+        let _ = ConfirmedParticipation::from_parts(&s, &s, &study, &mutate(&request, &mut rng), &mutate(&signature, &mut rng), rng.gen());
+        let _ = LedgerEntry::from_transaction(&bytes, rng.gen(), &r.confirmed, &mutate(&coin, &mut rng), &bytes);
+        let _ = LedgerEntry::from_payout(&previous, rng.gen(), &bytes, &mutate(&signature, &mut rng));
+    }
+}
+
+fn malicious_studies(r: &Run) {
+    let mut inverted = r.resource.clone();
+    inverted.addConstraint(AttributeConstraint::Range(0, 99, 18));
+    assert!(matches!(r.participant.participate(&inverted), Err(PrepamsError::Rejected(_))));
+    assert!(AttributeConstraint::parse(0, "number", &[99, 18]).is_err());
+    assert!(AttributeConstraint::parse(0, "number", &[18]).is_err());
+
+    let mut unsatisfiable = r.resource.clone();
+    unsatisfiable.addConstraint(AttributeConstraint::Range(1, 5, 10));
+    assert!(matches!(r.participant.participate(&unsatisfiable), Err(PrepamsError::Rejected(_))));
+
+    let mut zero = r.resource.clone();
+    zero.addQualifier(bls12_381::Scalar::zero(), vec![]);
+    assert!(matches!(r.participant.participate(&zero), Err(PrepamsError::Encoding(_))));
+
+    // a proof with inverted range bounds is rejected before variables are derived
+    let mut forged: Participation = decode(&encode(&r.participation).unwrap()).unwrap();
+    forged.proof.inputs.constraints.push(AttributeConstraint::Range(0, u32::MAX, 0));
+    assert_eq!(forged.check().unwrap_err().code(), "encoding");
+}
+
+// keys of a malicious issuer and studies a credential cannot prove for
+fn malicious_keys(r: &Run) {
+    let mut ipk = r.issuer.public_key().clone();
+    ipk.a.pop();
+    let mut participant = ParticipantCore::new("bob", &[30, 1], r.issuer.ledger_verification_key());
+    let request = participant.request_credential(ipk, r.issuer.credit_verification_key().clone(), ChaCha20Rng::seed_from_u64(0));
+    assert!(matches!(request, Err(PrepamsError::Encoding(_))));
+
+    let mut cvk = r.issuer.credit_verification_key().clone();
+    cvk.U.push(cvk.U[0]);
+    let mut participant = ParticipantCore::new("bob", &[30, 1], r.issuer.ledger_verification_key());
+    let request = participant.request_credential(r.issuer.public_key().clone(), cvk, ChaCha20Rng::seed_from_u64(0)).unwrap();
+    participant.retrieve_credential(&r.issuer.issue_credential(&request).unwrap()).unwrap();
+    assert!(matches!(participant.request_nulls(), Err(PrepamsError::Encoding(_))));
+    assert!(matches!(participant.participate(&r.resource), Err(PrepamsError::Encoding(_))));
+
+    // the tag of a study with the id `-sk` is undefined
+    let sk = r.participant.credential().unwrap().sk;
+    let unprovable = Resource { id: -sk, ..r.resource.clone() };
+    assert!(matches!(r.participant.participate(&unprovable), Err(PrepamsError::Encoding(_))));
+
+    let mut disqualified = r.resource.clone();
+    disqualified.addDisqualifier(-sk, vec![]);
+    assert!(matches!(r.participant.participate(&disqualified), Err(PrepamsError::Encoding(_))));
+}
+
+#[test]
+fn untrusted_inputs() {
+    let mut r = run();
+
+    credentials(&mut r);
+    participations(&mut r);
+    payouts(&mut r);
+    constructors(&r);
+    malicious_studies(&r);
+    malicious_keys(&r);
+    ledgers(&mut r);
+}
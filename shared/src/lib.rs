@@ -1,10 +1,26 @@
-extern crate simple_error;
-
 mod pbss;
 mod serialization;
+pub mod error;
 pub mod proofs;
 mod credential;
 pub mod external;
 pub mod types;
+pub mod storage;
+pub mod audit;
+pub mod api;
+pub mod vectors;
+pub mod msm;
+mod parallel;
+#[cfg(feature = "wasm")]
 pub mod bindings;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod soundness;
+
+pub use error::PrepamsError;
+pub use serialization::{encode, decode, seal, open, envelope, format};
+pub use api::{IssuerCore, OrganizerCore, ParticipantCore};
 
+#[cfg(all(feature = "parallel", feature = "wasm", target_family = "wasm"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
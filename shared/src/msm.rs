@@ -0,0 +1,321 @@
+//! Multi-scalar multiplication over G1.
+//!
+//! [`multiscalar_mul`] runs in constant time and is used wherever a scalar
+//! may be secret, e.g. the commitments of the prover. [`vartime_multiscalar_mul`]
+//! branches on the scalars and is reserved for public values, it switches
+//! from Straus to Pippenger's bucket method for large inputs. [`FixedBase`]
+//! keeps precomputed multiples of the fixed generators of the protocol and
+//! [`Equation`] collects the final checks of proofs for batch verification.
+//!
+//! With the `parallel` feature large multiplications are split into chunks
+//! that run on the thread pool.
+
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+use crate::parallel;
+
+// bits of a 4 bit window table
+const WINDOW: usize = 4;
+const WINDOWS: usize = 256 / WINDOW;
+
+// inputs from which on pippenger beats straus
+const PIPPENGER_THRESHOLD: usize = 128;
+
+// smallest share of a multiplication that is worth a thread
+const MIN_CHUNK: usize = 256;
+
+// reads `width` bits of a little-endian scalar starting at bit `offset`
+fn digit(scalar: &[u8; 32], offset: usize, width: usize) -> usize {
+    (0..width)
+        .map(|i| offset + i)
+        .filter(|bit| *bit < 256)
+        .fold(0, |d, bit| d | ((((scalar[bit / 8] >> (bit % 8)) & 1) as usize) << (bit - offset)))
+}
+
+// multiples 0..16 of every point, normalized with a single inversion
+fn tables(points: &[G1Affine]) -> Vec<[G1Affine; 1 << WINDOW]> {
+    let mut multiples = vec![G1Projective::identity(); points.len() << WINDOW];
+    for (row, point) in multiples.chunks_mut(1 << WINDOW).zip(points) {
+        for i in 1..row.len() {
+            row[i] = row[i - 1] + point;
+        }
+    }
+
+    let mut affine = vec![G1Affine::identity(); multiples.len()];
+    G1Projective::batch_normalize(&multiples, &mut affine);
+    affine.chunks(1 << WINDOW).map(|row| row.try_into().unwrap()).collect()
+}
+
+// constant time lookup of `table[d]`
+fn select(table: &[G1Affine; 1 << WINDOW], d: usize) -> G1Affine {
+    let mut point = G1Affine::identity();
+    for (i, entry) in table.iter().enumerate().skip(1) {
+        point.conditional_assign(entry, (i as u8).ct_eq(&(d as u8)));
+    }
+    point
+}
+
+// adds up `f` of the chunks the terms are split into for the thread pool
+fn chunked(scalars: &[Scalar], points: &[G1Affine], f: impl Fn(&[Scalar], &[G1Affine]) -> G1Projective + Sync + Send) -> G1Projective {
+    let n = scalars.len().min(points.len());
+    let size = parallel::chunk_size(n, MIN_CHUNK);
+    let chunks: Vec<(&[Scalar], &[G1Affine])> = scalars[..n].chunks(size).zip(points[..n].chunks(size)).collect();
+    parallel::map(&chunks, |(scalars, points)| f(scalars, points)).into_iter().sum()
+}
+
+/// Computes `sum(scalars[i] * points[i])` in constant time, surplus
+/// scalars or points are ignored.
+pub fn multiscalar_mul(scalars: &[Scalar], points: &[G1Affine]) -> G1Projective {
+    chunked(scalars, points, constant_time)
+}
+
+fn constant_time(scalars: &[Scalar], points: &[G1Affine]) -> G1Projective {
+    let scalars: Vec<[u8; 32]> = scalars.iter().map(Scalar::to_bytes).collect();
+    let tables = tables(points);
+
+    let mut result = G1Projective::identity();
+    for w in (0..WINDOWS).rev() {
+        for _ in 0..WINDOW {
+            result = result.double();
+        }
+        for (scalar, table) in scalars.iter().zip(&tables) {
+            result += select(table, digit(scalar, w * WINDOW, WINDOW));
+        }
+    }
+    result
+}
+
+/// Computes `sum(scalars[i] * points[i])` with a running time that depends
+/// on the scalars, surplus scalars or points are ignored.
+pub fn vartime_multiscalar_mul(scalars: &[Scalar], points: &[G1Affine]) -> G1Projective {
+    chunked(scalars, points, |scalars, points| {
+        let scalars: Vec<[u8; 32]> = scalars.iter().map(Scalar::to_bytes).collect();
+        if scalars.len() < PIPPENGER_THRESHOLD {
+            straus(&scalars, points)
+        } else {
+            pippenger(&scalars, points)
+        }
+    })
+}
+
+fn straus(scalars: &[[u8; 32]], points: &[G1Affine]) -> G1Projective {
+    let tables = tables(points);
+
+    let mut result = G1Projective::identity();
+    for w in (0..WINDOWS).rev() {
+        if w + 1 < WINDOWS {
+            for _ in 0..WINDOW {
+                result = result.double();
+            }
+        }
+        for (scalar, table) in scalars.iter().zip(&tables) {
+            let d = digit(scalar, w * WINDOW, WINDOW);
+            if d != 0 {
+                result += table[d];
+            }
+        }
+    }
+    result
+}
+
+fn pippenger(scalars: &[[u8; 32]], points: &[G1Affine]) -> G1Projective {
+    // roughly minimizes windows * (points + 2 * buckets)
+    let c = (usize::BITS - scalars.len().leading_zeros()).saturating_sub(3).clamp(4, 16) as usize;
+    let windows = 256_usize.div_ceil(c);
+
+    let mut result = G1Projective::identity();
+    let mut buckets = vec![G1Projective::identity(); (1 << c) - 1];
+    for w in (0..windows).rev() {
+        if w + 1 < windows {
+            for _ in 0..c {
+                result = result.double();
+            }
+        }
+
+        buckets.fill(G1Projective::identity());
+        for (scalar, point) in scalars.iter().zip(points) {
+            let d = digit(scalar, w * c, c);
+            if d != 0 {
+                buckets[d - 1] += point;
+            }
+        }
+
+        // sum(i * buckets[i - 1]) as a sum of suffix sums
+        let mut running = G1Projective::identity();
+        let mut sum = G1Projective::identity();
+        for bucket in buckets.iter().rev() {
+            running += bucket;
+            sum += running;
+        }
+        result += sum;
+    }
+    result
+}
+
+/// Linear relation `sum(scalars[i] * points[i]) == 0` between public points.
+///
+/// The relations of many proofs are checked with a single variable time
+/// multi-scalar multiplication after merging them with random weights.
+#[derive(Debug, Clone, Default)]
+pub struct Equation {
+    scalars: Vec<Scalar>,
+    points: Vec<G1Affine>,
+}
+
+impl Equation {
+    pub fn new() -> Equation {
+        Equation::default()
+    }
+
+    pub fn add(&mut self, scalar: Scalar, point: G1Affine) {
+        self.scalars.push(scalar);
+        self.points.push(point);
+    }
+
+    pub fn extend(&mut self, terms: impl IntoIterator<Item = (Scalar, G1Affine)>) {
+        for (scalar, point) in terms {
+            self.add(scalar, point);
+        }
+    }
+
+    /// Adds `weight` times the terms of `other`.
+    pub fn merge(&mut self, weight: &Scalar, other: Equation) {
+        self.scalars.extend(other.scalars.iter().map(|s| s * weight));
+        self.points.extend(other.points);
+    }
+
+    /// Computes the left hand side of the relation.
+    pub fn eval(&self) -> G1Projective {
+        vartime_multiscalar_mul(&self.scalars, &self.points)
+    }
+
+    pub fn holds(&self) -> bool {
+        self.eval().is_identity().into()
+    }
+}
+
+/// Precomputed multiples `d * 16^i * base` of a fixed generator.
+///
+/// Multiplication takes 64 constant time table lookups and additions instead
+/// of 255 doublings and additions.
+pub struct FixedBase {
+    base: G1Affine,
+    tables: Vec<[G1Affine; 1 << WINDOW]>,
+}
+
+impl FixedBase {
+    pub fn new(base: G1Affine) -> FixedBase {
+        let mut powers = Vec::with_capacity(WINDOWS);
+        let mut power = G1Projective::from(base);
+        for _ in 0..WINDOWS {
+            powers.push(power);
+            for _ in 0..WINDOW {
+                power = power.double();
+            }
+        }
+
+        let mut affine = vec![G1Affine::identity(); WINDOWS];
+        G1Projective::batch_normalize(&powers, &mut affine);
+
+        FixedBase { base, tables: tables(&affine) }
+    }
+
+    pub fn base(&self) -> G1Affine {
+        self.base
+    }
+
+    /// Computes `scalar * base` in constant time.
+    pub fn mul(&self, scalar: &Scalar) -> G1Projective {
+        let scalar = scalar.to_bytes();
+        self.tables.iter()
+            .enumerate()
+            .fold(G1Projective::identity(), |acc, (w, table)| acc + select(table, digit(&scalar, w * WINDOW, WINDOW)))
+    }
+}
+
+/// Defines a function that returns the lazily computed [`FixedBase`] table of
+/// a generator.
+macro_rules! fixed_base {
+    ($(#[$meta:meta])* $name:ident, $base:expr) => {
+        $(#[$meta])*
+        pub(crate) fn $name() -> &'static $crate::msm::FixedBase {
+            static TABLE: std::sync::OnceLock<$crate::msm::FixedBase> = std::sync::OnceLock::new();
+            TABLE.get_or_init(|| $crate::msm::FixedBase::new($base))
+        }
+    };
+}
+
+pub(crate) use fixed_base;
+
+fixed_base!(
+    /// Table of the standard generator of G1.
+    #[allow(non_snake_case)]
+    GENERATOR, G1Affine::generator()
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use group::Curve;
+    use rand_chacha::ChaCha20Rng;
+    use rand_chacha::rand_core::SeedableRng;
+
+    fn naive(scalars: &[Scalar], points: &[G1Affine]) -> G1Projective {
+        scalars.iter().zip(points).fold(G1Projective::identity(), |acc, (s, p)| acc + p * s)
+    }
+
+    #[test]
+    fn multiscalar() {
+        let mut rng = ChaCha20Rng::from_seed([1; 32]);
+
+        for n in [0, 1, 2, 3, 17, PIPPENGER_THRESHOLD - 1, PIPPENGER_THRESHOLD, 300] {
+            let mut scalars: Vec<Scalar> = (0..n).map(|_| Scalar::random(&mut rng)).collect();
+            let mut points: Vec<G1Affine> = (0..n).map(|_| (G1Affine::generator() * Scalar::random(&mut rng)).to_affine()).collect();
+
+            // edge cases of the digit decomposition and the identity
+            if n >= 3 {
+                scalars[0] = Scalar::zero();
+                scalars[1] = -Scalar::one();
+                points[2] = G1Affine::identity();
+            }
+
+            let expected = naive(&scalars, &points);
+            assert_eq!(multiscalar_mul(&scalars, &points), expected, "constant time, n = {}", n);
+            assert_eq!(vartime_multiscalar_mul(&scalars, &points), expected, "variable time, n = {}", n);
+        }
+    }
+
+    #[test]
+    fn equation() {
+        let mut rng = ChaCha20Rng::from_seed([3; 32]);
+        let (a, b) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let p = (G1Affine::generator() * b).to_affine();
+
+        // a * b * G - a * P
+        let mut equation = Equation::new();
+        equation.extend([(a * b, G1Affine::generator()), (-a, p)]);
+        assert!(equation.holds());
+
+        let mut batch = Equation::new();
+        batch.merge(&Scalar::random(&mut rng), equation.clone());
+        batch.merge(&Scalar::random(&mut rng), equation.clone());
+        assert!(batch.holds());
+
+        equation.add(Scalar::one(), p);
+        batch.merge(&Scalar::random(&mut rng), equation);
+        assert!(!batch.holds());
+    }
+
+    #[test]
+    fn fixed_base() {
+        let mut rng = ChaCha20Rng::from_seed([2; 32]);
+        let table = FixedBase::new(crate::credential::CREDENTIAL_V());
+
+        for scalar in [Scalar::zero(), Scalar::one(), -Scalar::one(), Scalar::random(&mut rng)] {
+            assert_eq!(table.mul(&scalar), table.base() * scalar);
+            assert_eq!(GENERATOR().mul(&scalar), G1Affine::generator() * scalar);
+        }
+    }
+}
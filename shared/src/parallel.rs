@@ -0,0 +1,52 @@
+//! Loops of the prover and verifier that run on the rayon thread pool with
+//! the `parallel` feature and serially otherwise.
+//!
+//! Both paths give identical results, items keep their order. In the browser
+//! the pool is started from JavaScript with `initThreadPool` before the first
+//! proof, which needs a build with wasm threads.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Maps `f` over `items`.
+#[cfg(feature = "parallel")]
+pub(crate) fn map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync + Send) -> Vec<U> {
+    items.par_iter().map(f).collect()
+}
+
+/// Maps `f` over `items`.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map<T, U>(items: &[T], f: impl Fn(&T) -> U) -> Vec<U> {
+    items.iter().map(f).collect()
+}
+
+/// Size of the chunks `n` items are split into: one chunk per thread, but at
+/// least `min` items each.
+#[cfg(feature = "parallel")]
+pub(crate) fn chunk_size(n: usize, min: usize) -> usize {
+    n.div_ceil(rayon::current_num_threads()).max(min).max(1)
+}
+
+/// Size of the chunks `n` items are split into, a single chunk.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn chunk_size(n: usize, _min: usize) -> usize {
+    n.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order() {
+        let items: Vec<u64> = (0..1000).collect();
+        assert_eq!(map(&items, |i| i * i), items.iter().map(|i| i * i).collect::<Vec<u64>>());
+        assert!(map(&[] as &[u64], |i| *i).is_empty());
+
+        // chunks are never empty and small inputs are not split
+        for n in [0, 1, 255, 256, 10_000] {
+            let size = chunk_size(n, 256);
+            assert!(size >= 1 && size >= n.min(256));
+        }
+    }
+}
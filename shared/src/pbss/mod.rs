@@ -1,65 +1,101 @@
 #![allow(non_snake_case)]
 #![allow(warnings)]
 
+use std::sync::{Arc, Mutex};
+
 use ed25519_zebra::VerificationKey;
 use ff::Field;
 use group::Curve;
 use rand::RngCore;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 use serde_with::serde_as;
 use sha2::{Digest, Sha256};
 use merlin::Transcript;
 use serde::{Serialize, Deserialize};
-use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+use schemars::JsonSchema;
+use bls12_381::{multi_miller_loop, pairing, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar};
 use bls12_381::hash_to_curve::{HashToCurve, ExpandMsgXmd};
-use simple_error::SimpleError;
 
+use crate::error::{Check, PrepamsError};
 use crate::external::transcript::TranscriptProtocol;
+use crate::msm::{multiscalar_mul, vartime_multiscalar_mul};
 use crate::types::*;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct SecretKey {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub x: Scalar
 }
 
 type BlindingFactor = Scalar;
 
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, JsonSchema)]
 pub struct PublicKey {
     pub tag: String,
 
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub U: Vec<G1Affine>,
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub V: Vec<G1Affine>,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub h: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub b: G1Affine,
 
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub g1: G1Affine,
     #[serde(with = "crate::serialization::G2Affine")]
+    #[schemars(with = "crate::serialization::schema::G2")]
     pub g2: G2Affine,
     #[serde(with = "crate::serialization::Gt")]
-    pub e: Gt
+    #[schemars(with = "crate::serialization::schema::Gt")]
+    pub e: Gt,
+}
+
+/// `g2` of the last public key used for verification, prepared for the
+/// Miller loop. It is kept outside of the key, so keys stay plain data.
+static PREPARED_G2: Mutex<Option<(G2Affine, Arc<G2Prepared>)>> = Mutex::new(None);
+
+impl PublicKey {
+    pub(crate) fn g2_prepared(&self) -> Arc<G2Prepared> {
+        let mut cache = PREPARED_G2.lock().unwrap_or_else(|e| e.into_inner());
+        match cache.as_ref() {
+            Some((g2, prepared)) if *g2 == self.g2 => prepared.clone(),
+            _ => {
+                let prepared = Arc::new(G2Prepared::from(self.g2));
+                *cache = Some((self.g2, prepared.clone()));
+                prepared
+            }
+        }
+    }
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, JsonSchema)]
 pub struct BlindedSignRequest {
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub m: Vec<Scalar>,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub alpha: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub gamma: G1Affine,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub z1: Vec<Scalar>,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub z2: Scalar,
 }
 
@@ -77,14 +113,17 @@ impl std::hash::Hash for BlindedSignRequest {
   }
 }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct BlindedSignature {
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     sigma1: G1Affine,
     #[serde(with = "crate::serialization::G2Affine")]
+    #[schemars(with = "crate::serialization::schema::G2")]
     sigma2: G2Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     sigma3: G1Affine
 }
 
@@ -97,35 +136,43 @@ impl std::hash::Hash for BlindedSignature {
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default, JsonSchema)]
 pub struct UnblindedSignature {
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub s: Vec<Scalar>,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub m: Vec<Scalar>,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     sigma1: G1Affine,
     #[serde(with = "crate::serialization::G2Affine")]
+    #[schemars(with = "crate::serialization::schema::G2")]
     sigma2: G2Affine
 }
 
+/// Rerandomized signature with the commitment `bp` of the proof of possession.
+///
+/// The pairing commitments of all signatures of a proof are aggregated, see
+/// [`CommitRerandomized`].
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct RerandomizedProof {
     #[serde(with = "crate::serialization::G2Affine")]
+    #[schemars(with = "crate::serialization::schema::G2")]
     pub s2: G2Affine,
-    #[serde(with = "crate::serialization::Gt")]
-    pub e1: Gt,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub bp: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub vc: G1Affine
 }
 
 impl RerandomizedProof {
     pub fn commit(&self, transcript: &mut Transcript) {
         transcript.append_g2(b"s2", &self.s2);
-        transcript.append_gt(b"e1", &self.e1);
         transcript.append_g1(b"vc", &self.vc);
         transcript.append_g1(b"bp", &self.bp);
     }
@@ -143,66 +190,119 @@ pub struct RerandomizedWitness {
     pub s1: G1Affine
 }
 
-impl RerandomizedWitness {
-    pub fn prove(&self, t: &mut Transcript) -> RerandomizedProofResponse {
-        let c = t.challenge_scalar(b"c");
-
-        // response
-        let z1: Vec<Scalar> = self.s.s.iter().zip(self.b1.iter()).map(|(x, b)| b + &c * x).collect();
-        let z2: Vec<Scalar> = self.s.m.iter().zip(self.b2.iter()).map(|(x, b)| b + &c * x).collect();
-        let z3 = (&self.s1 * &c + &self.j1).to_affine();
-        let zv = &self.bb + &c * &self.r;
-
-        RerandomizedProofResponse { z1, z2, z3, zv }
-    }
-}
-
+/// Response of a single rerandomized signature.
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
-pub struct RerandomizedProofResponse {
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, JsonSchema)]
+pub struct RerandomizedResponse {
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub z1: Vec<Scalar>,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub z2: Vec<Scalar>,
-    #[serde(with = "crate::serialization::G1Affine")]
-    pub z3: G1Affine,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub zv: Scalar
 }
 
+/// Proof of possession of many rerandomized signatures under a shared
+/// challenge, `z3` is the weighted sum of the responses in G1.
+#[serde_as]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default, JsonSchema)]
+pub struct RerandomizedProofResponse {
+    pub responses: Vec<RerandomizedResponse>,
+    #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
+    pub z3: G1Affine
+}
+
+// weights of the pairing equations of the signatures, derived from all of
+// them so that the prover cannot offset an invalid signature by another one
+fn weights(proofs: &[RerandomizedProof]) -> Vec<Scalar> {
+    let mut t = Transcript::new(b"rerandomized-weights");
+    for proof in proofs {
+        proof.commit(&mut t);
+    }
+    proofs.iter().map(|_| t.challenge_scalar(b"w")).collect()
+}
+
+// sum(V[i] * s[i]) + sum(U[i] * m[i]) with the multiplication `msm`
+fn commitment(vk: &PublicKey, s: &[Scalar], m: &[Scalar], msm: fn(&[Scalar], &[G1Affine]) -> G1Projective) -> G1Projective {
+    let scalars: Vec<Scalar> = s.iter().chain(m).copied().collect();
+    let points: Vec<G1Affine> = vk.V.iter().chain(&vk.U).copied().collect();
+    msm(&scalars, &points)
+}
+
+/// Aggregated pairing commitment `sum(w[i] * (e(j1[i], g2) - e(V * b1[i] + U * b2[i], s2[i])))`
+/// of the rerandomized signatures, which is committed before the challenge.
+pub fn CommitRerandomized(vk: &PublicKey, proofs: &[RerandomizedProof], witnesses: &[RerandomizedWitness]) -> Gt {
+    let weights = weights(proofs);
+
+    let j1 = witnesses.iter().zip(&weights).map(|(w, weight)| w.j1 * weight).sum::<G1Projective>().to_affine();
+    let pairs: Vec<(G1Affine, G2Prepared)> = proofs.iter().zip(witnesses).zip(&weights)
+        .map(|((p, w), weight)| ((-commitment(vk, &w.b1, &w.b2, multiscalar_mul) * weight).to_affine(), G2Prepared::from(p.s2)))
+        .collect();
+
+    let g2 = vk.g2_prepared();
+    let terms: Vec<(&G1Affine, &G2Prepared)> = std::iter::once((&j1, g2.as_ref()))
+        .chain(pairs.iter().map(|(p, q)| (p, q)))
+        .collect();
+
+    multi_miller_loop(&terms).final_exponentiation()
+}
+
 impl RerandomizedProofResponse {
-    pub fn verify(&self, p: &RerandomizedProof, vk: &PublicKey, t: &mut Transcript) -> bool {
+    pub fn prove(proofs: &[RerandomizedProof], witnesses: &[RerandomizedWitness], t: &mut Transcript) -> RerandomizedProofResponse {
+        let c = t.challenge_scalar(b"c");
+        let weights = weights(proofs);
+
+        // response
+        let responses = witnesses.iter().map(|w| RerandomizedResponse {
+            z1: w.s.s.iter().zip(w.b1.iter()).map(|(x, b)| b + &c * x).collect(),
+            z2: w.s.m.iter().zip(w.b2.iter()).map(|(x, b)| b + &c * x).collect(),
+            zv: &w.bb + &c * &w.r
+        }).collect();
+        let z3 = witnesses.iter().zip(&weights)
+            .map(|(w, weight)| (w.s1 * c + w.j1) * weight)
+            .sum::<G1Projective>()
+            .to_affine();
+
+        RerandomizedProofResponse { responses, z3 }
+    }
+
+    /// Checks the responses against the rerandomized signatures and their
+    /// aggregated commitment `e1`.
+    pub fn verify(&self, proofs: &[RerandomizedProof], e1: &Gt, vk: &PublicKey, t: &mut Transcript) -> bool {
         let c = t.challenge_scalar(b"c");
 
-        if vk.V.len() != self.z1.len() {
+        if proofs.len() != self.responses.len() {
             return false;
         }
 
-        if vk.U.len() != self.z2.len() {
+        if self.responses.iter().any(|r| vk.V.len() != r.z1.len() || vk.U.len() != r.z2.len()) {
             return false;
         }
 
-        let tmp = vk.U.iter().zip(&self.z2).fold(
-            vk.V.iter().zip(&self.z1).fold(
-                G1Projective::identity(),
-                |s, (v, z)| s + v * z.neg()
-            ),
-            |s, (u, z)| s + u * z.neg()
-        );
+        // c * vc + bp == zv * b + V * z1 + U * z2 for every signature
+        let weights = weights(proofs);
+        let mut pairs = Vec::with_capacity(proofs.len());
+        for ((p, r), weight) in proofs.iter().zip(&self.responses).zip(&weights) {
+            let tmp = commitment(vk, &r.z1, &r.z2, vartime_multiscalar_mul);
+            if p.vc * c + p.bp != vk.b * r.zv + tmp {
+                return false;
+            }
 
-        let l1 = p.e1 + (vk.e * &c) + (pairing(&vk.h, &p.s2) * &c);
-        let r1 = pairing(&self.z3, &vk.g2) + pairing(&tmp.to_affine(), &p.s2);
+            pairs.push(((-(tmp + vk.h * c) * weight).to_affine(), G2Prepared::from(p.s2)));
+        }
 
-        let l2 = p.vc * c + p.bp;
-        let r2 = vk.U.iter().zip(&self.z2).fold(
-            vk.V.iter().zip(&self.z1).fold(
-                vk.b * self.zv,
-                |s, (v, z)| s + v * z
-            ),
-            |s, (u, z)| s + u * z
-        );
+        // e1 + c * sum(w) * e == e(z3, g2) - sum(e(w * (V * z1 + U * z2 + c * h), s2))
+        let w: Scalar = weights.iter().sum();
+        let g2 = vk.g2_prepared();
+        let terms: Vec<(&G1Affine, &G2Prepared)> = std::iter::once((&self.z3, g2.as_ref()))
+            .chain(pairs.iter().map(|(p, q)| (p, q)))
+            .collect();
 
-        l1 == r1 && l2 == r2
+        *e1 + vk.e * (c * w) == multi_miller_loop(&terms).final_exponentiation()
     }
 }
 
@@ -229,11 +329,16 @@ pub fn VerifyPublicKey(pk: &PublicKey) -> bool {
     vk.U == pk.U && vk.V == pk.V && vk.h == pk.h && vk.g1 == pk.g1 && vk.g2 == pk.g2
 }
 
-pub fn Blind(vk: &PublicKey, m: &Vec<Scalar>, s: &Vec<Scalar>, d: &BlindingFactor, mut rng: impl RngCore) -> BlindedSignRequest {
+pub fn Blind(vk: &PublicKey, m: &Vec<Scalar>, s: &Vec<Scalar>, d: &BlindingFactor, mut rng: impl RngCore) -> Result<BlindedSignRequest, PrepamsError> {
     let m = m.clone();
 
-    assert!(m.len() == vk.U.len());
-    assert!(s.len() == vk.V.len());
+    if m.len() != vk.U.len() {
+        Err(PrepamsError::encoding("Invalid attributes supplied"))?;
+    }
+
+    if s.len() != vk.V.len() {
+        Err(PrepamsError::encoding("Invalid secrets supplied"))?;
+    }
 
     let alpha  = vk.V.iter()
         .zip(s.iter())
@@ -281,19 +386,19 @@ pub fn Blind(vk: &PublicKey, m: &Vec<Scalar>, s: &Vec<Scalar>, d: &BlindingFacto
 
     let z2 = b2 + c * d;
 
-    BlindedSignRequest { m, alpha, gamma, z1, z2 }
+    Ok(BlindedSignRequest { m, alpha, gamma, z1, z2 })
 }
 
-pub fn Sign(vk: &PublicKey, sk: &SecretKey, req: &BlindedSignRequest, mut rng: impl RngCore) -> Result<BlindedSignature, SimpleError> {
+pub fn Sign(vk: &PublicKey, sk: &SecretKey, req: &BlindedSignRequest, mut rng: impl RngCore) -> Result<BlindedSignature, PrepamsError> {
     let w = Scalar::random(&mut rng);
     let m = req.m.clone();
 
     if vk.U.len() != req.m.len() {
-        Err(SimpleError::new("Invalid attributes supplied"))?;
+        Err(PrepamsError::encoding("Invalid attributes supplied"))?;
     }
 
     if vk.V.len() != req.z1.len() {
-        Err(SimpleError::new("Invalid responses supplied"))?;
+        Err(PrepamsError::encoding("Invalid responses supplied"))?;
     }
 
     let mut t = Transcript::new(b"sign-request");
@@ -325,7 +430,7 @@ pub fn Sign(vk: &PublicKey, sk: &SecretKey, req: &BlindedSignRequest, mut rng: i
 
     let r1 = req.alpha * c + req.gamma;
     if l1.to_affine() != r1.to_affine() {
-        Err(SimpleError::new("proof of knowledge failed"))?;
+        Err(PrepamsError::verification(Check::Request, "proof of knowledge failed"))?;
     }
 
     let sigma1 = (vk.g1 * sk.x + vk.U.iter().zip(req.m.iter()).fold(
@@ -338,7 +443,7 @@ pub fn Sign(vk: &PublicKey, sk: &SecretKey, req: &BlindedSignRequest, mut rng: i
     Ok(BlindedSignature { sigma1, sigma2, sigma3 })
 }
 
-pub fn Unblind(vk: &PublicKey, sig: &BlindedSignature, m: &Vec<Scalar>, s: &Vec<Scalar>, d: &BlindingFactor) -> Result<UnblindedSignature, String> {
+pub fn Unblind(vk: &PublicKey, sig: &BlindedSignature, m: &Vec<Scalar>, s: &Vec<Scalar>, d: &BlindingFactor) -> Result<UnblindedSignature, PrepamsError> {
     let sigma1 = (sig.sigma1 - sig.sigma3 * d).to_affine();
     let sigma2 = sig.sigma2.clone();
 
@@ -352,21 +457,26 @@ pub fn Unblind(vk: &PublicKey, sig: &BlindedSignature, m: &Vec<Scalar>, s: &Vec<
     Ok(res)
 } 
 
-pub fn Verify(vk: &PublicKey, sig: &UnblindedSignature) -> Result<(), String> {
+pub fn Verify(vk: &PublicKey, sig: &UnblindedSignature) -> Result<(), PrepamsError> {
     let m = sig.m.clone();
 
-    let lhs = pairing(&sig.sigma1, &vk.g2);
-
-    let rhs = vk.e + pairing(&vk.U.iter().zip(sig.m.iter()).fold(
+    let x = vk.U.iter().zip(sig.m.iter()).fold(
         vk.V.iter().zip(sig.s.iter()).fold(
             G1Projective::identity() + vk.h,
             |a, (v, s)| a + v * s
         ),
         |s, (u, m)| s + u * m
-    ).to_affine(), &sig.sigma2);
+    );
+
+    // e(sigma1, g2) == e + e(x, sigma2)
+    let lhs = multi_miller_loop(&[
+        (&sig.sigma1, &vk.g2_prepared()),
+        (&(-x).to_affine(), &G2Prepared::from(sig.sigma2)),
+    ]).final_exponentiation();
+    let rhs = vk.e;
 
     if lhs != rhs {
-        Err(String::from("signature not valid"))?;
+        Err(PrepamsError::verification(Check::Signature, "signature not valid"))?;
     }
 
     Ok(())
@@ -395,16 +505,6 @@ pub fn Rerandomize(s: &UnblindedSignature, vk: &PublicKey, mut rng: impl RngCore
     let s1 = (s.sigma1 + (u + v + &vk.h) * &d).to_affine();
     let s2 = (s.sigma2 + vk.g2 * &d).to_affine();
 
-    let vb = &vk.V.iter().zip(b1.iter()).fold(
-        G1Projective::identity(),
-        |a, (v, b)| a + v * b.neg()
-    );
-
-    let ub = &vk.U.iter().zip(b2.iter()).fold(
-        G1Projective::identity(),
-        |a, (u, b)| a + u * b.neg()
-    );
-
     let bp = vk.U.iter().zip(b2.iter()).fold(
         vk.V.iter().zip(b1.iter()).fold(
             vk.b * bb,
@@ -414,10 +514,9 @@ pub fn Rerandomize(s: &UnblindedSignature, vk: &PublicKey, mut rng: impl RngCore
     ).to_affine();
 
     let j1 = (vk.g1 * Scalar::random(&mut rng)).to_affine();
-    let e1 = pairing(&j1, &vk.g2) + pairing(&(ub + vb).to_affine(), &s2);
 
     (
-        RerandomizedProof { s2, e1, bp, vc },
+        RerandomizedProof { s2, bp, vc },
         RerandomizedWitness {
             s: s.clone(),
             bb: bb,
@@ -460,9 +559,18 @@ mod tests {
 
         let d = Scalar::random(&mut csrng);
 
-        let req = Blind(&pk, &m, &s, &d, &mut csrng);
+        let req = Blind(&pk, &m, &s, &d, &mut csrng).unwrap();
         let blinded_sig = Sign(&pk, &sk, &req, &mut csrng).unwrap();
         let sig = Unblind(&pk, &blinded_sig, &m, &s, &d).unwrap();
+        Verify(&pk, &sig).unwrap();
+
+        // keys are built from their public fields and the cached g2 follows the key in use
+        let rebuilt = PublicKey { tag: pk.tag.clone(), U: pk.U.clone(), V: pk.V.clone(), h: pk.h, b: pk.b, g1: pk.g1, g2: pk.g2, e: pk.e };
+        Verify(&rebuilt, &sig).unwrap();
+        let mut other = pk.clone();
+        other.g2 = (other.g2 * Scalar::random(&mut csrng)).to_affine();
+        assert_eq!(Verify(&other, &sig).unwrap_err().code(), "verification.signature");
+        assert!(Verify(&pk, &sig).is_ok());
     }
 
     #[test]
@@ -470,24 +578,43 @@ mod tests {
         let mut csrng = thread_rng();
         let (sk, pk) = Gen(&mut csrng, 2, 3, "test");
 
-        let s = vec![Scalar::random(&mut csrng), Scalar::random(&mut csrng)];
-        let m = vec![Scalar::random(&mut csrng), Scalar::random(&mut csrng), Scalar::random(&mut csrng)];
-
-        let d = Scalar::random(&mut csrng);
-
-        let req = Blind(&pk, &m, &s, &d, &mut csrng);
-        let blinded_sig = Sign(&pk, &sk, &req, &mut csrng).unwrap();
-        let sig = Unblind(&pk, &blinded_sig, &m, &s, &d).unwrap();
-
-        let (p, w) = Rerandomize(&sig, &pk, &mut csrng);
-        let mut prover_transcript = Transcript::new(b"prove_test");
-        p.commit(&mut prover_transcript);
-        let r = w.prove(&mut prover_transcript);
-
-        let mut verifier_transcript = Transcript::new(b"prove_test");
-        p.commit(&mut verifier_transcript);
-
-        assert!(r.verify(&p, &pk, &mut verifier_transcript));
+        let signatures: Vec<UnblindedSignature> = (0..3).map(|_| {
+            let s = vec![Scalar::random(&mut csrng), Scalar::random(&mut csrng)];
+            let m = vec![Scalar::random(&mut csrng), Scalar::random(&mut csrng), Scalar::random(&mut csrng)];
+            let d = Scalar::random(&mut csrng);
+
+            let req = Blind(&pk, &m, &s, &d, &mut csrng).unwrap();
+            let blinded_sig = Sign(&pk, &sk, &req, &mut csrng).unwrap();
+            Unblind(&pk, &blinded_sig, &m, &s, &d).unwrap()
+        }).collect();
+
+        let (proofs, witnesses): (Vec<RerandomizedProof>, Vec<RerandomizedWitness>) = signatures.iter()
+            .map(|sig| Rerandomize(sig, &pk, &mut csrng))
+            .unzip();
+        let e1 = CommitRerandomized(&pk, &proofs, &witnesses);
+
+        let transcript = |proofs: &[RerandomizedProof]| {
+            let mut t = Transcript::new(b"prove_test");
+            proofs.iter().for_each(|p| p.commit(&mut t));
+            t.append_gt(b"e1", &e1);
+            t
+        };
+
+        let r = RerandomizedProofResponse::prove(&proofs, &witnesses, &mut transcript(&proofs));
+        assert!(r.verify(&proofs, &e1, &pk, &mut transcript(&proofs)));
+
+        // a signature that is not covered by the commitment
+        let mut forged = proofs.clone();
+        forged[1].s2 = (forged[1].s2 * Scalar::random(&mut csrng)).to_affine();
+        assert!(!r.verify(&forged, &e1, &pk, &mut transcript(&forged)));
+
+        // responses cannot be dropped or moved to another signature
+        let mut dropped = r.clone();
+        dropped.responses.pop();
+        assert!(!dropped.verify(&proofs, &e1, &pk, &mut transcript(&proofs)));
+
+        let mut swapped = r.clone();
+        swapped.responses.swap(0, 2);
+        assert!(!swapped.verify(&proofs, &e1, &pk, &mut transcript(&proofs)));
     }
 }
-
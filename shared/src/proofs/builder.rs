@@ -0,0 +1,534 @@
+//! Builder for the statements of [`GenericProof`](crate::proofs::generic::GenericProof).
+//!
+//! A statement is written once as a function over a [`ConstraintSystem`] and
+//! runs twice: with the challenge `u` it collects the [`Variables`] of the
+//! prover or verifier, with the challenge `y` it collects the [`Constraint`]s.
+//! Variables are referred to by [`Var`] handles, so both runs agree on the ids
+//! and on the number of variables by construction.
+//!
+//! Inner variables are terms of linear relations between group elements, the
+//! relations are separated by powers of `u` that the system keeps track of.
+//! Constraints are collected in named [`Batch`]es whose rows are separated by
+//! powers of `y`. On top of that the system provides gadgets for bit
+//! decompositions, one-of-n selections and inverses. A third run with
+//! [`ConstraintSystem::rows`] collects every row as a constraint of its own for
+//! the [debugger](crate::proofs::debug).
+
+#![allow(non_snake_case)]
+
+use group::Curve;
+use bls12_381::{G1Affine, G1Projective, Scalar};
+
+use crate::msm::{vartime_multiscalar_mul, FixedBase};
+use crate::proofs::generic::{Constraint, ConstraintType, Variable, Variables};
+
+/// Handle of a variable of a statement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Var(String);
+
+impl Var {
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Public point an inner variable is multiplied with in its relation.
+pub trait Base {
+    /// Computes the point scaled by the separator `sep` of the relation.
+    fn at(&self, sep: &Scalar) -> G1Projective;
+}
+
+impl Base for G1Affine {
+    fn at(&self, sep: &Scalar) -> G1Projective {
+        self * sep
+    }
+}
+
+impl Base for &G1Affine {
+    fn at(&self, sep: &Scalar) -> G1Projective {
+        *self * sep
+    }
+}
+
+impl Base for &FixedBase {
+    fn at(&self, sep: &Scalar) -> G1Projective {
+        self.mul(sep)
+    }
+}
+
+/// Multiple of a fixed generator.
+impl Base for (Scalar, &FixedBase) {
+    fn at(&self, sep: &Scalar) -> G1Projective {
+        self.1.mul(&(self.0 * sep))
+    }
+}
+
+/// Collects either the variables or the constraints of a statement.
+pub struct ConstraintSystem {
+    // `None` if only the constraints are collected
+    vars: Option<Variables>,
+    u: Scalar,
+    // separator of the current relation
+    sep: Scalar,
+    y: Scalar,
+    constraints: Vec<Constraint>,
+    // `None` unless the rows are collected
+    rows: Option<Vec<(String, Constraint)>>,
+}
+
+impl ConstraintSystem {
+    /// System that collects the variables, their relations are separated by
+    /// powers of `u`.
+    pub fn variables(u: &Scalar) -> ConstraintSystem {
+        ConstraintSystem {
+            vars: Some(Variables::new()),
+            u: *u,
+            sep: Scalar::one(),
+            y: Scalar::zero(),
+            constraints: vec![],
+            rows: None,
+        }
+    }
+
+    /// System that collects the constraints, rows of batches are separated by
+    /// powers of `y`. Witness values are ignored.
+    pub fn constraints(y: &Scalar) -> ConstraintSystem {
+        ConstraintSystem {
+            vars: None,
+            u: Scalar::zero(),
+            sep: Scalar::one(),
+            y: *y,
+            constraints: vec![],
+            rows: None,
+        }
+    }
+
+    /// System that collects the rows of all batches as constraints of their
+    /// own, named after their batch and position, e.g. `binary.mul[3]`.
+    /// Witness values are ignored.
+    pub fn rows() -> ConstraintSystem {
+        ConstraintSystem {
+            vars: None,
+            u: Scalar::zero(),
+            sep: Scalar::one(),
+            y: Scalar::one(),
+            constraints: vec![],
+            rows: Some(vec![]),
+        }
+    }
+
+    pub fn into_variables(self) -> Variables {
+        self.vars.unwrap_or_else(Variables::new)
+    }
+
+    pub fn into_constraints(self) -> Vec<Constraint> {
+        self.constraints
+    }
+
+    pub fn into_rows(self) -> Vec<(String, Constraint)> {
+        self.rows.unwrap_or_default()
+    }
+
+    /// Adds the term `value * base` to the current relation.
+    pub fn inner(&mut self, id: impl Into<String>, base: impl Base, value: Scalar) -> Var {
+        self.term(id.into(), base, value, Scalar::zero())
+    }
+
+    fn term(&mut self, id: String, base: impl Base, cl: Scalar, cr: Scalar) -> Var {
+        if let Some(vars) = &mut self.vars {
+            vars.add(Variable::Inner { id: id.clone(), G: base.at(&self.sep).to_affine(), cl, cr });
+        }
+        Var(id)
+    }
+
+    /// Adds the term `value * bases[i]` to each of the relations that follow
+    /// the current one, the caller moves past them with [`Self::relation`].
+    pub fn span(&mut self, id: impl Into<String>, bases: &[G1Affine], value: Scalar) -> Var {
+        let id = id.into();
+        if let Some(vars) = &mut self.vars {
+            let mut sep = self.sep;
+            let seps: Vec<Scalar> = bases.iter().map(|_| {
+                sep *= self.u;
+                sep
+            }).collect();
+
+            vars.add(Variable::Inner {
+                id: id.clone(),
+                G: vartime_multiscalar_mul(&seps, bases).to_affine(),
+                cl: value,
+                cr: Scalar::zero()
+            });
+        }
+        Var(id)
+    }
+
+    /// Adds a variable that is not part of any relation.
+    pub fn scratch(&mut self, id: impl Into<String>, cl: Scalar, cr: Scalar) -> Var {
+        let id = id.into();
+        if let Some(vars) = &mut self.vars {
+            vars.add(Variable::Scratch { id: id.clone(), cl, cr });
+        }
+        Var(id)
+    }
+
+    /// Closes the current relation, the terms that follow belong to the next one.
+    pub fn relation(&mut self) {
+        self.sep *= self.u;
+    }
+
+    /// Starts an empty batch of constraints.
+    pub fn batch(&self, name: &str, kind: ConstraintType) -> Batch {
+        let constraint = Constraint::new(kind, Scalar::zero());
+        Batch {
+            name: name.to_string(),
+            rows: self.rows.as_ref().map(|_| vec![empty(&constraint)]),
+            constraint,
+            y: self.y,
+            weight: Scalar::one(),
+        }
+    }
+
+    /// Appends a batch to the constraints of the statement.
+    pub fn push(&mut self, batch: Batch) {
+        if let (Some(rows), Some(batch_rows)) = (&mut self.rows, batch.rows) {
+            rows.extend(batch_rows.into_iter().enumerate()
+                .filter(|(_, row)| !is_empty(row))
+                .map(|(i, row)| (format!("{}[{}]", batch.name, i), row)));
+        }
+        self.constraints.push(batch.constraint);
+    }
+
+    /// Adds the `n` least significant bits of `value` as scratch variables.
+    /// The caller shows that they are bits with [`Binary::add`].
+    pub fn bits(&mut self, id: impl Fn(u32) -> String, value: u64, n: u32) -> Vec<Var> {
+        (0..n).map(|j| {
+            let bit = Scalar::from((value >> j) & 1);
+            self.scratch(id(j), bit, bit - Scalar::one())
+        }).collect()
+    }
+
+    /// Adds a selector per option to the current relation, whose term is
+    /// `base` if the option is selected and the identity otherwise. Shows that
+    /// the selectors are bits and that exactly one is set in a row of `selection`.
+    pub fn one_of_n<B: Base>(&mut self, binary: &mut Binary, selection: &mut Batch, id: impl Fn(usize) -> String, options: impl IntoIterator<Item = (B, bool)>) -> Vec<Var> {
+        let selectors: Vec<Var> = options.into_iter().enumerate().map(|(j, (base, selected))| {
+            let (cl, cr) = if selected { (Scalar::one(), Scalar::zero()) } else { (Scalar::zero(), -Scalar::one()) };
+            let var = self.term(id(j), base, cl, cr);
+            binary.add(&[&var]);
+            var
+        }).collect();
+
+        selection.linear(selectors.iter().map(|s| (s, Scalar::one())), Scalar::one());
+        selectors
+    }
+
+    /// Adds a scratch variable `x` that holds `value` and its inverse, and
+    /// shows that `x * x^-1 = 1` and `negated = -x^-1`.
+    pub fn inverse(&mut self, inverse: &mut Inverse, id: impl Into<String>, value: Scalar, negated: &Var) -> Var {
+        let x = self.scratch(id, value, value.invert().unwrap_or(Scalar::zero()));
+
+        inverse.mul.right(&x, Scalar::one()).result(Scalar::one()).next_row();
+        inverse.sum.left(negated, Scalar::one()).right(&x, Scalar::one()).next_row();
+        x
+    }
+}
+
+/// Constraint whose rows are separated by powers of `y`.
+///
+/// The terms of a row are added with [`Batch::left`], [`Batch::right`] and
+/// [`Batch::result`], [`Batch::next_row`] moves on to the next row. The row
+/// helpers do both. Which side of the variables a term refers to depends on
+/// the type of the constraint, see [`Constraint`].
+pub struct Batch {
+    name: String,
+    constraint: Constraint,
+    // unweighted rows, the last one is the current row
+    rows: Option<Vec<Constraint>>,
+    y: Scalar,
+    // separator of the current row
+    weight: Scalar,
+}
+
+impl Batch {
+    /// Adds `coefficient * var` to the left side of the current row.
+    pub fn left(&mut self, var: &Var, coefficient: Scalar) -> &mut Self {
+        add_left(&mut self.constraint, var, coefficient * self.weight);
+        if let Some(row) = self.rows.as_mut().and_then(|rows| rows.last_mut()) {
+            add_left(row, var, coefficient);
+        }
+        self
+    }
+
+    /// Adds `coefficient * var` to the right side of the current row.
+    pub fn right(&mut self, var: &Var, coefficient: Scalar) -> &mut Self {
+        add_right(&mut self.constraint, var, coefficient * self.weight);
+        if let Some(row) = self.rows.as_mut().and_then(|rows| rows.last_mut()) {
+            add_right(row, var, coefficient);
+        }
+        self
+    }
+
+    /// Adds `value` to the result of the current row.
+    pub fn result(&mut self, value: Scalar) -> &mut Self {
+        add_result(&mut self.constraint, value * self.weight);
+        if let Some(row) = self.rows.as_mut().and_then(|rows| rows.last_mut()) {
+            add_result(row, value);
+        }
+        self
+    }
+
+    /// Moves on to the next row.
+    pub fn next_row(&mut self) -> &mut Self {
+        self.weight *= self.y;
+        if let Some(rows) = &mut self.rows {
+            rows.push(empty(&self.constraint));
+        }
+        self
+    }
+
+    /// Skips the separators of `n` rows.
+    pub fn skip(mut self, n: usize) -> Self {
+        for _ in 0..n {
+            self.weight *= self.y;
+        }
+        self
+    }
+
+    /// Adds the row `sum(coefficient * var) = result` over the left side.
+    pub fn linear<'a>(&mut self, terms: impl IntoIterator<Item = (&'a Var, Scalar)>, result: Scalar) -> &mut Self {
+        for (var, coefficient) in terms {
+            self.left(var, coefficient);
+        }
+        self.result(result).next_row()
+    }
+
+    /// Adds the row `a - b = c`.
+    pub fn equal(&mut self, a: &Var, b: &Var, c: Scalar) -> &mut Self {
+        self.linear([(a, Scalar::one()), (b, -Scalar::one())], c)
+    }
+
+    /// Adds the row `a = c`.
+    pub fn constant(&mut self, a: &Var, c: Scalar) -> &mut Self {
+        self.linear([(a, Scalar::one())], c)
+    }
+
+    /// Adds `factor * sum(2^j * bits[j])` to the left side of the current row.
+    pub fn bits(&mut self, bits: &[Var], factor: Scalar) -> &mut Self {
+        let mut power = factor;
+        for bit in bits {
+            self.left(bit, power);
+            power = power.double();
+        }
+        self
+    }
+}
+
+fn add_left(constraint: &mut Constraint, var: &Var, term: Scalar) {
+    if let Constraint::Sum { left, .. } | Constraint::Dir { left, .. } = constraint {
+        *left.entry(var.id().to_string()).or_insert(Scalar::zero()) += term;
+    }
+}
+
+fn add_right(constraint: &mut Constraint, var: &Var, term: Scalar) {
+    if let Constraint::Sum { right, .. } | Constraint::Mul { right, .. } | Constraint::One { right, .. } = constraint {
+        *right.entry(var.id().to_string()).or_insert(Scalar::zero()) += term;
+    }
+}
+
+fn add_result(constraint: &mut Constraint, term: Scalar) {
+    match constraint {
+        Constraint::Sum { result, .. } | Constraint::Mul { result, .. } | Constraint::One { result, .. } | Constraint::Dir { result, .. } => {
+            *result += term;
+        }
+    }
+}
+
+// empty constraint of the same type
+fn empty(constraint: &Constraint) -> Constraint {
+    let kind = match constraint {
+        Constraint::Sum { .. } => ConstraintType::Sum,
+        Constraint::Mul { .. } => ConstraintType::Mul,
+        Constraint::One { .. } => ConstraintType::One,
+        Constraint::Dir { .. } => ConstraintType::Dir,
+    };
+    Constraint::new(kind, Scalar::zero())
+}
+
+fn is_empty(constraint: &Constraint) -> bool {
+    match constraint {
+        Constraint::Sum { left, right, result } => left.is_empty() && right.is_empty() && result == &Scalar::zero(),
+        Constraint::Mul { right, result } | Constraint::One { right, result } => right.is_empty() && result == &Scalar::zero(),
+        Constraint::Dir { left, result } => left.is_empty() && result == &Scalar::zero(),
+    }
+}
+
+/// Constraints showing that variables are bits, i.e. `cl * cr = 0` and
+/// `cr = cl - 1`.
+pub struct Binary {
+    pub mul: Batch,
+    pub one: Batch,
+}
+
+impl Binary {
+    pub fn new(cs: &ConstraintSystem) -> Binary {
+        Binary {
+            mul: cs.batch("binary.mul", ConstraintType::Mul),
+            one: cs.batch("binary.one", ConstraintType::One),
+        }
+    }
+
+    /// Adds a row per variable of `vars` that holds if the variable is a bit.
+    /// A row over several variables would only bound their sum.
+    pub fn add(&mut self, vars: &[&Var]) {
+        for var in vars {
+            self.mul.right(var, Scalar::one()).next_row();
+            self.one.right(var, Scalar::one()).next_row();
+        }
+    }
+}
+
+/// Constraints of [`ConstraintSystem::inverse`].
+pub struct Inverse {
+    pub mul: Batch,
+    pub sum: Batch,
+}
+
+impl Inverse {
+    pub fn new(cs: &ConstraintSystem) -> Inverse {
+        Inverse {
+            mul: cs.batch("inverse.mul", ConstraintType::Mul),
+            sum: cs.batch("inverse.sum", ConstraintType::Sum),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use crate::msm::GENERATOR;
+    use crate::external::util::as_scalar;
+    use crate::external::transcript::TranscriptProtocol;
+    use crate::proofs::generic::{GenericProof, Proof, ProofInput, Transcript, Variable};
+
+    // x * g is the point and x is one of the options
+    #[derive(Serialize, Clone)]
+    struct Input {
+        #[serde(with = "crate::serialization::G1Affine")]
+        point: G1Affine,
+        options: Vec<u32>,
+    }
+
+    impl ProofInput for Input {
+        fn commit(&self, transcript: &mut Transcript) {
+            transcript.append_g1(b"point", &self.point);
+        }
+    }
+
+    #[derive(Default)]
+    struct Secret(u32);
+
+    struct Statement {}
+
+    fn statement(cs: &mut ConstraintSystem, input: &Input, x: u32) {
+        let p = cs.inner("p", input.point, -Scalar::one());
+        let g = cs.inner("g", GENERATOR(), as_scalar(x));
+        cs.relation();
+
+        let c = cs.inner("c", GENERATOR(), -as_scalar(x));
+        let mut binary = Binary::new(cs);
+        let mut selection = cs.batch("selection", ConstraintType::Dir);
+        cs.one_of_n(&mut binary, &mut selection, |j| format!("c_{}", j), input.options.iter().map(|o| ((as_scalar(*o), GENERATOR()), *o == x)));
+        cs.relation();
+
+        let bits = cs.bits(|j| format!("bit_{}", j), x as u64, 8);
+        for bit in &bits {
+            binary.add(&[bit]);
+        }
+
+        let mut linear = cs.batch("linear", ConstraintType::Dir);
+        linear.constant(&p, -Scalar::one());
+        linear.linear([(&g, Scalar::one()), (&c, Scalar::one())], Scalar::zero());
+        linear.bits(&bits, Scalar::one()).linear([(&g, -Scalar::one())], Scalar::zero());
+
+        for batch in [linear, selection, binary.one, binary.mul] {
+            cs.push(batch);
+        }
+    }
+
+    impl Proof<Input, Secret, ()> for Statement {
+        fn get_variables(inputs: &Input, secrets: &Secret, u: &Scalar) -> Variables {
+            let mut cs = ConstraintSystem::variables(u);
+            statement(&mut cs, inputs, secrets.0);
+            cs.into_variables()
+        }
+
+        fn get_constraints(inputs: &Input, y: &Scalar) -> Vec<Constraint> {
+            let mut cs = ConstraintSystem::constraints(y);
+            statement(&mut cs, inputs, 0);
+            cs.into_constraints()
+        }
+    }
+
+    // decomposes 5 as 5 * 2^0 instead of 2^0 + 2^2
+    struct NonBinary {}
+
+    impl Proof<Input, Secret, ()> for NonBinary {
+        fn get_variables(inputs: &Input, secrets: &Secret, u: &Scalar) -> Variables {
+            let mut vars = Statement::get_variables(inputs, secrets, u);
+            vars.add(Variable::Scratch { id: "bit_0".to_string(), cl: Scalar::from(5), cr: Scalar::zero() });
+            vars.add(Variable::Scratch { id: "bit_2".to_string(), cl: Scalar::zero(), cr: -Scalar::one() });
+            vars
+        }
+
+        fn get_constraints(inputs: &Input, y: &Scalar) -> Vec<Constraint> {
+            Statement::get_constraints(inputs, y)
+        }
+    }
+
+    fn prove<F: Proof<Input, Secret, ()>>(x: u32, options: Vec<u32>) -> bool {
+        let input = Input { point: (G1Affine::generator() * as_scalar(x)).to_affine(), options };
+
+        let mut transcript = Transcript::new(b"builder test");
+        let proof = GenericProof::<Input, ()>::proove::<Secret, F>(&mut transcript, input, Secret(x)).unwrap();
+
+        let mut transcript = Transcript::new(b"builder test");
+        proof.verify::<Secret, Statement>(&mut transcript).is_ok()
+    }
+
+    #[test]
+    fn statement_runs_agree() {
+        let input = Input { point: G1Affine::generator(), options: vec![1, 2, 3] };
+
+        let mut cs = ConstraintSystem::variables(&Scalar::from(7));
+        statement(&mut cs, &input, 1);
+        let vars = cs.into_variables();
+
+        let mut cs = ConstraintSystem::constraints(&Scalar::from(11));
+        statement(&mut cs, &input, 0);
+        let constraints = cs.into_constraints();
+
+        // every constraint refers to variables of the statement only
+        for constraint in &constraints {
+            let ids = match constraint {
+                Constraint::Sum { left, right, .. } => left.keys().chain(right.keys()).collect::<Vec<_>>(),
+                Constraint::Mul { right, .. } | Constraint::One { right, .. } => right.keys().collect(),
+                Constraint::Dir { left, .. } => left.keys().collect(),
+            };
+            assert!(ids.iter().all(|id| vars.get(id).is_some()));
+        }
+        assert_eq!(vars.len(), 3 + 3 + 8);
+        assert_eq!(constraints.len(), 4);
+    }
+
+    #[test]
+    fn gadgets() {
+        assert!(prove::<Statement>(5, vec![3, 5, 7]));
+        assert!(!prove::<Statement>(4, vec![3, 5, 7]));
+    }
+
+    #[test]
+    fn non_binary_bits() {
+        assert!(!prove::<NonBinary>(5, vec![3, 5, 7]));
+    }
+}
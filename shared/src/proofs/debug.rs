@@ -0,0 +1,248 @@
+//! Debugger for the statements of [`GenericProof`].
+//!
+//! [`debug`] evaluates every constraint of a statement against the witness of
+//! the prover before it is blinded, then proves and verifies the statement and
+//! reports the check of the verifier that failed. Statements written with the
+//! [builder](crate::proofs::builder) name their constraints row by row, e.g.
+//! `constraint_0.from[0]`, other statements by their position.
+
+use std::fmt;
+
+use bls12_381::Scalar;
+use serde::Serialize;
+
+use crate::types::ProofError;
+use crate::proofs::generic::{Constraint, GenericProof, Proof, ProofInput, Transcript, Variable, Variables};
+use crate::external::transcript::TranscriptProtocol;
+
+/// Term of an unsatisfied constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub id: String,
+    /// coefficient of the variable on the left side of the constraint
+    pub left: Scalar,
+    /// coefficient of the variable on the right side of the constraint
+    pub right: Scalar,
+    /// `cl` and `cr` of the witness, `None` if the statement has no such
+    /// variable and the verifier ignores the term
+    pub value: Option<(Scalar, Scalar)>,
+}
+
+/// Constraint that does not hold for the witness of the prover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsatisfied {
+    pub name: String,
+    /// value of the constraint for the witness
+    pub value: Scalar,
+    pub result: Scalar,
+    pub terms: Vec<Term>,
+}
+
+/// Outcome of [`debug`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub unsatisfied: Vec<Unsatisfied>,
+    /// Ids of the variables the verifier derives differently from the prover,
+    /// i.e. variables that do not only depend on the inputs of the statement.
+    pub mismatched: Vec<String>,
+    /// First error of the verifier: [`Check::InnerProduct`](crate::error::Check::InnerProduct)
+    /// and [`Check::Commitment`](crate::error::Check::Commitment) for the inner
+    /// product argument and the t-check,
+    /// any other check comes from the additional checks of the statement.
+    /// [`ProofError::InvalidError`] if the inputs do not validate.
+    pub failed: Option<ProofError>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.unsatisfied.is_empty() && self.mismatched.is_empty() && self.failed.is_none()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for constraint in &self.unsatisfied {
+            writeln!(f, "constraint {} unsatisfied: {} != {}", constraint.name, constraint.value, constraint.result)?;
+            for term in &constraint.terms {
+                match term.value {
+                    Some((cl, cr)) => writeln!(f, "    {}: left {}, right {}, cl {}, cr {}", term.id, term.left, term.right, cl, cr)?,
+                    None => writeln!(f, "    {}: not a variable of the statement", term.id)?,
+                }
+            }
+        }
+
+        for id in &self.mismatched {
+            writeln!(f, "variable {} differs between prover and verifier", id)?;
+        }
+
+        match &self.failed {
+            Some(ProofError::VerificationError(check)) => writeln!(f, "verification failed at {}: {}", check.code(), check),
+            Some(ProofError::InvalidError) => writeln!(f, "inputs of the statement are invalid"),
+            None => writeln!(f, "verification succeeded"),
+        }
+    }
+}
+
+/// Evaluates `constraint` for the witness in `vars`.
+///
+/// Per variable a constraint of type `Dir` sums `left * cl`, `Mul` sums
+/// `right * cl * cr`, `One` sums `right * (cl - cr - 1)` and `Sum` sums
+/// `left * cl + right * cr`. Returns `None` if the sum equals the result
+/// and all terms refer to variables of the statement.
+pub fn evaluate(vars: &Variables, name: &str, constraint: &Constraint) -> Option<Unsatisfied> {
+    let none = std::collections::HashMap::new();
+    let (left, right, result) = match constraint {
+        Constraint::Sum { left, right, result } => (left, right, result),
+        Constraint::Mul { right, result } | Constraint::One { right, result } => (&none, right, result),
+        Constraint::Dir { left, result } => (left, &none, result),
+    };
+
+    let mut ids: Vec<&String> = left.keys().chain(right.keys()).collect();
+    ids.sort();
+    ids.dedup();
+
+    let mut value = Scalar::zero();
+    let terms: Vec<Term> = ids.into_iter().map(|id| {
+        let l = left.get(id).copied().unwrap_or(Scalar::zero());
+        let r = right.get(id).copied().unwrap_or(Scalar::zero());
+        let witness = vars.get(id).map(|var| match var {
+            Variable::Inner { cl, cr, .. } | Variable::Scratch { cl, cr, .. } => (*cl, *cr),
+        });
+
+        if let Some((cl, cr)) = witness {
+            value += match constraint {
+                Constraint::Sum { .. } => l * cl + r * cr,
+                Constraint::Mul { .. } => r * cl * cr,
+                Constraint::One { .. } => r * (cl - cr - Scalar::one()),
+                Constraint::Dir { .. } => l * cl,
+            };
+        }
+
+        Term { id: id.clone(), left: l, right: r, value: witness }
+    }).collect();
+
+    if &value == result && terms.iter().all(|term| term.value.is_some()) {
+        return None;
+    }
+
+    Some(Unsatisfied { name: name.to_string(), value, result: *result, terms })
+}
+
+/// Checks the statement `F` for `inputs` and `secrets` like the prover and
+/// verifier would with `transcript`, see [`Report`].
+pub fn debug<P, S, A, F>(transcript: &Transcript, inputs: P, secrets: S) -> Report
+where
+    P: ProofInput + Clone,
+    S: Default,
+    A: Serialize + Clone + Default,
+    F: Proof<P, S, A>,
+{
+    // the witness does not depend on the challenge
+    let witness = F::get_variables(&inputs, &secrets, &Scalar::zero());
+    let unsatisfied = F::get_named_constraints(&inputs)
+        .iter()
+        .filter_map(|(name, constraint)| evaluate(&witness, name, constraint))
+        .collect();
+
+    // blinded variables of the prover, see GenericProof::variables
+    let mut prover = transcript.clone();
+    prover.append_u64(b"m", witness.len() as u64);
+    inputs.commit(&mut prover);
+    let u = prover.challenge_scalar(b"u for exponents");
+    let vars = F::get_variables(&inputs, &secrets, &u).blind();
+
+    let mut mismatched = vec![];
+    let failed = GenericProof::<P, A>::proove::<S, F>(&mut transcript.clone(), inputs, secrets).and_then(|proof| {
+        let derived = proof.variables::<S, F>(&mut transcript.clone())?;
+        mismatched = vars.ids().chain(derived.ids())
+            .filter(|id| vars.get(id) != derived.get(id))
+            .map(str::to_string)
+            .collect();
+        mismatched.sort();
+        mismatched.dedup();
+
+        proof.verify::<S, F>(&mut transcript.clone())
+    }).err();
+
+    Report { unsatisfied, mismatched, failed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+    use crate::error::Check;
+    use group::Curve;
+    use bls12_381::G1Affine;
+    use crate::pbss;
+    use crate::credential::{init, issue_request, issue, get_credential};
+    use crate::types::{Resource, AttributeConstraint};
+    use crate::types::prerequisites::Qualifier;
+    use crate::external::util::rand_scalar;
+    use crate::proofs::participation::{ParticipationProof, ParticipationProofInput, ParticipationProofSecrets};
+
+    fn participation(constraints: Vec<AttributeConstraint>) -> (ParticipationProofInput, ParticipationProofSecrets) {
+        let mut rng = rand::thread_rng();
+        let (ipk, isk) = init(&mut rng, 2);
+        let (_csk, cvk) = pbss::Gen(&mut rng, 2, 1, "payment");
+
+        let (request, mut credential) = issue_request(&mut rng, &ipk, "user@example.com", vec![Scalar::from(2), Scalar::from(1985)]).unwrap();
+        let response = issue(&mut rng, &ipk, &isk, &request).unwrap();
+        get_credential(&ipk, &response, &mut credential).unwrap();
+
+        let qid = rand_scalar();
+        let resource = Resource {
+            id: rand_scalar(),
+            name: "name".to_string(),
+            summary: "summary".to_string(),
+            description: "description".to_string(),
+            duration: "duration".to_string(),
+            reward: 1,
+            webBased: false,
+            studyUrl: None,
+            qualifier: vec![Qualifier {
+                id: qid,
+                tags: vec![
+                    (G1Affine::generator() * Scalar::random(&mut rng)).to_affine(),
+                    credential.derive_tag(&qid).unwrap(),
+                ]
+            }],
+            disqualifier: vec![],
+            constraints
+        };
+
+        ParticipationProofInput::new(&ipk, &cvk, &credential, &resource).unwrap()
+    }
+
+    fn debug_participation(inputs: ParticipationProofInput, secrets: ParticipationProofSecrets) -> Report {
+        debug::<_, _, (), ParticipationProof>(&Transcript::new(b"participation"), inputs, secrets)
+    }
+
+    #[test]
+    fn satisfied() {
+        let (inputs, secrets) = participation(vec![AttributeConstraint::Range(1, 1980, 1990)]);
+        let report = debug_participation(inputs, secrets);
+        assert!(report.is_ok(), "{}", report);
+    }
+
+    #[test]
+    fn unsatisfied_range() {
+        let (inputs, secrets) = participation(vec![AttributeConstraint::Range(1, 1990, 2000)]);
+        let report = debug_participation(inputs, secrets);
+
+        let names: Vec<&str> = report.unsatisfied.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["constraint_0.from[0]"], "{}", report);
+        assert!(report.unsatisfied[0].terms.iter().any(|term| term.id == "attr_1" && term.value == Some((Scalar::from(1985), Scalar::zero()))));
+        assert!(matches!(report.failed, Some(ProofError::VerificationError(Check::InnerProduct | Check::Commitment))), "{}", report);
+    }
+
+    #[test]
+    fn failed_additional_check() {
+        let (mut inputs, secrets) = participation(vec![]);
+        inputs.auth_request.token = G1Affine::generator();
+        let report = debug_participation(inputs, secrets);
+
+        assert!(report.unsatisfied.is_empty(), "{}", report);
+        assert_eq!(report.failed, Some(ProofError::VerificationError(Check::Tag)));
+    }
+}
@@ -4,22 +4,24 @@
 pub use merlin::Transcript;
 use core::iter;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use std::hash::{Hash, Hasher};
 use std::collections::HashMap;
 use std::collections::BTreeMap;
 
 use ff::Field;
 use group::Curve;
+use rand::{CryptoRng, RngCore};
 
 use bls12_381::{G1Affine, Scalar};
+use crate::error::Check;
 use crate::types::ProofError;
-use crate::external::console::*;
-use crate::external::inner_product_proof::{InnerProductProof, inner_product, vartime_multiscalar_mul};
+use crate::external::inner_product_proof::{InnerProductProof, inner_product};
 use crate::external::util::{add_vec, smul_vec, mul_vec, sub_vec, VecPoly1};
 use crate::external::transcript::TranscriptProtocol;
 use crate::external::inner_product_proof;
-
-const DEBUG_ASSERTIONS: bool = false;
+use crate::msm::{self, Equation, GENERATOR};
+use crate::parallel;
 
 #[derive(Debug, Clone)]
 pub enum Constraint {
@@ -45,7 +47,7 @@ pub enum Constraint {
     },
 }
 
-#[derive(Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, JsonSchema)]
 pub struct Variables {
     inner: BTreeMap<String, Variable>,
     scratch: BTreeMap<String, Variable>
@@ -102,6 +104,11 @@ impl Variables {
         self.inner.len() + self.scratch.len()
     }
 
+    /// Ids of the inner variables followed by the ids of the scratch variables.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.inner.keys().chain(self.scratch.keys()).map(String::as_str)
+    }
+
     pub fn get_cl(&self) -> Vec<Scalar> {
         self.inner.iter().map(|v| match v {
             (_, Variable::Inner { id: _, G: _, cl, cr: _ }) => cl.clone(),
@@ -123,10 +130,11 @@ impl Variables {
     }
 
     pub fn get_G(&self, w: &Scalar, P: &Vec<G1Affine>, Gprime: &Vec<G1Affine>) -> Vec<G1Affine> {
-        let mut Gw: Vec<G1Affine> = self.inner.iter().zip(P).map(|(v, iP)| match v {
-            (_, Variable::Inner { id: _, G: gP, cl: _, cr: _ }) => (gP * w + iP).to_affine(),
+        let inner: Vec<(&Variable, &G1Affine)> = self.inner.values().zip(P).collect();
+        let mut Gw: Vec<G1Affine> = parallel::map(&inner, |(v, iP)| match v {
+            Variable::Inner { id: _, G: gP, cl: _, cr: _ } => (gP * w + *iP).to_affine(),
             _ => panic!()
-        }).collect();
+        });
 
         Gw.extend(Gprime);
         Gw
@@ -348,23 +356,28 @@ impl Constraint {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, JsonSchema)]
 pub enum Variable {
     Inner {
         id: String,
         #[serde(with = "crate::serialization::G1Affine")]
+        #[schemars(with = "crate::serialization::schema::G1")]
         G: G1Affine,
         #[serde(with = "crate::serialization::Scalar")]
+        #[schemars(with = "crate::serialization::schema::Scalar")]
         cl: Scalar,
         #[serde(with = "crate::serialization::Scalar")]
+        #[schemars(with = "crate::serialization::schema::Scalar")]
         cr: Scalar
     },
 
     Scratch {
         id: String,
         #[serde(with = "crate::serialization::Scalar")]
+        #[schemars(with = "crate::serialization::schema::Scalar")]
         cl: Scalar,
         #[serde(with = "crate::serialization::Scalar")]
+        #[schemars(with = "crate::serialization::schema::Scalar")]
         cr: Scalar,
     }
 }
@@ -404,36 +417,62 @@ impl Variable {
 
 pub trait ProofInput: Serialize {
     fn commit(&self, transcript: &mut Transcript);
+
+    /// Checks that the inputs are well-formed before variables are derived from them.
+    fn validate(&self) -> Result<(), ProofError> {
+        Ok(())
+    }
 }
 
 pub trait Proof<P: ProofInput + Clone, S: Default, A: Serialize + Clone + Default> {
     fn get_variables(inputs: &P, secrets: &S, u: &Scalar) -> Variables;
     fn get_constraints(inputs: &P, y: &Scalar) -> Vec<Constraint>;
-    fn additional_checks(_inputs: &P, _data: &A, _transcript: &mut Transcript) -> bool {
-        true
+    /// Constraints of the statement with their names for the
+    /// [debugger](crate::proofs::debug), by default the constraints for a
+    /// random `y` named by their position.
+    fn get_named_constraints(inputs: &P) -> Vec<(String, Constraint)> {
+        let y = Scalar::random(rand::thread_rng());
+        Self::get_constraints(inputs, &y).into_iter().enumerate().map(|(i, c)| (format!("constraint_{}", i), c)).collect()
+    }
+    /// Checks that the secrets satisfy the statement for `inputs` before proving.
+    fn validate_secrets(_inputs: &P, _secrets: &S) -> Result<(), ProofError> {
+        Ok(())
+    }
+    fn additional_checks(_inputs: &P, _data: &A, _transcript: &mut Transcript) -> Result<(), Check> {
+        Ok(())
     }
     fn additional_data(_inputs: &P, _secrets: &S, _transcript: &mut Transcript) -> A {
         A::default()
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct GenericProof<P: ProofInput + Clone, A: Serialize + Clone + Default> {
-    pub vars: Variables,
+/// Proof of a statement over the public inputs `P`.
+///
+/// The proof only carries the cryptographic elements, the verifier derives
+/// the blinded variables from the inputs, see [`GenericProof::variables`].
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct GenericProof<P, A> {
     pub inputs: P,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub A: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub S: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub T1: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub T2: G1Affine,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub tau: Scalar,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub r: Scalar,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub t: Scalar,
     pub ipp_proof: InnerProductProof,
     pub add_data: A
@@ -441,7 +480,13 @@ pub struct GenericProof<P: ProofInput + Clone, A: Serialize + Clone + Default> {
 
 impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
     pub fn proove<S: Default, F: Proof<P, S, A>>(transcript: &mut Transcript, inputs: P, secrets: S) -> Result<GenericProof<P, A>, ProofError> {
-        let mut csrng = rand::thread_rng();
+        GenericProof::proove_with_rng::<S, F>(transcript, inputs, secrets, rand::thread_rng())
+    }
+
+    /// Creates the proof with blinding factors drawn from `csrng`, the
+    /// challenges are derived from the transcript.
+    pub fn proove_with_rng<S: Default, F: Proof<P, S, A>>(transcript: &mut Transcript, inputs: P, secrets: S, mut csrng: impl RngCore + CryptoRng) -> Result<GenericProof<P, A>, ProofError> {
+        F::validate_secrets(&inputs, &secrets)?;
 
         // get temporary vars
         let vars = F::get_variables(&inputs, &secrets, &Scalar::zero());
@@ -457,10 +502,6 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
 
         let vars = F::get_variables(&inputs, &secrets, &u);
 
-        if cfg!(debug_assertions) && DEBUG_ASSERTIONS {
-            console_log!("{:?}", vars);
-        }
-
         let GP: Vec<G1Affine> = vars.get_challenge(transcript);
 
         let Gprime: Vec<G1Affine> = (0..(vars.len() - GP.len())).map(|_| transcript.challenge_point(b"Gtypes")).collect();
@@ -471,15 +512,12 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
         let cr: Vec<Scalar> = vars.get_cr();
 
         let rA = Scalar::random(&mut csrng);
-        let A = (F * rA + vartime_multiscalar_mul(&cl, &G0) + vartime_multiscalar_mul(&cr, &H)).to_affine();
+        let A = msm::multiscalar_mul(
+            &[&[rA], &cl[..], &cr[..]].concat(),
+            &[&[F], &G0[..], &H[..]].concat()
+        ).to_affine();
         transcript.append_g1(b"A commitment", &A);
 
-        if cfg!(debug_assertions) && DEBUG_ASSERTIONS {
-            let G_test = vars.get_G(&Scalar::random(&mut csrng), &GP, &Gprime);
-            let A_test = (F * rA + vartime_multiscalar_mul(&cl, &G_test) + vartime_multiscalar_mul(&cr, &H)).to_affine();
-            assert_eq!(A_test, A, "A should be independent of w");
-        }
-
         let w = transcript.challenge_scalar(b"w");
         let Gw = vars.get_G(&w, &GP, &Gprime);
 
@@ -490,7 +528,10 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
         }).collect();
 
         let rS = Scalar::random(&mut csrng);
-        let S = (F * rS + vartime_multiscalar_mul(&sl, &Gw) + vartime_multiscalar_mul(&sr, &H)).to_affine();
+        let S = msm::multiscalar_mul(
+            &[&[rS], &sl[..], &sr[..]].concat(),
+            &[&[F], &Gw[..], &H[..]].concat()
+        ).to_affine();
         transcript.append_g1(b"S commitment", &S);
 
         let y = transcript.challenge_scalar(b"y");
@@ -500,7 +541,7 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
         let vars = vars.blind();
 
         let constraints = F::get_constraints(&inputs, &y);
-        let (theta, inv_theta, mu, _nu, _omega, alpha, beta, delta) = vars.get_constraints(&constraints, &z);
+        let (theta, inv_theta, mu, _nu, _omega, alpha, _beta, _delta) = vars.get_constraints(&constraints, &z);
 
         let l_x = VecPoly1(add_vec(&cl,&alpha),sl.clone());
         let r_x = VecPoly1(add_vec(&mul_vec(&theta, &cr),&mu),mul_vec(&theta,&sr));
@@ -510,8 +551,8 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
         let tau_1 = Scalar::random(&mut csrng);
         let tau_2 = Scalar::random(&mut csrng);
 
-        let T1 = (G1Affine::generator() * t_x.1 + F * tau_1).to_affine();
-        let T2 = (G1Affine::generator() * t_x.2 + F * tau_2).to_affine();
+        let T1 = (GENERATOR().mul(&t_x.1) + F * tau_1).to_affine();
+        let T2 = (GENERATOR().mul(&t_x.2) + F * tau_2).to_affine();
 
         transcript.append_g1(b"T1 commitment", &T1);
         transcript.append_g1(b"T2 commitment", &T2);
@@ -528,25 +569,9 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
         transcript.append_scalar(b"r", &r);
         transcript.append_scalar(b"t", &t);
 
-        if cfg!(debug_assertions) && DEBUG_ASSERTIONS {
-            let lhs = (F * r + vartime_multiscalar_mul(&lvec, &Gw) + vartime_multiscalar_mul(&mul_vec(&inv_theta, &rvec), &H)).to_affine();
-            let rhs = (A + S * x + vartime_multiscalar_mul(&alpha, &Gw) + vartime_multiscalar_mul(&beta, &H)).to_affine();
-            console_log!("rvec: {:#?}", lvec);
-            console_log!("lvec: {:#?}", rvec);
-            console_log!("theta: {:#?}", theta);
-            console_log!("inv_theta: {:#?}", inv_theta);
-            console_log!("lhs: {:#?}", lhs);
-            console_log!("rhs: {:#?}", rhs);
-            console_log!("t_x: {:#?}", t_x.0);
-            console_log!("delta: {:#?}", delta);
-            assert_eq!(lhs, rhs, "verification eq should hold");
-            assert_eq!(t_x.0, delta, "offset should be delta");
-            assert_eq!(t, t_x.0 + x*t_x.1 + x*x*t_x.2, "polynomial holds");
-        }
-
         // Get a challenge value to combine statements for the IPP
         let ippw = transcript.challenge_scalar(b"ippw");
-        let Q = (G1Affine::generator() * ippw).to_affine();
+        let Q = GENERATOR().mul(&ippw).to_affine();
 
         // pad to next power of two
         let padlen = m.next_power_of_two() - m;
@@ -581,28 +606,64 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
 
         let add_data: A = F::additional_data(&inputs, &secrets, transcript);
 
-        Ok(GenericProof { A, S, T1, T2, tau, r, ipp_proof, t, vars, inputs, add_data })
+        Ok(GenericProof { A, S, T1, T2, tau, r, ipp_proof, t, inputs, add_data })
     }
 
-    pub fn verify<S: Default, F: Proof<P, S, A>>(&self, transcript: &mut Transcript) -> Result<(), ProofError> {
-        let m = self.vars.len();
+    /// Derives the blinded variables of the proof from its inputs like the
+    /// verifier, `transcript` is the one the proof was created with.
+    pub fn variables<S: Default, F: Proof<P, S, A>>(&self, transcript: &mut Transcript) -> Result<Variables, ProofError> {
+        self.inputs.validate()?;
+
+        // the number of variables does not depend on the challenge
+        let m = F::get_variables(&self.inputs, &S::default(), &Scalar::zero()).len();
         transcript.append_u64(b"m", m as u64);
 
         // commit public inputs
         self.inputs.commit(transcript);
 
         let u = transcript.challenge_scalar(b"u for exponents");
-        let F = transcript.challenge_point(b"F for vec-com");
+        Ok(F::get_variables(&self.inputs, &S::default(), &u).blind())
+    }
 
-        let vars = F::get_variables(&self.inputs, &S::default(), &u).blind();
-        if vars != self.vars {
-            return Err(ProofError::VerificationError)
+    pub fn verify<S: Default, F: Proof<P, S, A>>(&self, transcript: &mut Transcript) -> Result<(), ProofError> {
+        let (ipp, commitment) = self.equations::<S, F>(transcript)?;
+
+        if !ipp.holds() {
+            return Err(ProofError::VerificationError(Check::InnerProduct))
+        }
+
+        if !commitment.holds() {
+            return Err(ProofError::VerificationError(Check::Commitment))
         }
-        let vars = &self.vars;
+
+        F::additional_checks(&self.inputs, &self.add_data, transcript).map_err(ProofError::VerificationError)?;
+
+        Ok(())
+    }
+
+    /// Replays the proof like [`GenericProof::verify`], but instead of checking
+    /// the final group equations merges them into `batch` with random weights.
+    /// The additional checks of the statement are left to the caller.
+    pub(crate) fn verify_deferred<S: Default, F: Proof<P, S, A>>(&self, transcript: &mut Transcript, batch: &mut Equation, mut rng: impl RngCore + CryptoRng) -> Result<(), ProofError> {
+        let (ipp, commitment) = self.equations::<S, F>(transcript)?;
+
+        batch.merge(&Scalar::random(&mut rng), ipp);
+        batch.merge(&Scalar::random(&mut rng), commitment);
+
+        Ok(())
+    }
+
+    // replays the transcript and returns the equations of the inner product
+    // argument and of the polynomial commitment
+    fn equations<S: Default, F: Proof<P, S, A>>(&self, transcript: &mut Transcript) -> Result<(Equation, Equation), ProofError> {
+        let vars = self.variables::<S, F>(transcript)?;
+        let m = vars.len();
+
+        let F = transcript.challenge_point(b"F for vec-com");
 
         let GP: Vec<G1Affine> = vars.get_challenge(transcript);
 
-        let Gprime: Vec<G1Affine> = (0..(self.vars.len() - GP.len())).map(|_| transcript.challenge_point(b"Gtypes")).collect();
+        let Gprime: Vec<G1Affine> = (0..(vars.len() - GP.len())).map(|_| transcript.challenge_point(b"Gtypes")).collect();
         let G0 = vars.get_G(&Scalar::zero(), &GP, &Gprime);
         let H: Vec<G1Affine> = G0.iter().map(|_| transcript.challenge_point(b"blinding Ps")).collect();
 
@@ -629,21 +690,7 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
         let (_theta, inv_theta, _mu, _nu, _omega, alpha, beta, delta) = vars.get_constraints(&constraints, &z);
 
         let ippw = transcript.challenge_scalar(b"ippw");
-        let Q = (G1Affine::generator() * ippw).to_affine();
-
-        let ipPmQ = vartime_multiscalar_mul(
-            iter::once(&Scalar::one())
-                .chain(iter::once(&x))
-                .chain(iter::once(&-self.r))
-                .chain(iter::once(&self.t))
-                .chain(alpha.iter())
-                .chain(beta.iter()),
-            iter::once(&self.A)
-                .chain(iter::once(&self.S))
-                .chain(iter::once(&F))
-                .chain(iter::once(&Q))
-                .chain(Gw.iter())
-                .chain(H.iter()));
+        let Q = GENERATOR().mul(&ippw).to_affine();
 
         // pad to next power of two
         let padlen = m.next_power_of_two() - m;
@@ -660,21 +707,21 @@ impl<P: ProofInput + Clone, A: Serialize + Clone + Default> GenericProof<P, A> {
             H.push(transcript.challenge_point(b"padding H"));
         }
 
-        if self.ipp_proof.verify(Gw.len(), transcript, G_factors, H_factors, &ipPmQ, &Q, &Gw, &H).is_err() {
-            return Err(ProofError::VerificationError)
-        }
+        let mut ipp = self.ipp_proof.verification_equation(Gw.len(), transcript, G_factors, H_factors, &Q, &Gw, &H)?;
 
-        let lnd = G1Affine::generator() * self.t + F * self.tau;
-        let rnd = G1Affine::generator() * delta + self.T1 * x + self.T2 * x * x;
+        // the argument opens A + x * S - r * F + t * Q + <alpha, Gw> + <beta, H>
+        ipp.extend([(-Scalar::one(), self.A), (-x, self.S), (self.r, F), (-self.t, Q)]);
+        ipp.extend(alpha.iter().map(|a| -a).zip(Gw.iter().copied()));
+        ipp.extend(beta.iter().map(|b| -b).zip(H.iter().copied()));
 
-        if lnd.to_affine() != rnd.to_affine() {
-            return Err(ProofError::VerificationError)
-        }
+        let mut commitment = Equation::new();
+        commitment.extend([
+            (self.t - delta, G1Affine::generator()),
+            (self.tau, F),
+            (-x, self.T1),
+            (-(x * x), self.T2),
+        ]);
 
-        if !F::additional_checks(&self.inputs, &self.add_data, transcript) {
-            return Err(ProofError::VerificationError)
-        }
-
-        Ok(())
+        Ok((ipp, commitment))
     }
 }
@@ -1,3 +1,5 @@
+pub mod builder;
+pub mod debug;
 pub mod generic;
 pub mod participation;
 pub mod payout;
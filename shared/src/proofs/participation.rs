@@ -1,14 +1,17 @@
 #![allow(non_snake_case)]
 use ff::Field;
 use group::Curve;
+use rand::{CryptoRng, RngCore};
 
 use serde::{Serialize, Deserialize};
-use bls12_381::{G1Projective, G1Affine, Scalar};
+use schemars::JsonSchema;
+use bls12_381::{G1Affine, Scalar};
 use serde_with::serde_as;
 
 use crate::credential;
-use crate::credential::BINDING_G;
-use crate::credential::CREDENTIAL_I;
+use crate::error::{Check, PrepamsError};
+use crate::types::ProofError;
+use crate::credential::{BINDING_G_TABLE, CREDENTIAL_I_TABLE};
 use crate::external::util::as_scalar;
 use crate::external::util::as_u32;
 use crate::pbss::BlindedSignRequest;
@@ -16,10 +19,12 @@ use crate::types::AttributeConstraint;
 use crate::types::credential::*;
 use crate::types::prerequisites::*;
 use crate::external::transcript::TranscriptProtocol;
-use crate::proofs::generic::{Proof, Variables, Variable, Constraint, ConstraintType, ProofInput, Transcript};
-use crate::external::util::{exp_iter, sum_of_powers};
+use crate::proofs::generic::{GenericProof, Proof, Variables, Constraint, ConstraintType, ProofInput, Transcript};
+use crate::proofs::builder::{Binary, ConstraintSystem, Inverse, Var};
 use crate::types::Resource;
-use crate::proofs::payout::{PAYOUT_G1, PAYOUT_V0, PAYOUT_V1};
+use crate::proofs::payout::{PAYOUT_G1_TABLE, PAYOUT_V0_TABLE, PAYOUT_V1_TABLE};
+use crate::msm::{Equation, GENERATOR};
+use crate::parallel;
 use crate::pbss;
 
 pub struct ParticipationProof {}
@@ -46,16 +51,20 @@ impl Default for ParticipationProofSecrets {
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct ParticipationProofInput {
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub tag: G1Affine,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub study: Scalar,
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub attributes: Vec<G1Affine>,
 
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub reward: Scalar,
     pub reward_request: BlindedSignRequest,
 
@@ -68,6 +77,7 @@ pub struct ParticipationProofInput {
     pub constraints: Vec<AttributeConstraint>,
 
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub commitment: G1Affine,
 }
 
@@ -105,13 +115,45 @@ impl ProofInput for ParticipationProofInput {
             }
         }
     }
+
+    fn validate(&self) -> Result<(), ProofError> {
+        // study ids are inverted when deriving tags
+        if self.qualifiers.iter().chain(self.disqualifiers.iter()).any(|q| q.id == Scalar::zero()) {
+            return Err(ProofError::InvalidError);
+        }
+
+        for constraint in &self.constraints {
+            let (AttributeConstraint::Element(i, _) | AttributeConstraint::Range(i, _, _)) = constraint;
+            if *i as usize >= self.attributes.len() {
+                return Err(ProofError::InvalidError);
+            }
+
+            // range proofs decompose the difference into at most 32 bits
+            if let AttributeConstraint::Range(_, from, to) = constraint {
+                if from > to || to - from > 1 << 31 {
+                    return Err(ProofError::InvalidError);
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl ParticipationProofInput {
-    pub fn new(ipk: &IssuerPublicKey, cvk: &pbss::PublicKey, credential: &Credential, resource: &Resource) -> (ParticipationProofInput, ParticipationProofSecrets) {
-        let (auth_request, (randomness, commitment)) = credential::authenticate(&credential, &resource.id);
-        let tag = credential.derive_tag(&resource.id).unwrap();
-        let rD = Scalar::random(&mut rand::thread_rng());
+    pub fn new(ipk: &IssuerPublicKey, cvk: &pbss::PublicKey, credential: &Credential, resource: &Resource) -> Result<(ParticipationProofInput, ParticipationProofSecrets), PrepamsError> {
+        ParticipationProofInput::new_with_rng(ipk, cvk, credential, resource, rand::thread_rng())
+    }
+
+    pub fn new_with_rng(ipk: &IssuerPublicKey, cvk: &pbss::PublicKey, credential: &Credential, resource: &Resource, mut rng: impl RngCore + CryptoRng) -> Result<(ParticipationProofInput, ParticipationProofSecrets), PrepamsError> {
+        if !credential.is_signed() {
+            Err(PrepamsError::state("credential not signed"))?;
+        }
+
+        // fails for the one study id the credential cannot authenticate for
+        let tag = credential.derive_tag(&resource.id)?;
+        let (auth_request, (randomness, commitment)) = credential::authenticate(&mut rng, credential, &resource.id);
+        let rD = Scalar::random(&mut rng);
 
         let ipk = ipk.clone();
         let study = resource.id.clone();
@@ -121,22 +163,19 @@ impl ParticipationProofInput {
         let disqualifiers = resource.disqualifier.to_vec();
         let constraints = resource.constraints.to_vec();
 
-        let randomized_disqualifiers: Vec<RandomizedDisqualifier> = disqualifiers
-            .iter()
-            .map(|d| RandomizedDisqualifier {
-                id: d.id,
-                randomized_tag: (&credential.derive_tag(&d.id).unwrap() * &rD).to_affine(),
-                tags: d.tags.iter().map(|t| (t * rD).to_affine()).collect()
-            })
-            .collect();
+        let randomized_disqualifiers = parallel::map(&disqualifiers, |d| Ok(RandomizedDisqualifier {
+            id: d.id,
+            randomized_tag: (credential.derive_tag(&d.id)? * rD).to_affine(),
+            tags: parallel::map(&d.tags, |t| (t * rD).to_affine())
+        })).into_iter().collect::<Result<Vec<RandomizedDisqualifier>, ProofError>>()?;
 
         let mut prng = credential.derive_reward_rng(&study);
         let s = <bls12_381::Scalar as ff::Field>::random(&mut prng);
         let d = <bls12_381::Scalar as ff::Field>::random(&mut prng);
         let reward = as_scalar(resource.reward.into());
-        let reward_request = pbss::Blind(cvk, &vec![reward], &vec![s, credential.identity], &d, &mut prng);
+        let reward_request = pbss::Blind(cvk, &vec![reward], &vec![s, credential.identity], &d, &mut prng)?;
 
-        (
+        Ok((
             ParticipationProofInput {
                 tag,
                 study,
@@ -158,365 +197,213 @@ impl ParticipationProofInput {
                 reward_s: s.clone(),
                 reward_d: d.clone()
             }
-        )
+        ))
     }
 }
 
-impl Proof<ParticipationProofInput, ParticipationProofSecrets, ()> for ParticipationProof {
-    fn get_variables(inputs: &ParticipationProofInput, secrets: &ParticipationProofSecrets, u: &Scalar) -> Variables {
-        let mut vars = Variables::new();
-
-        let mut sep = Scalar::one();
-
-        vars.add(Variable::Inner {
-            id: "g".to_string(),
-            G: (G1Affine::generator() * &sep).to_affine(),
-            cl: -Scalar::one(),
-            cr: Scalar::zero()
-        });
-        vars.add(Variable::Inner {
-            id: "tag".to_string(),
-            G: (inputs.tag * &sep).to_affine(),
-            cl: secrets.credential.sk + inputs.study,
-            cr: Scalar::zero()
-        });
-        vars.add(Variable::Scratch {
-            id: "sk".to_string(),
-            cl: secrets.credential.sk.clone(),
-            cr: Scalar::zero()
-        });
-
-        sep = &sep * u;
-
-        vars.add(Variable::Inner {
-            id: format!("vc"),
-            G: (inputs.commitment * &sep).to_affine(),
-            cl: -Scalar::one(),
-            cr: Scalar::zero()
-        });
-        vars.add(Variable::Inner {
-            id: "vr".to_string(),
-            G: (BINDING_G() * &sep).to_affine(),
-            cl: secrets.randomness.clone(),
-            cr: Scalar::zero()
-        });
-
-        vars.add(Variable::Inner {
-            id: "auth_identity".to_string(),
-            G: (CREDENTIAL_I() * &sep).to_affine(),
-            cl: secrets.credential.identity,
-            cr: Scalar::zero()
-        });
-
-        for (i, attr_u) in inputs.attributes.iter().enumerate() {
-            vars.add(Variable::Inner {
-                id: format!("attr_{}", i),
-                G: (attr_u * &sep).to_affine(),
-                cl: secrets.credential.values.get(i).unwrap_or(&Scalar::default()).clone(),
-                cr: Scalar::zero()
-            });
-        }
-
-        sep = &sep * u;
-
-        vars.add(Variable::Inner {
-            id: "reward_s".to_string(),
-            G: (PAYOUT_V0() * &sep).to_affine(),
-            cl: secrets.reward_s,
-            cr: Scalar::zero()
-        });
-
-        vars.add(Variable::Inner {
-            id: "reward_identity".to_string(),
-            G: (PAYOUT_V1() * &sep).to_affine(),
-            cl: secrets.credential.identity.clone(),
-            cr: Scalar::zero()
-        });
-
-        vars.add(Variable::Inner {
-            id: "reward_d".to_string(),
-            G: (PAYOUT_G1() * &sep).to_affine(),
-            cl: secrets.reward_d,
-            cr: Scalar::zero()
-        });
-
-        vars.add(Variable::Inner {
-            id: "reward_alpha".to_string(),
-            G: (inputs.reward_request.alpha.clone() * &sep).to_affine(),
-            cl: -Scalar::one(),
-            cr: Scalar::zero()
-        });
-
-        sep = &sep * u;
-
-        for (i, qualifier) in inputs.qualifiers.iter().enumerate() {
-            let q = secrets.credential.derive_tag(&qualifier.id).unwrap();
-
-            vars.add(Variable::Inner {
-                id: format!("qg_{}", i),
-                G: (G1Affine::generator() * &sep).to_affine(),
-                cl: -(secrets.credential.sk + qualifier.id).invert().unwrap(),
-                cr: Scalar::zero()
-            });
-            vars.add(Variable::Scratch {
-                id: format!("qsk_{}", i),
-                cl: secrets.credential.sk + qualifier.id,
-                cr: (secrets.credential.sk + qualifier.id).invert().unwrap()
-            });
-
-            for (j, tag) in qualifier.tags.iter().enumerate() {
-                vars.add(Variable::Inner {
-                    id: format!("qtag_{}_{}", i, j),
-                    G: (tag * &sep).to_affine(),
-                    cl: match tag == &q {
-                        true => Scalar::one(),
-                        false => Scalar::zero()
-                    },
-                    cr: match tag == &q {
-                        true => Scalar::zero(),
-                        false => -Scalar::one()
-                    }
-                });
-            }
-
-            sep = &sep * u;
-        }
-
-        for (i, (disqualifier, randomized)) in inputs.disqualifiers.iter().zip(&inputs.randomized_disqualifiers).enumerate() {
-            let mut sep_d = sep.clone();
-            let mut sep_r = sep.clone();
-            vars.add(Variable::Inner {
-                id: format!("dg_{}", i),
-                G: (G1Affine::generator() * &sep).to_affine(),
-                cl: -secrets.disqualifier_random,
-                cr: Scalar::zero()
-            });
-            vars.add(Variable::Inner {
-                id: format!("dtag_{}", i),
-                G: (randomized.randomized_tag * &sep).to_affine(),
-                cl: secrets.credential.sk + disqualifier.id,
-                cr: Scalar::zero()
-            });
-            vars.add(Variable::Inner {
-                id: format!("dtags_{}", i),
-                G: disqualifier.tags.iter().map(|tag| {
-                    sep_d = &sep_d * u;
-                    tag * &sep_d
-                }).sum::<G1Projective>().to_affine(),
-                cl: secrets.disqualifier_random.clone(),
-                cr: Scalar::zero()
-            });
-            vars.add(Variable::Inner {
-                id: format!("drand_{}", i),
-                G: randomized.tags.iter().map(|rtag| {
-                    sep_r = &sep_r * u;
-                    rtag * &sep_r
-                }).sum::<G1Projective>().to_affine(),
-                cl: -Scalar::one(),
-                cr: Scalar::zero()
-            });
-            sep = &sep_d * u;
-        }
+/// Number of bits the differences of a range constraint are decomposed into.
+fn range_bits(from: u32, to: u32) -> u32 {
+    (to - from).next_power_of_two().trailing_zeros() + 1
+}
 
-        for (cid, constraint) in inputs.constraints.iter().enumerate() {
-            match constraint {
-                AttributeConstraint::Element(i, options) => {
-                    vars.add(Variable::Inner {
-                        id: format!("cstr_{}", cid),
-                        G: (G1Affine::generator() * &sep).to_affine(),
-                        cl: -secrets.credential.values.get(*i as usize).unwrap_or(&Scalar::zero()),
-                        cr: Scalar::zero()
-                    });
-                    for (j, option) in options.iter().enumerate() {
-                        let o = as_scalar(*option);
-                        vars.add(Variable::Inner {
-                            id: format!("cstr_{}_{}", cid, j),
-                            G: (G1Affine::generator() * &o * &sep).to_affine(),
-                            cl: match &o == secrets.credential.values.get(*i as usize).unwrap_or(&Scalar::zero()) {
-                                true => Scalar::one(),
-                                false => Scalar::zero()
-                            },
-                            cr: match &o == secrets.credential.values.get(*i as usize).unwrap_or(&Scalar::zero()) {
-                                true => Scalar::zero(),
-                                false => -Scalar::one()
-                            }
-                        });
-                    }
-                },
-                AttributeConstraint::Range(i, from, to) => {
-                    let value = as_u32(&secrets.credential.values.get(*i as usize).unwrap_or(&as_scalar(*from)));
-
-                    assert!(value >= *from && value <= *to);
-
-                    let diff = value - from;
-                    let diff2 = to - value;
-
-                    let bits = (to - from).next_power_of_two().trailing_zeros() + 1;
-
-                    for j in 0..bits {
-                        let bit = Scalar::from(((diff & u32::pow(2, j)) >> j) as u64);
-                        vars.add(Variable::Scratch {
-                            id: format!("cstr_{}_{}_1", cid, j),
-                            cl: bit,
-                            cr: bit - Scalar::one()
-                        });
-                        let bit2 = Scalar::from(((diff2 & u32::pow(2, j)) >> j) as u64);
-                        vars.add(Variable::Scratch {
-                            id: format!("cstr_{}_{}_2", cid, j),
-                            cl: bit2,
-                            cr: bit2 - Scalar::one()
-                        });
-                    }
-                },
-            }
+fn statement(cs: &mut ConstraintSystem, inputs: &ParticipationProofInput, secrets: &ParticipationProofSecrets) {
+    let credential = &secrets.credential;
+    let value = |i: u32| credential.values.get(i as usize).copied().unwrap_or(Scalar::zero());
+
+    let g = cs.inner("g", GENERATOR(), -Scalar::one());
+    let tag = cs.inner("tag", inputs.tag, credential.sk + inputs.study);
+    let sk = cs.scratch("sk", credential.sk, Scalar::zero());
+    cs.relation();
+
+    let vc = cs.inner("vc", inputs.commitment, -Scalar::one());
+    cs.inner("vr", BINDING_G_TABLE(), secrets.randomness);
+    let auth_identity = cs.inner("auth_identity", CREDENTIAL_I_TABLE(), credential.identity);
+    let attributes: Vec<Var> = inputs.attributes.iter().enumerate().map(|(i, attr)| {
+        cs.inner(format!("attr_{}", i), attr, value(i as u32))
+    }).collect();
+    cs.relation();
+
+    cs.inner("reward_s", PAYOUT_V0_TABLE(), secrets.reward_s);
+    let reward_identity = cs.inner("reward_identity", PAYOUT_V1_TABLE(), credential.identity);
+    cs.inner("reward_d", PAYOUT_G1_TABLE(), secrets.reward_d);
+    let reward_alpha = cs.inner("reward_alpha", inputs.reward_request.alpha, -Scalar::one());
+    cs.relation();
+
+    // show that tag - sk = study
+    let mut v1 = cs.batch("tag", ConstraintType::Dir);
+    v1.equal(&tag, &sk, inputs.study);
+
+    let mut vr = cs.batch("identity", ConstraintType::Dir);
+    vr.equal(&reward_identity, &auth_identity, Scalar::zero());
+
+    // show that there is a -1 below g
+    let mut v2 = cs.batch("negated", ConstraintType::Dir);
+    v2.constant(&g, -Scalar::one()).constant(&vc, -Scalar::one()).constant(&reward_alpha, -Scalar::one());
+
+    // show that all cl/cr values for qualifier/disqualifiers are binary
+    let mut binary = Binary::new(cs);
+
+    // show that one tag is selected per qualifier
+    let mut v3 = cs.batch("qualifier.selection", ConstraintType::Dir);
+    // show that qsk matches sk for all qualifiers
+    let mut vq = cs.batch("qualifier.sk", ConstraintType::Dir);
+    // show that qsk's cr is inverse of qsk's cl
+    let mut inverse = Inverse::new(cs);
+
+    for (i, qualifier) in inputs.qualifiers.iter().enumerate() {
+        let q = credential.derive_tag(&qualifier.id).ok();
+        let qsk = credential.sk + qualifier.id;
+
+        let qg = cs.inner(format!("qg_{}", i), GENERATOR(), -qsk.invert().unwrap_or(Scalar::zero()));
+        let qsk = cs.inverse(&mut inverse, format!("qsk_{}", i), qsk, &qg);
+        cs.one_of_n(&mut binary, &mut v3, |j| format!("qtag_{}_{}", i, j), qualifier.tags.iter().map(|tag| (tag, Some(*tag) == q)));
+        vq.equal(&qsk, &sk, qualifier.id);
+        cs.relation();
+    }
 
-            sep = &sep * u;
+    // ensure that the study id of randomized disqualifier is correct
+    let mut v5 = cs.batch("disqualifier.sk", ConstraintType::Dir);
+    // show that the randomness in dg and dtags is the same
+    let mut v5a = cs.batch("disqualifier.random", ConstraintType::Dir);
+    // show that there is a -1 in drandomized
+    let mut v5b = cs.batch("disqualifier.negated", ConstraintType::Dir);
+
+    for (i, (disqualifier, randomized)) in inputs.disqualifiers.iter().zip(&inputs.randomized_disqualifiers).enumerate() {
+        let dg = cs.inner(format!("dg_{}", i), GENERATOR(), -secrets.disqualifier_random);
+        let dtag = cs.inner(format!("dtag_{}", i), randomized.randomized_tag, credential.sk + disqualifier.id);
+        let dtags = cs.span(format!("dtags_{}", i), &disqualifier.tags, secrets.disqualifier_random);
+        let drand = cs.span(format!("drand_{}", i), &randomized.tags, -Scalar::one());
+
+        v5.equal(&dtag, &sk, disqualifier.id);
+        v5a.linear([(&dg, Scalar::one()), (&dtags, Scalar::one())], Scalar::zero());
+        v5b.constant(&drand, -Scalar::one());
+
+        for _ in 0..=disqualifier.tags.len() {
+            cs.relation();
         }
-
-        vars
     }
 
-    fn get_constraints(inputs: &ParticipationProofInput, y: &Scalar) -> Vec<Constraint> {
-        // show that tag - sk = study
-        let mut v1 = Constraint::new(ConstraintType::Dir, inputs.study.clone());
-        v1.left_set("tag", Scalar::one());
-        v1.left_set("sk", -Scalar::one());
-
-        let mut vr = Constraint::new(ConstraintType::Dir, Scalar::zero());
-        vr.left_set("auth_identity", -Scalar::one());
-        vr.left_set("reward_identity", Scalar::one());
-
-        // show that there is a -1 below g
-        let mut v2 = Constraint::new(ConstraintType::Dir, -sum_of_powers(y, 3));
-        v2.left_set("g", Scalar::one());
-        v2.left_set("vc", y.clone());
-        v2.left_set("reward_alpha", y * y);
-
-        // show that one tag is selected per qualifier separated by y
-        let mut v3 = Constraint::new(ConstraintType::Dir, sum_of_powers(y, inputs.qualifiers.len()));
-
-        // show that all cl/cr values for qualifier/disqualifiers are binary
-        let mut v_one = Constraint::new(ConstraintType::One, Scalar::zero());
-        let mut v_mul = Constraint::new(ConstraintType::Mul, Scalar::zero());
-
-        // show that qsk matches sk for all qualifiers
-        let mut vq = Constraint::new(ConstraintType::Dir, inputs.qualifiers.iter().zip(exp_iter(y.clone())).map(|(a, b)| a.id * b).sum());
-        vq.left_set("sk", -sum_of_powers(y, inputs.qualifiers.len()));
-
-        // show that qsk's cr is inverse of qsk's cl
-        let mut vqa = Constraint::new(ConstraintType::Mul, sum_of_powers(y, inputs.qualifiers.len()));
-        let mut vqb = Constraint::new(ConstraintType::Sum, Scalar::zero());
-
-        let mut sep = Scalar::one();
-        let mut sep_binary = Scalar::one();
-
-        for (i, qualifier) in inputs.qualifiers.iter().enumerate() {
-            vq.left_set(&format!("qsk_{}", i), sep.clone());
-            vqa.right_set(&format!("qsk_{}", i), sep.clone());
-            vqb.left_set(&format!("qg_{}", i), sep.clone());
-            vqb.right_set(&format!("qsk_{}", i), sep.clone());
-
-            for (j, _) in qualifier.tags.iter().enumerate() {
-                v3.left_set(&format!("qtag_{}_{}", i, j), sep.clone());
-                v_one.left_set(&format!("qtag_{}_{}", i, j), sep_binary.clone());
-                v_mul.right_set(&format!("qtag_{}_{}", i, j), sep_binary.clone());
-                sep_binary = &sep_binary * y;
-            }
-            sep = &sep * y;
-        }
+    for batch in [v1, vr, v2, v3, vq, inverse.mul, inverse.sum, v5, v5a, v5b] {
+        cs.push(batch);
+    }
 
-        // ensure that the study id of randomized disqualifier is correct
-        let mut v5 = Constraint::new(ConstraintType::Dir, inputs.disqualifiers.iter().zip(exp_iter(y.clone())).map(|(a, b)| a.id * b).sum());
-        v5.left_set("sk", -sum_of_powers(y, inputs.disqualifiers.len()));
+    // proove attribute constraints
+    for (cid, constraint) in inputs.constraints.iter().enumerate() {
+        match constraint {
+            AttributeConstraint::Element(i, options) => {
+                let value = value(*i);
+                let cstr = cs.inner(format!("cstr_{}", cid), GENERATOR(), -value);
+
+                // show that one option is selected
+                let mut v3 = cs.batch(&format!("constraint_{}.selection", cid), ConstraintType::Dir).skip(inputs.disqualifiers.len() + cid);
+                cs.one_of_n(&mut binary, &mut v3, |j| format!("cstr_{}_{}", cid, j), options.iter().map(|option| {
+                    let o = as_scalar(*option);
+                    ((o, GENERATOR()), o == value)
+                }));
+
+                // show that attribute is the same
+                let mut va = cs.batch(&format!("constraint_{}.attribute", cid), ConstraintType::Dir);
+                if let Some(attr) = attributes.get(*i as usize) {
+                    va.linear([(&cstr, Scalar::one()), (attr, Scalar::one())], Scalar::zero());
+                }
 
-        // show that the randomness in dg and dtags is the same
-        let mut v5a = Constraint::new(ConstraintType::Dir, Scalar::zero());
+                cs.push(v3);
+                cs.push(va);
+            },
+            AttributeConstraint::Range(i, from, to) => {
+                let value = credential.values.get(*i as usize).map_or(*from, as_u32);
 
-        // show that there is a -1 in drandomized
-        let mut v5b = Constraint::new(ConstraintType::Dir, -sum_of_powers(y, inputs.disqualifiers.len()));
+                // a value out of range does not decompose and fails the constraints
+                let bits = range_bits(*from, *to);
+                let diff = cs.bits(|j| format!("cstr_{}_{}_1", cid, j), value.wrapping_sub(*from) as u64, bits);
+                let diff2 = cs.bits(|j| format!("cstr_{}_{}_2", cid, j), to.wrapping_sub(value) as u64, bits);
 
-        let mut sep = Scalar::one();
-        for (i, _) in inputs.disqualifiers.iter().enumerate() {
-            v5.left_set(&format!("dtag_{}", i), sep.clone());
+                for (bit, bit2) in diff.iter().zip(&diff2) {
+                    binary.add(&[bit, bit2]);
+                }
 
-            v5a.left_set(&format!("dg_{}", i), sep.clone());
-            v5a.left_set(&format!("dtags_{}", i), sep.clone());
+                // show that attr - diff = from and attr + diff2 = to
+                let mut vd = cs.batch(&format!("constraint_{}.from", cid), ConstraintType::Dir);
+                let mut vk = cs.batch(&format!("constraint_{}.to", cid), ConstraintType::Dir);
+                if let Some(attr) = attributes.get(*i as usize) {
+                    vd.bits(&diff, -Scalar::one()).constant(attr, as_scalar(*from));
+                    vk.bits(&diff2, Scalar::one()).constant(attr, as_scalar(*to));
+                }
 
-            v5b.left_set(&format!("drand_{}", i), sep.clone());
-            sep = &sep * y;
+                cs.push(vd);
+                cs.push(vk);
+            },
         }
 
-        let mut constraints = vec![v1, vr, v2, v3, vq, vqa, vqb, v5, v5a, v5b];
-        
-        // proove attribute constraints
-        for (cid, constraint) in inputs.constraints.iter().enumerate() {
-            match constraint {
-                AttributeConstraint::Element(i, options) => {
-                    // show that one tag is selected per attribute separated by y
-                    let mut v3 = Constraint::new(ConstraintType::Dir, sep.clone());
-
-                    // mul/one = 0
-
-                    for (j, _) in options.iter().enumerate() {
-                        v3.left_set(&format!("cstr_{}_{}", cid, j), sep.clone());
-
-                        v_mul.right_set(&format!("cstr_{}_{}", cid, j), sep_binary.clone());
-                        v_one.left_set(&format!("cstr_{}_{}", cid, j), sep_binary.clone());
-
-                        sep_binary = &sep_binary * y;
-                    }
-
-                    // show that attribute is the same
-                    let mut va = Constraint::new(ConstraintType::Dir, Scalar::zero());
-                    va.left_set(&format!("cstr_{}", cid), Scalar::one());
-                    va.left_set(&format!("attr_{}", i), Scalar::one());
+        cs.relation();
+    }
 
-                    constraints.push(v3);
-                    constraints.push(va);
-                },
-                AttributeConstraint::Range(i, from, to) => {
-                    let mut vd = Constraint::new(ConstraintType::Dir, as_scalar(*from));
-                    vd.left_set(&format!("attr_{}", i), Scalar::one());
+    cs.push(binary.one);
+    cs.push(binary.mul);
+}
 
-                    let mut vk = Constraint::new(ConstraintType::Dir, as_scalar(*to));
-                    vk.left_set(&format!("attr_{}", i), Scalar::one());
+impl Proof<ParticipationProofInput, ParticipationProofSecrets, ()> for ParticipationProof {
+    fn get_variables(inputs: &ParticipationProofInput, secrets: &ParticipationProofSecrets, u: &Scalar) -> Variables {
+        let mut cs = ConstraintSystem::variables(u);
+        statement(&mut cs, inputs, secrets);
+        cs.into_variables()
+    }
 
-                    let bits = 32 - (to - from).leading_zeros() + 1;
-                    for j in 0..bits {
-                        v_mul.right_set(&format!("cstr_{}_{}_1", cid, j), sep_binary.clone());
-                        v_one.left_set(&format!("cstr_{}_{}_1", cid, j), sep_binary.clone());
+    fn get_constraints(inputs: &ParticipationProofInput, y: &Scalar) -> Vec<Constraint> {
+        let mut cs = ConstraintSystem::constraints(y);
+        statement(&mut cs, inputs, &ParticipationProofSecrets::default());
+        cs.into_constraints()
+    }
 
-                        vd.left_set(&format!("cstr_{}_{}_1", cid, j), -Scalar::from(u64::pow(2, j)));
+    fn get_named_constraints(inputs: &ParticipationProofInput) -> Vec<(String, Constraint)> {
+        let mut cs = ConstraintSystem::rows();
+        statement(&mut cs, inputs, &ParticipationProofSecrets::default());
+        cs.into_rows()
+    }
 
-                        v_mul.right_set(&format!("cstr_{}_{}_2", cid, j), sep_binary.clone());
-                        v_one.left_set(&format!("cstr_{}_{}_2", cid, j), sep_binary.clone());
+    fn additional_checks(inputs: &ParticipationProofInput, _: &(), _: &mut Transcript) -> Result<(), Check> {
+        check_tags(inputs)?;
+        if !crate::credential::verify(&inputs.ipk, &inputs.auth_request) {
+            return Err(Check::Credential);
+        }
 
-                        vk.left_set(&format!("cstr_{}_{}_2", cid, j), Scalar::from(u64::pow(2, j)));
+        Ok(())
+    }
+}
 
-                        sep_binary = &sep_binary * y;
-                    }
+fn check_tags(inputs: &ParticipationProofInput) -> Result<(), Check> {
+    if !inputs.randomized_disqualifiers.iter().all(|disqualifier| !disqualifier.tags.contains(&disqualifier.randomized_tag)) {
+        return Err(Check::Disqualifier);
+    }
+    if inputs.auth_request.token != inputs.tag {
+        return Err(Check::Tag);
+    }
 
-                    constraints.push(vd);
-                    constraints.push(vk);
-                },
-            }
+    Ok(())
+}
 
-            sep = &sep * y;
+/// Verifies many participation proofs at once.
+///
+/// The final group equations of all proofs are merged with random weights
+/// into one multi-scalar multiplication, the authentication requests are
+/// checked together with [`credential::verify_batch`]. Returns `false` if any
+/// proof is invalid, without telling which.
+pub(crate) fn verify_batch<'a>(proofs: impl IntoIterator<Item = &'a GenericProof<ParticipationProofInput, ()>>, mut rng: impl RngCore + CryptoRng) -> bool {
+    let mut batch = Equation::new();
+    let mut requests = vec![];
+
+    for proof in proofs {
+        let mut transcript = Transcript::new(b"participation");
+        if proof.verify_deferred::<ParticipationProofSecrets, ParticipationProof>(&mut transcript, &mut batch, &mut rng).is_err() {
+            return false;
         }
-
-        constraints.push(v_one);
-        constraints.push(v_mul);
-        return constraints;
+        if check_tags(&proof.inputs).is_err() {
+            return false;
+        }
+        requests.push((&proof.inputs.ipk, &proof.inputs.auth_request));
     }
 
-    fn additional_checks(inputs: &ParticipationProofInput, _: &(), _: &mut Transcript) -> bool {
-        let b1 = inputs.randomized_disqualifiers.iter()
-            .all(|disqualifier| !disqualifier.tags.contains(&disqualifier.randomized_tag));
-        let b2 = crate::credential::verify(&inputs.ipk, &inputs.auth_request);
-        let b3 = inputs.auth_request.token == inputs.tag;
-
-        b1 && b2 && b3
-    }
+    batch.holds() && credential::verify_batch(&requests)
 }
 
 #[cfg(test)]
@@ -539,8 +426,8 @@ mod tests {
         let (_csk, cvk) = pbss::Gen(&mut rng, 2, 1, "payment");
 
         let attrs = vec![Scalar::from(2 as u64), Scalar::from(1985 as u64), rand_scalar()];
-        let (request, mut credential) = issue_request(&mut rng, &ipk, &identity, attrs);
-        let response = issue(&ipk, &isk, &request).unwrap();
+        let (request, mut credential) = issue_request(&mut rng, &ipk, identity, attrs).unwrap();
+        let response = issue(&mut rng, &ipk, &isk, &request).unwrap();
         get_credential(&ipk, &response, &mut credential).unwrap();
 
         let qid = rand_scalar();
@@ -606,7 +493,7 @@ mod tests {
         };
 
         // generate test data
-        let (inputs, secrets) = ParticipationProofInput::new(&ipk, &cvk, &credential, &resource);
+        let (inputs, secrets) = ParticipationProofInput::new(&ipk, &cvk, &credential, &resource).unwrap();
 
         // constraints
         let mut prover_transcript = Transcript::new(b"test example");
@@ -1,21 +1,28 @@
 #![allow(non_snake_case)]
-use group::Curve;
-
+use rand::{CryptoRng, RngCore};
 use rand::seq::SliceRandom;
 
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use serde_with::serde_as;
-use bls12_381::{G1Affine, Scalar};
+use bls12_381::{G1Affine, Gt, Scalar};
 use sha2::{Digest, Sha512};
 
+use crate::error::Check;
 use crate::types::credential::IssuerPublicKey;
-use crate::pbss::{UnblindedSignature, PublicKey, Rerandomize, RerandomizedProof, RerandomizedWitness, RerandomizedProofResponse};
+use crate::pbss::{UnblindedSignature, PublicKey, CommitRerandomized, Rerandomize, RerandomizedProof, RerandomizedWitness, RerandomizedProofResponse};
 use crate::external::transcript::TranscriptProtocol;
-use crate::proofs::generic::{Proof, Variables, Variable, Constraint, ConstraintType, ProofInput, Transcript};
-use crate::external::util::{as_scalar, as_u32, sum_of_powers, exp_iter};
+use crate::types::ProofError;
+use crate::proofs::generic::{GenericProof, Proof, Variables, Constraint, ConstraintType, ProofInput, Transcript};
+use crate::proofs::builder::{Binary, ConstraintSystem, Var};
+use crate::external::util::{as_scalar, as_u32};
+use crate::msm::fixed_base;
 
 pub const MAX_INPUTS: usize = 10;
 
+/// Payout proof as sent from the participant to the issuer.
+pub type PayoutProofData = GenericProof<PayoutProofInput, RerandomizedProofResponse>;
+
 pub fn BINDING_G() -> G1Affine {
     G1Affine::from_compressed(&[182, 75, 166, 124, 162, 220, 249, 19, 0, 228, 164, 54, 26, 219, 4, 21, 221, 179, 19, 116, 142, 11, 175, 115, 205, 12, 241, 225, 22, 216, 143, 92, 70, 173, 178, 79, 50, 132, 88, 209, 56, 91, 91, 13, 43, 174, 117, 131]).unwrap()
 }
@@ -32,6 +39,10 @@ pub fn PAYOUT_V1() -> G1Affine {
     G1Affine::from_compressed(&[132, 148, 112, 4, 19, 245, 186, 172, 88, 36, 203, 142, 222, 14, 239, 104, 118, 187, 114, 132, 92, 177, 149, 205, 44, 211, 51, 163, 26, 155, 14, 62, 134, 25, 231, 249, 114, 49, 47, 47, 203, 170, 208, 203, 22, 0, 234, 31]).unwrap()
 }
 
+fixed_base!(PAYOUT_G1_TABLE, PAYOUT_G1());
+fixed_base!(PAYOUT_V0_TABLE, PAYOUT_V0());
+fixed_base!(PAYOUT_V1_TABLE, PAYOUT_V1());
+
 pub struct PayoutProof {}
 
 #[derive(Default)]
@@ -40,7 +51,7 @@ pub struct PayoutProofSecrets {
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct PayoutProofInput {
     pub value: u8,
     pub target: String,
@@ -48,7 +59,12 @@ pub struct PayoutProofInput {
     pub ivk: IssuerPublicKey,
     pub cvk: PublicKey,
     pub inputs: Vec<RerandomizedProof>,
+    /// aggregated pairing commitment of the proofs of possession of `inputs`
+    #[serde(with = "crate::serialization::Gt")]
+    #[schemars(with = "crate::serialization::schema::Gt")]
+    pub e1: Gt,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub nullifier: Vec<Scalar>,
 }
 
@@ -60,15 +76,33 @@ impl ProofInput for PayoutProofInput {
         for input in &self.inputs {
             input.commit(transcript);
         }
+        transcript.append_gt(b"e1", &self.e1);
         for nullifier in &self.nullifier {
             transcript.append_scalar(b"n", &nullifier);
         }
     }
+
+    /// Checks that the inputs are padded to `MAX_INPUTS` coins with a nullifier each.
+    fn validate(&self) -> Result<(), ProofError> {
+        if self.inputs.len() != MAX_INPUTS || self.nullifier.len() != MAX_INPUTS {
+            return Err(ProofError::InvalidError);
+        }
+
+        if self.cvk.U.is_empty() || self.cvk.V.len() < 2 {
+            return Err(ProofError::InvalidError);
+        }
+
+        Ok(())
+    }
 }
 
 impl PayoutProofInput {
     pub fn new(ivk: &IssuerPublicKey, cvk: &PublicKey, value: u8, target: &str, recipient: &str, spend: Vec<UnblindedSignature>, nulls: Vec<UnblindedSignature>) -> (PayoutProofInput, PayoutProofSecrets) {
-        let mut rng = rand::thread_rng();
+        PayoutProofInput::new_with_rng(ivk, cvk, value, target, recipient, spend, nulls, rand::thread_rng())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_rng(ivk: &IssuerPublicKey, cvk: &PublicKey, value: u8, target: &str, recipient: &str, spend: Vec<UnblindedSignature>, nulls: Vec<UnblindedSignature>, mut rng: impl RngCore + CryptoRng) -> (PayoutProofInput, PayoutProofSecrets) {
         let mut sample = spend;
         let mut nulls = nulls;
 
@@ -89,6 +123,7 @@ impl PayoutProofInput {
             .map(|e| Rerandomize(e, &cvk, &mut rng))
             .unzip();
 
+        let e1 = CommitRerandomized(cvk, &inputs, &witnesses);
         let nullifier = witnesses.iter().map(|e| e.s.s[0].clone()).collect();
 
         (
@@ -99,6 +134,7 @@ impl PayoutProofInput {
                 ivk: ivk.clone(),
                 cvk: cvk.clone(),
                 inputs: inputs,
+                e1,
                 nullifier: nullifier
             },
             PayoutProofSecrets {
@@ -108,126 +144,106 @@ impl PayoutProofInput {
     }
 }
 
-impl Proof<PayoutProofInput, PayoutProofSecrets, Vec<RerandomizedProofResponse>> for PayoutProof {
-    fn get_variables(inputs: &PayoutProofInput, secrets: &PayoutProofSecrets, u: &Scalar) -> Variables {
-        let mut vars = Variables::new();
-
-        let mut sep = Scalar::one();
-
-        for (i, input) in inputs.inputs.iter().enumerate() {
-            vars.add(Variable::Inner {
-                id: format!("input_{}", i),
-                G: (input.vc * &sep).to_affine(),
-                cl: -Scalar::one(),
-                cr: Scalar::zero()
-            });
-            vars.add(Variable::Inner {
-                id: format!("input_{}_opening", i),
-                G: (inputs.cvk.b * &sep).to_affine(),
-                cl: secrets.witnesses.get(i).map_or(Scalar::zero(), |w| w.r.clone()),
-                cr: Scalar::zero()
-            });
-            let witness = secrets.witnesses.get(i).map_or(UnblindedSignature::default(), |w| w.s.clone());
-            for (j, u) in inputs.cvk.U.iter().enumerate() {
-                vars.add(Variable::Inner {
-                    id: format!("input_{}_u_{}", i, j),
-                    G: (u * &sep).to_affine(),
-                    cl: witness.m.get(j).map_or(Scalar::zero(), |v| v.clone()),
-                    cr: Scalar::zero()
-                });
-            }
-            for (j, v) in inputs.cvk.V.iter().enumerate() {
-                vars.add(Variable::Inner {
-                    id: format!("input_{}_v_{}", i, j),
-                    G: (v * &sep).to_affine(),
-                    cl: witness.s.get(j).map_or(Scalar::zero(), |v| v.clone()),
-                    cr: Scalar::zero()
-                });
-            }
-
-            sep = &sep * u;
-        }
+/// Variables of a rerandomized coin.
+struct Coin {
+    value: Var,
+    nullifier: Var,
+    identity: Var,
+}
 
-        let sum = secrets.witnesses.iter().fold(0, |acc, w| acc + as_u32(&w.s.m[0]));
-        let remainder = match sum > 0 {
-            true => sum - (inputs.value as u32),
-            _ => 0
-        } as u8;
-
-        for i in 0..8 {
-            let bit = as_scalar(((remainder & u8::pow(2, i)) >> i) as u32);
-            vars.add(Variable::Scratch {
-                id: format!("bit_{}", i),
-                cl: bit,
-                cr: bit - Scalar::one()
-            });
-        }
+/// Value left over after paying out `inputs.value` from the coins, `None` if
+/// the value exceeds the sum of the coins or the remainder exceeds 8 bits.
+fn remainder(inputs: &PayoutProofInput, secrets: &PayoutProofSecrets) -> Option<u8> {
+    let sum = secrets.witnesses.iter().fold(0, |acc, w| acc + as_u32(&w.s.m[0]));
+    sum.checked_sub(inputs.value as u32).and_then(|r| u8::try_from(r).ok())
+}
 
-        vars
+fn statement(cs: &mut ConstraintSystem, inputs: &PayoutProofInput, secrets: &PayoutProofSecrets) {
+    let coins: Vec<Coin> = inputs.inputs.iter().enumerate().map(|(i, input)| {
+        let witness = secrets.witnesses.get(i);
+        let signature = witness.map_or(UnblindedSignature::default(), |w| w.s.clone());
+
+        cs.inner(format!("input_{}", i), input.vc, -Scalar::one());
+        cs.inner(format!("input_{}_opening", i), inputs.cvk.b, witness.map_or(Scalar::zero(), |w| w.r));
+        let u: Vec<Var> = inputs.cvk.U.iter().enumerate().map(|(j, u)| {
+            cs.inner(format!("input_{}_u_{}", i, j), u, signature.m.get(j).copied().unwrap_or(Scalar::zero()))
+        }).collect();
+        let v: Vec<Var> = inputs.cvk.V.iter().enumerate().map(|(j, v)| {
+            cs.inner(format!("input_{}_v_{}", i, j), v, signature.s.get(j).copied().unwrap_or(Scalar::zero()))
+        }).collect();
+        cs.relation();
+
+        Coin { value: u[0].clone(), nullifier: v[0].clone(), identity: v[1].clone() }
+    }).collect();
+
+    // the verifier has no witnesses, the prover has checked the remainder
+    let remainder = remainder(inputs, secrets).unwrap_or(0);
+    let bits = cs.bits(|i| format!("bit_{}", i), remainder as u64, 8);
+
+    let mut hasher = Sha512::new();
+    hasher.update(b"id");
+    hasher.update(inputs.recipient.as_bytes());
+    let hash: [u8;64] = hasher.finalize().into();
+    let identity = Scalar::from_bytes_wide(&hash);
+
+    // identity of all tx matches payout target
+    let mut a = cs.batch("identity", ConstraintType::Dir);
+    // show validity of nullifier
+    let mut b = cs.batch("nullifier", ConstraintType::Dir);
+
+    for (coin, nullifier) in coins.iter().zip(&inputs.nullifier) {
+        a.constant(&coin.identity, identity);
+        b.constant(&coin.nullifier, *nullifier);
     }
 
-    fn get_constraints(inputs: &PayoutProofInput, y: &Scalar) -> Vec<Constraint> {
-        let mut hasher = Sha512::new();
-        hasher.update(b"id");
-        hasher.update(inputs.recipient.as_bytes());
-        let hash: [u8;64] = hasher.finalize().into();
-        let identity = Scalar::from_bytes_wide(&hash);
-
-        // identity of all tx matches payout target
-        let mut a = Constraint::new(
-            ConstraintType::Dir,
-            exp_iter(y.clone()).take(MAX_INPUTS).map(|v| v * identity).sum()
-        );
-        a.left_set("auth_identity", -sum_of_powers(y, MAX_INPUTS));
-        let mut sep = Scalar::one();
-
-        // show validity of nullifier
-        let mut b = Constraint::new(
-            ConstraintType::Dir,
-            inputs.nullifier.iter().zip(exp_iter(y.clone())).map(|(v, a)| v * a).sum()
-        );
+    // sum of all tx and remainder has to equal payout value
+    let mut c = cs.batch("value", ConstraintType::Dir);
+    c.bits(&bits, -Scalar::one()).linear(coins.iter().map(|coin| (&coin.value, Scalar::one())), as_scalar(inputs.value as u32));
 
-        for i in 0..MAX_INPUTS {
-            a.left_set(&format!("input_{}_v_1", i), sep);
-            b.left_set(&format!("input_{}_v_0", i), sep);
-            sep = sep * y;
-        }
-
-        // sum of all tx and remainder has to equal payout value
-        let mut c = Constraint::new(ConstraintType::Dir, as_scalar(inputs.value as u32));
+    // show that remainder bits are binary
+    let mut binary = Binary::new(cs);
+    for bit in &bits {
+        binary.add(&[bit]);
+    }
 
-        // show that remainder bits are binary
-        let mut d = Constraint::new(ConstraintType::Mul, Scalar::zero());
-        let mut e = Constraint::new(ConstraintType::One, Scalar::zero());
+    for batch in [a, b, c, binary.mul, binary.one] {
+        cs.push(batch);
+    }
+}
 
-        for i in 0..MAX_INPUTS {
-            c.left_set(&format!("input_{}_u_0", i), Scalar::one());
-        }
+impl Proof<PayoutProofInput, PayoutProofSecrets, RerandomizedProofResponse> for PayoutProof {
+    fn get_variables(inputs: &PayoutProofInput, secrets: &PayoutProofSecrets, u: &Scalar) -> Variables {
+        let mut cs = ConstraintSystem::variables(u);
+        statement(&mut cs, inputs, secrets);
+        cs.into_variables()
+    }
 
-        let mut sep_binary = Scalar::one();
+    fn get_constraints(inputs: &PayoutProofInput, y: &Scalar) -> Vec<Constraint> {
+        let mut cs = ConstraintSystem::constraints(y);
+        statement(&mut cs, inputs, &PayoutProofSecrets::default());
+        cs.into_constraints()
+    }
 
-        for i in 0..8 {
-            c.left_set(&format!("bit_{}", i), -as_scalar(u32::pow(2, i)));
-            d.right_set(&format!("bit_{}", i), sep_binary.clone());
-            e.left_set(&format!("bit_{}", i), sep_binary.clone());
-            sep_binary = &sep_binary * y;
-        }
+    fn get_named_constraints(inputs: &PayoutProofInput) -> Vec<(String, Constraint)> {
+        let mut cs = ConstraintSystem::rows();
+        statement(&mut cs, inputs, &PayoutProofSecrets::default());
+        cs.into_rows()
+    }
 
-        vec![a, b, c, d, e]
+    fn validate_secrets(inputs: &PayoutProofInput, secrets: &PayoutProofSecrets) -> Result<(), ProofError> {
+        remainder(inputs, secrets).map(|_| ()).ok_or(ProofError::InvalidError)
     }
 
-    fn additional_data(_inputs: &PayoutProofInput, secrets: &PayoutProofSecrets, transcript: &mut Transcript) -> Vec<RerandomizedProofResponse> {
-        secrets.witnesses.iter().map(|w| w.prove(transcript)).collect()
+    fn additional_data(inputs: &PayoutProofInput, secrets: &PayoutProofSecrets, transcript: &mut Transcript) -> RerandomizedProofResponse {
+        RerandomizedProofResponse::prove(&inputs.inputs, &secrets.witnesses, transcript)
     }
 
-    fn additional_checks(inputs: &PayoutProofInput, data: &Vec<RerandomizedProofResponse>, transcript: &mut Transcript) -> bool {
-        if inputs.inputs.len() != data.len() {
-            return false;
+    fn additional_checks(inputs: &PayoutProofInput, data: &RerandomizedProofResponse, transcript: &mut Transcript) -> Result<(), Check> {
+        if !data.verify(&inputs.inputs, &inputs.e1, &inputs.cvk, transcript) {
+            return Err(Check::Rerandomization);
         }
 
-        inputs.inputs.iter()
-            .zip(data.iter())
-            .all(|(proof, response)| response.verify(proof, &inputs.cvk, transcript))
+        Ok(())
     }
 }
 
@@ -269,8 +285,8 @@ mod tests {
         let identity = "user@example.com";
         let (ipk, isk) = init(&mut csrng, 0);
         let attrs = vec![];
-        let (request, mut credential) = issue_request(&mut csrng, &ipk, &identity, attrs);
-        let response = issue(&ipk, &isk, &request).unwrap();
+        let (request, mut credential) = issue_request(&mut csrng, &ipk, identity, attrs).unwrap();
+        let response = issue(&mut csrng, &ipk, &isk, &request).unwrap();
         get_credential(&ipk, &response, &mut credential).unwrap();
 
         let resources: Vec<(Scalar, u8)> = vec![
@@ -286,7 +302,7 @@ mod tests {
                 let s = vec![<bls12_381::Scalar as ff::Field>::random(&mut rng), credential.identity];
                 let d = <bls12_381::Scalar as ff::Field>::random(&mut rng);
                 let m = vec![as_scalar(*v as u32)];
-                let req = pbss::Blind(&cvk, &m, &s, &d, &mut rng);
+                let req = pbss::Blind(&cvk, &m, &s, &d, &mut rng).unwrap();
                 let sig = pbss::Sign(&cvk, &csk, &req, &mut csrng).unwrap();
                 pbss::Unblind(&cvk, &sig, &m, &s, &d).unwrap()
             })
@@ -294,7 +310,7 @@ mod tests {
 
         let value = 30;
 
-        let nr = NullRequest::new(&cvk, &credential);
+        let nr = NullRequest::new(&cvk, &credential).unwrap();
         let m = vec![Scalar::zero()];
         let nulls: Vec<UnblindedSignature> = nr.request.iter().enumerate()
             .map(|(i, req)| {
@@ -317,7 +333,7 @@ mod tests {
 
         // create proof
         let mut prover_transcript = Transcript::new(b"payout test");
-        let proof = GenericProof::<PayoutProofInput, Vec<RerandomizedProofResponse>>::proove::<PayoutProofSecrets, PayoutProof>(&mut prover_transcript, inputs, secrets).unwrap();
+        let proof = GenericProof::<PayoutProofInput, RerandomizedProofResponse>::proove::<PayoutProofSecrets, PayoutProof>(&mut prover_transcript, inputs, secrets).unwrap();
 
         // verify proof
         let mut verifier_transcript = Transcript::new(b"payout test");
@@ -3,9 +3,14 @@ use base64::{encode_config, decode_config};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{self, Visitor};
 use serde_with::{SerializeAs, DeserializeAs};
-use simple_error::SimpleError;
+
+use crate::error::PrepamsError;
 
 pub fn serialize<S: Serializer>(p: &G1Affine, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        return serializer.serialize_str(&SerializableG1Affine::to_string(p));
+    }
+
     let a: [u8; 48] = p.to_compressed();
     serializer.serialize_bytes(&a)
 }
@@ -40,6 +45,11 @@ impl<'de> Visitor<'de> for G1AffineVisitor {
 }
 
 pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<G1Affine, D::Error> {
+    if deserializer.is_human_readable() {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        return SerializableG1Affine::from_string(&s).map_err(de::Error::custom);
+    }
+
     deserializer.deserialize_bytes(G1AffineVisitor)
 }
 
@@ -57,7 +67,7 @@ impl From<G1Affine> for SerializableG1Affine {
 
 impl SerializeAs<G1Affine> for SerializableG1Affine {
     fn serialize_as<S>(value: &G1Affine, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {  
-        SerializableG1Affine::serialize(&SerializableG1Affine { v: *value }, serializer)
+        serialize(value, serializer)
     }
 }
 
@@ -68,15 +78,15 @@ impl<'de> DeserializeAs<'de, G1Affine> for SerializableG1Affine {
 }
 
 impl SerializableG1Affine {
-    pub fn from_string(s: &str) -> Result<G1Affine, SimpleError> {
+    pub fn from_string(s: &str) -> Result<G1Affine, PrepamsError> {
         match decode_config(s, base64::URL_SAFE_NO_PAD) {
-            Err(e) => Err(SimpleError::new(e.to_string())),
+            Err(e) => Err(PrepamsError::Encoding(e.to_string())),
             Ok(vec) => {
-                let bytes: [u8; 48] = vec.try_into().ok().ok_or(SimpleError::new(""))?;
+                let bytes: [u8; 48] = vec.try_into().ok().ok_or(PrepamsError::encoding("invalid length"))?;
                 let p = G1Affine::from_compressed(&bytes);
 
                 if p.is_none().into() {
-                    Err(SimpleError::new(""))
+                    Err(PrepamsError::encoding("invalid encoding"))
                 } else {
                     Ok(p.unwrap())
                 }
@@ -84,7 +94,6 @@ impl SerializableG1Affine {
         }
     }
 
-    #[allow(dead_code)]
     pub fn to_string(s: &G1Affine) -> String {
         encode_config(s.to_compressed(), base64::URL_SAFE_NO_PAD)
     }
@@ -1,9 +1,16 @@
 use bls12_381::G2Affine;
+use base64::{encode_config, decode_config};
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde::de::{self, Visitor};
 use serde_with::{SerializeAs, DeserializeAs};
 
+use crate::error::PrepamsError;
+
 pub fn serialize<S: Serializer>(p: &G2Affine, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        return serializer.serialize_str(&SerializableG2Affine::to_string(p));
+    }
+
     let a: [u8; 96] = p.to_compressed();
     serializer.serialize_bytes(&a)
 }
@@ -38,6 +45,11 @@ impl<'de> Visitor<'de> for G2AffineVisitor {
 }
 
 pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<G2Affine, D::Error> {
+    if deserializer.is_human_readable() {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        return SerializableG2Affine::from_string(&s).map_err(de::Error::custom);
+    }
+
     deserializer.deserialize_bytes(G2AffineVisitor)
 }
 
@@ -49,7 +61,7 @@ pub struct SerializableG2Affine {
 
 impl SerializeAs<G2Affine> for SerializableG2Affine {
     fn serialize_as<S>(value: &G2Affine, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {  
-        SerializableG2Affine::serialize(&SerializableG2Affine { v: *value }, serializer)
+        serialize(value, serializer)
     }
 }
 
@@ -59,6 +71,17 @@ impl<'de> DeserializeAs<'de, G2Affine> for SerializableG2Affine {
     }
 }
 
+impl SerializableG2Affine {
+    pub fn from_string(s: &str) -> Result<G2Affine, PrepamsError> {
+        let bytes = decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|e| PrepamsError::Encoding(e.to_string()))?;
+        G2AffineVisitor.visit_bytes(&bytes).map_err(|e: de::value::Error| PrepamsError::Encoding(e.to_string()))
+    }
+
+    pub fn to_string(s: &G2Affine) -> String {
+        encode_config(s.to_compressed(), base64::URL_SAFE_NO_PAD)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use group::Curve;
@@ -1,11 +1,18 @@
 use bls12_381::{Gt, Scalar};
+use base64::{encode_config, decode_config};
 use serde::de::{self, Visitor};
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde_with::{SerializeAs, DeserializeAs};
 
+use crate::error::PrepamsError;
+
 const LEN: usize = std::mem::size_of::<Gt>();
 
 pub fn serialize<S: Serializer>(p: &Gt, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        return serializer.serialize_str(&SerializableGt::to_string(p));
+    }
+
     serializer.serialize_bytes(&gt_to_bytes(&p))
 }
 
@@ -37,6 +44,11 @@ impl<'de> Visitor<'de> for GtVisitor {
 }
 
 pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Gt, D::Error> {
+    if deserializer.is_human_readable() {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        return SerializableGt::from_string(&s).map_err(de::Error::custom);
+    }
+
     deserializer.deserialize_bytes(GtVisitor)
 }
 
@@ -60,7 +72,7 @@ impl From<Gt> for SerializableGt {
 
 impl SerializeAs<Gt> for SerializableGt {
     fn serialize_as<S>(value: &Gt, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
-        SerializableGt::serialize(&SerializableGt { v: *value }, serializer)
+        serialize(value, serializer)
     }
 }
 
@@ -70,13 +82,24 @@ impl<'de> DeserializeAs<'de, Gt> for SerializableGt {
     }
 }
 
+impl SerializableGt {
+    pub fn from_string(s: &str) -> Result<Gt, PrepamsError> {
+        let bytes = decode_config(s, base64::URL_SAFE_NO_PAD).map_err(|e| PrepamsError::Encoding(e.to_string()))?;
+        GtVisitor.visit_bytes(&bytes).map_err(|e: de::value::Error| PrepamsError::Encoding(e.to_string()))
+    }
+
+    pub fn to_string(s: &Gt) -> String {
+        encode_config(gt_to_bytes(s), base64::URL_SAFE_NO_PAD)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use group::Curve;
     use bls12_381::{pairing, G1Affine, G2Affine};
 
     use crate::external::util::rand_scalar;
-    use crate::serialization::SerializableGt;
+    use super::SerializableGt;
 
     #[test]
     fn test_serialization() {
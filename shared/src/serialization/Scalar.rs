@@ -2,7 +2,8 @@ use bls12_381::Scalar;
 use base64::{encode_config, decode_config};
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde_with::{SerializeAs, DeserializeAs};
-use simple_error::SimpleError;
+
+use crate::error::PrepamsError;
 
 pub fn serialize<S: Serializer>(p: &Scalar, serializer: S) -> Result<S::Ok, S::Error> {
     encode_config(p.to_bytes(), base64::URL_SAFE_NO_PAD).serialize(serializer)
@@ -56,7 +57,7 @@ impl<'a> From<&'a SerializableScalar> for &'a bls12_381::Scalar {
 
 impl SerializeAs<Scalar> for SerializableScalar {
     fn serialize_as<S>(value: &Scalar, serializer: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {  
-        SerializableScalar::serialize(&SerializableScalar { v: *value }, serializer)
+        serialize(value, serializer)
     }
 }
 
@@ -74,15 +75,15 @@ impl std::hash::Hash for SerializableScalar {
 
 
 impl SerializableScalar {
-    pub fn from_string(s: &str) -> Result<Scalar, SimpleError> {
+    pub fn from_string(s: &str) -> Result<Scalar, PrepamsError> {
         match decode_config(s, base64::URL_SAFE_NO_PAD) {
-            Err(e) => Err(SimpleError::new(e.to_string())),
+            Err(e) => Err(PrepamsError::Encoding(e.to_string())),
             Ok(vec) => {
-                let bytes: [u8; 32] = vec.try_into().ok().ok_or(SimpleError::new(""))?;
+                let bytes: [u8; 32] = vec.try_into().ok().ok_or(PrepamsError::encoding("invalid length"))?;
                 let p = Scalar::from_bytes(&bytes);
 
                 if p.is_none().into() {
-                    Err(SimpleError::new(""))
+                    Err(PrepamsError::encoding("invalid encoding"))
                 } else {
                     Ok(p.unwrap())
                 }
@@ -0,0 +1,379 @@
+//! Versioned, self-describing envelope around the postcard wire format.
+//!
+//! Every object that leaves a role is sealed into an envelope consisting of
+//! a magic number, the type of the object, the version of its encoding and
+//! the domain of the deployment it belongs to, followed by the postcard
+//! encoding of the object itself:
+//!
+//! ```text
+//! "PPMS" | kind (u8) | version (varint) | domain (varint length, utf-8) | payload
+//! ```
+//!
+//! Opening an envelope rejects foreign data, objects of another type, newer
+//! versions and objects of other deployments. Objects of an older version are
+//! upgraded by the [`Message::migrate`] hook of their type.
+//!
+//! The same envelope is available as JSON and CBOR document, see
+//! [`crate::serialization::format`].
+//!
+//! Keys and signatures are fixed-size byte strings and cross the wire raw.
+
+use std::fmt;
+use std::sync::RwLock;
+
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::api::{IssuerCore, OrganizerCore, ParticipantCore};
+use crate::error::PrepamsError;
+use crate::pbss::{self, BlindedSignRequest, BlindedSignature, UnblindedSignature};
+use crate::proofs::payout::PayoutProofData;
+use crate::types::*;
+use crate::types::credential::*;
+
+/// Magic number every envelope starts with.
+pub const MAGIC: [u8; 4] = *b"PPMS";
+
+/// Domain of envelopes sealed before [`set_domain`] is called.
+pub const DEFAULT_DOMAIN: &str = "prepams";
+
+static DOMAIN: RwLock<Option<String>> = RwLock::new(None);
+
+/// Sets the domain of the deployment that envelopes are sealed for and
+/// opened in, e.g. the url of the issuer.
+pub fn set_domain(domain: &str) {
+    *DOMAIN.write().unwrap_or_else(|e| e.into_inner()) = Some(domain.to_string());
+}
+
+/// Returns the domain of the deployment.
+pub fn domain() -> String {
+    DOMAIN.read().unwrap_or_else(|e| e.into_inner()).clone().unwrap_or_else(|| DEFAULT_DOMAIN.to_string())
+}
+
+/// Type of the object in an envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Kind {
+    IssuerPublicKey = 1,
+    CreditVerificationKey = 2,
+    IssueRequest = 3,
+    IssueResponse = 4,
+    Resource = 5,
+    SignedResource = 6,
+    Participation = 7,
+    ConfirmedParticipation = 8,
+    RewardRequest = 9,
+    Coin = 10,
+    Transaction = 11,
+    Payout = 12,
+    LedgerEntry = 13,
+    Ledger = 14,
+    LedgerSlice = 15,
+    NullRequest = 16,
+    NullRequests = 17,
+    NullResponses = 18,
+    Nulls = 19,
+    PayoutRequest = 20,
+    IssuerState = 21,
+    OrganizerState = 22,
+    ParticipantState = 23,
+}
+
+impl Kind {
+    const ALL: [Kind; 23] = [
+        Kind::IssuerPublicKey, Kind::CreditVerificationKey, Kind::IssueRequest, Kind::IssueResponse,
+        Kind::Resource, Kind::SignedResource, Kind::Participation, Kind::ConfirmedParticipation,
+        Kind::RewardRequest, Kind::Coin, Kind::Transaction, Kind::Payout, Kind::LedgerEntry,
+        Kind::Ledger, Kind::LedgerSlice, Kind::NullRequest, Kind::NullRequests, Kind::NullResponses,
+        Kind::Nulls, Kind::PayoutRequest, Kind::IssuerState, Kind::OrganizerState, Kind::ParticipantState,
+    ];
+
+    /// Returns all object types.
+    pub fn all() -> &'static [Kind] {
+        &Kind::ALL
+    }
+
+    /// Returns the stable identifier of the type used in JSON and CBOR documents.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Kind::IssuerPublicKey => "issuer_public_key",
+            Kind::CreditVerificationKey => "credit_verification_key",
+            Kind::IssueRequest => "issue_request",
+            Kind::IssueResponse => "issue_response",
+            Kind::Resource => "resource",
+            Kind::SignedResource => "signed_resource",
+            Kind::Participation => "participation",
+            Kind::ConfirmedParticipation => "confirmed_participation",
+            Kind::RewardRequest => "reward_request",
+            Kind::Coin => "coin",
+            Kind::Transaction => "transaction",
+            Kind::Payout => "payout",
+            Kind::LedgerEntry => "ledger_entry",
+            Kind::Ledger => "ledger",
+            Kind::LedgerSlice => "ledger_slice",
+            Kind::NullRequest => "null_request",
+            Kind::NullRequests => "null_requests",
+            Kind::NullResponses => "null_responses",
+            Kind::Nulls => "nulls",
+            Kind::PayoutRequest => "payout_request",
+            Kind::IssuerState => "issuer_state",
+            Kind::OrganizerState => "organizer_state",
+            Kind::ParticipantState => "participant_state",
+        }
+    }
+
+    /// Looks up an object type by its identifier.
+    pub fn from_id(id: &str) -> Option<Kind> {
+        Kind::ALL.iter().find(|k| k.id() == id).copied()
+    }
+
+    fn from_u8(kind: u8) -> Option<Kind> {
+        Kind::ALL.iter().find(|k| **k as u8 == kind).copied()
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::IssuerPublicKey => "issuer public key",
+            Kind::CreditVerificationKey => "credit verification key",
+            Kind::IssueRequest => "credential request",
+            Kind::IssueResponse => "credential response",
+            Kind::Resource => "study",
+            Kind::SignedResource => "signed study",
+            Kind::Participation => "participation",
+            Kind::ConfirmedParticipation => "confirmed participation",
+            Kind::RewardRequest => "reward request",
+            Kind::Coin => "reward coin",
+            Kind::Transaction => "transaction",
+            Kind::Payout => "payout",
+            Kind::LedgerEntry => "ledger entry",
+            Kind::Ledger => "ledger",
+            Kind::LedgerSlice => "ledger slice",
+            Kind::NullRequest => "null request secrets",
+            Kind::NullRequests => "null coin requests",
+            Kind::NullResponses => "null coin responses",
+            Kind::Nulls => "null coins",
+            Kind::PayoutRequest => "payout request",
+            Kind::IssuerState => "issuer state",
+            Kind::OrganizerState => "organizer state",
+            Kind::ParticipantState => "participant state",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Payload of an envelope in the format it was received in.
+pub enum Payload<'a> {
+    Postcard(&'a [u8]),
+    Json(serde_json::Value),
+    Cbor(ciborium::Value),
+}
+
+impl Payload<'_> {
+    /// Decodes the payload, e.g. as the type of an older version.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, PrepamsError> {
+        match self {
+            Payload::Postcard(data) => Ok(postcard::from_bytes(data)?),
+            Payload::Json(value) => T::deserialize(value).map_err(|e| PrepamsError::Encoding(e.to_string())),
+            Payload::Cbor(value) => value.deserialized().map_err(|e| PrepamsError::Encoding(e.to_string())),
+        }
+    }
+}
+
+/// Object that crosses the wire in an envelope.
+pub trait Message: Serialize + DeserializeOwned + JsonSchema {
+    /// Type tag of the object.
+    const KIND: Kind;
+
+    /// Current version of the encoding, bumped whenever the encoding changes.
+    const VERSION: u16 = 1;
+
+    /// Upgrades the payload of an envelope sealed with an older `version`.
+    fn migrate(version: u16, _payload: Payload) -> Result<Self, PrepamsError> {
+        Err(PrepamsError::Encoding(format!("{} version {} is no longer supported", Self::KIND, version)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    magic: [u8; 4],
+    kind: u8,
+    version: u16,
+    domain: String,
+}
+
+/// Seals an object into an envelope of the configured domain.
+pub fn seal<T: Message>(o: &T) -> Result<Vec<u8>, PrepamsError> {
+    seal_in(&domain(), o)
+}
+
+/// Opens an envelope of the configured domain.
+pub fn open<T: Message>(data: &[u8]) -> Result<T, PrepamsError> {
+    open_in(&domain(), data)
+}
+
+/// Seals an object into an envelope of the given domain.
+pub fn seal_in<T: Message>(domain: &str, o: &T) -> Result<Vec<u8>, PrepamsError> {
+    let header = Header { magic: MAGIC, kind: T::KIND as u8, version: T::VERSION, domain: domain.to_string() };
+    let mut data = postcard::to_stdvec(&header)?;
+    data.append(&mut postcard::to_stdvec(o)?);
+    Ok(data)
+}
+
+/// Opens an envelope of the given domain.
+pub fn open_in<T: Message>(domain: &str, data: &[u8]) -> Result<T, PrepamsError> {
+    if !data.starts_with(&MAGIC) {
+        Err(PrepamsError::Encoding(format!("expected a {} but got data of an unknown format", T::KIND)))?;
+    }
+
+    let (header, payload): (Header, &[u8]) = postcard::take_from_bytes(data)?;
+    accept(Kind::from_u8(header.kind), header.version, &header.domain, domain, Payload::Postcard(payload))
+}
+
+/// Reads the type and domain of an envelope without decoding the object.
+pub(crate) fn peek(data: &[u8]) -> Result<(Option<Kind>, String), PrepamsError> {
+    let (header, _): (Header, &[u8]) = postcard::take_from_bytes(data)?;
+    Ok((Kind::from_u8(header.kind), header.domain))
+}
+
+// checks the header of an envelope in any format and decodes its payload
+pub(crate) fn accept<T: Message>(kind: Option<Kind>, version: u16, origin: &str, domain: &str, payload: Payload) -> Result<T, PrepamsError> {
+    match kind {
+        Some(kind) if kind == T::KIND => {},
+        Some(kind) => Err(PrepamsError::Encoding(format!("expected a {} but got a {}", T::KIND, kind)))?,
+        None => Err(PrepamsError::Encoding(format!("expected a {} but got an unknown object type", T::KIND)))?,
+    }
+
+    if origin != domain {
+        Err(PrepamsError::Rejected(format!("{} belongs to the deployment {:?}", T::KIND, origin)))?;
+    }
+
+    if version > T::VERSION {
+        Err(PrepamsError::Encoding(format!("{} version {} is newer than the supported version {}", T::KIND, version, T::VERSION)))?;
+    }
+
+    if version < T::VERSION {
+        return T::migrate(version, payload);
+    }
+
+    payload.decode()
+}
+
+/// Operation that is generic over the type of a message, see [`visit`].
+pub(crate) trait Visitor {
+    type Output;
+
+    fn visit<T: Message>(self) -> Self::Output;
+}
+
+// types after the `;` implement `Message` themselves, e.g. to migrate versions
+macro_rules! message {
+    ($($ty:ty => $kind:ident),* $(,)?; $($versioned:ty => $versioned_kind:ident),* $(,)?) => {
+        $(impl Message for $ty {
+            const KIND: Kind = Kind::$kind;
+        })*
+
+        /// Runs the visitor with the type of the given kind.
+        pub(crate) fn visit<V: Visitor>(kind: Kind, visitor: V) -> V::Output {
+            match kind {
+                $(Kind::$kind => visitor.visit::<$ty>(),)*
+                $(Kind::$versioned_kind => visitor.visit::<$versioned>(),)*
+            }
+        }
+    };
+}
+
+message! {
+    IssuerPublicKey => IssuerPublicKey,
+    pbss::PublicKey => CreditVerificationKey,
+    IssueRequest => IssueRequest,
+    IssueResponse => IssueResponse,
+    Resource => Resource,
+    SignedResource => SignedResource,
+    ConfirmedParticipation => ConfirmedParticipation,
+    BlindedSignRequest => RewardRequest,
+    BlindedSignature => Coin,
+    Transaction => Transaction,
+    Payout => Payout,
+    LedgerEntry => LedgerEntry,
+    Ledger => Ledger,
+    LedgerSlice => LedgerSlice,
+    NullRequest => NullRequest,
+    Vec<BlindedSignRequest> => NullRequests,
+    Vec<BlindedSignature> => NullResponses,
+    Vec<UnblindedSignature> => Nulls,
+    IssuerCore => IssuerState,
+    OrganizerCore => OrganizerState,
+    ParticipantCore => ParticipantState;
+    Participation => Participation,
+    PayoutProofData => PayoutRequest,
+}
+
+// version 4 constrains every bit of the range proofs in its own row, the
+// proofs of older participations no longer verify
+impl Message for Participation {
+    const KIND: Kind = Kind::Participation;
+    const VERSION: u16 = 4;
+}
+
+// version 3 aggregates the proofs of possession of the coins and version 4
+// constrains every bit of the remainder in its own row, older payout requests
+// cannot be converted without the secrets of the participant
+impl Message for PayoutProofData {
+    const KIND: Kind = Kind::PayoutRequest;
+    const VERSION: u16 = 4;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_chacha::rand_core::SeedableRng;
+
+    use crate::serialization::encode;
+
+    // a future encoding of a study that carries a title
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct TitledResource {
+        resource: Resource,
+        title: String,
+    }
+
+    impl Message for TitledResource {
+        const KIND: Kind = Kind::Resource;
+        const VERSION: u16 = 2;
+
+        fn migrate(version: u16, payload: Payload) -> Result<Self, PrepamsError> {
+            match version {
+                1 => Ok(TitledResource { resource: payload.decode()?, title: String::new() }),
+                _ => Err(PrepamsError::encoding("unsupported version")),
+            }
+        }
+    }
+
+    #[test]
+    fn envelope() {
+        let resource = Resource::random(ChaCha20Rng::seed_from_u64(1));
+        let data = seal_in("test", &resource).unwrap();
+        assert!(data.starts_with(&MAGIC));
+        assert_eq!(data[4], Kind::Resource as u8);
+
+        let opened: Resource = open_in("test", &data).unwrap();
+        assert_eq!(opened.id, resource.id);
+
+        // objects of another type, another deployment or without envelope are rejected
+        let err = open_in::<Participation>("test", &data).unwrap_err();
+        assert_eq!(err.to_string(), "expected a participation but got a study");
+        assert!(matches!(open_in::<Resource>("other", &data), Err(PrepamsError::Rejected(_))));
+        assert_eq!(open_in::<Resource>("test", &encode(&resource).unwrap()).unwrap_err().code(), "encoding");
+        assert_eq!(open_in::<Resource>("test", &data[..3]).unwrap_err().code(), "encoding");
+
+        // older versions are migrated, newer versions are rejected
+        let titled: TitledResource = open_in("test", &data).unwrap();
+        assert_eq!((titled.resource.id, titled.title.as_str()), (resource.id, ""));
+        let newer = seal_in("test", &titled).unwrap();
+        assert_eq!(open_in::<Resource>("test", &newer).unwrap_err().code(), "encoding");
+    }
+}
@@ -0,0 +1,260 @@
+//! Alternative encodings of the envelope for auditors and non-Rust tooling.
+//!
+//! Besides the compact postcard envelope, every message can be encoded as
+//! canonical JSON or as CBOR document:
+//!
+//! ```text
+//! { "domain": "prepams", "payload": { .. }, "type": "participation", "version": 1 }
+//! ```
+//!
+//! In JSON, group elements and scalars are base64url strings without padding,
+//! in CBOR they are byte strings. Canonical JSON has sorted keys and no
+//! whitespace. Every encoding converts losslessly into the others, see
+//! [`transcode`], and [`schema`] describes the JSON document of each type.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::error::PrepamsError;
+use crate::serialization::envelope::{self, accept, domain, peek, visit, Kind, Message, Payload, Visitor, MAGIC};
+
+/// Encoding of a message.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Postcard,
+    Json,
+    Cbor,
+}
+
+impl Format {
+    /// Detects the encoding of a message from its first bytes.
+    pub fn detect(data: &[u8]) -> Option<Format> {
+        match data.iter().find(|b| !b.is_ascii_whitespace()) {
+            _ if data.starts_with(&MAGIC) => Some(Format::Postcard),
+            Some(b'{') => Some(Format::Json),
+            // cbor map
+            Some(0xa0..=0xbf) => Some(Format::Cbor),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Postcard => write!(f, "postcard"),
+            Format::Json => write!(f, "json"),
+            Format::Cbor => write!(f, "cbor"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = PrepamsError;
+
+    fn from_str(s: &str) -> Result<Format, PrepamsError> {
+        match s {
+            "postcard" => Ok(Format::Postcard),
+            "json" => Ok(Format::Json),
+            "cbor" => Ok(Format::Cbor),
+            _ => Err(PrepamsError::Encoding(format!("unknown format {:?}", s))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document<T> {
+    #[serde(rename = "type")]
+    kind: String,
+    version: u16,
+    domain: String,
+    payload: T,
+}
+
+fn invalid(e: impl fmt::Display) -> PrepamsError {
+    PrepamsError::Encoding(e.to_string())
+}
+
+/// Encodes a message for the given domain.
+pub fn seal_as<T: Message>(format: Format, domain: &str, o: &T) -> Result<Vec<u8>, PrepamsError> {
+    let document = Document { kind: T::KIND.id().to_string(), version: T::VERSION, domain: domain.to_string(), payload: o };
+
+    match format {
+        Format::Postcard => envelope::seal_in(domain, o),
+        Format::Json => serde_json::to_vec(&serde_json::to_value(&document).map_err(invalid)?).map_err(invalid),
+        Format::Cbor => {
+            let mut data = vec![];
+            ciborium::ser::into_writer(&document, &mut data).map_err(invalid)?;
+            Ok(data)
+        }
+    }
+}
+
+/// Decodes a message of the given domain.
+pub fn open_as<T: Message>(format: Format, domain: &str, data: &[u8]) -> Result<T, PrepamsError> {
+    match format {
+        Format::Postcard => envelope::open_in(domain, data),
+        Format::Json => {
+            let document: Document<serde_json::Value> = serde_json::from_slice(data).map_err(invalid)?;
+            accept(Kind::from_id(&document.kind), document.version, &document.domain, domain, Payload::Json(document.payload))
+        },
+        Format::Cbor => {
+            let document: Document<ciborium::Value> = ciborium::de::from_reader(data).map_err(invalid)?;
+            accept(Kind::from_id(&document.kind), document.version, &document.domain, domain, Payload::Cbor(document.payload))
+        }
+    }
+}
+
+/// Decodes a message of the configured domain in any format.
+pub fn open_any<T: Message>(data: &[u8]) -> Result<T, PrepamsError> {
+    let format = Format::detect(data).ok_or_else(|| PrepamsError::Encoding(format!("expected a {} but got data of an unknown format", T::KIND)))?;
+    open_as(format, &domain(), data)
+}
+
+struct Transcode<'a> {
+    from: Format,
+    to: Format,
+    domain: &'a str,
+    data: &'a [u8],
+}
+
+impl Visitor for Transcode<'_> {
+    type Output = Result<Vec<u8>, PrepamsError>;
+
+    fn visit<T: Message>(self) -> Self::Output {
+        let o: T = open_as(self.from, self.domain, self.data)?;
+        seal_as(self.to, self.domain, &o)
+    }
+}
+
+/// Converts a message of any type and domain into another format.
+///
+/// The message is fully decoded, so transcoding also validates it.
+pub fn transcode(data: &[u8], to: Format) -> Result<Vec<u8>, PrepamsError> {
+    let from = Format::detect(data).ok_or_else(|| PrepamsError::encoding("message has an unknown format"))?;
+    let (kind, domain) = match from {
+        Format::Postcard => peek(data)?,
+        Format::Json => {
+            let document: Document<serde::de::IgnoredAny> = serde_json::from_slice(data).map_err(invalid)?;
+            (Kind::from_id(&document.kind), document.domain)
+        },
+        Format::Cbor => {
+            let document: Document<serde::de::IgnoredAny> = ciborium::de::from_reader(data).map_err(invalid)?;
+            (Kind::from_id(&document.kind), document.domain)
+        }
+    };
+
+    let kind = kind.ok_or_else(|| PrepamsError::encoding("message has an unknown object type"))?;
+    visit(kind, Transcode { from, to, domain: &domain, data })
+}
+
+struct Schema;
+
+impl Visitor for Schema {
+    type Output = serde_json::Value;
+
+    fn visit<T: Message>(self) -> Self::Output {
+        let mut payload = serde_json::to_value(schemars::schema_for!(T)).unwrap_or_default();
+        let definitions = payload.as_object_mut().and_then(|o| {
+            o.remove("$schema");
+            o.remove("definitions")
+        });
+
+        serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": T::KIND.to_string(),
+            "type": "object",
+            "required": ["type", "version", "domain", "payload"],
+            "additionalProperties": false,
+            "properties": {
+                "type": { "const": T::KIND.id() },
+                "version": { "const": T::VERSION },
+                "domain": { "type": "string" },
+                "payload": payload
+            },
+            "definitions": definitions.unwrap_or_default()
+        })
+    }
+}
+
+/// Returns the JSON schema of the JSON document of a message type.
+pub fn schema(kind: Kind) -> serde_json::Value {
+    visit(kind, Schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_chacha::rand_core::SeedableRng;
+
+    use crate::api::{IssuerCore, OrganizerCore, ParticipantCore};
+    use crate::types::*;
+
+    fn round_trip<T: Message>(o: &T) {
+        let postcard = seal_as(Format::Postcard, "test", o).unwrap();
+        let json = transcode(&postcard, Format::Json).unwrap();
+        let cbor = transcode(&json, Format::Cbor).unwrap();
+
+        assert_eq!(transcode(&cbor, Format::Postcard).unwrap(), postcard);
+        assert_eq!(transcode(&cbor, Format::Json).unwrap(), json);
+        assert_eq!(seal_as(Format::Json, "test", &open_as::<T>(Format::Json, "test", &json).unwrap()).unwrap(), json);
+        assert!(matches!(open_as::<T>(Format::Cbor, "other", &cbor), Err(PrepamsError::Rejected(_))));
+    }
+
+    #[test]
+    fn encodings() {
+        let mut issuer = IssuerCore::new(2, ChaCha20Rng::seed_from_u64(1));
+        let organizer = OrganizerCore::new("lab", issuer.public_key().clone(), ChaCha20Rng::seed_from_u64(2));
+        let mut participant = ParticipantCore::new("alice", &[30, 1], issuer.ledger_verification_key());
+
+        let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), ChaCha20Rng::seed_from_u64(3)).unwrap();
+        participant.retrieve_credential(&issuer.issue_credential(&request).unwrap()).unwrap();
+
+        let mut resource = Resource::random(ChaCha20Rng::seed_from_u64(4));
+        resource.addConstraint(AttributeConstraint::Range(0, 18, 99));
+        let signed = organizer.sign_resource(&resource).unwrap();
+        let participation = participant.participate(&resource).unwrap();
+        let confirmed = organizer.confirm_participation(&participation, "p1".to_string()).unwrap();
+        issuer.issue_reward(&confirmed, &organizer.public_key(), 1).unwrap();
+
+        round_trip(&issuer);
+        round_trip(&participant);
+        round_trip(&signed);
+        round_trip(&participation);
+        round_trip(&issuer.ledger().unwrap());
+
+        // points and scalars are base64url strings in canonical json
+        let json = seal_as(Format::Json, "test", &confirmed).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(serde_json::to_vec(&value).unwrap(), json);
+        assert_eq!(value["type"], "confirmed_participation");
+        assert_eq!(value["payload"]["tag"], confirmed.tag());
+        assert_eq!(value["payload"]["study"], confirmed.study());
+        assert_eq!(open_as::<ConfirmedParticipation>(Format::Json, "test", &json).unwrap(), confirmed);
+
+        assert_eq!(Format::detect(&json), Some(Format::Json));
+        assert_eq!(Format::detect(b"PPMS"), Some(Format::Postcard));
+        assert_eq!(Format::detect(b"\x00"), None);
+    }
+
+    #[test]
+    fn schemas() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+
+        for kind in Kind::all() {
+            let schema = schema(*kind);
+            assert_eq!(schema["properties"]["type"]["const"], kind.id());
+
+            // the published schemas are regenerated with `prepams schema --out schema`
+            let published = std::fs::read(dir.join(format!("{}.json", kind.id()))).unwrap();
+            let published: serde_json::Value = serde_json::from_slice(&published).unwrap();
+            assert_eq!(published, schema, "schema/{}.json is outdated", kind.id());
+        }
+    }
+}
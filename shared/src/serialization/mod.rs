@@ -1,5 +1,8 @@
+#[cfg(feature = "wasm")]
 use wasm_bindgen::{JsError, JsValue};
 
+use crate::error::PrepamsError;
+
 #[allow(non_snake_case)]
 pub mod Scalar;
 #[allow(non_snake_case)]
@@ -8,32 +11,57 @@ pub mod G1Affine;
 pub mod G2Affine;
 #[allow(non_snake_case)]
 pub mod Gt;
+pub mod envelope;
+pub mod format;
+pub mod schema;
 
 pub use G1Affine::SerializableG1Affine;
 pub use G2Affine::SerializableG2Affine;
 pub use Scalar::SerializableScalar;
-pub use Gt::SerializableGt;
+pub use envelope::{seal, open};
+
+/// Encodes an object in the postcard wire format.
+pub fn encode<T>(o: &T) -> Result<Vec<u8>, PrepamsError> where T: serde::Serialize {
+    Ok(postcard::to_stdvec(o)?)
+}
+
+/// Decodes an object from the postcard wire format.
+pub fn decode<T>(i: &[u8]) -> Result<T, PrepamsError> where T: for<'a> serde::de::Deserialize<'a> {
+    Ok(postcard::from_bytes(i)?)
+}
+
+/// Maps an error to a `JsError` whose message is prefixed with the stable
+/// error code, e.g. `rejected: coin already spent`.
+#[cfg(feature = "wasm")]
+pub fn throw(e: PrepamsError) -> JsError {
+    JsError::new(&format!("{}: {}", e.code(), e))
+}
 
+#[cfg(feature = "wasm")]
 pub fn convert<T, E>(o: Result<T, E>) -> Result<T, JsError> where E: std::fmt::Display {
     match o {
-        Err(e) => Err (JsError::new(&e.to_string())),
+        Err(e) => Err (throw(PrepamsError::Encoding(e.to_string()))),
         Ok(d) => Ok (d)
     }
 }
 
-pub fn output<T>(o: T) -> Result<Vec<u8>, JsError> where T: serde::Serialize {
-    convert(postcard::to_stdvec(&o))
+#[cfg(feature = "wasm")]
+pub fn output<T>(o: &T) -> Result<Vec<u8>, JsError> where T: envelope::Message {
+    seal(o).map_err(throw)
 }
 
-pub fn input<T>(i: &[u8]) -> Result<T, JsError> where T: for<'a> serde::de::Deserialize<'a> {
-    convert(postcard::from_bytes(&i))
+#[cfg(feature = "wasm")]
+pub fn input<T>(i: &[u8]) -> Result<T, JsError> where T: envelope::Message {
+    format::open_any(i).map_err(throw)
 }
 
+#[cfg(feature = "wasm")]
 #[allow(dead_code)]
 pub fn to_js<T>(o: T) -> Result<JsValue, JsError> where T: serde::Serialize {
     convert(serde_wasm_bindgen::to_value(&o))
 }
 
+#[cfg(feature = "wasm")]
 pub fn from_js<T>(i: JsValue) -> Result<T, JsError> where T: for<'a> serde::de::Deserialize<'a> {
     convert(serde_wasm_bindgen::from_value(i))
 }
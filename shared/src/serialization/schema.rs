@@ -0,0 +1,60 @@
+//! JSON schemas of the primitives that serde encodes with custom helpers.
+//!
+//! The types are only used in `#[schemars(with = "...")]` attributes. They
+//! describe the human-readable encoding: group elements and scalars are
+//! base64url strings without padding, keys and signatures byte arrays.
+
+use schemars::JsonSchema;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{ArrayValidation, InstanceType, Metadata, Schema, SchemaObject, StringValidation};
+
+fn base64url(bytes: u32, description: &str) -> Schema {
+    let length = (4 * bytes).div_ceil(3);
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata { description: Some(description.to_string()), ..Default::default() })),
+        string: Some(Box::new(StringValidation {
+            min_length: Some(length),
+            max_length: Some(length),
+            pattern: Some("^[A-Za-z0-9_-]*$".to_string()),
+        })),
+        ..Default::default()
+    }.into()
+}
+
+fn byte_array(gen: &mut SchemaGenerator, bytes: u32, description: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        metadata: Some(Box::new(Metadata { description: Some(description.to_string()), ..Default::default() })),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(gen.subschema_for::<u8>().into()),
+            min_items: Some(bytes),
+            max_items: Some(bytes),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }.into()
+}
+
+macro_rules! primitive {
+    ($name:ident, |$gen:pat_param| $schema:expr) => {
+        pub struct $name;
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema($gen: &mut SchemaGenerator) -> Schema {
+                $schema
+            }
+        }
+    };
+}
+
+primitive!(Scalar, |_| base64url(32, "BLS12-381 scalar, 32 bytes little-endian"));
+primitive!(G1, |_| base64url(48, "compressed BLS12-381 G1 point, 48 bytes"));
+primitive!(G2, |_| base64url(96, "compressed BLS12-381 G2 point, 96 bytes"));
+primitive!(Gt, |_| base64url(std::mem::size_of::<bls12_381::Gt>() as u32, "BLS12-381 target group element"));
+primitive!(Ed25519Key, |gen| byte_array(gen, 32, "ed25519 key"));
+primitive!(Ed25519Signature, |gen| byte_array(gen, 64, "ed25519 signature"));
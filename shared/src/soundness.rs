@@ -0,0 +1,341 @@
+//! Malicious provers for the participation and payout proofs.
+//!
+//! Every test tries to convince the verifier of a false statement, either by
+//! proving with inputs that do not match the secrets of the prover or by
+//! tampering with the witness of the statement with [`Forged`] before it is
+//! proven. All of them have to be rejected.
+
+use std::marker::PhantomData;
+
+use ff::Field;
+use group::Curve;
+use rand::thread_rng;
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::SeedableRng;
+use serde::Serialize;
+use bls12_381::{G1Affine, Scalar};
+
+use crate::api::{IssuerCore, OrganizerCore, ParticipantCore, PayoutProofData};
+use crate::credential::{init, issue_request, issue, get_credential};
+use crate::error::{Check, PrepamsError};
+use crate::external::util::{as_scalar, rand_scalar};
+use crate::pbss::{self, RerandomizedProofResponse, UnblindedSignature};
+use crate::proofs::generic::{Constraint, GenericProof, Proof, ProofInput, Transcript, Variable, Variables};
+use crate::proofs::participation::{ParticipationProof, ParticipationProofInput, ParticipationProofSecrets};
+use crate::proofs::payout::{PayoutProof, PayoutProofInput, PayoutProofSecrets};
+use crate::types::*;
+use crate::types::credential::{Credential, IssuerPublicKey};
+use crate::types::prerequisites::Qualifier;
+
+/// Changes the witness of a statement.
+trait Tamper<P> {
+    fn tamper(inputs: &P, vars: &mut Variables);
+}
+
+/// Statement `F` whose witness is changed by `T` before it is proven.
+struct Forged<F, T>(PhantomData<(F, T)>);
+
+impl<P, S, A, F, T> Proof<P, S, A> for Forged<F, T>
+where
+    P: ProofInput + Clone,
+    S: Default,
+    A: Serialize + Clone + Default,
+    F: Proof<P, S, A>,
+    T: Tamper<P>,
+{
+    fn get_variables(inputs: &P, secrets: &S, u: &Scalar) -> Variables {
+        let mut vars = F::get_variables(inputs, secrets, u);
+        T::tamper(inputs, &mut vars);
+        vars
+    }
+
+    fn get_constraints(inputs: &P, y: &Scalar) -> Vec<Constraint> {
+        F::get_constraints(inputs, y)
+    }
+
+    fn additional_data(inputs: &P, secrets: &S, transcript: &mut Transcript) -> A {
+        F::additional_data(inputs, secrets, transcript)
+    }
+}
+
+// overwrites the witness of the variable `id`
+fn set(vars: &mut Variables, id: &str, cl: Scalar, cr: Scalar) {
+    let var = match vars.get(id).unwrap().clone() {
+        Variable::Inner { id, G, .. } => Variable::Inner { id, G, cl, cr },
+        Variable::Scratch { id, .. } => Variable::Scratch { id, cl, cr },
+    };
+    vars.add(var);
+}
+
+fn bit(b: bool) -> (Scalar, Scalar) {
+    match b {
+        true => (Scalar::one(), Scalar::zero()),
+        false => (Scalar::zero(), -Scalar::one()),
+    }
+}
+
+fn random_tag() -> G1Affine {
+    (G1Affine::generator() * Scalar::random(thread_rng())).to_affine()
+}
+
+struct Participant {
+    ipk: IssuerPublicKey,
+    cvk: pbss::PublicKey,
+    credential: Credential,
+}
+
+// a participant born in 1985
+fn participant() -> Participant {
+    let mut rng = thread_rng();
+    let (ipk, isk) = init(&mut rng, 2);
+    let (_csk, cvk) = pbss::Gen(&mut rng, 2, 1, "payment");
+
+    let (request, mut credential) = issue_request(&mut rng, &ipk, "user@example.com", vec![Scalar::from(2), Scalar::from(1985)]).unwrap();
+    let response = issue(&mut rng, &ipk, &isk, &request).unwrap();
+    get_credential(&ipk, &response, &mut credential).unwrap();
+
+    Participant { ipk, cvk, credential }
+}
+
+fn study(qualifier: Vec<Qualifier>, disqualifier: Vec<Qualifier>, constraints: Vec<AttributeConstraint>) -> Resource {
+    Resource {
+        id: rand_scalar(),
+        name: "name".to_string(),
+        summary: "summary".to_string(),
+        description: "description".to_string(),
+        duration: "duration".to_string(),
+        reward: 2,
+        webBased: false,
+        studyUrl: None,
+        qualifier,
+        disqualifier,
+        constraints
+    }
+}
+
+fn participate<F: Proof<ParticipationProofInput, ParticipationProofSecrets, ()>>(inputs: ParticipationProofInput, secrets: ParticipationProofSecrets) -> Result<(), ProofError> {
+    let proof = GenericProof::<ParticipationProofInput, ()>::proove::<ParticipationProofSecrets, F>(&mut Transcript::new(b"participation"), inputs, secrets)?;
+    proof.verify::<ParticipationProofSecrets, ParticipationProof>(&mut Transcript::new(b"participation"))
+}
+
+fn payout<F: Proof<PayoutProofInput, PayoutProofSecrets, RerandomizedProofResponse>>(inputs: PayoutProofInput, secrets: PayoutProofSecrets) -> Result<(), ProofError> {
+    let proof = GenericProof::<PayoutProofInput, RerandomizedProofResponse>::proove::<PayoutProofSecrets, F>(&mut Transcript::new(b"payout"), inputs, secrets)?;
+    proof.verify::<PayoutProofSecrets, PayoutProof>(&mut Transcript::new(b"payout"))
+}
+
+#[test]
+fn honest() {
+    let p = participant();
+    let qid = rand_scalar();
+    let resource = study(
+        vec![Qualifier { id: qid, tags: vec![random_tag(), p.credential.derive_tag(&qid).unwrap()] }],
+        vec![Qualifier { id: rand_scalar(), tags: vec![random_tag()] }],
+        vec![AttributeConstraint::Range(1, 1980, 1990), AttributeConstraint::Element(0, vec![1, 2])]
+    );
+
+    let (inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    assert_eq!(participate::<ParticipationProof>(inputs, secrets), Ok(()));
+}
+
+// the difference to the lower bound is -5 and decomposed as -5 * 2^0
+struct NegativeDifference {}
+
+impl Tamper<ParticipationProofInput> for NegativeDifference {
+    fn tamper(_: &ParticipationProofInput, vars: &mut Variables) {
+        set(vars, "cstr_0_0_1", -Scalar::from(5), Scalar::zero());
+        for j in 1..5 {
+            let (cl, cr) = bit(false);
+            set(vars, &format!("cstr_0_{}_1", j), cl, cr);
+        }
+    }
+}
+
+#[test]
+fn range_outside_bounds() {
+    let p = participant();
+    let resource = study(vec![], vec![], vec![AttributeConstraint::Range(1, 1990, 2000)]);
+
+    let (inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    assert!(participate::<ParticipationProof>(inputs, secrets).is_err());
+
+    let (inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    assert!(participate::<Forged<ParticipationProof, NegativeDifference>>(inputs, secrets).is_err());
+}
+
+// selects the first tag of the qualifier
+struct SelectFirst {}
+
+impl Tamper<ParticipationProofInput> for SelectFirst {
+    fn tamper(inputs: &ParticipationProofInput, vars: &mut Variables) {
+        for j in 0..inputs.qualifiers[0].tags.len() {
+            let (cl, cr) = bit(j == 0);
+            set(vars, &format!("qtag_0_{}", j), cl, cr);
+        }
+    }
+}
+
+#[test]
+fn foreign_qualifier_tag() {
+    let p = participant();
+    let other = participant();
+    let qid = rand_scalar();
+    let resource = study(vec![Qualifier { id: qid, tags: vec![other.credential.derive_tag(&qid).unwrap(), random_tag()] }], vec![], vec![]);
+
+    let (inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    assert!(participate::<ParticipationProof>(inputs, secrets).is_err());
+
+    let (inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    assert!(participate::<Forged<ParticipationProof, SelectFirst>>(inputs, secrets).is_err());
+}
+
+#[test]
+fn disqualified() {
+    let p = participant();
+    let did = rand_scalar();
+    let resource = study(vec![], vec![Qualifier { id: did, tags: vec![random_tag(), p.credential.derive_tag(&did).unwrap()] }], vec![]);
+
+    let (inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    assert_eq!(participate::<ParticipationProof>(inputs, secrets), Err(ProofError::VerificationError(Check::Disqualifier)));
+
+    // hide the own tag among the randomized tags of the disqualifier
+    let (mut inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    let randomized = &mut inputs.randomized_disqualifiers[0];
+    let own = randomized.tags.iter().position(|tag| tag == &randomized.randomized_tag).unwrap();
+    randomized.tags[own] = random_tag();
+    assert!(participate::<ParticipationProof>(inputs, secrets).is_err());
+}
+
+#[test]
+fn swapped_reward_alpha() {
+    let p = participant();
+    let other = participant();
+    let resource = study(vec![], vec![], vec![]);
+
+    // redirects the reward to the identity of another participant
+    let (mut inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    let (redirected, _) = ParticipationProofInput::new(&other.ipk, &p.cvk, &other.credential, &resource).unwrap();
+    inputs.reward_request.alpha = redirected.reward_request.alpha;
+    assert!(participate::<ParticipationProof>(inputs, secrets).is_err());
+
+    // requests the reward of another study
+    let (mut inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    let (reused, _) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &study(vec![], vec![], vec![])).unwrap();
+    inputs.reward_request.alpha = reused.reward_request.alpha;
+    assert!(participate::<ParticipationProof>(inputs, secrets).is_err());
+}
+
+#[test]
+fn mismatched_token() {
+    let p = participant();
+    let resource = study(vec![], vec![], vec![]);
+
+    // authenticated for another study
+    let (mut inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    let (other, _) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &study(vec![], vec![], vec![])).unwrap();
+    inputs.auth_request = other.auth_request;
+    assert_eq!(participate::<ParticipationProof>(inputs, secrets), Err(ProofError::VerificationError(Check::Tag)));
+
+    // claims the tag of the other study
+    let (mut inputs, secrets) = ParticipationProofInput::new(&p.ipk, &p.cvk, &p.credential, &resource).unwrap();
+    inputs.tag = other.tag;
+    assert!(participate::<ParticipationProof>(inputs, secrets).is_err());
+}
+
+// coins worth `values` padded with null coins
+fn coins(values: &[u8], value: u8) -> (PayoutProofInput, PayoutProofSecrets) {
+    let mut rng = thread_rng();
+    let (csk, cvk) = pbss::Gen(&mut rng, 2, 1, "payment");
+    let p = participant();
+
+    let spend: Vec<UnblindedSignature> = values.iter().map(|v| {
+        let mut prng = p.credential.derive_reward_rng(&rand_scalar());
+        let s = vec![Scalar::random(&mut prng), p.credential.identity];
+        let d = Scalar::random(&mut prng);
+        let m = vec![as_scalar(*v as u32)];
+        let request = pbss::Blind(&cvk, &m, &s, &d, &mut prng).unwrap();
+        let signature = pbss::Sign(&cvk, &csk, &request, &mut rng).unwrap();
+        pbss::Unblind(&cvk, &signature, &m, &s, &d).unwrap()
+    }).collect();
+
+    let nulls = NullRequest::new(&cvk, &p.credential).unwrap();
+    let m = vec![Scalar::zero()];
+    let nulls: Vec<UnblindedSignature> = nulls.request.iter().enumerate().map(|(i, request)| {
+        let signature = pbss::Sign(&cvk, &csk, request, &mut rng).unwrap();
+        pbss::Unblind(&cvk, &signature, &m, &vec![nulls.s[2 * i], nulls.s[2 * i + 1]], &nulls.d[i]).unwrap()
+    }).collect();
+
+    PayoutProofInput::new(&p.ipk, &cvk, value, "test", "user@example.com", spend, nulls)
+}
+
+// the remainder of 35 - 40 is decomposed as -5 * 2^0
+struct NegativeRemainder {}
+
+impl Tamper<PayoutProofInput> for NegativeRemainder {
+    fn tamper(_: &PayoutProofInput, vars: &mut Variables) {
+        set(vars, "bit_0", -Scalar::from(5), Scalar::zero());
+        for i in 1..8 {
+            let (cl, cr) = bit(false);
+            set(vars, &format!("bit_{}", i), cl, cr);
+        }
+    }
+}
+
+#[test]
+fn inflated_value() {
+    let (inputs, secrets) = coins(&[10, 25], 35);
+    assert_eq!(payout::<PayoutProof>(inputs, secrets), Ok(()));
+
+    // an honest prover refuses a value above the sum of the coins
+    let (inputs, secrets) = coins(&[10, 25], 40);
+    assert_eq!(payout::<PayoutProof>(inputs, secrets), Err(ProofError::InvalidError));
+
+    // and a remainder that does not fit into the range proof
+    let (inputs, secrets) = coins(&[200, 100], 10);
+    assert_eq!(payout::<PayoutProof>(inputs, secrets), Err(ProofError::InvalidError));
+
+    let (inputs, secrets) = coins(&[10, 25], 40);
+    assert!(payout::<Forged<PayoutProof, NegativeRemainder>>(inputs, secrets).is_err());
+}
+
+#[test]
+fn reused_nullifier() {
+    // claims the nullifier of another coin of the payout
+    let (mut inputs, secrets) = coins(&[10, 25], 35);
+    inputs.nullifier[0] = inputs.nullifier[1];
+    assert!(payout::<PayoutProof>(inputs, secrets).is_err());
+
+    let seed = |n: u8| ChaCha20Rng::from_seed([n; 32]);
+    let mut issuer = IssuerCore::new(1, seed(0));
+    let organizer = OrganizerCore::new("lab", issuer.public_key().clone(), seed(1));
+    let mut participant = ParticipantCore::new("alice", &[30], issuer.ledger_verification_key());
+    let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), seed(2)).unwrap();
+    participant.retrieve_credential(&issuer.issue_credential(&request).unwrap()).unwrap();
+
+    let resource = Resource { reward: 2, ..Resource::random(seed(3)) };
+    let confirmed = organizer.confirm_participation(&participant.participate(&resource).unwrap(), "p1".to_string()).unwrap();
+    issuer.issue_reward(&confirmed, &organizer.public_key(), 2).unwrap();
+    let ledger = issuer.ledger().unwrap();
+
+    // spends the same coin twice in one payout, which the proof alone accepts
+    let credential = participant.credential().unwrap();
+    let tx = ledger.entries.iter().find_map(|e| e.transaction.as_ref()).unwrap();
+    let mut rng = credential.derive_reward_rng(&tx.participation.study);
+    let (s, d) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+    let coin = pbss::Unblind(issuer.credit_verification_key(), &tx.coin, &vec![as_scalar(2)], &vec![s, credential.identity], &d).unwrap();
+    let (inputs, secrets) = PayoutProofInput::new(issuer.public_key(), issuer.credit_verification_key(), 4, "bank", "alice", vec![coin.clone(), coin], nulls(&issuer, &participant));
+    let proof = PayoutProofData::proove::<PayoutProofSecrets, PayoutProof>(&mut Transcript::new(b"payout"), inputs, secrets).unwrap();
+    assert_eq!(proof.verify::<PayoutProofSecrets, PayoutProof>(&mut Transcript::new(b"payout")), Ok(()));
+    assert!(matches!(issuer.check_payout_request(&proof), Err(PrepamsError::Rejected(reason)) if reason == "coin already spent"));
+
+    // spends the same coin in two payouts against the same ledger
+    let first = participant.request_payout(2, "bank", "alice", nulls(&issuer, &participant), &ledger).unwrap();
+    let second = participant.request_payout(2, "bank", "alice", nulls(&issuer, &participant), &ledger).unwrap();
+    issuer.check_payout_request(&first.proof).unwrap();
+    assert!(matches!(issuer.check_payout_request(&second.proof), Err(PrepamsError::Rejected(reason)) if reason == "coin already spent"));
+}
+
+fn nulls(issuer: &IssuerCore, participant: &ParticipantCore) -> Vec<UnblindedSignature> {
+    let request = participant.request_nulls().unwrap();
+    let coins = issuer.issue_nulls(request.requests()).unwrap();
+    request.unblind_nulls(coins).unwrap()
+}
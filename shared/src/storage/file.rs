@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use bls12_381::{G1Affine, Scalar};
+use ed25519_zebra::Signature;
+use sha2::{Digest, Sha256};
+
+use crate::types::LedgerEntry;
+use super::{ChainIndex, EntryIter, LedgerStore, StorageError};
+
+const MAGIC: &[u8; 8] = b"PPMSLDG1";
+
+/// Append-only ledger file.
+///
+/// Each entry is stored as a record of its length (u32, big endian) and the
+/// checksum of the length, the postcard encoded entry and the checksum of the
+/// entry, each checksum being the first four bytes of the SHA-256 hash. A
+/// final record that was only partially written before a crash runs past the
+/// end of the file and is cut off when the file is opened again, a damaged
+/// record followed by further records is reported as corrupted.
+pub struct FileStore {
+    path: PathBuf,
+    file: File,
+    offsets: Vec<u64>,
+    // position of every entry by its signature
+    signatures: HashMap<[u8; 64], u64>,
+    index: ChainIndex,
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(data);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// length and checksums of a record around the encoded entry
+const OVERHEAD: u64 = 12;
+
+// reads the next complete record of a file with `remaining` bytes left,
+// returns None at the end of the file or at a torn final record
+fn read_record(reader: &mut impl Read, remaining: u64) -> Result<Option<Vec<u8>>, StorageError> {
+    if remaining < 8 {
+        return Ok(None);
+    }
+
+    let mut len = [0u8; 4];
+    let mut check = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    reader.read_exact(&mut check)?;
+    if checksum(&len) != check {
+        return Err(StorageError::Corrupted("record length checksum mismatch".to_string()));
+    }
+
+    // only a record that was cut off while appending runs past the end of the file
+    let size = OVERHEAD + u32::from_be_bytes(len) as u64;
+    if size > remaining {
+        return Ok(None);
+    }
+
+    let mut data = vec![0u8; (size - OVERHEAD) as usize];
+    reader.read_exact(&mut data)?;
+    reader.read_exact(&mut check)?;
+
+    if checksum(&data) != check {
+        return match size < remaining {
+            true => Err(StorageError::Corrupted("record checksum mismatch".to_string())),
+            false => Ok(None)
+        };
+    }
+
+    Ok(Some(data))
+}
+
+impl FileStore {
+    /// Opens or creates a ledger file and recovers from an interrupted append.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FileStore, StorageError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(&path)?;
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+            file.sync_all()?;
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(&file);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(StorageError::Corrupted("not a ledger file".to_string()));
+        }
+
+        let len = file.metadata()?.len();
+        let mut offset = MAGIC.len() as u64;
+        let mut offsets = vec![];
+        let mut signatures = HashMap::new();
+        let mut index = ChainIndex::default();
+
+        while let Some(data) = read_record(&mut reader, len - offset)? {
+            let entry: LedgerEntry = postcard::from_bytes(&data)?;
+            index.apply(&entry).map_err(|_| StorageError::Corrupted(format!("entry {} breaks the chain", offsets.len())))?;
+            signatures.insert(entry.signature.into(), offsets.len() as u64);
+            offsets.push(offset);
+            offset += OVERHEAD + data.len() as u64;
+        }
+        drop(reader);
+
+        // drop a partially written record
+        if len > offset {
+            file.set_len(offset)?;
+            file.sync_all()?;
+        }
+
+        Ok(FileStore { path, file, offsets, signatures, index })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl LedgerStore for FileStore {
+    fn head(&self) -> Signature {
+        self.index.head
+    }
+
+    fn len(&self) -> u64 {
+        self.offsets.len() as u64
+    }
+
+    fn append(&mut self, entry: &LedgerEntry) -> Result<(), StorageError> {
+        self.index.check(entry)?;
+
+        let data = postcard::to_stdvec(entry)?;
+        let len = (data.len() as u32).to_be_bytes();
+        let mut record = len.to_vec();
+        record.extend_from_slice(&checksum(&len));
+        record.extend_from_slice(&data);
+        record.extend_from_slice(&checksum(&data));
+
+        let offset = self.file.metadata()?.len();
+        if let Err(e) = self.file.write_all(&record).and_then(|_| self.file.sync_data()) {
+            self.file.set_len(offset)?;
+            return Err(e.into());
+        }
+
+        self.index.apply(entry)?;
+        self.signatures.insert(entry.signature.into(), self.offsets.len() as u64);
+        self.offsets.push(offset);
+
+        Ok(())
+    }
+
+    fn get(&self, index: u64) -> Result<Option<LedgerEntry>, StorageError> {
+        match self.offsets.get(index as usize) {
+            None => Ok(None),
+            Some(offset) => {
+                let mut file = File::open(&self.path)?;
+                let len = file.metadata()?.len();
+                file.seek(SeekFrom::Start(*offset))?;
+                match read_record(&mut file, len.saturating_sub(*offset))? {
+                    Some(data) => Ok(Some(postcard::from_bytes(&data)?)),
+                    None => Err(StorageError::Corrupted(format!("entry {} is not readable", index)))
+                }
+            }
+        }
+    }
+
+    fn range(&self, from: u64, to: u64) -> EntryIter<'_> {
+        let to = to.min(self.len());
+        let from = from.min(to);
+        let mut remaining = to - from;
+        let mut offset = self.offsets.get(from as usize).cloned().unwrap_or(0);
+        let reader = File::open(&self.path).and_then(|mut f| {
+            let len = f.metadata()?.len();
+            f.seek(SeekFrom::Start(offset))?;
+            Ok((BufReader::new(f), len))
+        });
+
+        match reader {
+            Err(e) => Box::new(std::iter::once(Err(e.into()))),
+            Ok((mut reader, len)) => Box::new(std::iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+                remaining -= 1;
+
+                Some(match read_record(&mut reader, len.saturating_sub(offset)) {
+                    Ok(Some(data)) => {
+                        offset += OVERHEAD + data.len() as u64;
+                        postcard::from_bytes(&data).map_err(StorageError::from)
+                    },
+                    Ok(None) => Err(StorageError::Corrupted("ledger file was truncated".to_string())),
+                    Err(e) => Err(e)
+                })
+            }))
+        }
+    }
+
+    fn find(&self, signature: &Signature) -> Result<Option<u64>, StorageError> {
+        Ok(self.signatures.get(&<[u8; 64]>::from(signature)).copied())
+    }
+
+    fn timestamp(&self) -> Result<u64, StorageError> {
+        Ok(self.index.time)
+    }
+
+    fn contains_tag(&self, tag: &G1Affine) -> Result<bool, StorageError> {
+        Ok(self.index.contains_tag(tag))
+    }
+
+    fn contains_nullifier(&self, nullifier: &Scalar) -> Result<bool, StorageError> {
+        Ok(self.index.contains_nullifier(nullifier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_zebra::SigningKey;
+    use crate::storage::tests::{chain, check_store};
+
+    #[test]
+    fn store() {
+        let dir = tempfile::tempdir().unwrap();
+        check_store(&mut FileStore::open(dir.path().join("ledger")).unwrap());
+    }
+
+    #[test]
+    fn recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger");
+        let entries = chain(&SigningKey::new(rand::thread_rng()), 3);
+
+        let mut store = FileStore::open(&path).unwrap();
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        drop(store);
+
+        // simulate a crash in the middle of writing the last record
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 10).unwrap();
+
+        let mut store = FileStore::open(&path).unwrap();
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.head(), entries[1].signature);
+
+        store.append(&entries[2]).unwrap();
+        drop(store);
+
+        let store = FileStore::open(&path).unwrap();
+        let streamed: Vec<LedgerEntry> = store.entries().map(|e| e.unwrap()).collect();
+        assert_eq!(streamed, entries);
+        assert_eq!(store.find(&entries[2].signature).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn torn_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger");
+        let entries = chain(&SigningKey::new(rand::thread_rng()), 2);
+
+        let mut store = FileStore::open(&path).unwrap();
+        store.append(&entries[0]).unwrap();
+        drop(store);
+
+        // a torn record whose length claims far more than the file holds
+        let len = std::fs::metadata(&path).unwrap().len();
+        let huge = [0xff, 0xff, 0xff, 0xff];
+        let mut record = huge.to_vec();
+        record.extend_from_slice(&checksum(&huge));
+        record.extend_from_slice(&[1, 2]);
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(&record).unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+    }
+
+    #[test]
+    fn corrupted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger");
+        let entries = chain(&SigningKey::new(rand::thread_rng()), 3);
+
+        let mut store = FileStore::open(&path).unwrap();
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        let offset = store.offsets[1];
+        drop(store);
+
+        // flip a byte of the second record, the third one has to survive
+        let mut data = std::fs::read(&path).unwrap();
+        data[offset as usize + 10] ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+
+        assert!(matches!(FileStore::open(&path), Err(StorageError::Corrupted(_))));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+    }
+
+    #[test]
+    fn corrupted_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger");
+        let entries = chain(&SigningKey::new(rand::thread_rng()), 3);
+
+        let mut store = FileStore::open(&path).unwrap();
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        let offset = store.offsets[1] as usize;
+        drop(store);
+
+        // a length of the second record that runs past the end of the file
+        // must not be mistaken for a torn record and cut off the third one
+        let mut data = std::fs::read(&path).unwrap();
+        data[offset..offset + 4].copy_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        std::fs::write(&path, &data).unwrap();
+
+        assert!(matches!(FileStore::open(&path), Err(StorageError::Corrupted(_))));
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt;
+use std::error::Error;
+use std::collections::HashSet;
+
+use bls12_381::{G1Affine, Scalar};
+use ed25519_zebra::Signature;
+
+use crate::types::{Ledger, LedgerEntry};
+
+#[cfg(not(target_family = "wasm"))]
+pub mod file;
+#[cfg(not(target_family = "wasm"))]
+pub mod sqlite;
+
+#[cfg(not(target_family = "wasm"))]
+pub use file::FileStore;
+#[cfg(not(target_family = "wasm"))]
+pub use sqlite::SqliteStore;
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// This error occurs when the underlying file or database could not be accessed.
+    Io(String),
+
+    /// This error occurs when a stored entry could not be encoded or decoded.
+    Encoding(String),
+
+    /// This error occurs when an appended entry does not extend the current head.
+    Chain,
+
+    /// This error occurs when an appended entry reuses a coin tag or nullifier.
+    Duplicate,
+
+    /// This error occurs when the store content was modified outside of the append path.
+    Corrupted(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "storage not accessible: {}", e),
+            StorageError::Encoding(e) => write!(f, "ledger entry could not be encoded: {}", e),
+            StorageError::Chain => write!(f, "ledger entry does not extend the current head"),
+            StorageError::Duplicate => write!(f, "ledger entry reuses a coin tag or nullifier"),
+            StorageError::Corrupted(e) => write!(f, "ledger store is corrupted: {}", e),
+        }
+    }
+}
+
+impl Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+    fn from(e: std::io::Error) -> Self {
+        StorageError::Io(e.to_string())
+    }
+}
+
+impl From<postcard::Error> for StorageError {
+    fn from(e: postcard::Error) -> Self {
+        StorageError::Encoding(e.to_string())
+    }
+}
+
+pub type EntryIter<'a> = Box<dyn Iterator<Item = Result<LedgerEntry, StorageError>> + 'a>;
+
+/// Append-only storage of the signed ledger chain.
+///
+/// Implementations index the coin tags of transactions and the nullifiers of
+/// payouts, so that double rewards and double spending can be detected without
+/// scanning the whole ledger. Appending an entry that reuses a tag or nullifier
+/// fails with [`StorageError::Duplicate`].
+pub trait LedgerStore {
+    /// Signature of the latest entry, or the all-zero genesis signature.
+    fn head(&self) -> Signature;
+
+    /// Number of entries in the store.
+    fn len(&self) -> u64;
+
+    /// Persists an entry. The entry has to reference the current head and
+    /// must not reuse a tag or nullifier.
+    fn append(&mut self, entry: &LedgerEntry) -> Result<(), StorageError>;
+
+    /// Reads a single entry by its position in the chain.
+    fn get(&self, index: u64) -> Result<Option<LedgerEntry>, StorageError>;
+
+    /// Streams the entries at positions `from..to` in chain order.
+    fn range(&self, from: u64, to: u64) -> EntryIter<'_>;
+
+    /// Streams all entries in chain order without loading them at once.
+    fn entries(&self) -> EntryIter<'_> {
+        self.range(0, self.len())
+    }
+
+    /// Looks up the position of the entry with the given signature.
+    fn find(&self, signature: &Signature) -> Result<Option<u64>, StorageError> {
+        for (i, entry) in self.entries().enumerate() {
+            if &entry?.signature == signature {
+                return Ok(Some(i as u64));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Timestamp of the latest entry, or zero for an empty store.
+    fn timestamp(&self) -> Result<u64, StorageError> {
+        match self.len() {
+            0 => Ok(0),
+            n => Ok(self.get(n - 1)?.map_or(0, |entry| entry.timestamp))
+        }
+    }
+
+    fn contains_tag(&self, tag: &G1Affine) -> Result<bool, StorageError>;
+
+    fn contains_nullifier(&self, nullifier: &Scalar) -> Result<bool, StorageError>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Collects the whole store into the serializable ledger representation.
+    fn export(&self) -> Result<Ledger, StorageError> {
+        let entries = self.entries().collect::<Result<Vec<LedgerEntry>, StorageError>>()?;
+        Ok(Ledger { head: self.head(), entries })
+    }
+}
+
+pub(crate) fn genesis() -> Signature {
+    [0; 64].into()
+}
+
+/// In-memory view of the chain head and of all spent tags and nullifiers.
+pub(crate) struct ChainIndex {
+    pub(crate) head: Signature,
+    pub(crate) time: u64,
+    tags: HashSet<[u8; 48]>,
+    nullifiers: HashSet<[u8; 32]>,
+}
+
+impl Default for ChainIndex {
+    fn default() -> Self {
+        ChainIndex { head: genesis(), time: 0, tags: HashSet::new(), nullifiers: HashSet::new() }
+    }
+}
+
+// checks that a payout does not spend the same nullifier twice
+pub(crate) fn distinct(nullifiers: &[Scalar]) -> Result<(), StorageError> {
+    let mut seen = HashSet::new();
+    match nullifiers.iter().all(|nullifier| seen.insert(nullifier.to_bytes())) {
+        true => Ok(()),
+        false => Err(StorageError::Duplicate)
+    }
+}
+
+impl ChainIndex {
+    // checks that an entry can be appended without applying it
+    pub(crate) fn check(&self, entry: &LedgerEntry) -> Result<(), StorageError> {
+        if entry.previous != self.head {
+            return Err(StorageError::Chain);
+        }
+
+        distinct(entry.nullifiers())?;
+        if entry.tag().is_some_and(|tag| self.contains_tag(tag)) || entry.nullifiers().iter().any(|n| self.contains_nullifier(n)) {
+            return Err(StorageError::Duplicate);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn apply(&mut self, entry: &LedgerEntry) -> Result<(), StorageError> {
+        self.check(entry)?;
+
+        if let Some(tag) = entry.tag() {
+            self.tags.insert(tag.to_compressed());
+        }
+        for nullifier in entry.nullifiers() {
+            self.nullifiers.insert(nullifier.to_bytes());
+        }
+        self.head = entry.signature;
+        self.time = entry.timestamp;
+
+        Ok(())
+    }
+
+    pub(crate) fn contains_tag(&self, tag: &G1Affine) -> bool {
+        self.tags.contains(&tag.to_compressed())
+    }
+
+    pub(crate) fn contains_nullifier(&self, nullifier: &Scalar) -> bool {
+        self.nullifiers.contains(&nullifier.to_bytes())
+    }
+}
+
+/// Volatile store used when the issuer is not backed by persistent storage.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Vec<LedgerEntry>,
+    index: ChainIndex,
+}
+
+impl TryFrom<Ledger> for MemoryStore {
+    type Error = StorageError;
+
+    fn try_from(ledger: Ledger) -> Result<Self, StorageError> {
+        let mut store = MemoryStore::default();
+        for entry in &ledger.entries {
+            store.index.apply(entry)?;
+        }
+        if store.index.head != ledger.head {
+            return Err(StorageError::Chain);
+        }
+        store.entries = ledger.entries;
+
+        Ok(store)
+    }
+}
+
+impl LedgerStore for MemoryStore {
+    fn head(&self) -> Signature {
+        self.index.head
+    }
+
+    fn len(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    fn append(&mut self, entry: &LedgerEntry) -> Result<(), StorageError> {
+        self.index.apply(entry)?;
+        self.entries.push(entry.clone());
+        Ok(())
+    }
+
+    fn get(&self, index: u64) -> Result<Option<LedgerEntry>, StorageError> {
+        Ok(self.entries.get(index as usize).cloned())
+    }
+
+    fn range(&self, from: u64, to: u64) -> EntryIter<'_> {
+        let to = (to as usize).min(self.entries.len());
+        let from = (from as usize).min(to);
+        Box::new(self.entries[from..to].iter().cloned().map(Ok))
+    }
+
+    fn find(&self, signature: &Signature) -> Result<Option<u64>, StorageError> {
+        Ok(self.entries.iter().position(|e| &e.signature == signature).map(|i| i as u64))
+    }
+
+    fn timestamp(&self) -> Result<u64, StorageError> {
+        Ok(self.index.time)
+    }
+
+    fn contains_tag(&self, tag: &G1Affine) -> Result<bool, StorageError> {
+        Ok(self.index.contains_tag(tag))
+    }
+
+    fn contains_nullifier(&self, nullifier: &Scalar) -> Result<bool, StorageError> {
+        Ok(self.index.contains_nullifier(nullifier))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ed25519_zebra::SigningKey;
+    use crate::types::Payout;
+    use crate::external::util::rand_scalar;
+
+    // builds a signed chain of cheap payout entries
+    pub(crate) fn chain(key: &SigningKey, n: usize) -> Vec<LedgerEntry> {
+        let mut ledger = Ledger::default();
+        for i in 0..n {
+            let payout = Payout { nullifier: vec![rand_scalar(), rand_scalar()], recipient: [i as u8; 32], value: i as u8 };
+            ledger.appendPayout(key, payout).ok().unwrap();
+        }
+        ledger.entries
+    }
+
+    pub(crate) fn check_store<S: LedgerStore>(store: &mut S) {
+        let key = SigningKey::new(rand::thread_rng());
+        let entries = chain(&key, 5);
+
+        assert!(store.is_empty());
+        assert_eq!(store.head(), genesis());
+
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        assert!(matches!(store.append(&entries[2]), Err(StorageError::Chain)));
+
+        // nullifiers spent before or twice in the same payout are refused
+        let reuse = |nullifier: Vec<Scalar>| {
+            let mut ledger = Ledger { head: entries[4].signature, entries: entries.clone() };
+            ledger.appendPayout(&key, Payout { nullifier, recipient: [9; 32], value: 1 }).ok().unwrap()
+        };
+        let fresh = rand_scalar();
+        assert!(matches!(store.append(&reuse(vec![fresh, entries[1].nullifiers()[1]])), Err(StorageError::Duplicate)));
+        assert!(matches!(store.append(&reuse(vec![fresh, fresh])), Err(StorageError::Duplicate)));
+        assert!(!store.contains_nullifier(&fresh).unwrap());
+
+        assert_eq!(store.len(), 5);
+        assert_eq!(store.head(), entries[4].signature);
+        assert_eq!(store.get(3).unwrap().as_ref(), Some(&entries[3]));
+        assert_eq!(store.get(5).unwrap(), None);
+        assert!(store.contains_nullifier(&entries[1].nullifiers()[1]).unwrap());
+        assert!(!store.contains_nullifier(&rand_scalar()).unwrap());
+
+        let streamed: Vec<LedgerEntry> = store.entries().map(|e| e.unwrap()).collect();
+        assert_eq!(streamed, entries);
+
+        let streamed: Vec<LedgerEntry> = store.range(1, 3).map(|e| e.unwrap()).collect();
+        assert_eq!(streamed, entries[1..3]);
+        assert_eq!(store.range(4, 9).count(), 1);
+        assert_eq!(store.range(7, 9).count(), 0);
+
+        assert_eq!(store.find(&entries[3].signature).unwrap(), Some(3));
+        assert_eq!(store.find(&genesis()).unwrap(), None);
+    }
+
+    #[test]
+    fn memory() {
+        check_store(&mut MemoryStore::default());
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use bls12_381::{G1Affine, Scalar};
+use ed25519_zebra::Signature;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::types::LedgerEntry;
+use super::{distinct, genesis, EntryIter, LedgerStore, StorageError};
+
+// number of entries fetched per query while streaming
+const PAGE: u64 = 256;
+
+/// Ledger store in an embedded SQLite database.
+///
+/// Entries, tags and nullifiers of an append are written in a single
+/// transaction, so a crash either persists the whole entry or nothing.
+pub struct SqliteStore {
+    db: Connection,
+    head: Signature,
+    len: u64,
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        StorageError::Io(e.to_string())
+    }
+}
+
+impl SqliteStore {
+    /// Opens or creates the ledger tables in the given database file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore, StorageError> {
+        SqliteStore::from_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<SqliteStore, StorageError> {
+        SqliteStore::from_connection(Connection::open_in_memory()?)
+    }
+
+    pub fn from_connection(db: Connection) -> Result<SqliteStore, StorageError> {
+        db.execute_batch("
+            CREATE TABLE IF NOT EXISTS ledger_entries (
+                idx INTEGER PRIMARY KEY,
+                entry BLOB NOT NULL,
+                signature BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS ledger_tags (
+                tag BLOB PRIMARY KEY,
+                idx INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS ledger_nullifiers (
+                nullifier BLOB PRIMARY KEY,
+                idx INTEGER NOT NULL
+            );
+            CREATE UNIQUE INDEX IF NOT EXISTS ledger_signatures ON ledger_entries (signature);
+        ")?;
+
+        let last: Option<(u64, Vec<u8>)> = db.query_row(
+            "SELECT idx, signature FROM ledger_entries ORDER BY idx DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).optional()?;
+
+        let (head, len) = match last {
+            None => (genesis(), 0),
+            Some((idx, signature)) => {
+                let signature: [u8; 64] = signature.try_into()
+                    .map_err(|_| StorageError::Corrupted("invalid head signature".to_string()))?;
+                (signature.into(), idx + 1)
+            }
+        };
+
+        Ok(SqliteStore { db, head, len })
+    }
+
+    fn page(&self, from: u64) -> Result<Vec<LedgerEntry>, StorageError> {
+        let mut stmt = self.db.prepare_cached("SELECT entry FROM ledger_entries WHERE idx >= ?1 ORDER BY idx ASC LIMIT ?2")?;
+        let rows = stmt.query_map(params![from, PAGE], |row| row.get::<_, Vec<u8>>(0))?;
+
+        let mut entries = vec![];
+        for data in rows {
+            entries.push(postcard::from_bytes(&data?)?);
+        }
+        Ok(entries)
+    }
+}
+
+impl LedgerStore for SqliteStore {
+    fn head(&self) -> Signature {
+        self.head
+    }
+
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn append(&mut self, entry: &LedgerEntry) -> Result<(), StorageError> {
+        if entry.previous != self.head {
+            return Err(StorageError::Chain);
+        }
+
+        // checked up front, so that a duplicate is not reported as a failed insert
+        distinct(entry.nullifiers())?;
+        if let Some(tag) = entry.tag() {
+            if self.contains_tag(tag)? {
+                return Err(StorageError::Duplicate);
+            }
+        }
+        for nullifier in entry.nullifiers() {
+            if self.contains_nullifier(nullifier)? {
+                return Err(StorageError::Duplicate);
+            }
+        }
+
+        let data = postcard::to_stdvec(entry)?;
+        let signature: [u8; 64] = entry.signature.into();
+
+        let tx = self.db.transaction()?;
+        tx.execute("INSERT INTO ledger_entries (idx, entry, signature) VALUES (?1, ?2, ?3)", params![self.len, data, &signature[..]])?;
+        if let Some(tag) = entry.tag() {
+            tx.execute("INSERT INTO ledger_tags (tag, idx) VALUES (?1, ?2)", params![&tag.to_compressed()[..], self.len])?;
+        }
+        for nullifier in entry.nullifiers() {
+            tx.execute("INSERT INTO ledger_nullifiers (nullifier, idx) VALUES (?1, ?2)", params![&nullifier.to_bytes()[..], self.len])?;
+        }
+        tx.commit()?;
+
+        self.head = entry.signature;
+        self.len += 1;
+
+        Ok(())
+    }
+
+    fn get(&self, index: u64) -> Result<Option<LedgerEntry>, StorageError> {
+        let data: Option<Vec<u8>> = self.db.query_row(
+            "SELECT entry FROM ledger_entries WHERE idx = ?1",
+            params![index],
+            |row| row.get(0)
+        ).optional()?;
+
+        match data {
+            None => Ok(None),
+            Some(data) => Ok(Some(postcard::from_bytes(&data)?))
+        }
+    }
+
+    fn range(&self, from: u64, to: u64) -> EntryIter<'_> {
+        let to = to.min(self.len);
+        let mut next = from;
+        let mut page = vec![].into_iter();
+
+        Box::new(std::iter::from_fn(move || {
+            if next >= to {
+                return None;
+            }
+
+            if page.len() == 0 {
+                match self.page(next) {
+                    Ok(entries) if !entries.is_empty() => page = entries.into_iter(),
+                    Ok(_) => {
+                        let missing = next;
+                        next = to;
+                        return Some(Err(StorageError::Corrupted(format!("entry {} is missing", missing))));
+                    },
+                    Err(e) => {
+                        next = to;
+                        return Some(Err(e));
+                    }
+                }
+            }
+
+            next += 1;
+            page.next().map(Ok)
+        }))
+    }
+
+    fn find(&self, signature: &Signature) -> Result<Option<u64>, StorageError> {
+        let signature: [u8; 64] = signature.into();
+        Ok(self.db.query_row(
+            "SELECT idx FROM ledger_entries WHERE signature = ?1",
+            params![&signature[..]],
+            |row| row.get(0)
+        ).optional()?)
+    }
+
+    fn contains_tag(&self, tag: &G1Affine) -> Result<bool, StorageError> {
+        let found: Option<u64> = self.db.query_row(
+            "SELECT idx FROM ledger_tags WHERE tag = ?1",
+            params![&tag.to_compressed()[..]],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(found.is_some())
+    }
+
+    fn contains_nullifier(&self, nullifier: &Scalar) -> Result<bool, StorageError> {
+        let found: Option<u64> = self.db.query_row(
+            "SELECT idx FROM ledger_nullifiers WHERE nullifier = ?1",
+            params![&nullifier.to_bytes()[..]],
+            |row| row.get(0)
+        ).optional()?;
+        Ok(found.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_zebra::SigningKey;
+    use crate::storage::tests::{chain, check_store};
+
+    #[test]
+    fn store() {
+        check_store(&mut SqliteStore::in_memory().unwrap());
+    }
+
+    #[test]
+    fn reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.sqlite");
+        let entries = chain(&SigningKey::new(rand::thread_rng()), 300);
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        for entry in &entries {
+            store.append(entry).unwrap();
+        }
+        drop(store);
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.len(), 300);
+        assert_eq!(store.head(), entries[299].signature);
+        assert!(store.contains_nullifier(&entries[42].nullifiers()[0]).unwrap());
+
+        let streamed: Vec<LedgerEntry> = store.entries().map(|e| e.unwrap()).collect();
+        assert_eq!(streamed, entries);
+    }
+}
@@ -2,6 +2,7 @@ use bls12_381::{G1Affine, G2Affine, Gt, Scalar};
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use group::Curve;
 use serde_with::serde_as;
 use sha2::{Digest, Sha256};
@@ -9,58 +10,74 @@ use sha2::{Digest, Sha256};
 use crate::types::ProofError;
 
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, JsonSchema)]
 pub struct IssuerPublicKey {
     #[serde(with = "crate::serialization::Gt")]
+    #[schemars(with = "crate::serialization::schema::Gt")]
     pub pk: Gt,
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub a: Vec<G1Affine>
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct IssuerSecretKey {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub sk: Scalar
 }
 
 #[serde_as]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct IssueRequest {
     pub id: String,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub alpha: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub gamma: G1Affine,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub z1: Scalar,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub z2: Scalar,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub attributes: Vec<Scalar>
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
 pub struct Credential {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub sk: Scalar,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub d: Scalar,
 
     pub id: String,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub identity: Scalar,
 
     #[serde_as(as = "Option<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Option<crate::serialization::schema::G1>")]
     pub sigma_1: Option<G1Affine>,
     #[serde_as(as = "Option<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Option<crate::serialization::schema::G1>")]
     pub sigma_2: Option<G1Affine>,
     #[serde_as(as = "Option<crate::serialization::SerializableG2Affine>")]
+    #[schemars(with = "Option<crate::serialization::schema::G2>")]
     pub sigma_3: Option<G2Affine>,
 
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub attributes: Vec<G1Affine>,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub values: Vec<Scalar>
 }
 
@@ -89,41 +106,54 @@ impl Credential {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct IssueResponse {
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub sigma_1: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub sigma_2: G1Affine,
     #[serde(with = "crate::serialization::G2Affine")]
+    #[schemars(with = "crate::serialization::schema::G2")]
     pub sigma_3: G2Affine
 }
 
+/// Proof of possession of a credential for the resource `id`.
+///
+/// The request carries the challenge instead of the commitments of the
+/// proof, the verifier recomputes them from the responses.
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct AuthenticationRequest {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub id: Scalar,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub token: G1Affine,
     #[serde(with = "crate::serialization::G2Affine")]
+    #[schemars(with = "crate::serialization::schema::G2")]
     pub s2: G2Affine,
-    #[serde(with = "crate::serialization::Gt")]
-    pub e1: Gt,
-    #[serde(with = "crate::serialization::Gt")]
-    pub e2: Gt,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
+    pub c: Scalar,
+    #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub z1: Scalar,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub z2: Scalar,
     #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+    #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
     pub za: Vec<Scalar>,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub z3: G1Affine,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub vc: G1Affine,
-    #[serde(with = "crate::serialization::G1Affine")]
-    pub bp: G1Affine,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub zv: Scalar,
 }
@@ -6,33 +6,40 @@ use std::collections::HashMap;
 use std::fmt;
 use std::error::Error;
 use postcard::to_stdvec;
-use rand::RngCore;
+use rand::{CryptoRng, RngCore};
 use sha2::{Sha256, Digest};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 
 use ed25519_zebra::{Signature, SigningKey, VerificationKey};
 use bls12_381::{G1Affine, Scalar};
 use serde_with::serde_as;
 
-use crate::bindings::issuer::Issuer;
-use crate::pbss::{BlindedSignRequest, BlindedSignature, PublicKey, self, UnblindedSignature, RerandomizedProofResponse};
-use crate::external::util::{as_u32, rand_scalar};
+use crate::api::IssuerCore;
+use crate::error::{Check, PrepamsError};
+use crate::pbss::{BlindedSignRequest, BlindedSignature, PublicKey, self, UnblindedSignature};
+use crate::external::util::as_u32;
+#[cfg(feature = "wasm")]
+use crate::external::util::rand_scalar;
 use crate::serialization::SerializableG1Affine;
 use crate::serialization::SerializableScalar;
-use crate::serialization::{input, output, from_js, convert};
+use crate::serialization::{seal, open};
+#[cfg(feature = "wasm")]
+use crate::serialization::{input, output, from_js, convert, throw};
 use crate::types::credential::*;
 use crate::types::prerequisites::*;
 use crate::proofs::generic::{Transcript, GenericProof};
-use crate::proofs::participation::{ParticipationProof, ParticipationProofInput, ParticipationProofSecrets};
-use crate::proofs::payout::{MAX_INPUTS, PayoutProofInput};
+use crate::proofs::participation::{self, ParticipationProof, ParticipationProofInput, ParticipationProofSecrets};
+use crate::proofs::payout::{MAX_INPUTS, PayoutProofData};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "std", derive(Error))]
 pub enum ProofError {
-    /// This error occurs when a proof failed to verify.
+    /// This error occurs when a check of a proof failed to verify.
     #[cfg_attr(feature = "std", error("Proof verification failed."))]
-    VerificationError,
+    VerificationError(Check),
 
     /// This error occurs when the proof encoding is malformed.
     #[cfg_attr(feature = "std", error("Proof data could not be parsed."))]
@@ -49,40 +56,76 @@ impl fmt::Display for ProofError {
 impl Error for ProofError {}
 
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct Participation {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub(crate) id: Scalar,
     pub(crate) proof: GenericProof::<ParticipationProofInput, ()>,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
 impl Participation {
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn id(&self) -> String {
     SerializableScalar::to_string(&self.id)
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn reward(&self) -> u8 {
     as_u32(&self.proof.inputs.reward) as u8
   }
+}
 
-  pub fn verify(&self) -> Result<bool, JsError> {
+impl Participation {
+  pub fn proof(&self) -> &GenericProof<ParticipationProofInput, ()> {
+    &self.proof
+  }
+
+  /// Verifies the participation proof.
+  pub fn check(&self) -> Result<(), PrepamsError> {
     let mut verifier_transcript = Transcript::new(b"participation");
-    if !self.proof.verify::<ParticipationProofSecrets, ParticipationProof>(&mut verifier_transcript).is_ok() {
-      Err(JsError::new("prerequisites failed"))?;
+    self.proof.verify::<ParticipationProofSecrets, ParticipationProof>(&mut verifier_transcript)?;
+    Ok(())
+  }
+
+  /// Verifies many participation proofs at once, which is considerably
+  /// cheaper than checking them one by one.
+  ///
+  /// If the batch fails, the proofs are checked individually and the index
+  /// of the first invalid proof is returned together with its error.
+  pub fn check_batch(participations: &[Participation]) -> Result<(), (usize, PrepamsError)> {
+    Participation::check_batch_with_rng(participations, rand::thread_rng())
+  }
+
+  pub fn check_batch_with_rng(participations: &[Participation], rng: impl RngCore + CryptoRng) -> Result<(), (usize, PrepamsError)> {
+    if participation::verify_batch(participations.iter().map(|p| &p.proof), rng) {
+      return Ok(());
+    }
+
+    for (i, participation) in participations.iter().enumerate() {
+      participation.check().map_err(|e| (i, e))?;
     }
 
+    Ok(())
+  }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+impl Participation {
+  pub fn verify(&self) -> Result<bool, JsError> {
+    self.check().map_err(throw)?;
     Ok(true)
   }
 
   #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
   #[wasm_bindgen]
@@ -91,17 +134,20 @@ impl Participation {
   }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct ConfirmedParticipation {
     pub(crate) id: String,
     pub(crate) value: u8,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub(crate) tag: G1Affine,
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub(crate) study: Scalar,
     pub(crate) request: BlindedSignRequest,
+    #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
     pub(crate) signature: Signature
 }
 
@@ -116,42 +162,47 @@ impl std::hash::Hash for ConfirmedParticipation {
   }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
 impl ConfirmedParticipation {
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn value(&self) -> u8 {
     self.value
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn tag(&self) -> String {
     SerializableG1Affine::to_string(&self.tag)
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn id(&self) -> String {
     self.id.clone()
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn study(&self) -> String {
     SerializableScalar::to_string(&self.study)
   }
+}
 
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+impl ConfirmedParticipation {
   #[wasm_bindgen(getter)]
   pub fn request(&self) -> Result<Vec<u8>, JsError> {
-    convert(to_stdvec(&self.request))
+    output(&self.request)
   }
 
   #[wasm_bindgen(getter)]
-  pub fn signature(&self) -> Result<Vec<u8>, JsError> {
-    output(self.signature)
+  pub fn signature(&self) -> Vec<u8> {
+    self.signature.to_bytes().to_vec()
   }
 
   #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
   #[wasm_bindgen]
@@ -161,30 +212,58 @@ impl ConfirmedParticipation {
 
   #[wasm_bindgen]
   pub fn from(id: &str, tag: &str, study: &str, request: &[u8], signature: &[u8], value: u8) -> Result<ConfirmedParticipation, JsError> {
+    ConfirmedParticipation::from_parts(id, tag, study, request, signature, value).map_err(throw)
+  }
+}
+
+impl ConfirmedParticipation {
+  /// Assembles a confirmed participation from the columns of a ledger database.
+  pub fn from_parts(id: &str, tag: &str, study: &str, request: &[u8], signature: &[u8], value: u8) -> Result<ConfirmedParticipation, PrepamsError> {
     Ok(ConfirmedParticipation {
       id: id.to_string(),
       tag: SerializableG1Affine::from_string(tag)?,
       study: SerializableScalar::from_string(study)?,
-      request: input(request)?,
-      signature: input(signature)?,
-      value: value,
+      request: open(request)?,
+      signature: Signature::try_from(signature)?,
+      value,
     })
   }
 }
 
 #[serde_as]
 #[allow(non_snake_case)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum AttributeConstraint {
   Range(u32, u32, u32),
   Element(u32, Vec<u32>)
 }
 
-#[wasm_bindgen]
+impl AttributeConstraint {
+  /// Parses a constraint on the attribute at `index` as sent by the frontend,
+  /// i.e. a `number` range `[from, to]` or a `select` of options.
+  pub fn parse(index: u32, kind: &str, params: &[u32]) -> Result<AttributeConstraint, PrepamsError> {
+    match (kind, params) {
+      ("number", [from, to, ..]) if from <= to => Ok(AttributeConstraint::Range(index, *from, *to)),
+      ("select", _) => Ok(AttributeConstraint::Element(index, params.to_vec())),
+      _ => Err(PrepamsError::encoding("invalid attribute constraint"))
+    }
+  }
+
+  /// Inverse of [`AttributeConstraint::parse`].
+  pub fn describe(&self) -> (u32, &'static str, Vec<u32>) {
+    match self {
+      AttributeConstraint::Range(index, lower, upper) => (*index, "number", vec![*lower, *upper]),
+      AttributeConstraint::Element(index, values) => (*index, "select", values.clone())
+    }
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Resource {
   #[serde(with = "crate::serialization::Scalar")]
+  #[schemars(with = "crate::serialization::schema::Scalar")]
   pub(crate) id: Scalar,
   pub(crate) name: String,
   pub(crate) summary: String,
@@ -227,8 +306,88 @@ impl Resource {
   pub fn addConstraint(&mut self, constraint: AttributeConstraint) {
     self.constraints.push(constraint);
   }
+
+  /// Ids of the studies a participant must have taken part in.
+  pub fn qualifier_ids(&self) -> Vec<String> {
+    self.qualifier.iter().map(|q| SerializableScalar::to_string(&q.id)).collect()
+  }
+
+  /// Ids of the studies a participant must not have taken part in.
+  pub fn disqualifier_ids(&self) -> Vec<String> {
+    self.disqualifier.iter().map(|q| SerializableScalar::to_string(&q.id)).collect()
+  }
+
+  pub fn attribute_constraints(&self) -> &[AttributeConstraint] {
+    &self.constraints
+  }
+
+  /// Resolves the qualifying and disqualifying studies to the participation
+  /// tags found on the ledger of the issuer.
+  pub fn update_references(&mut self, issuer: &IssuerCore) -> Result<(), PrepamsError> {
+    let mut qmap: HashMap<[u8; 32], Qualifier> = self.qualifier.iter().map(|q| (q.id.to_bytes(), Qualifier { id: q.id, tags: vec![] })).collect();
+    let mut dmap: HashMap<[u8; 32], Qualifier> = self.disqualifier.iter().map(|q| (q.id.to_bytes(), Qualifier { id: q.id, tags: vec![] })).collect();
+    for entry in issuer.entries() {
+      if let Some(transaction) = &entry?.transaction {
+        let id = transaction.participation.study.to_bytes();
+        if let Some(v) = qmap.get_mut(&id) {
+          v.tags.push(transaction.participation.tag);
+        }
+        if let Some(v) = dmap.get_mut(&id) {
+          v.tags.push(transaction.participation.tag);
+        }
+      }
+    }
+    self.qualifier = qmap.into_values().collect();
+    self.disqualifier = dmap.into_values().collect();
+    Ok(())
+  }
 }
 
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[allow(non_snake_case)]
+impl Resource {
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn id(&self) -> String {
+    SerializableScalar::to_string(&self.id)
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn name(&self) -> String {
+    self.name.to_owned()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn summary(&self) -> String {
+    self.summary.to_owned()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn description(&self) -> String {
+    self.description.to_owned()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn duration(&self) -> String {
+    self.duration.to_owned()
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn reward(&self) -> u8 {
+    self.reward
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn webBased(&self) -> bool {
+    self.webBased
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn studyUrl(&self) -> String {
+    self.studyUrl.as_ref().unwrap_or(&String::from("")).to_owned()
+  }
+}
+
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 impl Resource {
@@ -251,31 +410,24 @@ impl Resource {
       let d: Vec<String> = from_js(disqualifier)?;
       (
         rand_scalar(),
-        q.iter().map(|q| Qualifier {
-          id: SerializableScalar::from_string(q).unwrap(),
-          tags: vec![]
-        }).collect(),
-        d.iter().map(|q| Qualifier {
-          id: SerializableScalar::from_string(q).unwrap(),
-          tags: vec![]
-        }).collect()
+        q.iter().map(|q| Qualifier::parse(q, &[])).collect::<Result<Vec<_>, _>>().map_err(throw)?,
+        d.iter().map(|q| Qualifier::parse(q, &[])).collect::<Result<Vec<_>, _>>().map_err(throw)?
     )} else {
       let q: Vec<(String, Vec<String>)> = from_js(qualifier)?;
       let d: Vec<(String, Vec<String>)> = from_js(disqualifier)?;
+      let id = id.as_string().ok_or_else(|| throw(PrepamsError::encoding("study id is not a string")))?;
 
       (
-          SerializableScalar::from_string(&id.as_string().unwrap()).unwrap(),
-          q.iter().map(|(q, tags)| Qualifier {
-            id: SerializableScalar::from_string(q).unwrap(),
-            tags: tags.iter().map(|t| SerializableG1Affine::from_string(t).unwrap()).collect()
-          }).collect(),
-          d.iter().map(|(q, tags)| Qualifier {
-            id: SerializableScalar::from_string(q).unwrap(),
-            tags: tags.iter().map(|t| SerializableG1Affine::from_string(t).unwrap()).collect()
-          }).collect()
+          SerializableScalar::from_string(&id).map_err(throw)?,
+          q.iter().map(|(q, tags)| Qualifier::parse(q, tags)).collect::<Result<Vec<_>, _>>().map_err(throw)?,
+          d.iter().map(|(q, tags)| Qualifier::parse(q, tags)).collect::<Result<Vec<_>, _>>().map_err(throw)?
       )
     };
     let constraints: Vec<(u32, String, Vec<u32>)> = from_js(constraints)?;
+    let constraints = constraints.iter()
+      .map(|(i, t, p)| AttributeConstraint::parse(*i, t, p))
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(throw)?;
 
     Ok(Resource {
       id: id,
@@ -288,105 +440,33 @@ impl Resource {
       studyUrl: webBased.then_some(studyUrl.to_string()),
       qualifier: qualifier,
       disqualifier: disqualifier,
-      constraints: constraints.iter().map(|(i, t, p)| match t.as_str() {
-        "number" => AttributeConstraint::Range(*i, p[0] as u32, p[1] as u32),
-        "select" => AttributeConstraint::Element(*i, p.clone()),
-        _ => panic!()
-      }).collect()
+      constraints
     })
   }
 
-  pub fn updateReferences(&mut self, issuer: &Issuer) {
-    let mut qmap: HashMap<[u8; 32], Qualifier> = self.qualifier.iter().map(|q| (q.id.to_bytes(), Qualifier { id: q.id.clone(), tags: vec![] })).collect();
-    let mut dmap: HashMap<[u8; 32], Qualifier> = self.disqualifier.iter().map(|q| (q.id.to_bytes(), Qualifier { id: q.id.clone(), tags: vec![] })).collect();
-    for entry in issuer.ledger.entries.iter() {
-      if let Some(transaction) = &entry.transaction {
-        let id = transaction.participation.study.to_bytes();
-        if let Some(v) = qmap.get_mut(&id) {
-          v.tags.push(transaction.participation.tag.clone());
-        }
-        if let Some(v) = dmap.get_mut(&id) {
-          v.tags.push(transaction.participation.tag.clone());
-        }
-      }
-    }
-    self.qualifier = qmap.into_values().collect();
-    self.disqualifier = dmap.into_values().collect();
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn id(&self) -> String {
-    SerializableScalar::to_string(&self.id)
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn name(&self) -> String {
-    self.name.to_owned()
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn summary(&self) -> String {
-    self.summary.to_owned()
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn description(&self) -> String {
-    self.description.to_owned()
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn duration(&self) -> String {
-    self.duration.to_owned()
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn reward(&self) -> u8 {
-    self.reward
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn webBased(&self) -> bool {
-    self.webBased
-  }
-
-  #[wasm_bindgen(getter)]
-  pub fn studyUrl(&self) -> String {
-    self.studyUrl.as_ref().unwrap_or(&String::from("")).to_owned()
+  pub fn updateReferences(&mut self, issuer: &crate::bindings::issuer::Issuer) -> Result<(), JsError> {
+    self.update_references(issuer.core()).map_err(throw)
   }
 
   #[wasm_bindgen(getter)]
   pub fn qualifier(&self) -> Result<JsValue, JsError> {
-    let a: Vec<String> = self.qualifier.iter().map(|q| SerializableScalar::to_string(&q.id)).collect();
-    convert(serde_wasm_bindgen::to_value(&a))
+    convert(serde_wasm_bindgen::to_value(&self.qualifier_ids()))
   }
 
   #[wasm_bindgen(getter)]
   pub fn disqualifier(&self) -> Result<JsValue, JsError> {
-    let a: Vec<String> = self.disqualifier.iter().map(|q| SerializableScalar::to_string(&q.id)).collect();
-    convert(serde_wasm_bindgen::to_value(&a))
+    convert(serde_wasm_bindgen::to_value(&self.disqualifier_ids()))
   }
 
   #[wasm_bindgen(getter)]
   pub fn constraints(&self) -> Result<JsValue, JsError> {
-    let constraints: Vec<(u32, String, Vec<u32>)> = self.constraints.iter().map(|attr| match attr {
-      AttributeConstraint::Range(index, lower, upper) => (
-        *index,
-        "number".to_string(),
-        vec![*lower, *upper]
-      ),
-      AttributeConstraint::Element(index, values) => (
-        *index,
-        "select".to_string(),
-        values.clone()
-      )
-    }).collect();
-
+    let constraints: Vec<(u32, &str, Vec<u32>)> = self.constraints.iter().map(AttributeConstraint::describe).collect();
     convert(serde_wasm_bindgen::to_value(&constraints))
   }
 
   #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
   #[wasm_bindgen]
@@ -395,53 +475,60 @@ impl Resource {
   }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
 #[derive(Debug)]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct SignedResource {
     pub(crate) owner: String,
     pub(crate) resource: Resource,
+    #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
     pub(crate) signature: Signature,
 }
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 impl SignedResource {
   #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
   #[wasm_bindgen]
   pub fn deserialize(data: &[u8]) -> Result<SignedResource, JsError> {
     input(data)
   }
+}
 
-  #[wasm_bindgen(getter)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[allow(non_snake_case)]
+impl SignedResource {
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn owner(&self) -> String {
     self.owner.to_string()
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn resource(&self) -> Resource {
     self.resource.clone()
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn signature(&self) -> Vec<u8> {
     let bytes: [u8; 64] = self.signature.into();
     bytes.to_vec()
   }
 }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct Transaction {
   pub(crate) participation: ConfirmedParticipation,
   pub(crate) coin: BlindedSignature
 }
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen]
 #[allow(non_snake_case)]
 impl Transaction {
@@ -452,26 +539,32 @@ impl Transaction {
 }
 
 #[serde_as]
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct Payout {
   #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+  #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
   pub(crate) nullifier: Vec<Scalar>,
   pub(crate) recipient: [u8; 32],
   pub(crate) value: u8
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
 impl Payout {
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn value(&self) -> u8 {
     self.value
   }
+}
 
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+impl Payout {
   #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
   #[wasm_bindgen]
@@ -480,8 +573,8 @@ impl Payout {
   }
 }
 
-impl From<&GenericProof<PayoutProofInput, Vec<RerandomizedProofResponse>>> for Payout {
-    fn from(proof: &GenericProof<PayoutProofInput, Vec<RerandomizedProofResponse>>) -> Self {
+impl From<&PayoutProofData> for Payout {
+    fn from(proof: &PayoutProofData) -> Self {
         let mut hasher = Sha256::new();
         hasher.update(proof.inputs.target.as_bytes());
         hasher.update(proof.inputs.recipient.as_bytes());
@@ -494,26 +587,29 @@ impl From<&GenericProof<PayoutProofInput, Vec<RerandomizedProofResponse>>> for P
     }
 }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub enum LedgerEntryType {
   Transaction,
   Payout
 }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct LedgerEntry {
+  #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
   pub(crate) previous: Signature,
+  pub(crate) timestamp: u64,
   pub(crate) transaction: Option<Transaction>,
   pub(crate) payout: Option<Payout>,
+  #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
   pub(crate) signature: Signature,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[allow(non_snake_case)]
 impl LedgerEntry {
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn entryType(&self) -> LedgerEntryType {
     if self.transaction.is_some() {
       LedgerEntryType::Transaction
@@ -522,56 +618,73 @@ impl LedgerEntry {
     }
   }
 
-  #[wasm_bindgen(getter)]
-  pub fn transaction(&self) -> Transaction {
-    self.transaction.clone().unwrap()
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn transaction(&self) -> Option<Transaction> {
+    self.transaction.clone()
   }
 
-  #[wasm_bindgen(getter)]
-  pub fn payout(&self) -> Payout {
-    self.payout.clone().unwrap()
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn payout(&self) -> Option<Payout> {
+    self.payout.clone()
   }
 
-  #[wasm_bindgen(getter)]
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
   pub fn signature(&self) -> Vec<u8> {
     let bytes: [u8; 64] = self.signature.into();
     bytes.to_vec()
   }
 
-  pub fn fromTransaction(previous: &[u8], participation: &ConfirmedParticipation, coin: &[u8], signature: &[u8]) -> Result<LedgerEntry, JsError> {
+  /// Issuer time of the entry in seconds since the unix epoch.
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn timestamp(&self) -> f64 {
+    self.timestamp as f64
+  }
+}
+
+impl LedgerEntry {
+  /// Assembles a transaction entry from the columns of a ledger database.
+  pub fn from_transaction(previous: &[u8], timestamp: u64, participation: &ConfirmedParticipation, coin: &[u8], signature: &[u8]) -> Result<LedgerEntry, PrepamsError> {
     let tx = Transaction {
       participation: participation.clone(),
-      coin: input(coin)?,
+      coin: open(coin)?,
     };
 
-    let p: [u8; 64] = previous.try_into().unwrap();
-    let s: [u8; 64] = signature.try_into().unwrap();
-
     Ok(LedgerEntry {
-      previous: p.into(),
-      signature: s.into(),
+      previous: Signature::try_from(previous)?,
+      timestamp,
+      signature: Signature::try_from(signature)?,
       transaction: Some(tx),
       payout: None
     })
   }
 
-  pub fn fromPayout(previous: &[u8], payout: &[u8], signature: &[u8]) -> Result<LedgerEntry, JsError> {
-    let payout: Payout = input(payout)?;
-
-    let p: [u8; 64] = previous.try_into().unwrap();
-    let s: [u8; 64] = signature.try_into().unwrap();
-
+  /// Assembles a payout entry from the columns of a ledger database.
+  pub fn from_payout(previous: &[u8], timestamp: u64, payout: &[u8], signature: &[u8]) -> Result<LedgerEntry, PrepamsError> {
     Ok(LedgerEntry {
-      previous: p.into(),
-      signature: s.into(),
+      previous: Signature::try_from(previous)?,
+      timestamp,
+      signature: Signature::try_from(signature)?,
       transaction: None,
-      payout: Some(payout)
+      payout: Some(open(payout)?)
     })
   }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+impl LedgerEntry {
+  pub fn fromTransaction(previous: &[u8], timestamp: f64, participation: &ConfirmedParticipation, coin: &[u8], signature: &[u8]) -> Result<LedgerEntry, JsError> {
+    LedgerEntry::from_transaction(previous, timestamp as u64, participation, coin, signature).map_err(throw)
+  }
+
+  pub fn fromPayout(previous: &[u8], timestamp: f64, payout: &[u8], signature: &[u8]) -> Result<LedgerEntry, JsError> {
+    LedgerEntry::from_payout(previous, timestamp as u64, payout, signature).map_err(throw)
+  }
 
   #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
   pub fn deserialize(data: &[u8]) -> Result<LedgerEntry, JsError> {
@@ -579,9 +692,10 @@ impl LedgerEntry {
   }
 }
 
-#[wasm_bindgen]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Ledger {
+  #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
   pub(crate) head: Signature,
   pub(crate) entries: Vec<LedgerEntry>
 }
@@ -592,97 +706,393 @@ impl Default for Ledger {
   }
 }
 
-#[allow(non_snake_case)]
-impl Ledger {
-  pub fn appendTransaction(&mut self, signingKey: &SigningKey, tx: Transaction) -> Result<LedgerEntry, JsError> {
-    let mut data = "transaction:".as_bytes().to_vec();
-    data.extend_from_slice(&self.head.to_bytes());
-    data.append(&mut convert(to_stdvec(&tx))?);
+// returns the current unix time in seconds
+pub(crate) fn now() -> u64 {
+  #[cfg(all(feature = "wasm", target_family = "wasm"))]
+  return (js_sys::Date::now() / 1000.0) as u64;
 
-    let signature: Signature = signingKey.sign(&data);
-    let previous: Signature = self.head.into();
-    self.head = signature.clone();
+  #[cfg(not(all(feature = "wasm", target_family = "wasm")))]
+  return std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs());
+}
 
-    let entry = LedgerEntry {
-      previous,
-      transaction: Some(tx),
-      payout: None,
-      signature
-    };
-    self.entries.push(entry.clone());
+// entries are signed under a versioned label together with their timestamp,
+// entries of ledgers from before issuer timestamps have the timestamp 0 and
+// were signed under the bare label without it, see `LedgerEntry::check`
+fn signed_prefix(label: &str, head: &Signature, timestamp: Option<u64>) -> Vec<u8> {
+  let mut data = match timestamp {
+    Some(_) => format!("{}-v2:", label).into_bytes(),
+    None => format!("{}:", label).into_bytes()
+  };
+  data.extend_from_slice(&head.to_bytes());
+  if let Some(timestamp) = timestamp {
+    data.extend_from_slice(&timestamp.to_be_bytes());
+  }
+  data
+}
 
-    Ok(entry)
+impl Transaction {
+  pub fn participation(&self) -> &ConfirmedParticipation {
+    &self.participation
   }
 
-  pub fn appendPayout(&mut self, signingKey: &SigningKey, payout: Payout) -> Result<LedgerEntry, JsError> {
-    let mut data = "payout:".as_bytes().to_vec();
-    data.extend_from_slice(&self.head.to_bytes());
-    data.extend_from_slice(&payout.recipient);
-    data.extend_from_slice(&payout.value.to_be_bytes());
-    for coin in &payout.nullifier {
+  /// Blind signature on the reward request, unblinded by the participant.
+  pub fn reward_coin(&self) -> &BlindedSignature {
+    &self.coin
+  }
+
+  pub(crate) fn signed_data(&self, head: &Signature, timestamp: Option<u64>) -> Result<Vec<u8>, postcard::Error> {
+    let mut data = signed_prefix("transaction", head, timestamp);
+    data.append(&mut to_stdvec(&self)?);
+    Ok(data)
+  }
+}
+
+impl Payout {
+  pub(crate) fn signed_data(&self, head: &Signature, timestamp: Option<u64>) -> Vec<u8> {
+    let mut data = signed_prefix("payout", head, timestamp);
+    data.extend_from_slice(&self.recipient);
+    data.extend_from_slice(&self.value.to_be_bytes());
+    for coin in &self.nullifier {
       data.extend_from_slice(&coin.to_bytes());
     }
+    data
+  }
+}
+
+#[allow(non_snake_case)]
+impl LedgerEntry {
+  pub(crate) fn sign_transaction(signingKey: &SigningKey, head: &Signature, timestamp: u64, tx: Transaction) -> Result<LedgerEntry, PrepamsError> {
+    let signature = signingKey.sign(&tx.signed_data(head, Some(timestamp))?);
 
-    let signature: Signature = signingKey.sign(&data);
-    let previous: Signature = self.head.into();
-    self.head = signature.clone();
-    let entry = LedgerEntry {
-      previous,
+    Ok(LedgerEntry {
+      previous: *head,
+      timestamp,
+      transaction: Some(tx),
+      payout: None,
+      signature
+    })
+  }
+
+  pub(crate) fn sign_payout(signingKey: &SigningKey, head: &Signature, timestamp: u64, payout: Payout) -> LedgerEntry {
+    let signature = signingKey.sign(&payout.signed_data(head, Some(timestamp)));
+
+    LedgerEntry {
+      previous: *head,
+      timestamp,
       transaction: None,
       payout: Some(payout),
       signature
+    }
+  }
+
+  // checks that the entry extends the chain at `head`, is not older than `time`
+  // and is signed by the issuer
+  pub(crate) fn check(&self, vk: &VerificationKey, head: &Signature, time: u64) -> Result<(), PrepamsError> {
+    if self.timestamp < time {
+      Err(PrepamsError::verification(Check::Ledger, "ledger entry timestamp is not monotonic"))?;
+    }
+
+    let signed = |timestamp: Option<u64>| -> Result<bool, PrepamsError> {
+      let data = match (&self.transaction, &self.payout) {
+        (Some(tx), None) => tx.signed_data(head, timestamp)?,
+        (None, Some(payout)) => payout.signed_data(head, timestamp),
+        _ => Err(PrepamsError::encoding("ledger entry is malformed"))?
+      };
+      Ok(vk.verify(&self.signature, &data).is_ok())
     };
+
+    // only entries without a timestamp may carry a signature of a legacy ledger
+    if !(signed(Some(self.timestamp))? || self.timestamp == 0 && signed(None)?) {
+      Err(PrepamsError::verification(Check::Ledger, "ledger entry signature invalid"))?;
+    }
+    Ok(())
+  }
+
+  // returns the coin tag of a transaction, which may only be rewarded once
+  pub(crate) fn tag(&self) -> Option<&G1Affine> {
+    self.transaction.as_ref().map(|tx| &tx.participation.tag)
+  }
+
+  // returns the nullifiers spent by a payout
+  pub(crate) fn nullifiers(&self) -> &[Scalar] {
+    self.payout.as_ref().map_or(&[], |payout| &payout.nullifier)
+  }
+}
+
+#[allow(non_snake_case)]
+impl Ledger {
+  // timestamp of the latest entry, new entries must not be older
+  pub(crate) fn time(&self) -> u64 {
+    self.entries.last().map_or(0, |entry| entry.timestamp)
+  }
+
+  pub fn appendTransaction(&mut self, signingKey: &SigningKey, tx: Transaction) -> Result<LedgerEntry, PrepamsError> {
+    let entry = LedgerEntry::sign_transaction(signingKey, &self.head, now().max(self.time()), tx)?;
+    self.head = entry.signature;
     self.entries.push(entry.clone());
 
     Ok(entry)
   }
 
-  pub fn verify(&mut self, vk: &VerificationKey, entry: &LedgerEntry) -> Result<(), JsError> {
-    let head: [u8; 64] = self.head.into();
-    let data = match entry.entryType() {
-      LedgerEntryType::Transaction => {
-        let tx = entry.transaction.clone().unwrap();
-        let mut data = "transaction:".as_bytes().to_vec();
-        data.extend_from_slice(&head);
-        data.append(&mut convert(to_stdvec(&tx))?);
-        data
-      },
-      LedgerEntryType::Payout => {
-        let payout = entry.payout.clone().unwrap();
-        let mut data = "payout:".as_bytes().to_vec();
-        data.extend_from_slice(&head);
-        data.extend_from_slice(&payout.recipient);
-        data.extend_from_slice(&payout.value.to_be_bytes());
-        for coin in payout.nullifier {
-          data.extend_from_slice(&coin.to_bytes());
-        }
-        data
-      }
-    };
+  pub fn appendPayout(&mut self, signingKey: &SigningKey, payout: Payout) -> Result<LedgerEntry, PrepamsError> {
+    let entry = LedgerEntry::sign_payout(signingKey, &self.head, now().max(self.time()), payout);
+    self.head = entry.signature;
+    self.entries.push(entry.clone());
+
+    Ok(entry)
+  }
 
-    vk.verify(&entry.signature, &data)?;
+  pub fn verify(&mut self, vk: &VerificationKey, entry: &LedgerEntry) -> Result<(), PrepamsError> {
+    entry.check(vk, &self.head, self.time())?;
     self.head = entry.signature.into();
     self.entries.push(entry.clone());
 
     Ok(())
   }
 
+  /// Verifies the signatures and links of all entries and the head.
+  pub fn check(&self, vk: &VerificationKey) -> Result<(), PrepamsError> {
+    let mut ledger = Ledger::default();
+    for entry in &self.entries {
+      ledger.verify(vk, entry)?;
+    }
+
+    if ledger.head != self.head {
+      Err(PrepamsError::verification(Check::Ledger, "ledger head does not match its last entry"))?;
+    }
+    Ok(())
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn serialize(&self) -> Result<Vec<u8>, PrepamsError> {
+    seal(self)
+  }
+
+  pub fn deserialize(data: &[u8]) -> Result<Ledger, PrepamsError> {
+    open(data)
+  }
+}
+
+/// Selects the entries that are included in a ledger slice.
+#[allow(non_snake_case)]
+#[derive(Default, Debug, Clone)]
+pub struct LedgerFilter {
+  pub entryType: Option<LedgerEntryType>,
+  pub study: Option<Scalar>
+}
+
+impl LedgerFilter {
+  /// Parses the filter of a ledger query, i.e. an entry type `Transaction` or
+  /// `Payout` and a study id.
+  pub fn parse(entry_type: Option<&str>, study: Option<&str>) -> Result<LedgerFilter, PrepamsError> {
+    let kind = match entry_type {
+      None => None,
+      Some("Transaction") => Some(LedgerEntryType::Transaction),
+      Some("Payout") => Some(LedgerEntryType::Payout),
+      Some(t) => Err(PrepamsError::Encoding(format!("invalid entry type {:?}", t)))?
+    };
+    let study = study.map(SerializableScalar::from_string).transpose()?;
+
+    Ok(LedgerFilter { entryType: kind, study })
+  }
+
+  pub fn matches(&self, entry: &LedgerEntry) -> bool {
+    if let Some(t) = &self.entryType {
+      if &entry.entryType() != t {
+        return false;
+      }
+    }
+
+    match &self.study {
+      None => true,
+      Some(study) => entry.transaction.as_ref().is_some_and(|tx| &tx.participation.study == study)
+    }
+  }
+}
+
+/// Part of the ledger covering the positions `start..end`.
+///
+/// `anchor` is the signature preceding position `start` and `cursor` the
+/// signature at position `end - 1`, so an unfiltered slice can be verified on
+/// its own and appended to a ledger whose head equals `anchor`. Filtered
+/// slices only contain the matching entries, each still carrying the
+/// signature it extends.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct LedgerSlice {
+  pub(crate) start: u64,
+  pub(crate) end: u64,
+  pub(crate) total: u64,
+  #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
+  pub(crate) anchor: Signature,
+  #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
+  pub(crate) cursor: Signature,
+  #[schemars(with = "crate::serialization::schema::Ed25519Signature")]
+  pub(crate) head: Signature,
+  pub(crate) entries: Vec<(u64, LedgerEntry)>
+}
+
+impl LedgerSlice {
+  /// Checks all entry signatures and the chain links between adjacent entries.
+  pub fn check(&self, vk: &VerificationKey) -> Result<(), PrepamsError> {
+    if self.start > self.end || self.end > self.total {
+      Err(PrepamsError::verification(Check::Ledger, "ledger slice range is invalid"))?;
+    }
+    if self.start == self.end && self.anchor != self.cursor {
+      Err(PrepamsError::verification(Check::Ledger, "ledger slice is not linked"))?;
+    }
+
+    let mut last: Option<(u64, &Signature)> = None;
+    let mut time = 0;
+    for (i, entry) in &self.entries {
+      if *i < self.start || *i >= self.end || last.is_some_and(|(j, _)| *i <= j) {
+        Err(PrepamsError::verification(Check::Ledger, "ledger slice positions are invalid"))?;
+      }
+
+      let linked = match last {
+        _ if *i == self.start => Some(&self.anchor),
+        Some((j, signature)) if *i == j + 1 => Some(signature),
+        _ => None
+      };
+      if linked.is_some_and(|previous| previous != &entry.previous) {
+        Err(PrepamsError::verification(Check::Ledger, "ledger slice is not linked"))?;
+      }
+      if *i + 1 == self.end && entry.signature != self.cursor {
+        Err(PrepamsError::verification(Check::Ledger, "ledger slice is not linked"))?;
+      }
+
+      entry.check(vk, &entry.previous, time)?;
+      last = Some((*i, &entry.signature));
+      time = entry.timestamp;
+    }
+
+    if self.end == self.total && self.cursor != self.head {
+      Err(PrepamsError::verification(Check::Ledger, "ledger slice is not linked"))?;
+    }
+
+    Ok(())
+  }
+
+  pub fn entries(&self) -> &[(u64, LedgerEntry)] {
+    &self.entries
+  }
+
+  // returns true if the slice contains every entry of its range
+  pub(crate) fn is_complete(&self) -> bool {
+    self.entries.len() as u64 == self.end - self.start
+  }
+
+  /// Appends the entries of a complete slice to a ledger ending at its anchor.
+  pub fn extend_ledger(&self, ledger: &mut Ledger, vk: &VerificationKey) -> Result<(), PrepamsError> {
+    if !self.is_complete() {
+      Err(PrepamsError::rejected("filtered ledger slices cannot be appended"))?;
+    }
+    if self.anchor != ledger.head {
+      Err(PrepamsError::verification(Check::Ledger, "ledger slice does not extend the ledger"))?;
+    }
+
+    for (_, entry) in &self.entries {
+      entry.check(vk, &ledger.head, ledger.time())?;
+      ledger.head = entry.signature;
+      ledger.entries.push(entry.clone());
+    }
+    Ok(())
+  }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[allow(non_snake_case)]
+impl LedgerSlice {
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn start(&self) -> usize {
+    self.start as usize
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn end(&self) -> usize {
+    self.end as usize
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn total(&self) -> usize {
+    self.total as usize
+  }
+
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn length(&self) -> usize {
+    self.entries.len()
+  }
+
+  /// Signature of the last scanned entry, used to request the next page.
+  #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+  pub fn cursor(&self) -> Vec<u8> {
+    let bytes: [u8; 64] = self.cursor.into();
+    bytes.to_vec()
+  }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+impl LedgerSlice {
+
+  pub fn index(&self, i: usize) -> Result<usize, JsError> {
+    match self.entries.get(i) {
+      Some((index, _)) => Ok(*index as usize),
+      None => Err(throw(PrepamsError::rejected("index out of bounds")))
+    }
+  }
+
+  pub fn entry(&self, i: usize) -> Result<LedgerEntry, JsError> {
+    match self.entries.get(i) {
+      Some((_, entry)) => Ok(entry.clone()),
+      None => Err(throw(PrepamsError::rejected("index out of bounds")))
+    }
+  }
+
+  pub fn verify(&self, lvk: &[u8]) -> Result<bool, JsError> {
+    let vk = convert(VerificationKey::try_from(lvk))?;
+    Ok(self.check(&vk).is_ok())
+  }
+
+  /// Verifies the slice and appends it to a serialized ledger.
+  pub fn extend(&self, ledger: &[u8], lvk: &[u8]) -> Result<Vec<u8>, JsError> {
+    let vk = convert(VerificationKey::try_from(lvk))?;
+    let mut ledger: Ledger = input(ledger)?;
+    self.check(&vk).map_err(throw)?;
+    self.extend_ledger(&mut ledger, &vk).map_err(throw)?;
+    output(&ledger)
+  }
+
+  #[wasm_bindgen]
   pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
-    output(&self)
+    output(self)
   }
 
-  pub fn deserialize(data: &[u8]) -> Result<Ledger, JsError> {
+  #[wasm_bindgen]
+  pub fn deserialize(data: &[u8]) -> Result<LedgerSlice, JsError> {
     input(data)
   }
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[serde_as]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, JsonSchema)]
 pub struct NullRequest {
   #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+  #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
   pub(crate) s: Vec<Scalar>,
   #[serde_as(as = "Vec<crate::serialization::SerializableScalar>")]
+  #[schemars(with = "Vec<crate::serialization::schema::Scalar>")]
   pub(crate) d: Vec<Scalar>,
   pub(crate) vk: PublicKey,
   pub(crate) request: Vec<BlindedSignRequest>
@@ -690,9 +1100,11 @@ pub struct NullRequest {
 
 #[allow(non_snake_case)]
 impl NullRequest {
-  pub fn new(vk: &PublicKey, credential: &Credential) -> NullRequest {
-    let mut rng = rand::thread_rng();
+  pub fn new(vk: &PublicKey, credential: &Credential) -> Result<NullRequest, PrepamsError> {
+    NullRequest::new_with_rng(vk, credential, rand::thread_rng())
+  }
 
+  pub fn new_with_rng(vk: &PublicKey, credential: &Credential, mut rng: impl RngCore + CryptoRng) -> Result<NullRequest, PrepamsError> {
     let mut S = vec![];
     let mut D = vec![];
     let mut R = vec![];
@@ -702,42 +1114,51 @@ impl NullRequest {
     for _ in 0..MAX_INPUTS {
       let s = vec![<bls12_381::Scalar as ff::Field>::random(&mut rng), credential.identity];
       let d = <bls12_381::Scalar as ff::Field>::random(&mut rng);
-      R.push(pbss::Blind(vk, &m, &s, &d, &mut rng));
+      R.push(pbss::Blind(vk, &m, &s, &d, &mut rng)?);
       S.push(s[0]);
       S.push(s[1]);
       D.push(d);
     }
 
-    NullRequest { s: S, d: D, request: R, vk: vk.clone() }
+    Ok(NullRequest { s: S, d: D, request: R, vk: vk.clone() })
   }
-}
 
-#[wasm_bindgen]
-#[allow(non_snake_case)]
-impl NullRequest {
-  pub fn request(&self) -> Result<Vec<u8>, JsError> {
-    output(&self.request)
+  /// Blinded sign requests sent to the issuer.
+  pub fn requests(&self) -> &[BlindedSignRequest] {
+    &self.request
   }
 
-  pub fn unblind(mut self, nullResponse: &[u8]) -> Result<Vec<u8>, JsError> {
-    let mut responses: Vec<BlindedSignature> = input(nullResponse)?;
-
+  /// Unblinds the null coins returned by the issuer.
+  pub fn unblind_nulls(mut self, mut responses: Vec<BlindedSignature>) -> Result<Vec<UnblindedSignature>, PrepamsError> {
     if responses.len() != MAX_INPUTS {
-      Err(JsError::new("response length is invalid"))?;
+      Err(PrepamsError::rejected("response length is invalid"))?;
+    }
+
+    if self.s.len() != 2 * MAX_INPUTS || self.d.len() != MAX_INPUTS {
+      Err(PrepamsError::state("null request is malformed"))?;
     }
 
     let mut nulls: Vec<UnblindedSignature> = vec![];
     let m = vec![Scalar::zero()];
 
-    for _ in 0..MAX_INPUTS {
-      let sig = responses.pop().unwrap();
-      let s1 = self.s.pop().unwrap();
-      let s0 = self.s.pop().unwrap();
-      let d = self.d.pop().unwrap();
-
-      nulls.push(convert(pbss::Unblind(&self.vk, &sig, &m, &vec![s0, s1], &d))?);
+    while let (Some(sig), Some(s1), Some(s0), Some(d)) = (responses.pop(), self.s.pop(), self.s.pop(), self.d.pop()) {
+      nulls.push(pbss::Unblind(&self.vk, &sig, &m, &vec![s0, s1], &d)?);
     }
 
-    output(nulls)
+    Ok(nulls)
+  }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[allow(non_snake_case)]
+impl NullRequest {
+  pub fn request(&self) -> Result<Vec<u8>, JsError> {
+    output(&self.request)
+  }
+
+  pub fn unblind(self, nullResponse: &[u8]) -> Result<Vec<u8>, JsError> {
+    let responses: Vec<BlindedSignature> = input(nullResponse)?;
+    output(&self.unblind_nulls(responses).map_err(throw)?)
   }
 }
@@ -1,23 +1,42 @@
 use serde_with::serde_as;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use bls12_381::{G1Affine, Scalar};
 
+use crate::error::PrepamsError;
+use crate::serialization::{SerializableG1Affine, SerializableScalar};
+
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Qualifier {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub(crate) id: Scalar,
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub(crate) tags: Vec<G1Affine>
 }
 
+impl Qualifier {
+    /// Parses a study reference from its base64url encoded id and participation tags.
+    pub fn parse(id: &str, tags: &[String]) -> Result<Qualifier, PrepamsError> {
+        Ok(Qualifier {
+            id: SerializableScalar::from_string(id)?,
+            tags: tags.iter().map(|t| SerializableG1Affine::from_string(t)).collect::<Result<_, _>>()?
+        })
+    }
+}
+
 #[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct RandomizedDisqualifier {
     #[serde(with = "crate::serialization::Scalar")]
+    #[schemars(with = "crate::serialization::schema::Scalar")]
     pub(crate) id: Scalar,
     #[serde(with = "crate::serialization::G1Affine")]
+    #[schemars(with = "crate::serialization::schema::G1")]
     pub(crate) randomized_tag: G1Affine,
     #[serde_as(as = "Vec<crate::serialization::SerializableG1Affine>")]
+    #[schemars(with = "Vec<crate::serialization::schema::G1>")]
     pub(crate) tags: Vec<G1Affine>
 }
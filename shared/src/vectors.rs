@@ -0,0 +1,241 @@
+//! Known-answer test vectors of the protocol messages.
+//!
+//! The generator runs one complete protocol round from fixed [`Inputs`]:
+//! every randomized step draws from ChaCha20 seeded with `Inputs::seed` on
+//! its own stream, and the issuer clock is pinned to `Inputs::timestamp`.
+//!
+//! | stream | step                                  |
+//! |--------|---------------------------------------|
+//! | 0      | issuer keys                           |
+//! | 1      | organizer key                         |
+//! | 2      | study id                              |
+//! | 3      | credential request                    |
+//! | 4      | credential response                   |
+//! | 5      | participation and its authentication  |
+//! | 6      | reward coin                           |
+//! | 7      | null coin requests                    |
+//! | 8      | null coin responses                   |
+//! | 9      | payout request                        |
+//!
+//! Messages are sealed in postcard envelopes of the default domain, the
+//! authentication request, which only travels inside a participation, is
+//! encoded without an envelope. The published corpus `vectors/protocol.json`
+//! is regenerated with `prepams message vectors`, `vectors/protocol.v1.json`
+//! and `vectors/protocol.v2.json` keep the corpora of older message versions
+//! for the migration tests.
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::api::{IssuerCore, OrganizerCore, ParticipantCore};
+use crate::error::{PrepamsError, Result};
+use crate::serialization::encode;
+use crate::serialization::envelope::{seal_in, Message, DEFAULT_DOMAIN};
+use crate::types::*;
+
+/// Parameters of the protocol round.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Inputs {
+    /// 32 byte ChaCha20 seed, base64url without padding
+    pub seed: String,
+    pub identity: String,
+    pub attributes: Vec<u32>,
+    pub organizer: String,
+    /// range constraint of the study as attribute index, lower and upper bound
+    pub constraint: (u32, u32, u32),
+    pub reward: u8,
+    pub participation: String,
+    /// unix time of the reward transaction
+    pub timestamp: u64,
+    pub amount: u8,
+    pub target: String,
+    pub recipient: String,
+}
+
+impl Default for Inputs {
+    fn default() -> Self {
+        Inputs {
+            seed: base64::encode_config(*b"prepams known-answer test vector", base64::URL_SAFE_NO_PAD),
+            identity: "alice@example.com".to_string(),
+            attributes: vec![30, 1],
+            organizer: "lab@example.com".to_string(),
+            constraint: (0, 18, 99),
+            reward: 3,
+            participation: "p1".to_string(),
+            timestamp: 1_700_000_000,
+            amount: 2,
+            target: "bank".to_string(),
+            recipient: "alice".to_string(),
+        }
+    }
+}
+
+/// Expected encoding of a single message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Vector {
+    pub name: String,
+    /// `envelope` for sealed messages, `postcard` for bare payloads
+    pub format: String,
+    /// base64url without padding
+    pub data: String,
+}
+
+/// Inputs of a protocol round together with the messages it produces.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Corpus {
+    pub inputs: Inputs,
+    pub vectors: Vec<Vector>,
+}
+
+impl Corpus {
+    pub fn get(&self, name: &str) -> Option<&Vector> {
+        self.vectors.iter().find(|v| v.name == name)
+    }
+}
+
+fn sealed<T: Message>(o: &T) -> Result<Vector> {
+    Ok(Vector {
+        name: T::KIND.id().to_string(),
+        format: "envelope".to_string(),
+        data: base64::encode_config(seal_in(DEFAULT_DOMAIN, o)?, base64::URL_SAFE_NO_PAD),
+    })
+}
+
+/// Runs the protocol round of `inputs` and collects the messages.
+pub fn generate(inputs: &Inputs) -> Result<Corpus> {
+    let seed = base64::decode_config(&inputs.seed, base64::URL_SAFE_NO_PAD).map_err(|e| PrepamsError::Encoding(e.to_string()))?;
+    let seed: [u8; 32] = seed.try_into().map_err(|_| PrepamsError::Encoding("seed must be 32 bytes".to_string()))?;
+    let rng = |stream: u64| {
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        rng.set_stream(stream);
+        rng
+    };
+
+    let timestamp = inputs.timestamp;
+    let mut issuer = IssuerCore::new(inputs.attributes.len(), rng(0)).with_clock(move || timestamp);
+    let organizer = OrganizerCore::new(&inputs.organizer, issuer.public_key().clone(), rng(1));
+    let mut participant = ParticipantCore::new(&inputs.identity, &inputs.attributes, issuer.ledger_verification_key());
+
+    let mut resource = Resource::random(rng(2));
+    let (attribute, from, to) = inputs.constraint;
+    resource.reward = inputs.reward;
+    resource.addConstraint(AttributeConstraint::Range(attribute, from, to));
+
+    let request = participant.request_credential(issuer.public_key().clone(), issuer.credit_verification_key().clone(), rng(3))?;
+    let response = issuer.issue_credential_with_rng(&request, rng(4))?;
+    participant.retrieve_credential(&response)?;
+
+    let participation = participant.participate_with_rng(&resource, rng(5))?;
+    let confirmed = organizer.confirm_participation(&participation, inputs.participation.clone())?;
+    let entry = issuer.issue_reward_with_rng(&confirmed, &organizer.public_key(), inputs.reward, rng(6))?;
+
+    let nulls = participant.request_nulls_with_rng(rng(7))?;
+    let coins = issuer.issue_nulls_with_rng(nulls.requests(), rng(8))?;
+    let nulls = nulls.unblind_nulls(coins)?;
+    let payout = participant.request_payout_with_rng(inputs.amount, &inputs.target, &inputs.recipient, nulls, &issuer.ledger()?, rng(9))?;
+
+    Ok(Corpus {
+        inputs: inputs.clone(),
+        vectors: vec![
+            sealed(issuer.public_key())?,
+            sealed(issuer.credit_verification_key())?,
+            sealed(&request)?,
+            sealed(&response)?,
+            Vector {
+                name: "authentication_request".to_string(),
+                format: "postcard".to_string(),
+                data: base64::encode_config(encode(&participation.proof.inputs.auth_request)?, base64::URL_SAFE_NO_PAD),
+            },
+            sealed(&participation)?,
+            sealed(&confirmed)?,
+            sealed(&entry)?,
+            sealed(&payout.proof)?,
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::envelope::open_in;
+    use crate::serialization::decode;
+    use crate::types::credential::{AuthenticationRequest, IssuerPublicKey, IssueRequest, IssueResponse};
+    use crate::proofs::payout::PayoutProofData;
+    use crate::serialization::format::{open_as, seal_as, Format};
+
+    // opens a vector as `T` and checks that sealing it again gives the same bytes
+    fn reseal<T: Message>(vector: &Vector) -> T {
+        let data = base64::decode_config(&vector.data, base64::URL_SAFE_NO_PAD).unwrap();
+        let message: T = open_in(DEFAULT_DOMAIN, &data).unwrap();
+        assert_eq!(seal_in(DEFAULT_DOMAIN, &message).unwrap(), data, "{} does not round-trip", vector.name);
+        message
+    }
+
+    #[test]
+    fn corpus() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("vectors/protocol.json");
+        let published: Corpus = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        let corpus = generate(&published.inputs).unwrap();
+
+        // the published vectors are regenerated with `prepams message vectors`
+        assert_eq!(corpus.vectors.len(), published.vectors.len());
+        for (vector, expected) in corpus.vectors.iter().zip(&published.vectors) {
+            assert_eq!(vector, expected, "vectors/protocol.json: {} drifted", expected.name);
+        }
+
+        let ipk: IssuerPublicKey = reseal(corpus.get("issuer_public_key").unwrap());
+        reseal::<crate::pbss::PublicKey>(corpus.get("credit_verification_key").unwrap());
+        reseal::<IssueRequest>(corpus.get("issue_request").unwrap());
+        reseal::<IssueResponse>(corpus.get("issue_response").unwrap());
+        let participation: Participation = reseal(corpus.get("participation").unwrap());
+        reseal::<ConfirmedParticipation>(corpus.get("confirmed_participation").unwrap());
+        let entry: LedgerEntry = reseal(corpus.get("ledger_entry").unwrap());
+        let payout: PayoutProofData = reseal(corpus.get("payout_request").unwrap());
+
+        let auth = corpus.get("authentication_request").unwrap();
+        let auth: AuthenticationRequest = decode(&base64::decode_config(&auth.data, base64::URL_SAFE_NO_PAD).unwrap()).unwrap();
+        assert!(crate::credential::verify(&ipk, &auth));
+
+        participation.check().unwrap();
+        assert_eq!(entry.timestamp, published.inputs.timestamp);
+        assert_eq!(payout.inputs.target, published.inputs.target);
+    }
+
+    #[test]
+    fn legacy() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("vectors/protocol.json");
+        let corpus: Corpus = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        let current: Participation = reseal(corpus.get("participation").unwrap());
+
+        // the proofs before version 4 lack the bit constraints of the
+        // statements and cannot be converted
+        for (version, file) in [(1, "vectors/protocol.v1.json"), (2, "vectors/protocol.v2.json"), (3, "vectors/protocol.v3.json")] {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+            let legacy: Corpus = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+            let data = |name| base64::decode_config(&legacy.get(name).unwrap().data, base64::URL_SAFE_NO_PAD).unwrap();
+
+            let participation = open_in::<Participation>(DEFAULT_DOMAIN, &data("participation"));
+            assert!(matches!(participation, Err(PrepamsError::Encoding(_))), "{}", file);
+            let payout = open_in::<PayoutProofData>(DEFAULT_DOMAIN, &data("payout_request"));
+            assert!(matches!(payout, Err(PrepamsError::Encoding(_))), "{}", file);
+
+            // the same holds for json documents of an older version
+            let mut document: serde_json::Value = serde_json::from_slice(&seal_as(Format::Json, DEFAULT_DOMAIN, &current).unwrap()).unwrap();
+            document["version"] = version.into();
+            let participation = open_as::<Participation>(Format::Json, DEFAULT_DOMAIN, &serde_json::to_vec(&document).unwrap());
+            assert!(matches!(participation, Err(PrepamsError::Encoding(_))), "{}", file);
+        }
+    }
+
+    #[test]
+    fn inputs() {
+        let inputs = Inputs::default();
+        assert_eq!(generate(&inputs).unwrap(), generate(&inputs).unwrap());
+
+        let other = Inputs { seed: base64::encode_config([7; 32], base64::URL_SAFE_NO_PAD), ..Inputs::default() };
+        let (a, b) = (generate(&inputs).unwrap(), generate(&other).unwrap());
+        assert_ne!(a.get("participation"), b.get("participation"));
+        assert!(generate(&Inputs { seed: "short".to_string(), ..Inputs::default() }).is_err());
+    }
+}